@@ -0,0 +1,442 @@
+//! Rust code constructing the rule parsed by [nft_rule!](crate::nft_rule).
+
+use std::{borrow::Cow, collections::HashSet, fmt::Write};
+
+use nftables::{
+    expr::{self, BinaryOperation, Expression, NamedExpression, Payload, SetItem, Verdict},
+    schema::{self, Rule},
+    stmt::{self, Counter, QuotaOrQuotaRef, Statement},
+    types,
+};
+
+/// Prefix of the words standing in for interpolated variables.
+pub(crate) const PLACEHOLDER: &str = "__nft_rule_";
+
+/// Rust code being generated.
+///
+/// Interpolated variables are written as the identifier of their
+/// placeholder, which [expand](crate::expand) replaces with the variable.
+#[derive(Default)]
+pub(crate) struct Code {
+    pub(crate) text: String,
+    /// The first value without a Rust counterpart in the macro output.
+    pub(crate) unsupported: Option<&'static str>,
+}
+
+impl Code {
+    fn push(&mut self, text: &str) {
+        self.text.push_str(text);
+    }
+
+    /// Writes a tuple variant or call with the given arguments.
+    fn call(&mut self, name: &str, args: &[&dyn Construct]) {
+        self.push(name);
+        self.push("(");
+        for arg in args {
+            arg.construct(self);
+            self.push(",");
+        }
+        self.push(")");
+    }
+
+    /// Writes a call of a function of `nftables::macro_support` converting
+    /// the interpolated variable `index`.
+    fn var(&mut self, function: &str, index: usize) {
+        let _ = write!(
+            self.text,
+            "::nftables::macro_support::{function}({PLACEHOLDER}{index})"
+        );
+    }
+
+    fn unsupported(&mut self, what: &'static str) {
+        self.unsupported.get_or_insert(what);
+    }
+}
+
+/// Returns the index of the variable a string stands for, and whether it
+/// is a set reference (`@$name`).
+fn var(s: &str) -> Option<(usize, bool)> {
+    let (name, set) = match s.strip_prefix('@') {
+        Some(name) => (name, true),
+        None => (s, false),
+    };
+    let index = name.strip_prefix(PLACEHOLDER)?;
+    if !index.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((index.parse().ok()?, set))
+}
+
+/// Writes Rust code evaluating to a value.
+pub(crate) trait Construct {
+    fn construct(&self, code: &mut Code);
+}
+
+impl<T: Construct + ?Sized> Construct for &T {
+    fn construct(&self, code: &mut Code) {
+        (**self).construct(code)
+    }
+}
+
+macro_rules! construct_display {
+    ($($ty:ty),*) => {
+        $(impl Construct for $ty {
+            fn construct(&self, code: &mut Code) {
+                let _ = write!(code.text, "{self}");
+            }
+        })*
+    };
+}
+
+construct_display!(bool, u16, u32, u64, i64);
+
+impl<T: Construct> Construct for Option<T> {
+    fn construct(&self, code: &mut Code) {
+        match self {
+            Some(value) => code.call("::core::option::Option::Some", &[value]),
+            None => code.push("::core::option::Option::None"),
+        }
+    }
+}
+
+impl<T: Construct> Construct for Box<T> {
+    fn construct(&self, code: &mut Code) {
+        code.call("::std::boxed::Box::new", &[&**self]);
+    }
+}
+
+impl<T: Construct> Construct for [T] {
+    fn construct(&self, code: &mut Code) {
+        code.push("[");
+        for item in self {
+            item.construct(code);
+            code.push(",");
+        }
+        code.push("]");
+    }
+}
+
+impl<T: Construct, const N: usize> Construct for [T; N] {
+    fn construct(&self, code: &mut Code) {
+        self[..].construct(code);
+    }
+}
+
+impl<T: Construct> Construct for Vec<T> {
+    fn construct(&self, code: &mut Code) {
+        code.push("::std::vec!");
+        self[..].construct(code);
+    }
+}
+
+impl<T: Construct> Construct for HashSet<T> {
+    fn construct(&self, code: &mut Code) {
+        let items: Vec<&T> = self.iter().collect();
+        code.push("::std::collections::HashSet::from(");
+        items[..].construct(code);
+        code.push(")");
+    }
+}
+
+impl<T: Construct + Clone> Construct for Cow<'_, [T]> {
+    fn construct(&self, code: &mut Code) {
+        code.push("::std::borrow::Cow::Owned(::std::vec!");
+        self[..].construct(code);
+        code.push(")");
+    }
+}
+
+impl Construct for Cow<'_, expr::SocketAttr> {
+    fn construct(&self, code: &mut Code) {
+        code.call("::std::borrow::Cow::Owned", &[&**self]);
+    }
+}
+
+impl Construct for Cow<'_, str> {
+    fn construct(&self, code: &mut Code) {
+        match var(self) {
+            Some((index, false)) => code.var("name", index),
+            Some((index, true)) => code.var("set_reference", index),
+            None => {
+                let _ = write!(code.text, "::std::borrow::Cow::Borrowed({:?})", &**self);
+            }
+        }
+    }
+}
+
+/// Constructs structs field by field. The destructuring makes adding a
+/// field to one of them a compile error here.
+macro_rules! construct_struct {
+    ($($module:ident::$ty:ident $(<$lt:lifetime>)? { $($field:ident),* $(,)? })*) => {
+        $(impl Construct for $module::$ty $(<$lt>)? {
+            fn construct(&self, code: &mut Code) {
+                let $module::$ty { $($field),* } = self;
+                code.push(concat!("::nftables::", stringify!($module), "::", stringify!($ty), " {"));
+                $(
+                    code.push(concat!(stringify!($field), ": "));
+                    $field.construct(code);
+                    code.push(",");
+                )*
+                code.push("}");
+            }
+        })*
+    };
+}
+
+construct_struct! {
+    schema::Rule<'_> { family, table, chain, expr, handle, index, comment }
+
+    stmt::Accept {}
+    stmt::Drop {}
+    stmt::Continue {}
+    stmt::Return {}
+    stmt::JumpTarget<'_> { target }
+    stmt::Match<'_> { left, right, op }
+    stmt::AnonymousCounter { packets, bytes }
+    stmt::Mangle<'_> { key, value }
+    stmt::Quota<'_> { val, val_unit, used, used_unit, inv }
+    stmt::Limit<'_> { rate, rate_unit, per, burst, burst_unit, inv }
+    stmt::Flow<'_> { op, flowtable }
+    stmt::FWD<'_> { dev, family, addr }
+    stmt::Dup<'_> { addr, dev }
+    stmt::NAT<'_> { addr, family, port, flags }
+    stmt::Reject { _type, expr }
+    stmt::Set<'_> { op, elem, set }
+    stmt::Log<'_> { prefix, group, snaplen, queue_threshold, level, flags }
+    stmt::Meter<'_> { name, key, stmt }
+    stmt::Queue<'_> { num, flags }
+    stmt::VerdictMap<'_> { key, data }
+    stmt::CTCount<'_> { val, inv }
+    stmt::SynProxy { mss, wscale, flags }
+    stmt::TProxy<'_> { family, port, addr }
+
+    expr::Map<'_> { key, data }
+    expr::Prefix<'_> { addr, len }
+    expr::Range<'_> { range }
+    expr::PayloadRaw { base, offset, len }
+    expr::PayloadField<'_> { protocol, field }
+    expr::Exthdr<'_> { name, field, offset }
+    expr::TcpOption<'_> { name, field }
+    expr::SctpChunk<'_> { name, field }
+    expr::Meta { key }
+    expr::RT { key, family }
+    expr::CT<'_> { key, family, dir }
+    expr::Numgen { mode, ng_mod, offset }
+    expr::JHash<'_> { hash_mod, offset, expr, seed }
+    expr::SymHash { hash_mod, offset }
+    expr::Fib { result, flags }
+    expr::Elem<'_> { val, timeout, expires, comment, counter }
+    expr::Socket<'_> { key }
+    expr::Osf<'_> { key, ttl }
+}
+
+/// Constructs enums without fields, whose derived `Debug` output is the
+/// name of the variant.
+macro_rules! construct_unit_enum {
+    ($($module:ident::$ty:ident),* $(,)?) => {
+        $(impl Construct for $module::$ty {
+            fn construct(&self, code: &mut Code) {
+                let _ = write!(
+                    code.text,
+                    concat!("::nftables::", stringify!($module), "::", stringify!($ty), "::{:?}"),
+                    self
+                );
+            }
+        })*
+    };
+}
+
+construct_unit_enum!(
+    types::NfFamily,
+    types::RejectCode,
+    types::SynProxyFlag,
+    stmt::FWDFamily,
+    stmt::NATFamily,
+    stmt::NATFlag,
+    stmt::RejectType,
+    stmt::SetOp,
+    stmt::LogLevel,
+    stmt::LogFlag,
+    stmt::QueueFlag,
+    stmt::Operator,
+    expr::PayloadBase,
+    expr::MetaKey,
+    expr::RTKey,
+    expr::RTFamily,
+    expr::CTFamily,
+    expr::CTDir,
+    expr::NgMode,
+    expr::FibResult,
+    expr::FibFlag,
+    expr::SocketAttr,
+    expr::OsfTtl,
+);
+
+impl Construct for Statement<'_> {
+    fn construct(&self, code: &mut Code) {
+        code.push("::nftables::stmt::Statement::");
+        match self {
+            Statement::Accept(accept) => code.call("Accept", &[accept]),
+            Statement::Drop(drop) => code.call("Drop", &[drop]),
+            Statement::Continue(cont) => code.call("Continue", &[cont]),
+            Statement::Return(ret) => code.call("Return", &[ret]),
+            Statement::Jump(target) => code.call("Jump", &[target]),
+            Statement::Goto(target) => code.call("Goto", &[target]),
+            Statement::Match(m) => code.call("Match", &[m]),
+            Statement::Counter(counter) => code.call("Counter", &[counter]),
+            Statement::Mangle(mangle) => code.call("Mangle", &[mangle]),
+            Statement::Quota(quota) => code.call("Quota", &[quota]),
+            Statement::Limit(limit) => code.call("Limit", &[limit]),
+            Statement::Flow(flow) => code.call("Flow", &[flow]),
+            Statement::FWD(fwd) => code.call("FWD", &[fwd]),
+            Statement::Notrack => code.push("Notrack"),
+            Statement::Dup(dup) => code.call("Dup", &[dup]),
+            Statement::SNAT(nat) => code.call("SNAT", &[nat]),
+            Statement::DNAT(nat) => code.call("DNAT", &[nat]),
+            Statement::Masquerade(nat) => code.call("Masquerade", &[nat]),
+            Statement::Redirect(nat) => code.call("Redirect", &[nat]),
+            Statement::Reject(reject) => code.call("Reject", &[reject]),
+            Statement::Set(set) => code.call("Set", &[set]),
+            Statement::Log(log) => code.call("Log", &[log]),
+            Statement::CTHelper(helper) => code.call("CTHelper", &[helper]),
+            Statement::Meter(meter) => code.call("Meter", &[meter]),
+            Statement::Queue(queue) => code.call("Queue", &[queue]),
+            Statement::VerdictMap(map) => code.call("VerdictMap", &[map]),
+            Statement::CTCount(count) => code.call("CTCount", &[count]),
+            Statement::CTTimeout(timeout) => code.call("CTTimeout", &[timeout]),
+            Statement::CTExpectation(expectation) => code.call("CTExpectation", &[expectation]),
+            Statement::SynProxy(synproxy) => code.call("SynProxy", &[synproxy]),
+            Statement::TProxy(tproxy) => code.call("TProxy", &[tproxy]),
+            _ => code.unsupported("this statement"),
+        }
+    }
+}
+
+impl Construct for Counter<'_> {
+    fn construct(&self, code: &mut Code) {
+        code.push("::nftables::stmt::Counter::");
+        match self {
+            Counter::Named(name) => code.call("Named", &[name]),
+            Counter::Anonymous(counter) => code.call("Anonymous", &[counter]),
+        }
+    }
+}
+
+impl Construct for QuotaOrQuotaRef<'_> {
+    fn construct(&self, code: &mut Code) {
+        code.push("::nftables::stmt::QuotaOrQuotaRef::");
+        match self {
+            QuotaOrQuotaRef::Quota(quota) => code.call("Quota", &[quota]),
+            QuotaOrQuotaRef::QuotaRef(name) => code.call("QuotaRef", &[name]),
+        }
+    }
+}
+
+impl Construct for Expression<'_> {
+    fn construct(&self, code: &mut Code) {
+        if let Expression::String(s) = self {
+            match var(s) {
+                Some((index, false)) => return code.var("value", index),
+                Some((index, true)) => {
+                    code.push("::nftables::expr::Expression::String(");
+                    code.var("set_reference", index);
+                    return code.push(")");
+                }
+                None => {}
+            }
+        }
+        code.push("::nftables::expr::Expression::");
+        match self {
+            Expression::String(s) => code.call("String", &[s]),
+            Expression::Number(n) => code.call("Number", &[n]),
+            Expression::SignedNumber(n) => code.call("SignedNumber", &[n]),
+            Expression::Boolean(b) => code.call("Boolean", &[b]),
+            Expression::List(items) => code.call("List", &[items]),
+            Expression::BinaryOperation(op) => code.call("BinaryOperation", &[op]),
+            Expression::Range(range) => code.call("Range", &[range]),
+            Expression::Named(named) => code.call("Named", &[named]),
+            Expression::Verdict(verdict) => code.call("Verdict", &[verdict]),
+        }
+    }
+}
+
+impl Construct for NamedExpression<'_> {
+    fn construct(&self, code: &mut Code) {
+        code.push("::nftables::expr::NamedExpression::");
+        match self {
+            NamedExpression::Concat(items) => code.call("Concat", &[items]),
+            NamedExpression::Set(items) => code.call("Set", &[items]),
+            NamedExpression::Map(map) => code.call("Map", &[map]),
+            NamedExpression::Prefix(prefix) => code.call("Prefix", &[prefix]),
+            NamedExpression::Payload(payload) => code.call("Payload", &[payload]),
+            NamedExpression::Exthdr(exthdr) => code.call("Exthdr", &[exthdr]),
+            NamedExpression::TcpOption(option) => code.call("TcpOption", &[option]),
+            NamedExpression::SctpChunk(chunk) => code.call("SctpChunk", &[chunk]),
+            NamedExpression::Meta(meta) => code.call("Meta", &[meta]),
+            NamedExpression::RT(rt) => code.call("RT", &[rt]),
+            NamedExpression::CT(ct) => code.call("CT", &[ct]),
+            NamedExpression::Numgen(numgen) => code.call("Numgen", &[numgen]),
+            NamedExpression::JHash(hash) => code.call("JHash", &[hash]),
+            NamedExpression::SymHash(hash) => code.call("SymHash", &[hash]),
+            NamedExpression::Fib(fib) => code.call("Fib", &[fib]),
+            NamedExpression::Elem(elem) => code.call("Elem", &[elem]),
+            NamedExpression::Socket(socket) => code.call("Socket", &[socket]),
+            NamedExpression::Osf(osf) => code.call("Osf", &[osf]),
+        }
+    }
+}
+
+impl Construct for SetItem<'_> {
+    fn construct(&self, code: &mut Code) {
+        code.push("::nftables::expr::SetItem::");
+        match self {
+            SetItem::Element(element) => code.call("Element", &[element]),
+            SetItem::Mapping(key, value) => code.call("Mapping", &[key, value]),
+            SetItem::MappingStatement(key, stmt) => code.call("MappingStatement", &[key, stmt]),
+        }
+    }
+}
+
+impl Construct for Payload<'_> {
+    fn construct(&self, code: &mut Code) {
+        code.push("::nftables::expr::Payload::");
+        match self {
+            Payload::PayloadField(field) => code.call("PayloadField", &[field]),
+            Payload::PayloadRaw(raw) => code.call("PayloadRaw", &[raw]),
+        }
+    }
+}
+
+impl Construct for BinaryOperation<'_> {
+    fn construct(&self, code: &mut Code) {
+        code.push("::nftables::expr::BinaryOperation::");
+        match self {
+            BinaryOperation::AND(left, right) => code.call("AND", &[left, right]),
+            BinaryOperation::OR(items) => code.call("OR", &[items]),
+            BinaryOperation::XOR(left, right) => code.call("XOR", &[left, right]),
+            BinaryOperation::LSHIFT(left, right) => code.call("LSHIFT", &[left, right]),
+            BinaryOperation::RSHIFT(left, right) => code.call("RSHIFT", &[left, right]),
+        }
+    }
+}
+
+impl Construct for Verdict<'_> {
+    fn construct(&self, code: &mut Code) {
+        code.push("::nftables::expr::Verdict::");
+        match self {
+            Verdict::Accept => code.push("Accept"),
+            Verdict::Drop => code.push("Drop"),
+            Verdict::Continue => code.push("Continue"),
+            Verdict::Return => code.push("Return"),
+            Verdict::Jump(target) => code.call("Jump", &[target]),
+            Verdict::Goto(target) => code.call("Goto", &[target]),
+        }
+    }
+}
+
+/// Writes the code constructing a rule.
+pub(crate) fn rule(rule: &Rule) -> Code {
+    let mut code = Code::default();
+    rule.construct(&mut code);
+    code
+}
@@ -0,0 +1,291 @@
+//! Procedural macros for the [nftables](https://docs.rs/nftables) crate.
+//!
+//! [nft_rule!] writes a single rule in the native nft syntax and expands to a
+//! [`nftables::schema::Rule`]. The rule is parsed with [`nftables::parser`]
+//! at compile time, so syntax errors are reported at the offending token,
+//! and the macro expands to the Rust code constructing the parsed rule.
+//!
+//! This crate requires Rust 1.88, newer than the `nftables` crate: it reads
+//! the line and column of the input tokens to keep words written without
+//! spaces, such as `10.0.0.0/8`, together.
+
+use nftables::{
+    parser::parse,
+    schema::{NfCmd, NfListObject, NfObject},
+};
+use proc_macro::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+
+mod construct;
+
+/// Writes a rule in nft syntax, as in `nft add rule`.
+///
+/// The family, table and chain are followed by a colon and the statements of
+/// the rule. The macro evaluates to a [`Rule`](nftables::schema::Rule), which
+/// borrows the interpolated variables it does not own.
+///
+/// ```
+/// use nftables::schema::Rule;
+/// use nftables_macros::nft_rule;
+///
+/// let rule: Rule = nft_rule!(inet filter input: tcp dport { 22, 80 } ct state new accept);
+/// assert_eq!(rule.chain, "input");
+/// assert_eq!(rule.expr.len(), 3);
+/// ```
+///
+/// Rust variables are interpolated with `$name`, wherever nft accepts a word.
+/// Where the rule expects a value, the variable is converted with
+/// [`Into<Expression>`](nftables::expr::Expression), so it may be a string,
+/// number or anything else the expression converts from. Where it expects a
+/// name, such as a jump target or the table, and in set references
+/// (`@$name`), it is converted with `Into<Cow<str>>`. Variables must stand
+/// alone: `$net/24` is rejected, interpolate the complete
+/// [prefix](nftables::expr::Prefix) instead.
+///
+/// ```
+/// use nftables::expr::Prefix;
+/// use nftables_macros::nft_rule;
+///
+/// let trusted = Prefix::new("10.0.0.0", 8);
+/// let (port, blocked, chain) = (2222u32, "blocked", String::from("ssh"));
+/// let rule = nft_rule!(inet filter input: ip saddr $trusted tcp dport $port jump $chain);
+/// let rule = nft_rule!(inet filter input: ip saddr @$blocked drop);
+/// ```
+///
+/// Syntax errors fail to compile, and so do variables of a type that does
+/// not fit where they are used:
+///
+/// ```compile_fail
+/// let rule = nftables_macros::nft_rule!(inet filter input: tcp dport 22 acept);
+/// ```
+///
+/// ```compile_fail
+/// let target = 2u32;
+/// let rule = nftables_macros::nft_rule!(inet filter input: jump $target);
+/// ```
+#[proc_macro]
+pub fn nft_rule(input: TokenStream) -> TokenStream {
+    expand(input).unwrap_or_else(|err| err.to_compile_error())
+}
+
+/// Error reported as `compile_error!` at a span of the macro input.
+struct Error {
+    span: Span,
+    message: String,
+}
+
+impl Error {
+    fn new(span: Span, message: impl Into<String>) -> Self {
+        Error {
+            span,
+            message: message.into(),
+        }
+    }
+
+    fn to_compile_error(&self) -> TokenStream {
+        let mut message = Literal::string(&self.message);
+        message.set_span(self.span);
+        [
+            TokenTree::Punct(Punct::new(':', Spacing::Joint)),
+            TokenTree::Punct(Punct::new(':', Spacing::Alone)),
+            TokenTree::Ident(Ident::new("core", self.span)),
+            TokenTree::Punct(Punct::new(':', Spacing::Joint)),
+            TokenTree::Punct(Punct::new(':', Spacing::Alone)),
+            TokenTree::Ident(Ident::new("compile_error", self.span)),
+            TokenTree::Punct(Punct::new('!', Spacing::Alone)),
+            TokenTree::Group(Group::new(
+                Delimiter::Parenthesis,
+                TokenTree::Literal(message).into(),
+            )),
+        ]
+        .into_iter()
+        .map(|mut token| {
+            token.set_span(self.span);
+            token
+        })
+        .collect()
+    }
+}
+
+fn expand(input: TokenStream) -> Result<TokenStream, Error> {
+    let tokens: Vec<TokenTree> = input.into_iter().collect();
+    let colon = tokens
+        .iter()
+        .position(|token| matches!(token, TokenTree::Punct(p) if p.as_char() == ':'))
+        .ok_or_else(|| {
+            Error::new(
+                Span::call_site(),
+                "expected `family table chain: statements`",
+            )
+        })?;
+    if colon + 1 == tokens.len() {
+        return Err(Error::new(tokens[colon].span(), "expected statements"));
+    }
+
+    let mut nft = Nft {
+        text: "add rule".to_string(),
+        ..Nft::default()
+    };
+    nft.render(tokens[..colon].iter().cloned().collect())?;
+    nft.render(tokens[colon + 1..].iter().cloned().collect())?;
+
+    let document =
+        parse(&nft.text).map_err(|err| Error::new(nft.span_at(err.column), err.message))?;
+    let rule = match &document.objects[..] {
+        [NfObject::CmdObject(NfCmd::Add(NfListObject::Rule(rule)))] => rule,
+        _ => return Err(Error::new(Span::call_site(), "expected a single rule")),
+    };
+    let code = construct::rule(rule);
+    if let Some(what) = code.unsupported {
+        let message = format!("nft_rule! cannot construct {what}");
+        return Err(Error::new(Span::call_site(), message));
+    }
+    let output: TokenStream = code.text.parse().expect("valid tokens");
+
+    let mut used = vec![false; nft.vars.len()];
+    let output = substitute(output, &nft.vars, &mut used);
+    if let Some(i) = used.iter().position(|used| !used) {
+        return Err(Error::new(
+            nft.vars[i].span(),
+            "a variable cannot be interpolated here",
+        ));
+    }
+    Ok(output)
+}
+
+/// Replaces the placeholders in the generated code with the interpolated
+/// variables, so that type errors point at the variable.
+fn substitute(stream: TokenStream, vars: &[Ident], used: &mut [bool]) -> TokenStream {
+    stream
+        .into_iter()
+        .map(|token| match token {
+            TokenTree::Group(group) => {
+                let mut substituted =
+                    Group::new(group.delimiter(), substitute(group.stream(), vars, used));
+                substituted.set_span(group.span());
+                TokenTree::Group(substituted)
+            }
+            TokenTree::Ident(ident) => {
+                let name = ident.to_string();
+                let index = name
+                    .strip_prefix(construct::PLACEHOLDER)
+                    .and_then(|index| index.parse::<usize>().ok());
+                match index {
+                    Some(index) => {
+                        used[index] = true;
+                        TokenTree::Ident(vars[index].clone())
+                    }
+                    None => TokenTree::Ident(ident),
+                }
+            }
+            token => token,
+        })
+        .collect()
+}
+
+/// Renders the macro input as nft text.
+///
+/// Tokens are separated by a space unless they touch in the source, so
+/// addresses such as `10.0.0.0/8` and `fe80::1` keep their form.
+#[derive(Default)]
+struct Nft {
+    text: String,
+    /// Character offset of each token in `text`.
+    spans: Vec<(usize, Span)>,
+    /// The interpolated variables, in order of their placeholders.
+    vars: Vec<Ident>,
+    /// Span of the previous token.
+    last: Option<Span>,
+    /// Whether the previous token was an interpolated variable.
+    after_var: bool,
+}
+
+/// Characters the nft lexer reads as part of a word.
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | ':' | '-' | '/' | '*' | '@' | '[' | ']')
+}
+
+impl Nft {
+    fn render(&mut self, tokens: TokenStream) -> Result<(), Error> {
+        let mut tokens = tokens.into_iter();
+        while let Some(token) = tokens.next() {
+            match token {
+                TokenTree::Punct(dollar) if dollar.as_char() == '$' => {
+                    let Some(TokenTree::Ident(ident)) = tokens.next() else {
+                        return Err(Error::new(
+                            dollar.span(),
+                            "expected a variable name after `$`",
+                        ));
+                    };
+                    self.interpolate(dollar.span(), ident)?;
+                }
+                TokenTree::Group(group) => {
+                    let (open, close) = match group.delimiter() {
+                        Delimiter::Brace => ("{", "}"),
+                        Delimiter::Parenthesis => ("(", ")"),
+                        Delimiter::Bracket => ("[", "]"),
+                        Delimiter::None => ("", ""),
+                    };
+                    self.push(open, group.span_open())?;
+                    self.render(group.stream())?;
+                    self.push(close, group.span_close())?;
+                }
+                token => self.push(&token.to_string(), token.span())?,
+            }
+        }
+        Ok(())
+    }
+
+    fn interpolate(&mut self, dollar: Span, ident: Ident) -> Result<(), Error> {
+        let glued = self.adjacent(dollar);
+        let set = glued && self.text.ends_with('@');
+        if glued && !set && self.text.ends_with(is_word_char) {
+            return Err(Error::new(
+                dollar,
+                "interpolated variables must stand alone",
+            ));
+        }
+        let placeholder = format!("{}{}", construct::PLACEHOLDER, self.vars.len());
+        self.push(&placeholder, dollar)?;
+        self.last = Some(ident.span());
+        self.after_var = true;
+        self.vars.push(ident);
+        Ok(())
+    }
+
+    fn push(&mut self, text: &str, span: Span) -> Result<(), Error> {
+        if text.is_empty() {
+            return Ok(());
+        }
+        if self.adjacent(span) {
+            if self.after_var && text.starts_with(is_word_char) {
+                return Err(Error::new(span, "interpolated variables must stand alone"));
+            }
+        } else {
+            self.text.push(' ');
+        }
+        let start = self.text.chars().count();
+        self.text.push_str(text);
+        self.spans.push((start, span));
+        self.last = Some(span);
+        self.after_var = false;
+        Ok(())
+    }
+
+    /// Whether `span` directly follows the previous token in the source.
+    fn adjacent(&self, span: Span) -> bool {
+        self.last.is_some_and(|last| {
+            let (end, start) = (last.end(), span.start());
+            end.line() == start.line() && end.column() == start.column()
+        })
+    }
+
+    /// Returns the span of the token at the (1-based) column of the text.
+    fn span_at(&self, column: usize) -> Span {
+        let offset = column.saturating_sub(1);
+        self.spans
+            .iter()
+            .rev()
+            .find(|(start, _)| *start <= offset)
+            .map_or_else(Span::call_site, |(_, span)| *span)
+    }
+}
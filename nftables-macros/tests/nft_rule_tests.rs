@@ -0,0 +1,147 @@
+use nftables::{
+    expr::Prefix,
+    parser::parse,
+    schema::{NfCmd, NfListObject, NfObject, Rule},
+};
+use nftables_macros::nft_rule;
+use serde_json::{json, Value};
+
+fn rule(rule: Value) -> Rule<'static> {
+    match serde_json::from_value(json!({ "rule": rule })).unwrap() {
+        NfListObject::Rule(rule) => rule,
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+/// Expands nft syntax into the rule nft would add.
+fn test_nft_rule() {
+    let expected = rule(
+        json!({"family": "inet", "table": "filter", "chain": "input", "expr": [
+            {"match": {"op": "==", "left": {"payload": {"protocol": "tcp", "field": "dport"}},
+                "right": {"set": [22, 80]}}},
+            {"match": {"op": "in", "left": {"ct": {"key": "state"}}, "right": "new"}},
+            {"accept": null},
+        ]}),
+    );
+    assert_eq!(
+        nft_rule!(inet filter input: tcp dport { 22, 80 } ct state new accept),
+        expected
+    );
+
+    let expected = rule(
+        json!({"family": "ip", "table": "nat", "chain": "postrouting",
+        "comment": "uplink", "expr": [
+            {"match": {"op": "==", "left": {"payload": {"protocol": "ip", "field": "saddr"}},
+                "right": {"prefix": {"addr": "10.0.0.0", "len": 8}}}},
+            {"match": {"op": "==", "left": {"meta": {"key": "oifname"}}, "right": "wan0"}},
+            {"counter": null},
+            {"masquerade": null},
+        ]}),
+    );
+    assert_eq!(
+        nft_rule!(ip nat postrouting: ip saddr 10.0.0.0/8 oifname "wan0" counter masquerade comment "uplink"),
+        expected
+    );
+}
+
+#[test]
+/// Keeps addresses and ranges written without spaces together.
+fn test_nft_rule_words() {
+    let expected = rule(
+        json!({"family": "ip6", "table": "filter", "chain": "input", "expr": [
+            {"match": {"op": "==", "left": {"payload": {"protocol": "ip6", "field": "saddr"}},
+                "right": "fe80::1"}},
+            {"match": {"op": "==", "left": {"payload": {"protocol": "udp", "field": "dport"}},
+                "right": {"range": [1024, 65535]}}},
+            {"drop": null},
+        ]}),
+    );
+    assert_eq!(
+        nft_rule!(ip6 filter input: ip6 saddr fe80::1 udp dport 1024-65535 drop),
+        expected
+    );
+}
+
+#[test]
+/// Substitutes interpolated Rust variables.
+fn test_nft_rule_interpolation() {
+    let trusted = Prefix::new("192.168.0.0", 16);
+    let port = 2222;
+    let chain = "ssh";
+    let expected = rule(
+        json!({"family": "inet", "table": "filter", "chain": "input", "expr": [
+            {"match": {"op": "==", "left": {"payload": {"protocol": "ip", "field": "saddr"}},
+                "right": {"prefix": {"addr": "192.168.0.0", "len": 16}}}},
+            {"match": {"op": "==", "left": {"payload": {"protocol": "tcp", "field": "dport"}},
+                "right": {"set": [2222, 22]}}},
+            {"jump": {"target": "ssh"}},
+        ]}),
+    );
+    assert_eq!(
+        nft_rule!(inet filter input: ip saddr $trusted tcp dport { $port, 22 } jump $chain),
+        expected
+    );
+
+    let blocked = String::from("blocked");
+    let expected = rule(
+        json!({"family": "inet", "table": "filter", "chain": "input", "expr": [
+            {"match": {"op": "==", "left": {"payload": {"protocol": "ip", "field": "saddr"}},
+                "right": "@blocked"}},
+            {"drop": null},
+        ]}),
+    );
+    assert_eq!(
+        nft_rule!(inet filter input: ip saddr @$blocked drop),
+        expected
+    );
+}
+
+/// Parses the text of a single `add rule` command.
+fn parsed(text: &str) -> Rule<'static> {
+    match &parse(text).unwrap().objects[..] {
+        [NfObject::CmdObject(NfCmd::Add(NfListObject::Rule(rule)))] => rule.clone(),
+        objects => panic!("not a rule: {objects:?}"),
+    }
+}
+
+#[test]
+/// Constructs the same rules the parser reads at runtime.
+fn test_nft_rule_statements() {
+    assert_eq!(
+        nft_rule!(inet filter input: meta l4proto tcp tcp flags & (syn | ack) == syn limit rate over 10/second burst 5 packets log prefix "syn " level warn drop),
+        parsed(
+            r#"add rule inet filter input meta l4proto tcp tcp flags & (syn | ack) == syn limit rate over 10/second burst 5 packets log prefix "syn " level warn drop"#
+        )
+    );
+    assert_eq!(
+        nft_rule!(ip nat prerouting: iifname "wan0" tcp dport 8080 dnat to 10.0.0.2:80),
+        parsed("add rule ip nat prerouting iifname \"wan0\" tcp dport 8080 dnat to 10.0.0.2:80")
+    );
+    assert_eq!(
+        nft_rule!(ip nat postrouting: oifname "wan0" masquerade random,persistent),
+        parsed("add rule ip nat postrouting oifname \"wan0\" masquerade random,persistent")
+    );
+    assert_eq!(
+        nft_rule!(inet filter input: tcp dport vmap { 22 : jump ssh, 80 : accept }),
+        parsed("add rule inet filter input tcp dport vmap { 22 : jump ssh, 80 : accept }")
+    );
+    assert_eq!(
+        nft_rule!(inet filter input: ct state established,related counter accept),
+        parsed("add rule inet filter input ct state established,related counter accept")
+    );
+    assert_eq!(
+        nft_rule!(inet filter input: reject with tcp reset),
+        parsed("add rule inet filter input reject with tcp reset")
+    );
+    assert_eq!(
+        nft_rule!(inet filter input: meta mark set 0x10 quota over 10 mbytes queue num 3 bypass),
+        parsed(
+            "add rule inet filter input meta mark set 0x10 quota over 10 mbytes queue num 3 bypass"
+        )
+    );
+    assert_eq!(
+        nft_rule!(inet filter input: add @seen { ip saddr } fib daddr type local),
+        parsed("add rule inet filter input add @seen { ip saddr } fib daddr type local")
+    );
+}
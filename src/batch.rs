@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use crate::schema::{NfCmd, NfListObject, NfObject, Nftables};
+use crate::schema::{NfCmd, NfListObject, NfObject, Nftables, ResetObject, Table, TableFlag};
 
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 /// Batch manages nftables objects and is used to prepare an nftables payload.
@@ -25,11 +25,40 @@ impl<'a> Batch<'a> {
         self.data.push(NfObject::CmdObject(NfCmd::Add(obj)))
     }
 
+    /// Adds table with `add` command and the [owner flag](TableFlag::Owner) to Batch.
+    ///
+    /// The kernel removes an owned table with all its contents once the
+    /// netlink socket that created it is closed, e.g. when the process
+    /// crashes. The table must thus be created through a socket that stays
+    /// open, such as a long-lived `libnftables` context;
+    /// a table created by the `nft` executable is removed when it exits.
+    pub fn add_owned_table(&mut self, mut table: Table<'a>) {
+        table
+            .flags
+            .get_or_insert_with(Default::default)
+            .insert(TableFlag::Owner);
+        self.add(NfListObject::Table(table))
+    }
+
     /// Adds object with `delete` command to Batch.
     pub fn delete(&mut self, obj: NfListObject<'a>) {
         self.data.push(NfObject::CmdObject(NfCmd::Delete(obj)))
     }
 
+    /// Adds object with `destroy` command to Batch.
+    ///
+    /// Unlike [delete](Batch::delete), this succeeds if the object does not
+    /// exist, so a teardown does not need to list the ruleset first.
+    /// Requires nftables 1.0.8 or later.
+    pub fn destroy(&mut self, obj: NfListObject<'a>) {
+        self.data.push(NfObject::CmdObject(NfCmd::Destroy(obj)))
+    }
+
+    /// Adds object with `reset` command to Batch.
+    pub fn reset(&mut self, obj: ResetObject<'a>) {
+        self.data.push(NfObject::CmdObject(NfCmd::Reset(obj)))
+    }
+
     /// Adds a command to Batch.
     pub fn add_cmd(&mut self, cmd: NfCmd<'a>) {
         self.data.push(NfObject::CmdObject(cmd))
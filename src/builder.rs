@@ -0,0 +1,561 @@
+use std::{borrow::Cow, collections::HashSet};
+
+use thiserror::Error;
+
+use crate::{
+    expr::{Expression, Meta, MetaKey, NamedExpression, Payload, PayloadField, CT},
+    schema::{Chain, ChainFlag, Rule, Set, SetFlag, SetPolicy, SetType, SetTypeValue},
+    stmt::{Counter, JumpTarget, Log, Match, Operator, Statement, NAT},
+    types::{NfChainPolicy, NfChainType, NfFamily, NfHook},
+};
+
+/// Error returned when a builder lacks fields nftables requires.
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum BuildError {
+    #[error("{object} `{name}` is missing its {field}")]
+    MissingField {
+        object: &'static str,
+        name: String,
+        field: &'static str,
+    },
+    #[error("rule in chain `{chain}` has no statements")]
+    EmptyRule { chain: String },
+    #[error("set `{name}` holds ranges or prefixes but lacks the interval flag")]
+    MissingIntervalFlag { name: String },
+}
+
+fn require(
+    object: &'static str,
+    name: &str,
+    field: &'static str,
+    present: bool,
+) -> Result<(), BuildError> {
+    if present {
+        Ok(())
+    } else {
+        Err(BuildError::MissingField {
+            object,
+            name: name.to_string(),
+            field,
+        })
+    }
+}
+
+fn payload<'a>(protocol: &'static str, field: &'static str) -> Expression<'a> {
+    Expression::Named(NamedExpression::Payload(Payload::PayloadField(
+        PayloadField {
+            protocol: protocol.into(),
+            field: field.into(),
+        },
+    )))
+}
+
+fn meta<'a>(key: MetaKey) -> Expression<'a> {
+    Expression::Named(NamedExpression::Meta(Meta { key }))
+}
+
+impl<'a> Rule<'a> {
+    /// Returns a builder for a rule appended to the given chain.
+    pub fn builder(
+        family: NfFamily,
+        table: impl Into<Cow<'a, str>>,
+        chain: impl Into<Cow<'a, str>>,
+    ) -> RuleBuilder<'a> {
+        RuleBuilder::new(family, table, chain)
+    }
+}
+
+/// Builds a [rule](Rule) statement by statement.
+///
+/// Matches compare with `==` unless stated otherwise, verdicts and other
+/// statements are appended in call order.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RuleBuilder<'a> {
+    rule: Rule<'a>,
+    expr: Vec<Statement<'a>>,
+}
+
+impl<'a> RuleBuilder<'a> {
+    /// Creates a builder for a rule in the given chain.
+    pub fn new(
+        family: NfFamily,
+        table: impl Into<Cow<'a, str>>,
+        chain: impl Into<Cow<'a, str>>,
+    ) -> Self {
+        RuleBuilder {
+            rule: Rule {
+                family,
+                table: table.into(),
+                chain: chain.into(),
+                ..Rule::default()
+            },
+            expr: Vec::new(),
+        }
+    }
+
+    /// Appends an arbitrary statement.
+    pub fn statement(mut self, statement: Statement<'a>) -> Self {
+        self.expr.push(statement);
+        self
+    }
+
+    /// Appends a match of `left` against `right` using `op`.
+    pub fn match_op(
+        self,
+        left: Expression<'a>,
+        op: Operator,
+        right: impl Into<Expression<'a>>,
+    ) -> Self {
+        self.statement(Statement::Match(Match {
+            left,
+            right: right.into(),
+            op,
+        }))
+    }
+
+    /// Appends an equality match of `left` against `right`.
+    pub fn match_eq(self, left: Expression<'a>, right: impl Into<Expression<'a>>) -> Self {
+        self.match_op(left, Operator::EQ, right)
+    }
+
+    /// Matches the IPv4 source address or prefix (`ip saddr`).
+    pub fn match_ip_saddr(self, addr: impl Into<Expression<'a>>) -> Self {
+        self.match_eq(payload("ip", "saddr"), addr)
+    }
+
+    /// Matches the IPv4 destination address or prefix (`ip daddr`).
+    pub fn match_ip_daddr(self, addr: impl Into<Expression<'a>>) -> Self {
+        self.match_eq(payload("ip", "daddr"), addr)
+    }
+
+    /// Matches the IPv6 source address or prefix (`ip6 saddr`).
+    pub fn match_ip6_saddr(self, addr: impl Into<Expression<'a>>) -> Self {
+        self.match_eq(payload("ip6", "saddr"), addr)
+    }
+
+    /// Matches the IPv6 destination address or prefix (`ip6 daddr`).
+    pub fn match_ip6_daddr(self, addr: impl Into<Expression<'a>>) -> Self {
+        self.match_eq(payload("ip6", "daddr"), addr)
+    }
+
+    /// Matches the TCP source port, port range or set (`tcp sport`).
+    pub fn match_tcp_sport(self, port: impl Into<Expression<'a>>) -> Self {
+        self.match_eq(payload("tcp", "sport"), port)
+    }
+
+    /// Matches the TCP destination port, port range or set (`tcp dport`).
+    pub fn match_tcp_dport(self, port: impl Into<Expression<'a>>) -> Self {
+        self.match_eq(payload("tcp", "dport"), port)
+    }
+
+    /// Matches the UDP source port, port range or set (`udp sport`).
+    pub fn match_udp_sport(self, port: impl Into<Expression<'a>>) -> Self {
+        self.match_eq(payload("udp", "sport"), port)
+    }
+
+    /// Matches the UDP destination port, port range or set (`udp dport`).
+    pub fn match_udp_dport(self, port: impl Into<Expression<'a>>) -> Self {
+        self.match_eq(payload("udp", "dport"), port)
+    }
+
+    /// Matches the layer 4 protocol, e.g. `"tcp"` (`meta l4proto`).
+    pub fn match_l4proto(self, proto: impl Into<Expression<'a>>) -> Self {
+        self.match_eq(meta(MetaKey::L4proto), proto)
+    }
+
+    /// Matches the input interface name (`iifname`).
+    pub fn match_iifname(self, name: impl Into<Expression<'a>>) -> Self {
+        self.match_eq(meta(MetaKey::Iifname), name)
+    }
+
+    /// Matches the output interface name (`oifname`).
+    pub fn match_oifname(self, name: impl Into<Expression<'a>>) -> Self {
+        self.match_eq(meta(MetaKey::Oifname), name)
+    }
+
+    /// Matches any of the given conntrack states (`ct state`).
+    pub fn match_ct_state<S>(self, states: impl IntoIterator<Item = S>) -> Self
+    where
+        S: Into<Cow<'a, str>>,
+    {
+        let mut states: Vec<Expression<'a>> = states
+            .into_iter()
+            .map(|state| Expression::String(state.into()))
+            .collect();
+        let right = if states.len() == 1 {
+            states.remove(0)
+        } else {
+            Expression::List(states)
+        };
+        let left = Expression::Named(NamedExpression::CT(CT {
+            key: "state".into(),
+            family: None,
+            dir: None,
+        }));
+        self.match_op(left, Operator::IN, right)
+    }
+
+    /// Appends an anonymous counter.
+    pub fn counter(self) -> Self {
+        self.statement(Statement::Counter(Counter::Anonymous(None)))
+    }
+
+    /// Appends a log statement without options.
+    pub fn log(self) -> Self {
+        self.statement(Statement::Log(None))
+    }
+
+    /// Appends a log statement with the given prefix.
+    pub fn log_prefix(self, prefix: impl Into<Cow<'a, str>>) -> Self {
+        self.statement(Statement::Log(Some(Log {
+            prefix: Some(prefix.into()),
+            ..Log::new(None)
+        })))
+    }
+
+    /// Appends source NAT to the given address.
+    pub fn snat(self, addr: impl Into<Expression<'a>>) -> Self {
+        self.statement(Statement::SNAT(Some(NAT {
+            addr: Some(addr.into()),
+            family: None,
+            port: None,
+            flags: None,
+        })))
+    }
+
+    /// Appends destination NAT to the given address.
+    pub fn dnat(self, addr: impl Into<Expression<'a>>) -> Self {
+        self.statement(Statement::DNAT(Some(NAT {
+            addr: Some(addr.into()),
+            family: None,
+            port: None,
+            flags: None,
+        })))
+    }
+
+    /// Appends masquerading.
+    pub fn masquerade(self) -> Self {
+        self.statement(Statement::Masquerade(None))
+    }
+
+    /// Appends the `accept` verdict.
+    pub fn accept(self) -> Self {
+        self.statement(Statement::Accept(None))
+    }
+
+    /// Appends the `drop` verdict.
+    pub fn drop(self) -> Self {
+        self.statement(Statement::Drop(None))
+    }
+
+    /// Appends a `reject` with the default reply.
+    pub fn reject(self) -> Self {
+        self.statement(Statement::Reject(None))
+    }
+
+    /// Appends the `return` verdict.
+    pub fn ret(self) -> Self {
+        self.statement(Statement::Return(None))
+    }
+
+    /// Appends a `jump` to the given chain.
+    pub fn jump(self, target: impl Into<Cow<'a, str>>) -> Self {
+        self.statement(Statement::Jump(JumpTarget {
+            target: target.into(),
+        }))
+    }
+
+    /// Appends a `goto` to the given chain.
+    pub fn goto(self, target: impl Into<Cow<'a, str>>) -> Self {
+        self.statement(Statement::Goto(JumpTarget {
+            target: target.into(),
+        }))
+    }
+
+    /// Sets the rule comment.
+    pub fn comment(mut self, comment: impl Into<Cow<'a, str>>) -> Self {
+        self.rule.comment = Some(comment.into());
+        self
+    }
+
+    /// Sets the handle of the rule to place this rule after (or replace).
+    pub fn handle(mut self, handle: u32) -> Self {
+        self.rule.handle = Some(handle);
+        self
+    }
+
+    /// Sets the position of the rule within its chain.
+    pub fn index(mut self, index: u32) -> Self {
+        self.rule.index = Some(index);
+        self
+    }
+
+    /// Returns the rule after checking that it names its table and chain and
+    /// has at least one statement.
+    pub fn build(self) -> Result<Rule<'a>, BuildError> {
+        let RuleBuilder { mut rule, expr } = self;
+        require("rule", &rule.chain, "table", !rule.table.is_empty())?;
+        require("rule", &rule.table, "chain", !rule.chain.is_empty())?;
+        if expr.is_empty() {
+            return Err(BuildError::EmptyRule {
+                chain: rule.chain.into_owned(),
+            });
+        }
+        rule.expr = expr.into();
+        Ok(rule)
+    }
+}
+
+impl<'a> Chain<'a> {
+    /// Returns a builder for a chain in the given table.
+    pub fn builder(
+        family: NfFamily,
+        table: impl Into<Cow<'a, str>>,
+        name: impl Into<Cow<'a, str>>,
+    ) -> ChainBuilder<'a> {
+        ChainBuilder::new(family, table, name)
+    }
+}
+
+/// Builds a regular or base [chain](Chain).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ChainBuilder<'a> {
+    chain: Chain<'a>,
+}
+
+impl<'a> ChainBuilder<'a> {
+    /// Creates a builder for a regular chain in the given table.
+    pub fn new(
+        family: NfFamily,
+        table: impl Into<Cow<'a, str>>,
+        name: impl Into<Cow<'a, str>>,
+    ) -> Self {
+        ChainBuilder {
+            chain: Chain {
+                family,
+                table: table.into(),
+                name: name.into(),
+                ..Chain::default()
+            },
+        }
+    }
+
+    /// Turns the chain into a base chain of the given type, attached to
+    /// `hook` with priority `prio`.
+    pub fn base(self, chain_type: NfChainType, hook: NfHook, prio: i32) -> Self {
+        self.chain_type(chain_type).hook(hook).priority(prio)
+    }
+
+    /// Sets the chain type.
+    pub fn chain_type(mut self, chain_type: NfChainType) -> Self {
+        self.chain._type = Some(chain_type);
+        self
+    }
+
+    /// Sets the hook the chain is attached to.
+    pub fn hook(mut self, hook: NfHook) -> Self {
+        self.chain.hook = Some(hook);
+        self
+    }
+
+    /// Sets the chain priority.
+    pub fn priority(mut self, prio: i32) -> Self {
+        self.chain.prio = Some(prio);
+        self
+    }
+
+    /// Sets the policy of the base chain.
+    pub fn policy(mut self, policy: NfChainPolicy) -> Self {
+        self.chain.policy = Some(policy);
+        self
+    }
+
+    /// Adds a device of a netdev base chain.
+    pub fn dev(mut self, dev: impl Into<Cow<'a, str>>) -> Self {
+        self.chain
+            .dev
+            .get_or_insert_with(Default::default)
+            .to_mut()
+            .push(dev.into());
+        self
+    }
+
+    /// Adds a flag.
+    pub fn flag(mut self, flag: ChainFlag) -> Self {
+        self.chain
+            .flags
+            .get_or_insert_with(HashSet::new)
+            .insert(flag);
+        self
+    }
+
+    /// Sets the chain comment.
+    pub fn comment(mut self, comment: impl Into<Cow<'a, str>>) -> Self {
+        self.chain.comment = Some(comment.into());
+        self
+    }
+
+    /// Returns the chain after checking that base chain settings are
+    /// complete: a hook needs a type and priority, and type, priority, policy,
+    /// devices and flags are only valid together with a hook.
+    pub fn build(self) -> Result<Chain<'a>, BuildError> {
+        let chain = self.chain;
+        let name = &chain.name;
+        require("chain", name, "table", !chain.table.is_empty())?;
+        require("chain", name, "name", !name.is_empty())?;
+        let base = chain._type.is_some()
+            || chain.prio.is_some()
+            || chain.policy.is_some()
+            || chain.dev.is_some()
+            || chain.flags.is_some();
+        let Some(hook) = chain.hook else {
+            require("chain", name, "hook", !base)?;
+            return Ok(chain);
+        };
+        require("chain", name, "type", chain._type.is_some())?;
+        require("chain", name, "priority", chain.prio.is_some())?;
+        if chain.family == NfFamily::NetDev && matches!(hook, NfHook::Ingress | NfHook::Egress) {
+            require("chain", name, "device", chain.dev.is_some())?;
+        }
+        Ok(chain)
+    }
+}
+
+impl<'a> Set<'a> {
+    /// Returns a builder for a named set in the given table.
+    pub fn builder(
+        family: NfFamily,
+        table: impl Into<Cow<'a, str>>,
+        name: impl Into<Cow<'a, str>>,
+    ) -> SetBuilder<'a> {
+        SetBuilder::new(family, table, name)
+    }
+}
+
+/// Builds a named [set](Set).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SetBuilder<'a> {
+    set: Set<'a>,
+    set_type: Option<SetTypeValue<'a>>,
+    elem: Vec<Expression<'a>>,
+}
+
+impl<'a> SetBuilder<'a> {
+    /// Creates a builder for a set in the given table.
+    pub fn new(
+        family: NfFamily,
+        table: impl Into<Cow<'a, str>>,
+        name: impl Into<Cow<'a, str>>,
+    ) -> Self {
+        SetBuilder {
+            set: Set {
+                family,
+                table: table.into(),
+                name: name.into(),
+                ..Set::default()
+            },
+            set_type: None,
+            elem: Vec::new(),
+        }
+    }
+
+    /// Sets the datatype of the elements.
+    pub fn set_type(mut self, set_type: SetType) -> Self {
+        self.set_type = Some(SetTypeValue::Single(set_type));
+        self
+    }
+
+    /// Sets a concatenated datatype, e.g. `ipv4_addr . inet_service`.
+    pub fn concat_type(mut self, types: impl Into<Cow<'a, [SetType]>>) -> Self {
+        self.set_type = Some(SetTypeValue::Concatenated(types.into()));
+        self
+    }
+
+    /// Adds a flag.
+    pub fn flag(mut self, flag: SetFlag) -> Self {
+        self.set.flags.get_or_insert_with(HashSet::new).insert(flag);
+        self
+    }
+
+    /// Sets the set policy.
+    pub fn policy(mut self, policy: SetPolicy) -> Self {
+        self.set.policy = Some(policy);
+        self
+    }
+
+    /// Adds an initial element.
+    pub fn element(mut self, elem: impl Into<Expression<'a>>) -> Self {
+        self.elem.push(elem.into());
+        self
+    }
+
+    /// Adds several initial elements.
+    pub fn elements<E>(mut self, elems: impl IntoIterator<Item = E>) -> Self
+    where
+        E: Into<Expression<'a>>,
+    {
+        self.elem.extend(elems.into_iter().map(Into::into));
+        self
+    }
+
+    /// Sets the default element timeout in seconds.
+    pub fn timeout(mut self, timeout: u64) -> Self {
+        self.set.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the garbage collector interval in seconds.
+    pub fn gc_interval(mut self, gc_interval: u32) -> Self {
+        self.set.gc_interval = Some(gc_interval);
+        self
+    }
+
+    /// Sets the maximum number of elements.
+    pub fn size(mut self, size: u32) -> Self {
+        self.set.size = Some(size);
+        self
+    }
+
+    /// Sets the set comment.
+    pub fn comment(mut self, comment: impl Into<Cow<'a, str>>) -> Self {
+        self.set.comment = Some(comment.into());
+        self
+    }
+
+    /// Returns the set after checking that it has a datatype and that
+    /// interval elements are only used with the interval flag.
+    pub fn build(self) -> Result<Set<'a>, BuildError> {
+        let SetBuilder {
+            mut set,
+            set_type,
+            elem,
+        } = self;
+        require("set", &set.name, "table", !set.table.is_empty())?;
+        require("set", &set.name, "name", !set.name.is_empty())?;
+        let Some(set_type) = set_type else {
+            return Err(BuildError::MissingField {
+                object: "set",
+                name: set.name.into_owned(),
+                field: "type",
+            });
+        };
+        let interval = set
+            .flags
+            .as_ref()
+            .is_some_and(|flags| flags.contains(&SetFlag::Interval));
+        let ranges = elem.iter().any(|elem| {
+            matches!(
+                elem,
+                Expression::Range(_) | Expression::Named(NamedExpression::Prefix(_))
+            )
+        });
+        if ranges && !interval {
+            return Err(BuildError::MissingIntervalFlag {
+                name: set.name.into_owned(),
+            });
+        }
+        set.set_type = set_type;
+        set.elem = (!elem.is_empty()).then(|| elem.into());
+        Ok(set)
+    }
+}
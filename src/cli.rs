@@ -1,4 +1,4 @@
-use crate::schema::Nftables;
+use crate::{graph::CallGraph, schema::Nftables};
 use schemars::schema_for;
 use std::{env::args, fs, io::Read, process::exit};
 
@@ -18,7 +18,11 @@ pub fn handle_args(args: Vec<String>) {
             generate_json_schema(args.next().unwrap_or("./nftables.schema.json".to_string()));
             return;
         }
-        eprintln!("Unknown command: `{command}`. Try again with the schema command to generate a JSON Schema or call with stdin only.");
+        if command == "graph" {
+            print_call_graph(args.next());
+            return;
+        }
+        eprintln!("Unknown command: `{command}`. Try again with the schema command to generate a JSON Schema, the graph command to print the chain call graph or call with stdin only.");
         exit(1);
     }
     deserialize_stdin();
@@ -38,6 +42,36 @@ fn generate_json_schema(schema_dst_path: String) {
     println!("Wrote schema data to: {schema_dst_path}");
 }
 
+/// Prints the chain call graph of a nftables JSON document in the DOT
+/// language.
+///
+/// The document is read from the given file, or from the standard input.
+fn print_call_graph(src_path: Option<String>) {
+    let mut buffer = String::new();
+    let read = match &src_path {
+        Some(path) => fs::read_to_string(path).map(|s| buffer = s),
+        None => std::io::stdin().read_to_string(&mut buffer).map(|_| ()),
+    };
+    if let Err(err) = read {
+        eprintln!("Failed to read document: {err}");
+        exit(1);
+    }
+    let deserializer = &mut serde_json::Deserializer::from_str(&buffer);
+    let nftables: Nftables = match serde_path_to_error::deserialize(deserializer) {
+        Ok(nftables) => nftables,
+        Err(err) => {
+            eprintln!("Deserialization error: {err}");
+            exit(1);
+        }
+    };
+    let graph = CallGraph::new(&nftables);
+    print!("{}", graph.to_dot());
+    for cycle in graph.cycles() {
+        let chains: Vec<&str> = cycle.iter().map(|&c| graph.chains[c].name).collect();
+        eprintln!("Loop between chains: {}", chains.join(", "));
+    }
+}
+
 /// Deserializes nftables JSON from the standard input and prints the result.
 ///
 /// This is the default behavior when the executable is called without any
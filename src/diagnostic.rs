@@ -0,0 +1,210 @@
+use std::ops::Range;
+
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+/// Severity of a [diagnostic](NftDiagnostic).
+pub enum Severity {
+    /// The command was rejected.
+    Error,
+    /// The command was accepted, but nft reported a problem.
+    Warning,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+/// Stage in which nft detected the problem.
+pub enum DiagnosticKind {
+    /// The kernel rejected a command (`Could not process rule: ...`).
+    Netlink,
+    /// The input could not be parsed, e.g. invalid JSON or an unknown keyword.
+    Syntax,
+    /// Any other problem reported by nft.
+    Other,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+/// Error category derived from the `strerror(3)` text at the end of a message.
+pub enum ErrorCategory {
+    /// `ENOENT`: the referenced object (e.g. table or chain) does not exist.
+    NotFound,
+    /// `EEXIST`: the object already exists.
+    AlreadyExists,
+    /// `EBUSY`: the object is still in use, e.g. a chain that is jumped to.
+    Busy,
+    /// `EINVAL`: the kernel considers the command invalid.
+    InvalidArgument,
+    /// `EPERM` or `EACCES`: insufficient privileges.
+    PermissionDenied,
+    /// `EOPNOTSUPP`: the kernel does not support the requested feature.
+    NotSupported,
+    /// `ENOSPC`: a size limit (e.g. of a set) was reached.
+    NoSpace,
+    /// `ENOMEM`: the kernel ran out of memory.
+    OutOfMemory,
+    /// `EMLINK` or `ELOOP`: chain jumps are too deep or form a loop.
+    TooManyLinks,
+    /// `ERANGE`: a value is out of range.
+    OutOfRange,
+    /// `EAFNOSUPPORT`: the address family is not supported.
+    FamilyNotSupported,
+    /// Any other error text.
+    Other(String),
+}
+
+impl ErrorCategory {
+    fn from_strerror(text: &str) -> Option<ErrorCategory> {
+        let category = match text.trim() {
+            "No such file or directory" => ErrorCategory::NotFound,
+            "File exists" => ErrorCategory::AlreadyExists,
+            "Device or resource busy" => ErrorCategory::Busy,
+            "Invalid argument" => ErrorCategory::InvalidArgument,
+            "Operation not permitted" | "Permission denied" => ErrorCategory::PermissionDenied,
+            "Operation not supported" | "Not supported" => ErrorCategory::NotSupported,
+            "No space left on device" => ErrorCategory::NoSpace,
+            "Cannot allocate memory" => ErrorCategory::OutOfMemory,
+            "Too many links" | "Too many levels of symbolic links" => ErrorCategory::TooManyLinks,
+            "Numerical result out of range" => ErrorCategory::OutOfRange,
+            "Address family not supported by protocol" => ErrorCategory::FamilyNotSupported,
+            _ => return None,
+        };
+        Some(category)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+/// Input location prefixed to a message, e.g. `/dev/stdin:3:1-20`.
+pub struct Location {
+    /// Input name, e.g. `/dev/stdin` or a file path.
+    pub file: String,
+    /// Line number (1-based).
+    pub line: usize,
+    /// Column range within the line.
+    pub columns: Range<usize>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+/// A single error or warning printed by nft.
+pub struct NftDiagnostic {
+    /// Whether this is an error or a warning.
+    pub severity: Severity,
+    /// Stage in which the problem was detected.
+    pub kind: DiagnosticKind,
+    /// The message without the `Error: ` prefix.
+    pub message: String,
+    /// Error category, if the message ends with a known `strerror(3)` text.
+    pub category: Option<ErrorCategory>,
+    /// Input location, if printed by nft.
+    pub location: Option<Location>,
+    /// The offending input line, if printed by nft.
+    pub context: Option<String>,
+    /// Columns of [context](NftDiagnostic::context) marked by nft with `^` and `~`.
+    pub span: Option<Range<usize>>,
+    /// Index of the offending command in the submitted
+    /// [objects](crate::schema::Nftables::objects), if it could be determined.
+    pub command_index: Option<usize>,
+}
+
+/// Parse the error output of nft into diagnostics.
+///
+/// If the submitted `payload` is given, diagnostics whose context line is one
+/// of the submitted JSON commands are mapped back to its index.
+/// Lines that do not belong to any message are ignored.
+pub fn parse_diagnostics(stderr: &str, payload: Option<&str>) -> Vec<NftDiagnostic> {
+    let commands: Vec<Value> = payload
+        .and_then(|p| serde_json::from_str::<Value>(p).ok())
+        .and_then(|mut v| match v.get_mut("nftables").map(Value::take) {
+            Some(Value::Array(commands)) => Some(commands),
+            _ => None,
+        })
+        .unwrap_or_default();
+
+    let mut diagnostics: Vec<NftDiagnostic> = Vec::new();
+    for line in stderr.lines() {
+        if let Some(diagnostic) = parse_header(line) {
+            diagnostics.push(diagnostic);
+            continue;
+        }
+        let Some(current) = diagnostics.last_mut() else {
+            continue;
+        };
+        match (&current.context, &current.span) {
+            (None, _) if !line.trim().is_empty() => {
+                current.command_index = serde_json::from_str::<Value>(line)
+                    .ok()
+                    .and_then(|cmd| commands.iter().position(|c| *c == cmd));
+                current.context = Some(line.to_owned());
+            }
+            (Some(_), None) => current.span = parse_marker(line),
+            _ => {}
+        }
+    }
+    diagnostics
+}
+
+fn parse_header(line: &str) -> Option<NftDiagnostic> {
+    let (location, severity, message) = [
+        ("Error: ", Severity::Error),
+        ("Warning: ", Severity::Warning),
+    ]
+    .into_iter()
+    .find_map(|(prefix, severity)| {
+        let pos = line.find(prefix)?;
+        let location = match &line[..pos] {
+            "" => None,
+            loc => Some(loc.strip_suffix(": ")?),
+        };
+        Some((location, severity, &line[pos + prefix.len()..]))
+    })?;
+
+    let kind = if message.starts_with("Could not process rule") {
+        DiagnosticKind::Netlink
+    } else if message.contains("syntax error") || message.contains("parse JSON") {
+        DiagnosticKind::Syntax
+    } else {
+        DiagnosticKind::Other
+    };
+    let category = message.rsplit_once(": ").and_then(|(_, errno)| {
+        ErrorCategory::from_strerror(errno).or_else(|| {
+            (kind == DiagnosticKind::Netlink).then(|| ErrorCategory::Other(errno.into()))
+        })
+    });
+
+    Some(NftDiagnostic {
+        severity,
+        kind,
+        message: message.to_owned(),
+        category,
+        location: location.and_then(parse_location),
+        context: None,
+        span: None,
+        command_index: None,
+    })
+}
+
+/// Parse `file:line:first-last` (or `file:line:column`).
+fn parse_location(location: &str) -> Option<Location> {
+    let (rest, columns) = location.rsplit_once(':')?;
+    let (file, line) = rest.rsplit_once(':')?;
+    let columns = match columns.split_once('-') {
+        Some((first, last)) => first.parse().ok()?..last.parse::<usize>().ok()? + 1,
+        None => {
+            let column: usize = columns.parse().ok()?;
+            column..column + 1
+        }
+    };
+    Some(Location {
+        file: file.to_owned(),
+        line: line.parse().ok()?,
+        columns,
+    })
+}
+
+/// Parse a marker line like `      ^^^^^~~~~`.
+fn parse_marker(line: &str) -> Option<Range<usize>> {
+    let start = line.find(['^', '~'])?;
+    let end = line.rfind(['^', '~'])? + 1;
+    line[start..end]
+        .chars()
+        .all(|c| matches!(c, '^' | '~' | ' '))
+        .then_some(start..end)
+}
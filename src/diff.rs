@@ -0,0 +1,442 @@
+use std::{borrow::Cow, collections::HashMap, fmt};
+
+use serde::{Deserialize, Serialize};
+
+use crate::expr::Expression;
+use crate::schema::{Element, NfCmd, NfListObject, NfObject, Nftables, Rule};
+use crate::stmt::{Counter, QuotaOrQuotaRef, Statement};
+use crate::types::NfFamily;
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+/// A single difference between two [nftables documents](Nftables).
+///
+/// Set and map elements are reported as [element](NfListObject::Element)
+/// objects holding the added or removed elements.
+pub enum Change<'a> {
+    /// The object only exists in the new document.
+    Added(NfListObject<'a>),
+    /// The object only exists in the old document.
+    Removed(NfListObject<'a>),
+    /// The object exists in both documents with different properties.
+    Modified {
+        /// The object in the old document.
+        old: NfListObject<'a>,
+        /// The object in the new document.
+        new: NfListObject<'a>,
+    },
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+/// Changes between two [nftables documents](Nftables), as computed by [diff].
+///
+/// Renders as one line per change via [Display](fmt::Display): `+` for
+/// added, `-` for removed and `~` for modified objects.
+pub struct Diff<'a> {
+    /// The changes, removals first.
+    pub changes: Vec<Change<'a>>,
+}
+
+impl Diff<'_> {
+    /// Whether both documents are equivalent.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// Comment of a rule and its position among the rules with that comment,
+/// or its position among the rules without comment in its chain.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+enum RuleId<'s> {
+    Comment(&'s str, usize),
+    Index(usize),
+}
+
+/// Identity of a ruleset element, independent of its handle.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+struct Identity<'s> {
+    kind: &'static str,
+    family: NfFamily,
+    table: &'s str,
+    /// Name of the element, or chain of a rule.
+    name: &'s str,
+    rule: Option<RuleId<'s>>,
+}
+
+/// Ruleset elements of a document: plain elements and those of `add` and
+/// `create` commands.
+pub(crate) fn list_objects<'s, 'a>(
+    nftables: &'s Nftables<'a>,
+) -> impl Iterator<Item = &'s NfListObject<'a>> {
+    nftables.objects.iter().filter_map(|object| match object {
+        NfObject::ListObject(object)
+        | NfObject::CmdObject(NfCmd::Add(object))
+        | NfObject::CmdObject(NfCmd::Create(object)) => Some(object),
+        _ => None,
+    })
+}
+
+/// Kind, family, table and name of a ruleset element other than a rule.
+fn named_identity<'s>(object: &'s NfListObject) -> Option<Identity<'s>> {
+    let (kind, family, table, name): (_, _, &str, &str) = match object {
+        NfListObject::Table(o) => ("table", o.family, "", &o.name),
+        NfListObject::Chain(o) => ("chain", o.family, &o.table, &o.name),
+        NfListObject::Set(o) => ("set", o.family, &o.table, &o.name),
+        NfListObject::Map(o) => ("map", o.family, &o.table, &o.name),
+        NfListObject::FlowTable(o) => ("flowtable", o.family, &o.table, &o.name),
+        NfListObject::Counter(o) => ("counter", o.family, &o.table, &o.name),
+        NfListObject::Quota(o) => ("quota", o.family, &o.table, &o.name),
+        NfListObject::CTHelper(o) => ("ct helper", o.family, &o.table, &o.name),
+        NfListObject::Limit(o) => ("limit", o.family, &o.table, &o.name),
+        NfListObject::CTTimeout(o) => ("ct timeout", o.family, &o.table, &o.name),
+        NfListObject::CTExpectation(o) => ("ct expectation", o.family, &o.table, &o.name),
+        NfListObject::SynProxy(o) => ("synproxy", o.family, &o.table, &o.name),
+        NfListObject::Rule(_) | NfListObject::Element(_) | NfListObject::MetainfoObject(_) => {
+            return None
+        }
+    };
+    Some(Identity {
+        kind,
+        family,
+        table,
+        name,
+        rule: None,
+    })
+}
+
+/// Clears the handle and, for counters and quotas, the current state of a
+/// ruleset element. Inline set and map elements are cleared as well, as they
+/// are compared separately.
+pub(crate) fn normalize_object<'a>(object: &NfListObject<'a>) -> NfListObject<'a> {
+    let mut object = object.clone();
+    match &mut object {
+        NfListObject::Table(o) => o.handle = None,
+        NfListObject::Chain(o) => {
+            o.handle = None;
+            o.newname = None;
+        }
+        NfListObject::Rule(o) => *o = normalize_rule(o),
+        NfListObject::Set(o) => {
+            o.handle = None;
+            o.elem = None;
+        }
+        NfListObject::Map(o) => {
+            o.handle = None;
+            o.elem = None;
+        }
+        NfListObject::FlowTable(o) => o.handle = None,
+        NfListObject::Counter(o) => {
+            o.handle = None;
+            o.packets = None;
+            o.bytes = None;
+        }
+        NfListObject::Quota(o) => {
+            o.handle = None;
+            o.used = None;
+        }
+        NfListObject::CTHelper(o) => o.handle = None,
+        NfListObject::Limit(o) => o.handle = None,
+        NfListObject::CTTimeout(o) => o.handle = None,
+        NfListObject::CTExpectation(o) => o.handle = None,
+        NfListObject::SynProxy(o) => o.handle = None,
+        NfListObject::Element(_) | NfListObject::MetainfoObject(_) => {}
+    }
+    object
+}
+
+/// Clears the handle, index and the state of anonymous counters and quotas
+/// of a rule.
+pub(crate) fn normalize_rule<'a>(rule: &Rule<'a>) -> Rule<'a> {
+    let expr: Vec<Statement<'a>> = rule
+        .expr
+        .iter()
+        .map(|stmt| match stmt {
+            Statement::Counter(Counter::Anonymous(_)) => {
+                Statement::Counter(Counter::Anonymous(None))
+            }
+            Statement::Quota(QuotaOrQuotaRef::Quota(quota)) => {
+                let mut quota = quota.clone();
+                quota.used = None;
+                quota.used_unit = None;
+                Statement::Quota(QuotaOrQuotaRef::Quota(quota))
+            }
+            stmt => stmt.clone(),
+        })
+        .collect();
+    Rule {
+        expr: expr.into(),
+        handle: None,
+        index: None,
+        ..rule.clone()
+    }
+}
+
+/// Family, table and name of a set or map.
+type SetKey<'s> = (NfFamily, &'s str, &'s str);
+
+/// Ruleset elements of a document by identity, in document order, along
+/// with the elements of each set and map.
+struct Index<'s, 'a> {
+    objects: Vec<(Identity<'s>, &'s NfListObject<'a>)>,
+    positions: HashMap<Identity<'s>, usize>,
+    sets: Vec<SetKey<'s>>,
+    elements: HashMap<SetKey<'s>, Vec<&'s Expression<'a>>>,
+}
+
+impl<'s, 'a> Index<'s, 'a> {
+    fn new(nftables: &'s Nftables<'a>) -> Self {
+        let mut index = Index {
+            objects: Vec::new(),
+            positions: HashMap::new(),
+            sets: Vec::new(),
+            elements: HashMap::new(),
+        };
+        let mut rule_counts: HashMap<(NfFamily, &str, &str, Option<&str>), usize> = HashMap::new();
+        for object in list_objects(nftables) {
+            let identity = match object {
+                NfListObject::Rule(rule) => {
+                    let comment = rule.comment.as_deref();
+                    let count = rule_counts
+                        .entry((rule.family, &rule.table, &rule.chain, comment))
+                        .or_default();
+                    *count += 1;
+                    let id = match comment {
+                        Some(comment) => RuleId::Comment(comment, *count - 1),
+                        None => RuleId::Index(*count - 1),
+                    };
+                    Identity {
+                        kind: "rule",
+                        family: rule.family,
+                        table: &rule.table,
+                        name: &rule.chain,
+                        rule: Some(id),
+                    }
+                }
+                NfListObject::Element(o) => {
+                    index.add_elements((o.family, &o.table, &o.name), &o.elem);
+                    continue;
+                }
+                NfListObject::Set(o) => {
+                    let elem = o.elem.as_deref().unwrap_or_default();
+                    index.add_elements((o.family, &o.table, &o.name), elem);
+                    named_identity(object).unwrap()
+                }
+                NfListObject::Map(o) => {
+                    let elem = o.elem.as_deref().unwrap_or_default();
+                    index.add_elements((o.family, &o.table, &o.name), elem);
+                    named_identity(object).unwrap()
+                }
+                object => match named_identity(object) {
+                    Some(identity) => identity,
+                    None => continue,
+                },
+            };
+            index
+                .positions
+                .insert(identity.clone(), index.objects.len());
+            index.objects.push((identity, object));
+        }
+        index
+    }
+
+    fn add_elements(&mut self, set: SetKey<'s>, elements: &'s [Expression<'a>]) {
+        if !self.elements.contains_key(&set) {
+            self.sets.push(set);
+        }
+        self.elements.entry(set).or_default().extend(elements);
+    }
+
+    fn get(&self, identity: &Identity<'s>) -> Option<&'s NfListObject<'a>> {
+        self.positions.get(identity).map(|&i| self.objects[i].1)
+    }
+
+    /// Whether the document declares the given set or map.
+    fn has_set(&self, (family, table, name): SetKey<'s>) -> bool {
+        ["set", "map"].into_iter().any(|kind| {
+            self.positions.contains_key(&Identity {
+                kind,
+                family,
+                table,
+                name,
+                rule: None,
+            })
+        })
+    }
+
+    fn elements_of(&self, set: SetKey<'s>) -> &[&'s Expression<'a>] {
+        self.elements.get(&set).map_or(&[], Vec::as_slice)
+    }
+}
+
+fn element_change<'a>(
+    (family, table, name): SetKey,
+    elements: Vec<Expression<'a>>,
+    added: bool,
+) -> Change<'a> {
+    let element = NfListObject::Element(Element {
+        family,
+        table: Cow::Owned(table.to_owned()),
+        name: Cow::Owned(name.to_owned()),
+        elem: elements.into(),
+    });
+    match added {
+        true => Change::Added(element),
+        false => Change::Removed(element),
+    }
+}
+
+/// Compares two nftables documents semantically.
+///
+/// Ruleset elements are matched by family, table and name (for rules: chain
+/// and either comment or position in the chain). Handles, the state of
+/// counters and quotas, and the order of set elements are ignored.
+/// Elements are only reported for sets and maps that exist in both documents.
+pub fn diff<'a>(old: &Nftables<'a>, new: &Nftables<'a>) -> Diff<'a> {
+    let old = Index::new(old);
+    let new = Index::new(new);
+    let mut changes = Vec::new();
+
+    for (identity, object) in &old.objects {
+        if new.get(identity).is_none() {
+            changes.push(Change::Removed((*object).clone()));
+        }
+    }
+    for (identity, object) in &new.objects {
+        match old.get(identity) {
+            None => changes.push(Change::Added((*object).clone())),
+            Some(old_object) if normalize_object(old_object) != normalize_object(object) => changes
+                .push(Change::Modified {
+                    old: old_object.clone(),
+                    new: (*object).clone(),
+                }),
+            Some(_) => {}
+        }
+    }
+
+    let mut sets = new.sets.clone();
+    sets.extend(old.sets.iter().filter(|s| !new.elements.contains_key(s)));
+    for set in sets {
+        if !old.has_set(set) || !new.has_set(set) {
+            continue;
+        }
+        let (old_elements, new_elements) = (old.elements_of(set), new.elements_of(set));
+        let removed: Vec<Expression<'a>> = old_elements
+            .iter()
+            .filter(|e| !new_elements.contains(e))
+            .map(|e| (*e).clone())
+            .collect();
+        let added: Vec<Expression<'a>> = new_elements
+            .iter()
+            .filter(|e| !old_elements.contains(e))
+            .map(|e| (*e).clone())
+            .collect();
+        if !removed.is_empty() {
+            changes.push(element_change(set, removed, false));
+        }
+        if !added.is_empty() {
+            changes.push(element_change(set, added, true));
+        }
+    }
+    // Removed elements of existing sets are only known now; keep the
+    // documented order. The sort is stable.
+    changes.sort_by_key(|change| !matches!(change, Change::Removed(_)));
+
+    Diff { changes }
+}
+
+/// Short description of a ruleset element, e.g. `chain inet filter input`.
+fn describe(object: &NfListObject) -> String {
+    match object {
+        NfListObject::Table(o) => format!("table {} {}", family(o.family), o.name),
+        NfListObject::Rule(o) => {
+            let comment = match &o.comment {
+                Some(comment) => format!(" comment {comment:?}"),
+                None => String::new(),
+            };
+            format!(
+                "rule {} {} {}{comment}: {}",
+                family(o.family),
+                o.table,
+                o.chain,
+                json(&normalize_rule(o).expr)
+            )
+        }
+        NfListObject::Element(o) => format!(
+            "element {} {} {} {{ {} }}",
+            family(o.family),
+            o.table,
+            o.name,
+            o.elem.iter().map(json).collect::<Vec<_>>().join(", ")
+        ),
+        NfListObject::MetainfoObject(_) => "metainfo".to_string(),
+        object => {
+            let identity = named_identity(object).expect("named ruleset element");
+            format!(
+                "{} {} {} {}",
+                identity.kind,
+                family(identity.family),
+                identity.table,
+                identity.name
+            )
+        }
+    }
+}
+
+fn family(family: NfFamily) -> String {
+    json(&family).trim_matches('"').to_string()
+}
+
+/// The normalized properties of a ruleset element.
+fn properties(object: &NfListObject) -> serde_json::Map<String, serde_json::Value> {
+    match serde_json::to_value(normalize_object(object)) {
+        Ok(serde_json::Value::Object(map)) => match map.into_iter().next() {
+            Some((_, serde_json::Value::Object(properties))) => properties,
+            _ => serde_json::Map::new(),
+        },
+        _ => serde_json::Map::new(),
+    }
+}
+
+fn json<T: Serialize + ?Sized>(value: &T) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+impl fmt::Display for Change<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added(object) => write!(f, "+ {}", describe(object)),
+            Change::Removed(object) => write!(f, "- {}", describe(object)),
+            Change::Modified { old, new } => match (old, new) {
+                (NfListObject::Rule(_), NfListObject::Rule(_)) => {
+                    write!(f, "~ {}\n    was: {}", describe(new), describe(old))
+                }
+                _ => {
+                    write!(f, "~ {}", describe(new))?;
+                    let (old, new) = (properties(old), properties(new));
+                    let null = serde_json::Value::Null;
+                    let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+                    keys.sort();
+                    keys.dedup();
+                    for key in keys {
+                        let (was, now) =
+                            (old.get(key).unwrap_or(&null), new.get(key).unwrap_or(&null));
+                        if was != now {
+                            write!(f, "\n    {key}: {was} -> {now}")?;
+                        }
+                    }
+                    Ok(())
+                }
+            },
+        }
+    }
+}
+
+impl fmt::Display for Diff<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{change}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,708 @@
+use std::{cmp::Ordering, collections::HashMap, net::IpAddr};
+
+use thiserror::Error;
+
+use crate::expr::{
+    Expression, Meta, MetaKey, NamedExpression, Payload, PayloadField, SetItem, Verdict, CT,
+};
+use crate::graph::CallGraph;
+use crate::schema::{Chain, NfCmd, NfListObject, NfObject, Nftables, Rule};
+use crate::stmt::{JumpTarget, Match, Operator, Statement};
+use crate::types::{NfChainPolicy, NfFamily, NfHook};
+
+/// Maximum number of nested jumps, as enforced by the kernel.
+const JUMP_STACK_SIZE: usize = 16;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+/// Conntrack state of a [Packet] (`ct state`).
+pub enum CtState {
+    New,
+    Established,
+    Related,
+    Invalid,
+    Untracked,
+}
+
+impl CtState {
+    fn name(self) -> &'static str {
+        match self {
+            CtState::New => "new",
+            CtState::Established => "established",
+            CtState::Related => "related",
+            CtState::Invalid => "invalid",
+            CtState::Untracked => "untracked",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// A synthetic packet to [evaluate] a ruleset against.
+///
+/// Fields left `None` are unknown: matching a rule against them is an
+/// [error](EvalError::Unspecified), except for the interface names, which are
+/// empty where the hook has no such interface.
+pub struct Packet {
+    /// The hook the packet passes.
+    pub hook: NfHook,
+    /// The network protocol, [IP](NfFamily::IP) or [IP6](NfFamily::IP6).
+    ///
+    /// Chains of `inet` tables see both, chains of other tables only packets
+    /// of their own family.
+    pub family: NfFamily,
+    /// Input interface name (`iifname`).
+    pub iifname: Option<String>,
+    /// Output interface name (`oifname`).
+    pub oifname: Option<String>,
+    /// Source address.
+    pub saddr: Option<IpAddr>,
+    /// Destination address.
+    pub daddr: Option<IpAddr>,
+    /// Layer 4 protocol number (`meta l4proto`), e.g. 6 for TCP.
+    pub l4proto: Option<u8>,
+    /// Source port of TCP, UDP, SCTP, DCCP and UDP-Lite packets.
+    pub sport: Option<u16>,
+    /// Destination port of TCP, UDP, SCTP, DCCP and UDP-Lite packets.
+    pub dport: Option<u16>,
+    /// Conntrack state.
+    pub ct_state: Option<CtState>,
+    /// Packet mark (`meta mark`).
+    pub mark: u32,
+}
+
+impl Packet {
+    /// Creates a packet of which only the hook and family are known.
+    pub fn new(family: NfFamily, hook: NfHook) -> Self {
+        Packet {
+            hook,
+            family,
+            iifname: None,
+            oifname: None,
+            saddr: None,
+            daddr: None,
+            l4proto: None,
+            sport: None,
+            dport: None,
+            ct_state: None,
+            mark: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+/// What happens to a packet at its hook.
+pub enum Decision {
+    /// Every base chain accepted the packet.
+    Accept,
+    /// A `drop` verdict or chain policy dropped the packet.
+    Drop,
+    /// A `reject` statement rejected the packet.
+    Reject,
+    /// A `queue` statement passed the packet to userspace.
+    Queue,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// The result of [evaluate].
+pub struct Evaluation<'s, 'a> {
+    /// What happens to the packet.
+    pub decision: Decision,
+    /// The rules whose matches all held, in the order they were evaluated.
+    pub matched: Vec<&'s Rule<'a>>,
+}
+
+/// Error returned by [evaluate] instead of guessing.
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum EvalError {
+    /// A rule uses an expression or statement the evaluator does not model.
+    #[error("chain `{chain}`: cannot evaluate `{syntax}`")]
+    Unsupported { chain: String, syntax: String },
+    /// A rule matches a [Packet] field that is `None`.
+    #[error("chain `{chain}`: packet does not specify `{field}`")]
+    Unspecified { chain: String, field: &'static str },
+    /// A rule jumps to a chain the document does not declare.
+    #[error("chain `{chain}`: jump to unknown chain `{target}`")]
+    UnknownChain { chain: String, target: String },
+    /// A rule refers to a set or map the document does not declare.
+    #[error("chain `{chain}`: unknown set or map `{name}`")]
+    UnknownSet { chain: String, name: String },
+    /// Jumps nest deeper than the kernel allows.
+    #[error("chain `{chain}`: jumps nest deeper than {JUMP_STACK_SIZE} chains")]
+    TooDeep { chain: String },
+    /// Chains jump to each other in a loop, see [CallGraph::cycles].
+    #[error("loop between chains `{}`", chains.join("`, `"))]
+    Loop { chains: Vec<String> },
+}
+
+/// Family, table and name of a chain, set or map.
+type Key<'s> = (NfFamily, &'s str, &'s str);
+
+/// Evaluates how a ruleset treats a packet, without touching the system.
+///
+/// The base chains of the packet's hook run in priority order, as in the
+/// kernel: an `accept` ends a base chain, the next one still sees the packet,
+/// while `drop`, `reject` and `queue` end the evaluation. Rules match
+/// addresses, protocols and ports in the `ip`, `ip6`, `tcp`, `udp`, `sctp`,
+/// `dccp`, `udplite` and `th` headers, the meta keys `iifname`, `oifname`,
+/// `l4proto`, `nfproto`, `protocol` and `mark`, and `ct state`, against
+/// values, prefixes, ranges and anonymous or named sets. Jumps, gotos and
+/// verdict maps are followed. `counter` and `log` are skipped, `meta mark set`
+/// changes the mark and NAT statements accept the packet without rewriting
+/// it.
+///
+/// The document is read like [validate](crate::validate::validate) does:
+/// listed objects and `add`, `create` and `insert` commands declare chains,
+/// rules and elements; other commands are ignored. Anything else a rule uses
+/// on the way is reported as [EvalError::Unsupported].
+pub fn evaluate<'s, 'a>(
+    nftables: &'s Nftables<'a>,
+    packet: &Packet,
+) -> Result<Evaluation<'s, 'a>, EvalError> {
+    let graph = CallGraph::new(nftables);
+    if let Some(cycle) = graph.cycles().first() {
+        let chains = cycle.iter().map(|&c| graph.chains[c].name.to_string());
+        return Err(EvalError::Loop {
+            chains: chains.collect(),
+        });
+    }
+
+    let mut evaluator = Evaluator {
+        packet: packet.clone(),
+        chains: HashMap::new(),
+        rules: HashMap::new(),
+        elements: HashMap::new(),
+        chain: "",
+        matched: Vec::new(),
+    };
+    for object in nftables.objects.iter() {
+        let (object, insert) = match object {
+            NfObject::ListObject(object)
+            | NfObject::CmdObject(NfCmd::Add(object) | NfCmd::Create(object)) => (object, false),
+            NfObject::CmdObject(NfCmd::Insert(object)) => (object, true),
+            _ => continue,
+        };
+        evaluator.declare(object, insert);
+    }
+
+    let mut base: Vec<(usize, &Chain)> = evaluator
+        .chains
+        .values()
+        .copied()
+        .filter(|(_, chain)| chain.hook == Some(packet.hook) && sees(chain.family, packet.family))
+        .collect();
+    // Chains of equal priority run in document order.
+    base.sort_by_key(|&(order, chain)| (chain.prio.unwrap_or(0), order));
+
+    for (_, chain) in base {
+        let decision = match evaluator.run(chain)? {
+            Some(decision) => decision,
+            None if chain.policy == Some(NfChainPolicy::Drop) => Decision::Drop,
+            None => Decision::Accept,
+        };
+        if decision != Decision::Accept {
+            return Ok(Evaluation {
+                decision,
+                matched: evaluator.matched,
+            });
+        }
+    }
+    Ok(Evaluation {
+        decision: Decision::Accept,
+        matched: evaluator.matched,
+    })
+}
+
+/// Whether chains of a table family see packets of a network protocol.
+fn sees(table: NfFamily, packet: NfFamily) -> bool {
+    table == packet || (table == NfFamily::INet && matches!(packet, NfFamily::IP | NfFamily::IP6))
+}
+
+/// Numeric values of the symbolic constants nft accepts for protocols.
+fn symbol(name: &str) -> Option<u32> {
+    Some(match name {
+        "icmp" => 1,
+        "igmp" => 2,
+        "tcp" => 6,
+        "udp" => 17,
+        "dccp" => 33,
+        "gre" => 47,
+        "esp" => 50,
+        "ah" => 51,
+        "icmpv6" | "ipv6-icmp" => 58,
+        "sctp" => 132,
+        "udplite" => 136,
+        // meta nfproto
+        "ipv4" => 2,
+        "ipv6" => 10,
+        // meta protocol
+        "ip" => 0x0800,
+        "arp" => 0x0806,
+        "ip6" => 0x86dd,
+        _ => return name.parse().ok(),
+    })
+}
+
+/// Splits a map element into key and value.
+fn mapping<'e, 'a>(item: &'e Expression<'a>) -> Option<(&'e Expression<'a>, &'e Expression<'a>)> {
+    match item {
+        Expression::List(pair) if pair.len() == 2 => Some((&pair[0], &pair[1])),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A value taken from the packet.
+enum Value<'p> {
+    Number(u64),
+    Addr(IpAddr),
+    /// Interface names and conntrack states.
+    Name(&'p str),
+    Concat(Vec<Value<'p>>),
+}
+
+/// How evaluation continues after a rule.
+enum Flow<'s> {
+    /// With the next rule.
+    Next,
+    Decision(Decision),
+    /// With the rule after the last jump.
+    Return,
+    Jump(&'s str),
+    Goto(&'s str),
+}
+
+struct Evaluator<'s, 'a> {
+    packet: Packet,
+    /// Declared chains and the order of their first declaration.
+    chains: HashMap<Key<'s>, (usize, &'s Chain<'a>)>,
+    rules: HashMap<Key<'s>, Vec<&'s Rule<'a>>>,
+    /// Elements of named sets and maps.
+    elements: HashMap<Key<'s>, Vec<&'s Expression<'a>>>,
+    /// The chain of the rule being evaluated.
+    chain: &'s str,
+    matched: Vec<&'s Rule<'a>>,
+}
+
+impl<'s, 'a> Evaluator<'s, 'a> {
+    fn declare(&mut self, object: &'s NfListObject<'a>, insert: bool) {
+        let (key, elem) = match object {
+            NfListObject::Chain(chain) => {
+                let key = (chain.family, &*chain.table, &*chain.name);
+                let order = self.chains.len();
+                let declared = self.chains.entry(key).or_insert((order, chain));
+                if declared.1.hook.is_none() {
+                    declared.1 = chain;
+                }
+                return;
+            }
+            NfListObject::Rule(rule) => {
+                let rules = self
+                    .rules
+                    .entry((rule.family, &rule.table, &rule.chain))
+                    .or_default();
+                match insert {
+                    true => rules.insert(0, rule),
+                    false => rules.push(rule),
+                }
+                return;
+            }
+            NfListObject::Set(set) => ((set.family, &*set.table, &*set.name), &set.elem),
+            NfListObject::Map(map) => ((map.family, &*map.table, &*map.name), &map.elem),
+            NfListObject::Element(element) => {
+                let key = (element.family, &*element.table, &*element.name);
+                self.elements
+                    .entry(key)
+                    .or_default()
+                    .extend(element.elem.iter());
+                return;
+            }
+            _ => return,
+        };
+        let elements = self.elements.entry(key).or_default();
+        elements.extend(elem.iter().flat_map(|elem| elem.iter()));
+    }
+
+    fn unsupported(&self, syntax: impl ToString) -> EvalError {
+        EvalError::Unsupported {
+            chain: self.chain.to_string(),
+            syntax: syntax.to_string(),
+        }
+    }
+
+    fn unspecified(&self, field: &'static str) -> EvalError {
+        EvalError::Unspecified {
+            chain: self.chain.to_string(),
+            field,
+        }
+    }
+
+    /// Runs a base chain, returning its verdict or `None` if it reaches the
+    /// end and the policy applies.
+    fn run(&mut self, base: &'s Chain<'a>) -> Result<Option<Decision>, EvalError> {
+        let (family, table) = (base.family, &*base.table);
+        let mut stack: Vec<(&str, usize)> = Vec::new();
+        let (mut chain, mut position) = (&*base.name, 0);
+        loop {
+            self.chain = chain;
+            let rule = self
+                .rules
+                .get(&(family, table, chain))
+                .and_then(|rules| rules.get(position).copied());
+            position += 1;
+            let flow = match rule {
+                Some(rule) => self.rule(rule)?,
+                None => Flow::Return,
+            };
+            match flow {
+                Flow::Next => {}
+                Flow::Decision(decision) => return Ok(Some(decision)),
+                Flow::Return => match stack.pop() {
+                    Some(caller) => (chain, position) = caller,
+                    None => return Ok(None),
+                },
+                Flow::Jump(target) | Flow::Goto(target) => {
+                    if !self.chains.contains_key(&(family, table, target)) {
+                        return Err(EvalError::UnknownChain {
+                            chain: chain.to_string(),
+                            target: target.to_string(),
+                        });
+                    }
+                    if let Flow::Jump(_) = flow {
+                        if stack.len() == JUMP_STACK_SIZE {
+                            return Err(EvalError::TooDeep {
+                                chain: chain.to_string(),
+                            });
+                        }
+                        stack.push((chain, position));
+                    }
+                    (chain, position) = (target, 0);
+                }
+            }
+        }
+    }
+
+    fn rule(&mut self, rule: &'s Rule<'a>) -> Result<Flow<'s>, EvalError> {
+        for stmt in rule.expr.iter() {
+            let flow = match stmt {
+                Statement::Match(m) => match self.test(rule, m)? {
+                    true => continue,
+                    false => return Ok(Flow::Next),
+                },
+                Statement::Counter(_) | Statement::Log(_) => continue,
+                Statement::Mangle(mangle) => match (&mangle.key, &mangle.value) {
+                    (
+                        Expression::Named(NamedExpression::Meta(Meta { key: MetaKey::Mark })),
+                        Expression::Number(mark),
+                    ) => match u32::try_from(*mark) {
+                        Ok(mark) => {
+                            self.packet.mark = mark;
+                            continue;
+                        }
+                        Err(_) => return Err(self.unsupported(stmt)),
+                    },
+                    _ => return Err(self.unsupported(stmt)),
+                },
+                Statement::Accept(_)
+                | Statement::SNAT(_)
+                | Statement::DNAT(_)
+                | Statement::Masquerade(_)
+                | Statement::Redirect(_) => Flow::Decision(Decision::Accept),
+                Statement::Drop(_) => Flow::Decision(Decision::Drop),
+                Statement::Reject(_) => Flow::Decision(Decision::Reject),
+                Statement::Queue(_) => Flow::Decision(Decision::Queue),
+                Statement::Continue(_) => Flow::Next,
+                Statement::Return(_) => Flow::Return,
+                Statement::Jump(JumpTarget { target }) => Flow::Jump(target),
+                Statement::Goto(JumpTarget { target }) => Flow::Goto(target),
+                Statement::VerdictMap(vmap) => match self.lookup(rule, &vmap.key, &vmap.data)? {
+                    Some(flow) => flow,
+                    // No element matches: the rule does not match.
+                    None => return Ok(Flow::Next),
+                },
+                _ => return Err(self.unsupported(stmt)),
+            };
+            self.matched.push(rule);
+            return Ok(flow);
+        }
+        self.matched.push(rule);
+        Ok(Flow::Next)
+    }
+
+    fn test(&self, rule: &'s Rule<'a>, m: &'s Match<'a>) -> Result<bool, EvalError> {
+        // A match on a header the packet lacks fails, as nft adds a
+        // dependency on the protocol.
+        let Some(left) = self.value(&m.left)? else {
+            return Ok(false);
+        };
+        let ordering = |right| self.compare(&left, right);
+        Ok(match m.op {
+            Operator::EQ | Operator::IN => self.contains(rule, &left, &m.right)?,
+            Operator::NEQ => !self.contains(rule, &left, &m.right)?,
+            // `LT` and `GT` are serialized as `>` and `<`.
+            Operator::LT => ordering(&m.right)? == Ordering::Greater,
+            Operator::GT => ordering(&m.right)? == Ordering::Less,
+            Operator::LEQ => ordering(&m.right)? != Ordering::Greater,
+            Operator::GEQ => ordering(&m.right)? != Ordering::Less,
+            _ => return Err(self.unsupported(Statement::Match(m.clone()))),
+        })
+    }
+
+    /// Takes the value of a packet expression, or `None` if the packet lacks
+    /// the header.
+    fn value(&self, expr: &Expression) -> Result<Option<Value<'_>>, EvalError> {
+        let packet = &self.packet;
+        let value = match expr {
+            Expression::Named(NamedExpression::Payload(Payload::PayloadField(PayloadField {
+                protocol,
+                field,
+            }))) => match (&**protocol, &**field) {
+                ("ip", _) | ("ip6", _) if packet.family != symbol_family(protocol) => {
+                    return Ok(None)
+                }
+                ("ip", "saddr") | ("ip6", "saddr") => {
+                    Value::Addr(packet.saddr.ok_or_else(|| self.unspecified("saddr"))?)
+                }
+                ("ip", "daddr") | ("ip6", "daddr") => {
+                    Value::Addr(packet.daddr.ok_or_else(|| self.unspecified("daddr"))?)
+                }
+                ("ip", "protocol") | ("ip6", "nexthdr") => Value::Number(self.l4proto()?.into()),
+                ("tcp" | "udp" | "sctp" | "dccp" | "udplite" | "th", "sport" | "dport") => {
+                    let l4proto = self.l4proto()?.into();
+                    let expected = match &**protocol {
+                        "th" => [6, 17, 33, 132, 136].contains(&l4proto),
+                        protocol => symbol(protocol) == Some(l4proto),
+                    };
+                    if !expected {
+                        return Ok(None);
+                    }
+                    let port = match &**field {
+                        "sport" => packet.sport.ok_or_else(|| self.unspecified("sport"))?,
+                        _ => packet.dport.ok_or_else(|| self.unspecified("dport"))?,
+                    };
+                    Value::Number(port.into())
+                }
+                _ => return Err(self.unsupported(expr)),
+            },
+            Expression::Named(NamedExpression::Meta(Meta { key })) => match key {
+                MetaKey::Iifname => Value::Name(packet.iifname.as_deref().unwrap_or_default()),
+                MetaKey::Oifname => Value::Name(packet.oifname.as_deref().unwrap_or_default()),
+                MetaKey::L4proto => Value::Number(self.l4proto()?.into()),
+                MetaKey::Nfproto => Value::Number(match packet.family {
+                    NfFamily::IP => 2,
+                    _ => 10,
+                }),
+                MetaKey::Protocol => Value::Number(match packet.family {
+                    NfFamily::IP => 0x0800,
+                    _ => 0x86dd,
+                }),
+                MetaKey::Mark => Value::Number(packet.mark.into()),
+                _ => return Err(self.unsupported(expr)),
+            },
+            Expression::Named(NamedExpression::CT(CT {
+                key,
+                family: None,
+                dir: None,
+            })) if key == "state" => Value::Name(
+                packet
+                    .ct_state
+                    .ok_or_else(|| self.unspecified("ct state"))?
+                    .name(),
+            ),
+            Expression::Named(NamedExpression::Concat(exprs)) => {
+                let mut values = Vec::new();
+                for expr in exprs {
+                    match self.value(expr)? {
+                        Some(value) => values.push(value),
+                        None => return Ok(None),
+                    }
+                }
+                Value::Concat(values)
+            }
+            _ => return Err(self.unsupported(expr)),
+        };
+        Ok(Some(value))
+    }
+
+    fn l4proto(&self) -> Result<u8, EvalError> {
+        self.packet
+            .l4proto
+            .ok_or_else(|| self.unspecified("meta l4proto"))
+    }
+
+    /// Returns the elements of a named set or map in the rule's table.
+    fn elements(
+        &self,
+        rule: &'s Rule<'a>,
+        name: &'s str,
+    ) -> Result<&[&'s Expression<'a>], EvalError> {
+        match self.elements.get(&(rule.family, &*rule.table, name)) {
+            Some(elements) => Ok(elements),
+            None => Err(EvalError::UnknownSet {
+                chain: self.chain.to_string(),
+                name: name.to_string(),
+            }),
+        }
+    }
+
+    /// Whether a value equals the right hand side of a match, or is an
+    /// element of it.
+    fn contains(
+        &self,
+        rule: &'s Rule<'a>,
+        left: &Value,
+        right: &'s Expression<'a>,
+    ) -> Result<bool, EvalError> {
+        match right {
+            Expression::String(name) if name.starts_with('@') => {
+                for elem in self.elements(rule, &name[1..])? {
+                    if self.contains(rule, left, mapping(elem).map_or(elem, |(key, _)| key))? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+            Expression::List(items) => {
+                for item in items {
+                    if self.contains(rule, left, item)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+            Expression::Named(NamedExpression::Set(items)) => {
+                for item in items {
+                    let item = match item {
+                        SetItem::Element(item) => mapping(item).map_or(item, |(key, _)| key),
+                        SetItem::Mapping(key, _) | SetItem::MappingStatement(key, _) => key,
+                    };
+                    if self.contains(rule, left, item)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+            Expression::Named(NamedExpression::Elem(elem)) => self.contains(rule, left, &elem.val),
+            Expression::Range(range) => Ok(self.compare(left, &range.range[0])? != Ordering::Less
+                && self.compare(left, &range.range[1])? != Ordering::Greater),
+            Expression::Named(NamedExpression::Prefix(prefix)) => {
+                let (Value::Addr(addr), Expression::String(network)) = (left, &*prefix.addr) else {
+                    return Err(self.unsupported(right));
+                };
+                let Ok(network) = network.parse::<IpAddr>() else {
+                    return Err(self.unsupported(right));
+                };
+                Ok(in_prefix(*addr, network, prefix.len))
+            }
+            Expression::Named(NamedExpression::Concat(rights)) => {
+                let Value::Concat(lefts) = left else {
+                    return Err(self.unsupported(right));
+                };
+                if lefts.len() != rights.len() {
+                    return Ok(false);
+                }
+                for (left, right) in lefts.iter().zip(rights) {
+                    if !self.contains(rule, left, right)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            Expression::String(name) => match left {
+                // Interface names may end in a wildcard.
+                Value::Name(left) => Ok(match name.strip_suffix('*') {
+                    Some(prefix) => left.starts_with(prefix),
+                    None => left == name,
+                }),
+                _ => Ok(self.compare(left, right)? == Ordering::Equal),
+            },
+            _ => Ok(self.compare(left, right)? == Ordering::Equal),
+        }
+    }
+
+    /// Orders a value against a single value of the right hand side.
+    fn compare(&self, left: &Value, right: &Expression) -> Result<Ordering, EvalError> {
+        match (left, right) {
+            (Value::Number(left), Expression::Number(right)) => Ok(left.cmp(right)),
+            (Value::Number(left), Expression::String(right)) => match symbol(right) {
+                Some(right) => Ok(left.cmp(&right.into())),
+                None => Err(self.unsupported(right)),
+            },
+            (Value::Addr(left), Expression::String(right)) => match right.parse::<IpAddr>() {
+                Ok(right) => Ok(left.cmp(&right)),
+                Err(_) => Err(self.unsupported(right)),
+            },
+            (Value::Name(left), Expression::String(right)) => Ok((*left).cmp(right)),
+            _ => Err(self.unsupported(right)),
+        }
+    }
+
+    /// Looks up the verdict of a verdict map, or `None` if no element
+    /// matches.
+    fn lookup(
+        &self,
+        rule: &'s Rule<'a>,
+        key: &Expression,
+        data: &'s Expression<'a>,
+    ) -> Result<Option<Flow<'s>>, EvalError> {
+        let Some(key) = self.value(key)? else {
+            return Ok(None);
+        };
+        let elements: Vec<(&Expression, &Expression)> = match data {
+            Expression::String(name) if name.starts_with('@') => self
+                .elements(rule, &name[1..])?
+                .iter()
+                .filter_map(|elem| mapping(elem))
+                .collect(),
+            Expression::Named(NamedExpression::Set(items)) => items
+                .iter()
+                .filter_map(|item| match item {
+                    SetItem::Element(item) => mapping(item),
+                    SetItem::Mapping(key, value) => Some((key, value)),
+                    SetItem::MappingStatement(..) => None,
+                })
+                .collect(),
+            _ => return Err(self.unsupported(data)),
+        };
+        for (elem, verdict) in elements {
+            if !self.contains(rule, &key, elem)? {
+                continue;
+            }
+            return Ok(Some(match verdict {
+                Expression::Verdict(Verdict::Accept) => Flow::Decision(Decision::Accept),
+                Expression::Verdict(Verdict::Drop) => Flow::Decision(Decision::Drop),
+                Expression::Verdict(Verdict::Continue) => Flow::Next,
+                Expression::Verdict(Verdict::Return) => Flow::Return,
+                Expression::Verdict(Verdict::Jump(JumpTarget { target })) => Flow::Jump(target),
+                Expression::Verdict(Verdict::Goto(JumpTarget { target })) => Flow::Goto(target),
+                _ => return Err(self.unsupported(verdict)),
+            }));
+        }
+        Ok(None)
+    }
+}
+
+/// The network protocol of an `ip` or `ip6` payload expression.
+fn symbol_family(protocol: &str) -> NfFamily {
+    match protocol {
+        "ip" => NfFamily::IP,
+        _ => NfFamily::IP6,
+    }
+}
+
+/// Whether an address lies in the network `network/len`.
+fn in_prefix(addr: IpAddr, network: IpAddr, len: u32) -> bool {
+    let (addr, network, bits) = match (addr, network) {
+        (IpAddr::V4(addr), IpAddr::V4(network)) => (
+            u128::from(u32::from(addr)),
+            u128::from(u32::from(network)),
+            32,
+        ),
+        (IpAddr::V6(addr), IpAddr::V6(network)) => (u128::from(addr), u128::from(network), 128),
+        _ => return false,
+    };
+    let len = len.min(bits);
+    let mask = match len {
+        0 => 0,
+        len => u128::MAX << (128 - len) >> (128 - bits),
+    };
+    addr & mask == network & mask
+}
@@ -1,8 +1,16 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::{borrow::Cow, collections::HashSet};
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    ops::RangeInclusive,
+    str::FromStr,
+};
+use thiserror::Error;
 
 use crate::stmt::{Counter, JumpTarget, Statement};
+use crate::types::MacAddr;
 use crate::visitor::deserialize_flags;
 use strum_macros::EnumString;
 
@@ -20,7 +28,12 @@ pub enum Expression<'a> {
     ///   * `\*`: Construct a wildcard expression.
     String(Cow<'a, str>),
     /// An integer expression (*immediate expression*).
-    Number(u32),
+    Number(u64),
+    /// A negative integer expression (*immediate expression*), e.g. a
+    /// priority below zero.
+    ///
+    /// Non-negative integers are always deserialized as [Expression::Number].
+    SignedNumber(i64),
     /// A boolean expression (*immediate expression*).
     Boolean(bool),
     /// List expressions are constructed by plain arrays containing of an arbitrary number of expressions.
@@ -38,6 +51,309 @@ pub enum Expression<'a> {
     Verdict(Verdict<'a>),
 }
 
+impl<'a> From<&'a str> for Expression<'a> {
+    fn from(value: &'a str) -> Self {
+        Expression::String(value.into())
+    }
+}
+
+impl From<String> for Expression<'_> {
+    fn from(value: String) -> Self {
+        Expression::String(value.into())
+    }
+}
+
+impl<'a> From<Cow<'a, str>> for Expression<'a> {
+    fn from(value: Cow<'a, str>) -> Self {
+        Expression::String(value)
+    }
+}
+
+impl From<u32> for Expression<'_> {
+    fn from(value: u32) -> Self {
+        Expression::Number(value.into())
+    }
+}
+
+impl From<u64> for Expression<'_> {
+    fn from(value: u64) -> Self {
+        Expression::Number(value)
+    }
+}
+
+/// Converts non-negative values to [Expression::Number], like deserialization.
+impl From<i64> for Expression<'_> {
+    fn from(value: i64) -> Self {
+        match u64::try_from(value) {
+            Ok(value) => Expression::Number(value),
+            Err(_) => Expression::SignedNumber(value),
+        }
+    }
+}
+
+/// Converts non-negative values to [Expression::Number], like deserialization.
+///
+/// Unsuffixed integer literals such as `22` default to `i32`.
+impl From<i32> for Expression<'_> {
+    fn from(value: i32) -> Self {
+        i64::from(value).into()
+    }
+}
+
+impl From<bool> for Expression<'_> {
+    fn from(value: bool) -> Self {
+        Expression::Boolean(value)
+    }
+}
+
+/// Converts an inclusive port range such as `1024..=65535`.
+impl From<RangeInclusive<u16>> for Expression<'_> {
+    fn from(value: RangeInclusive<u16>) -> Self {
+        Expression::Range(Box::new(Range {
+            range: [
+                Expression::Number((*value.start()).into()),
+                Expression::Number((*value.end()).into()),
+            ],
+        }))
+    }
+}
+
+impl<'a> From<Prefix<'a>> for Expression<'a> {
+    fn from(value: Prefix<'a>) -> Self {
+        Expression::Named(NamedExpression::Prefix(value))
+    }
+}
+
+impl From<IpAddr> for Expression<'_> {
+    fn from(value: IpAddr) -> Self {
+        Expression::String(value.to_string().into())
+    }
+}
+
+impl From<Ipv4Addr> for Expression<'_> {
+    fn from(value: Ipv4Addr) -> Self {
+        Expression::String(value.to_string().into())
+    }
+}
+
+impl From<Ipv6Addr> for Expression<'_> {
+    fn from(value: Ipv6Addr) -> Self {
+        Expression::String(value.to_string().into())
+    }
+}
+
+/// Converts a socket address to the concatenation `addr . port`, as used by
+/// sets of type `ipv4_addr . inet_service`.
+impl From<SocketAddr> for Expression<'_> {
+    fn from(value: SocketAddr) -> Self {
+        Expression::Named(NamedExpression::Concat(vec![
+            value.ip().into(),
+            Expression::Number(value.port().into()),
+        ]))
+    }
+}
+
+impl From<MacAddr> for Expression<'_> {
+    fn from(value: MacAddr) -> Self {
+        Expression::String(value.to_string().into())
+    }
+}
+
+/// Converts a network to the prefix of its network address.
+#[cfg(feature = "ipnet")]
+impl From<ipnet::IpNet> for Expression<'_> {
+    fn from(value: ipnet::IpNet) -> Self {
+        Prefix::new(value.network(), value.prefix_len().into()).into()
+    }
+}
+
+#[cfg(feature = "ipnet")]
+impl From<ipnet::Ipv4Net> for Expression<'_> {
+    fn from(value: ipnet::Ipv4Net) -> Self {
+        ipnet::IpNet::from(value).into()
+    }
+}
+
+#[cfg(feature = "ipnet")]
+impl From<ipnet::Ipv6Net> for Expression<'_> {
+    fn from(value: ipnet::Ipv6Net) -> Self {
+        ipnet::IpNet::from(value).into()
+    }
+}
+
+/// Error returned when an [Expression] does not hold a value of the
+/// requested type.
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+#[error("expression is not {expected}")]
+pub struct TryFromExpressionError {
+    /// Description of the requested type.
+    pub expected: &'static str,
+}
+
+/// Parses the string of a string expression.
+fn parse_string<T: FromStr>(
+    value: &Expression<'_>,
+    expected: &'static str,
+) -> Result<T, TryFromExpressionError> {
+    match value {
+        Expression::String(s) => s.parse().map_err(|_| TryFromExpressionError { expected }),
+        _ => Err(TryFromExpressionError { expected }),
+    }
+}
+
+/// Returns the number of a number expression that fits a port.
+fn port(value: &Expression<'_>) -> Option<u16> {
+    match value {
+        Expression::Number(n) => u16::try_from(*n).ok(),
+        _ => None,
+    }
+}
+
+impl TryFrom<&Expression<'_>> for IpAddr {
+    type Error = TryFromExpressionError;
+
+    fn try_from(value: &Expression<'_>) -> Result<Self, Self::Error> {
+        parse_string(value, "an IP address")
+    }
+}
+
+impl TryFrom<&Expression<'_>> for Ipv4Addr {
+    type Error = TryFromExpressionError;
+
+    fn try_from(value: &Expression<'_>) -> Result<Self, Self::Error> {
+        parse_string(value, "an IPv4 address")
+    }
+}
+
+impl TryFrom<&Expression<'_>> for Ipv6Addr {
+    type Error = TryFromExpressionError;
+
+    fn try_from(value: &Expression<'_>) -> Result<Self, Self::Error> {
+        parse_string(value, "an IPv6 address")
+    }
+}
+
+/// Reads the concatenation `addr . port`.
+impl TryFrom<&Expression<'_>> for SocketAddr {
+    type Error = TryFromExpressionError;
+
+    fn try_from(value: &Expression<'_>) -> Result<Self, Self::Error> {
+        let expected = "an address and port concatenation";
+        let Expression::Named(NamedExpression::Concat(items)) = value else {
+            return Err(TryFromExpressionError { expected });
+        };
+        match &items[..] {
+            [addr, p] => {
+                let addr = parse_string(addr, expected)?;
+                let port = port(p).ok_or(TryFromExpressionError { expected })?;
+                Ok(SocketAddr::new(addr, port))
+            }
+            _ => Err(TryFromExpressionError { expected }),
+        }
+    }
+}
+
+impl TryFrom<&Expression<'_>> for RangeInclusive<u16> {
+    type Error = TryFromExpressionError;
+
+    fn try_from(value: &Expression<'_>) -> Result<Self, Self::Error> {
+        let expected = "a port range";
+        let Expression::Range(range) = value else {
+            return Err(TryFromExpressionError { expected });
+        };
+        match (port(&range.range[0]), port(&range.range[1])) {
+            (Some(start), Some(end)) => Ok(start..=end),
+            _ => Err(TryFromExpressionError { expected }),
+        }
+    }
+}
+
+impl TryFrom<&Expression<'_>> for MacAddr {
+    type Error = TryFromExpressionError;
+
+    fn try_from(value: &Expression<'_>) -> Result<Self, Self::Error> {
+        parse_string(value, "a MAC address")
+    }
+}
+
+/// Reads a prefix, or a single address as host network (nft lists
+/// full-length prefixes as plain addresses).
+#[cfg(feature = "ipnet")]
+impl TryFrom<&Expression<'_>> for ipnet::IpNet {
+    type Error = TryFromExpressionError;
+
+    fn try_from(value: &Expression<'_>) -> Result<Self, Self::Error> {
+        let expected = "an IP network";
+        match value {
+            Expression::Named(NamedExpression::Prefix(prefix)) => {
+                let addr = parse_string(&prefix.addr, expected)?;
+                u8::try_from(prefix.len)
+                    .ok()
+                    .and_then(|len| ipnet::IpNet::new(addr, len).ok())
+                    .ok_or(TryFromExpressionError { expected })
+            }
+            _ => Ok(parse_string::<IpAddr>(value, expected)?.into()),
+        }
+    }
+}
+
+#[cfg(feature = "ipnet")]
+impl TryFrom<&Expression<'_>> for ipnet::Ipv4Net {
+    type Error = TryFromExpressionError;
+
+    fn try_from(value: &Expression<'_>) -> Result<Self, Self::Error> {
+        match ipnet::IpNet::try_from(value) {
+            Ok(ipnet::IpNet::V4(net)) => Ok(net),
+            _ => Err(TryFromExpressionError {
+                expected: "an IPv4 network",
+            }),
+        }
+    }
+}
+
+#[cfg(feature = "ipnet")]
+impl TryFrom<&Expression<'_>> for ipnet::Ipv6Net {
+    type Error = TryFromExpressionError;
+
+    fn try_from(value: &Expression<'_>) -> Result<Self, Self::Error> {
+        match ipnet::IpNet::try_from(value) {
+            Ok(ipnet::IpNet::V6(net)) => Ok(net),
+            _ => Err(TryFromExpressionError {
+                expected: "an IPv6 network",
+            }),
+        }
+    }
+}
+
+/// Implements `TryFrom` for owned expressions by way of references.
+macro_rules! try_from_owned {
+    ($($(#[$attr:meta])* $ty:ty),* $(,)?) => {$(
+        $(#[$attr])*
+        impl TryFrom<Expression<'_>> for $ty {
+            type Error = TryFromExpressionError;
+
+            fn try_from(value: Expression<'_>) -> Result<Self, Self::Error> {
+                Self::try_from(&value)
+            }
+        }
+    )*};
+}
+
+try_from_owned!(
+    IpAddr,
+    Ipv4Addr,
+    Ipv6Addr,
+    SocketAddr,
+    RangeInclusive<u16>,
+    MacAddr,
+    #[cfg(feature = "ipnet")]
+    ipnet::IpNet,
+    #[cfg(feature = "ipnet")]
+    ipnet::Ipv4Net,
+    #[cfg(feature = "ipnet")]
+    ipnet::Ipv6Net,
+);
+
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 /// Wrapper for non-immediate [Expressions](Expression).
@@ -134,6 +450,16 @@ pub struct Prefix<'a> {
     pub len: u32,
 }
 
+impl<'a> Prefix<'a> {
+    /// Creates the prefix `addr/len`.
+    pub fn new(addr: impl Into<Expression<'a>>, len: u32) -> Self {
+        Prefix {
+            addr: Box::new(addr.into()),
+            len,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename = "range")]
 /// Construct a range of values.
@@ -720,9 +1046,9 @@ pub struct Elem<'a> {
     pub val: Box<Expression<'a>>,
     /// Timeout value for [sets](crate::schema::Set)/[maps](crate::schema::Map).
     /// with flag [timeout](crate::schema::SetFlag::Timeout)
-    pub timeout: Option<u32>,
+    pub timeout: Option<u64>,
     /// The time until given element expires, useful for ruleset replication only.
-    pub expires: Option<u32>,
+    pub expires: Option<u64>,
     /// Per element comment field.
     pub comment: Option<Cow<'a, str>>,
     /// Enable a [counter][crate::stmt::Counter] per element.
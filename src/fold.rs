@@ -0,0 +1,608 @@
+use std::borrow::Cow;
+
+use crate::expr::{BinaryOperation, Expression, NamedExpression, SetItem, Verdict};
+use crate::schema::{
+    CTExpectation, CTHelper, CTTimeout, Chain, Counter, Element, FlowTable, FlushObject, Limit,
+    ListObject, Map, MetainfoObject, NfCmd, NfListObject, NfObject, Nftables, Quota, ResetObject,
+    Rule, Set, SetTypeValue, SynProxy, Table,
+};
+use crate::stmt::{JumpTarget, Match, Statement, NAT};
+
+/// Rebuilds a document by value, in the style of `syn::fold`.
+///
+/// Each method folds one type of node. Its default implementation calls the
+/// function of the same name in this module, which folds the children of the
+/// node and puts it back together. Override the methods of the nodes of
+/// interest and call the function to keep folding their children.
+///
+/// ```
+/// use nftables::{
+///     fold::{self, Fold},
+///     parser::parse,
+///     stmt::JumpTarget,
+/// };
+///
+/// /// Renames the chain `old` to `new` in jumps and gotos.
+/// struct RenameTarget;
+///
+/// impl<'a> Fold<'a> for RenameTarget {
+///     fn fold_jump_target(&mut self, node: JumpTarget<'a>) -> JumpTarget<'a> {
+///         match &*node.target {
+///             "old" => JumpTarget { target: "new".into() },
+///             _ => fold::fold_jump_target(self, node),
+///         }
+///     }
+/// }
+///
+/// let ruleset = parse("add rule inet filter input tcp dport vmap { 22 : jump old }").unwrap();
+/// let ruleset = RenameTarget.fold_nftables(ruleset);
+/// assert_eq!(
+///     ruleset,
+///     parse("add rule inet filter input tcp dport vmap { 22 : jump new }").unwrap()
+/// );
+/// ```
+pub trait Fold<'a> {
+    fn fold_nftables(&mut self, node: Nftables<'a>) -> Nftables<'a> {
+        fold_nftables(self, node)
+    }
+
+    fn fold_object(&mut self, node: NfObject<'a>) -> NfObject<'a> {
+        fold_object(self, node)
+    }
+
+    fn fold_cmd(&mut self, node: NfCmd<'a>) -> NfCmd<'a> {
+        fold_cmd(self, node)
+    }
+
+    fn fold_list_object(&mut self, node: NfListObject<'a>) -> NfListObject<'a> {
+        fold_list_object(self, node)
+    }
+
+    fn fold_reset_object(&mut self, node: ResetObject<'a>) -> ResetObject<'a> {
+        fold_reset_object(self, node)
+    }
+
+    fn fold_flush_object(&mut self, node: FlushObject<'a>) -> FlushObject<'a> {
+        fold_flush_object(self, node)
+    }
+
+    fn fold_table(&mut self, node: Table<'a>) -> Table<'a> {
+        fold_table(self, node)
+    }
+
+    fn fold_chain(&mut self, node: Chain<'a>) -> Chain<'a> {
+        fold_chain(self, node)
+    }
+
+    fn fold_rule(&mut self, node: Rule<'a>) -> Rule<'a> {
+        fold_rule(self, node)
+    }
+
+    fn fold_set(&mut self, node: Set<'a>) -> Set<'a> {
+        fold_set(self, node)
+    }
+
+    fn fold_map(&mut self, node: Map<'a>) -> Map<'a> {
+        fold_map(self, node)
+    }
+
+    fn fold_element(&mut self, node: Element<'a>) -> Element<'a> {
+        fold_element(self, node)
+    }
+
+    fn fold_flowtable(&mut self, node: FlowTable<'a>) -> FlowTable<'a> {
+        fold_flowtable(self, node)
+    }
+
+    /// Folds a named counter object, not a [counter statement](Statement::Counter).
+    fn fold_counter(&mut self, node: Counter<'a>) -> Counter<'a> {
+        fold_counter(self, node)
+    }
+
+    /// Folds a named quota object, not a [quota statement](Statement::Quota).
+    fn fold_quota(&mut self, node: Quota<'a>) -> Quota<'a> {
+        fold_quota(self, node)
+    }
+
+    fn fold_ct_helper(&mut self, node: CTHelper<'a>) -> CTHelper<'a> {
+        fold_ct_helper(self, node)
+    }
+
+    /// Folds a named limit object, not a [limit statement](Statement::Limit).
+    fn fold_limit(&mut self, node: Limit<'a>) -> Limit<'a> {
+        fold_limit(self, node)
+    }
+
+    fn fold_metainfo(&mut self, node: MetainfoObject<'a>) -> MetainfoObject<'a> {
+        fold_metainfo(self, node)
+    }
+
+    fn fold_ct_timeout(&mut self, node: CTTimeout<'a>) -> CTTimeout<'a> {
+        fold_ct_timeout(self, node)
+    }
+
+    fn fold_ct_expectation(&mut self, node: CTExpectation<'a>) -> CTExpectation<'a> {
+        fold_ct_expectation(self, node)
+    }
+
+    /// Folds a named synproxy object, not a [synproxy statement](Statement::SynProxy).
+    fn fold_synproxy(&mut self, node: SynProxy<'a>) -> SynProxy<'a> {
+        fold_synproxy(self, node)
+    }
+
+    fn fold_statement(&mut self, node: Statement<'a>) -> Statement<'a> {
+        fold_statement(self, node)
+    }
+
+    fn fold_match(&mut self, node: Match<'a>) -> Match<'a> {
+        fold_match(self, node)
+    }
+
+    /// Folds the target of a `jump` or `goto` statement or verdict.
+    fn fold_jump_target(&mut self, node: JumpTarget<'a>) -> JumpTarget<'a> {
+        fold_jump_target(self, node)
+    }
+
+    fn fold_expression(&mut self, node: Expression<'a>) -> Expression<'a> {
+        fold_expression(self, node)
+    }
+
+    fn fold_named_expression(&mut self, node: NamedExpression<'a>) -> NamedExpression<'a> {
+        fold_named_expression(self, node)
+    }
+
+    fn fold_set_item(&mut self, node: SetItem<'a>) -> SetItem<'a> {
+        fold_set_item(self, node)
+    }
+
+    fn fold_binary_operation(&mut self, node: BinaryOperation<'a>) -> BinaryOperation<'a> {
+        fold_binary_operation(self, node)
+    }
+}
+
+/// Folds each item of a list, taking ownership of a borrowed list.
+fn fold_list<'a, T: Clone>(list: Cow<'a, [T]>, fold: impl FnMut(T) -> T) -> Cow<'a, [T]> {
+    Cow::Owned(list.into_owned().into_iter().map(fold).collect())
+}
+
+fn fold_expressions<'a, F>(f: &mut F, exprs: Vec<Expression<'a>>) -> Vec<Expression<'a>>
+where
+    F: Fold<'a> + ?Sized,
+{
+    exprs
+        .into_iter()
+        .map(|expr| f.fold_expression(expr))
+        .collect()
+}
+
+fn fold_boxed<'a, F>(f: &mut F, expr: &mut Box<Expression<'a>>)
+where
+    F: Fold<'a> + ?Sized,
+{
+    let folded = f.fold_expression(std::mem::replace(&mut **expr, Expression::Boolean(false)));
+    **expr = folded;
+}
+
+pub fn fold_nftables<'a, F>(f: &mut F, node: Nftables<'a>) -> Nftables<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    Nftables {
+        objects: fold_list(node.objects, |object| f.fold_object(object)),
+    }
+}
+
+pub fn fold_object<'a, F>(f: &mut F, node: NfObject<'a>) -> NfObject<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    match node {
+        NfObject::CmdObject(cmd) => NfObject::CmdObject(f.fold_cmd(cmd)),
+        NfObject::ListObject(object) => NfObject::ListObject(f.fold_list_object(object)),
+    }
+}
+
+pub fn fold_cmd<'a, F>(f: &mut F, node: NfCmd<'a>) -> NfCmd<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    match node {
+        NfCmd::Add(object) => NfCmd::Add(f.fold_list_object(object)),
+        NfCmd::Replace(rule) => NfCmd::Replace(f.fold_rule(rule)),
+        NfCmd::Create(object) => NfCmd::Create(f.fold_list_object(object)),
+        NfCmd::Insert(object) => NfCmd::Insert(f.fold_list_object(object)),
+        NfCmd::Delete(object) => NfCmd::Delete(f.fold_list_object(object)),
+        NfCmd::Destroy(object) => NfCmd::Destroy(f.fold_list_object(object)),
+        NfCmd::List(ListObject::Object(object)) => {
+            NfCmd::List(ListObject::Object(f.fold_list_object(object)))
+        }
+        NfCmd::List(object) => NfCmd::List(object),
+        NfCmd::Reset(object) => NfCmd::Reset(f.fold_reset_object(object)),
+        NfCmd::Flush(object) => NfCmd::Flush(f.fold_flush_object(object)),
+        NfCmd::Rename(chain) => NfCmd::Rename(f.fold_chain(chain)),
+    }
+}
+
+pub fn fold_list_object<'a, F>(f: &mut F, node: NfListObject<'a>) -> NfListObject<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    match node {
+        NfListObject::Table(table) => NfListObject::Table(f.fold_table(table)),
+        NfListObject::Chain(chain) => NfListObject::Chain(f.fold_chain(chain)),
+        NfListObject::Rule(rule) => NfListObject::Rule(f.fold_rule(rule)),
+        NfListObject::Set(set) => NfListObject::Set(Box::new(f.fold_set(*set))),
+        NfListObject::Map(map) => NfListObject::Map(Box::new(f.fold_map(*map))),
+        NfListObject::Element(element) => NfListObject::Element(f.fold_element(element)),
+        NfListObject::FlowTable(flowtable) => NfListObject::FlowTable(f.fold_flowtable(flowtable)),
+        NfListObject::Counter(counter) => NfListObject::Counter(f.fold_counter(counter)),
+        NfListObject::Quota(quota) => NfListObject::Quota(f.fold_quota(quota)),
+        NfListObject::CTHelper(helper) => NfListObject::CTHelper(f.fold_ct_helper(helper)),
+        NfListObject::Limit(limit) => NfListObject::Limit(f.fold_limit(limit)),
+        NfListObject::MetainfoObject(metainfo) => {
+            NfListObject::MetainfoObject(f.fold_metainfo(metainfo))
+        }
+        NfListObject::CTTimeout(timeout) => NfListObject::CTTimeout(f.fold_ct_timeout(timeout)),
+        NfListObject::CTExpectation(expectation) => {
+            NfListObject::CTExpectation(f.fold_ct_expectation(expectation))
+        }
+        NfListObject::SynProxy(synproxy) => NfListObject::SynProxy(f.fold_synproxy(synproxy)),
+    }
+}
+
+pub fn fold_reset_object<'a, F>(f: &mut F, node: ResetObject<'a>) -> ResetObject<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    match node {
+        ResetObject::Counter(counter) => ResetObject::Counter(f.fold_counter(counter)),
+        ResetObject::Counters(counters) => {
+            ResetObject::Counters(fold_list(counters, |counter| f.fold_counter(counter)))
+        }
+        ResetObject::Quota(quota) => ResetObject::Quota(f.fold_quota(quota)),
+        ResetObject::Quotas(quotas) => {
+            ResetObject::Quotas(fold_list(quotas, |quota| f.fold_quota(quota)))
+        }
+        ResetObject::Rule(rule) => ResetObject::Rule(f.fold_rule(rule)),
+        ResetObject::Rules(filter) => ResetObject::Rules(filter),
+        ResetObject::Set(set) => ResetObject::Set(Box::new(f.fold_set(*set))),
+        ResetObject::Map(map) => ResetObject::Map(Box::new(f.fold_map(*map))),
+        ResetObject::Element(element) => ResetObject::Element(f.fold_element(element)),
+        ResetObject::Table(table) => ResetObject::Table(f.fold_table(table)),
+    }
+}
+
+pub fn fold_flush_object<'a, F>(f: &mut F, node: FlushObject<'a>) -> FlushObject<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    match node {
+        FlushObject::Table(table) => FlushObject::Table(f.fold_table(table)),
+        FlushObject::Chain(chain) => FlushObject::Chain(f.fold_chain(chain)),
+        FlushObject::Set(set) => FlushObject::Set(Box::new(f.fold_set(*set))),
+        FlushObject::Map(map) => FlushObject::Map(Box::new(f.fold_map(*map))),
+        FlushObject::Meter(mut meter) => {
+            meter.key = f.fold_expression(meter.key);
+            meter.stmt = Box::new(f.fold_statement(*meter.stmt));
+            FlushObject::Meter(meter)
+        }
+        FlushObject::Ruleset(ruleset) => FlushObject::Ruleset(ruleset),
+    }
+}
+
+pub fn fold_table<'a, F>(_f: &mut F, node: Table<'a>) -> Table<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    node
+}
+
+pub fn fold_chain<'a, F>(_f: &mut F, node: Chain<'a>) -> Chain<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    node
+}
+
+pub fn fold_rule<'a, F>(f: &mut F, node: Rule<'a>) -> Rule<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    Rule {
+        expr: fold_list(node.expr, |stmt| f.fold_statement(stmt)),
+        ..node
+    }
+}
+
+pub fn fold_set<'a, F>(f: &mut F, node: Set<'a>) -> Set<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    Set {
+        set_type: fold_set_type(f, node.set_type),
+        elem: node
+            .elem
+            .map(|elem| fold_list(elem, |expr| f.fold_expression(expr))),
+        ..node
+    }
+}
+
+pub fn fold_map<'a, F>(f: &mut F, node: Map<'a>) -> Map<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    Map {
+        set_type: fold_set_type(f, node.set_type),
+        map: fold_set_type(f, node.map),
+        elem: node
+            .elem
+            .map(|elem| fold_list(elem, |expr| f.fold_expression(expr))),
+        ..node
+    }
+}
+
+/// Folds the expression of a `typeof` set type.
+fn fold_set_type<'a, F>(f: &mut F, node: SetTypeValue<'a>) -> SetTypeValue<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    match node {
+        SetTypeValue::Typeof(expr) => SetTypeValue::Typeof(f.fold_expression(expr)),
+        set_type => set_type,
+    }
+}
+
+pub fn fold_element<'a, F>(f: &mut F, node: Element<'a>) -> Element<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    Element {
+        elem: fold_list(node.elem, |expr| f.fold_expression(expr)),
+        ..node
+    }
+}
+
+pub fn fold_flowtable<'a, F>(_f: &mut F, node: FlowTable<'a>) -> FlowTable<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    node
+}
+
+pub fn fold_counter<'a, F>(_f: &mut F, node: Counter<'a>) -> Counter<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    node
+}
+
+pub fn fold_quota<'a, F>(_f: &mut F, node: Quota<'a>) -> Quota<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    node
+}
+
+pub fn fold_ct_helper<'a, F>(_f: &mut F, node: CTHelper<'a>) -> CTHelper<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    node
+}
+
+pub fn fold_limit<'a, F>(_f: &mut F, node: Limit<'a>) -> Limit<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    node
+}
+
+pub fn fold_metainfo<'a, F>(_f: &mut F, node: MetainfoObject<'a>) -> MetainfoObject<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    node
+}
+
+pub fn fold_ct_timeout<'a, F>(_f: &mut F, node: CTTimeout<'a>) -> CTTimeout<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    node
+}
+
+pub fn fold_ct_expectation<'a, F>(_f: &mut F, node: CTExpectation<'a>) -> CTExpectation<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    node
+}
+
+pub fn fold_synproxy<'a, F>(_f: &mut F, node: SynProxy<'a>) -> SynProxy<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    node
+}
+
+pub fn fold_statement<'a, F>(f: &mut F, node: Statement<'a>) -> Statement<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    match node {
+        Statement::Jump(target) => Statement::Jump(f.fold_jump_target(target)),
+        Statement::Goto(target) => Statement::Goto(f.fold_jump_target(target)),
+        Statement::Match(m) => Statement::Match(f.fold_match(m)),
+        Statement::Mangle(mut mangle) => {
+            mangle.key = f.fold_expression(mangle.key);
+            mangle.value = f.fold_expression(mangle.value);
+            Statement::Mangle(mangle)
+        }
+        Statement::FWD(Some(mut fwd)) => {
+            fwd.dev = fwd.dev.map(|dev| f.fold_expression(dev));
+            fwd.addr = fwd.addr.map(|addr| f.fold_expression(addr));
+            Statement::FWD(Some(fwd))
+        }
+        Statement::Dup(mut dup) => {
+            dup.addr = f.fold_expression(dup.addr);
+            dup.dev = dup.dev.map(|dev| f.fold_expression(dev));
+            Statement::Dup(dup)
+        }
+        Statement::SNAT(nat) => Statement::SNAT(nat.map(|nat| fold_nat(f, nat))),
+        Statement::DNAT(nat) => Statement::DNAT(nat.map(|nat| fold_nat(f, nat))),
+        Statement::Masquerade(nat) => Statement::Masquerade(nat.map(|nat| fold_nat(f, nat))),
+        Statement::Redirect(nat) => Statement::Redirect(nat.map(|nat| fold_nat(f, nat))),
+        Statement::Set(mut set) => {
+            set.elem = f.fold_expression(set.elem);
+            Statement::Set(set)
+        }
+        Statement::Meter(mut meter) => {
+            meter.key = f.fold_expression(meter.key);
+            meter.stmt = Box::new(f.fold_statement(*meter.stmt));
+            Statement::Meter(meter)
+        }
+        Statement::Queue(mut queue) => {
+            queue.num = f.fold_expression(queue.num);
+            Statement::Queue(queue)
+        }
+        Statement::VerdictMap(mut vmap) => {
+            vmap.key = f.fold_expression(vmap.key);
+            vmap.data = f.fold_expression(vmap.data);
+            Statement::VerdictMap(vmap)
+        }
+        Statement::CTCount(mut count) => {
+            count.val = f.fold_expression(count.val);
+            Statement::CTCount(count)
+        }
+        Statement::CTTimeout(expr) => Statement::CTTimeout(f.fold_expression(expr)),
+        Statement::CTExpectation(expr) => Statement::CTExpectation(f.fold_expression(expr)),
+        stmt => stmt,
+    }
+}
+
+fn fold_nat<'a, F>(f: &mut F, node: NAT<'a>) -> NAT<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    NAT {
+        addr: node.addr.map(|addr| f.fold_expression(addr)),
+        port: node.port.map(|port| f.fold_expression(port)),
+        ..node
+    }
+}
+
+pub fn fold_match<'a, F>(f: &mut F, node: Match<'a>) -> Match<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    Match {
+        left: f.fold_expression(node.left),
+        right: f.fold_expression(node.right),
+        op: node.op,
+    }
+}
+
+pub fn fold_jump_target<'a, F>(_f: &mut F, node: JumpTarget<'a>) -> JumpTarget<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    node
+}
+
+pub fn fold_expression<'a, F>(f: &mut F, node: Expression<'a>) -> Expression<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    match node {
+        Expression::List(exprs) => Expression::List(fold_expressions(f, exprs)),
+        Expression::BinaryOperation(op) => {
+            Expression::BinaryOperation(Box::new(f.fold_binary_operation(*op)))
+        }
+        Expression::Range(mut range) => {
+            let [low, high] = range.range;
+            range.range = [f.fold_expression(low), f.fold_expression(high)];
+            Expression::Range(range)
+        }
+        Expression::Named(named) => Expression::Named(f.fold_named_expression(named)),
+        Expression::Verdict(Verdict::Jump(target)) => {
+            Expression::Verdict(Verdict::Jump(f.fold_jump_target(target)))
+        }
+        Expression::Verdict(Verdict::Goto(target)) => {
+            Expression::Verdict(Verdict::Goto(f.fold_jump_target(target)))
+        }
+        expr @ (Expression::String(_)
+        | Expression::Number(_)
+        | Expression::SignedNumber(_)
+        | Expression::Boolean(_)
+        | Expression::Verdict(_)) => expr,
+    }
+}
+
+pub fn fold_named_expression<'a, F>(f: &mut F, node: NamedExpression<'a>) -> NamedExpression<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    match node {
+        NamedExpression::Concat(exprs) => NamedExpression::Concat(fold_expressions(f, exprs)),
+        NamedExpression::Set(items) => NamedExpression::Set(
+            items
+                .into_iter()
+                .map(|item| f.fold_set_item(item))
+                .collect(),
+        ),
+        NamedExpression::Map(mut map) => {
+            map.key = f.fold_expression(map.key);
+            map.data = f.fold_expression(map.data);
+            NamedExpression::Map(map)
+        }
+        NamedExpression::Prefix(mut prefix) => {
+            fold_boxed(f, &mut prefix.addr);
+            NamedExpression::Prefix(prefix)
+        }
+        NamedExpression::JHash(mut hash) => {
+            fold_boxed(f, &mut hash.expr);
+            NamedExpression::JHash(hash)
+        }
+        NamedExpression::Elem(mut elem) => {
+            fold_boxed(f, &mut elem.val);
+            NamedExpression::Elem(elem)
+        }
+        named => named,
+    }
+}
+
+pub fn fold_set_item<'a, F>(f: &mut F, node: SetItem<'a>) -> SetItem<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    match node {
+        SetItem::Element(expr) => SetItem::Element(f.fold_expression(expr)),
+        SetItem::Mapping(key, value) => {
+            SetItem::Mapping(f.fold_expression(key), f.fold_expression(value))
+        }
+        SetItem::MappingStatement(key, stmt) => {
+            SetItem::MappingStatement(f.fold_expression(key), f.fold_statement(stmt))
+        }
+    }
+}
+
+pub fn fold_binary_operation<'a, F>(f: &mut F, node: BinaryOperation<'a>) -> BinaryOperation<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    match node {
+        BinaryOperation::AND(left, right) => {
+            BinaryOperation::AND(f.fold_expression(left), f.fold_expression(right))
+        }
+        BinaryOperation::OR(exprs) => BinaryOperation::OR(fold_expressions(f, exprs)),
+        BinaryOperation::XOR(left, right) => {
+            BinaryOperation::XOR(f.fold_expression(left), f.fold_expression(right))
+        }
+        BinaryOperation::LSHIFT(left, right) => {
+            BinaryOperation::LSHIFT(f.fold_expression(left), f.fold_expression(right))
+        }
+        BinaryOperation::RSHIFT(left, right) => {
+            BinaryOperation::RSHIFT(f.fold_expression(left), f.fold_expression(right))
+        }
+    }
+}
@@ -0,0 +1,411 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+use crate::expr::{Expression, NamedExpression, SetItem, Verdict};
+use crate::schema::{NfCmd, NfListObject, NfObject, Nftables, Rule};
+use crate::stmt::{JumpTarget, Statement};
+use crate::types::{NfFamily, NfHook};
+use crate::validate::lower;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+/// A chain in a [CallGraph].
+pub struct ChainNode<'s> {
+    /// The table’s family.
+    pub family: NfFamily,
+    /// The table’s name.
+    pub table: &'s str,
+    /// The chain’s name.
+    pub name: &'s str,
+    /// The hook of a base chain, `None` for regular chains.
+    pub hook: Option<NfHook>,
+    /// Index of the first object declaring the chain in
+    /// [objects](Nftables::objects).
+    pub index: usize,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+/// How a rule passes control to another chain.
+pub enum JumpKind {
+    /// `jump`: evaluation returns to the calling chain.
+    Jump,
+    /// `goto`: evaluation does not return to the calling chain.
+    Goto,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+/// A `jump` or `goto` from one chain to another, by index into
+/// [chains](CallGraph::chains).
+pub struct Edge {
+    /// The chain containing the rule.
+    pub from: usize,
+    /// The target chain.
+    pub to: usize,
+    /// Whether the rule jumps or goes to the target.
+    pub kind: JumpKind,
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+/// The chains of a document and the jumps between them.
+///
+/// Jumps are collected from `jump` and `goto` statements and from the
+/// verdicts of anonymous and named verdict maps. Jumps to chains the document
+/// does not declare are left out, see [validate](crate::validate::validate).
+pub struct CallGraph<'s> {
+    /// The chains in document order.
+    pub chains: Vec<ChainNode<'s>>,
+    /// The jumps in document order, each listed once.
+    pub edges: Vec<Edge>,
+}
+
+/// Family, table and name of a chain or map.
+type Key<'s> = (NfFamily, &'s str, &'s str);
+
+/// Returns the declared or listed object of a document object.
+fn list_object<'s, 'a>(object: &'s NfObject<'a>) -> Option<&'s NfListObject<'a>> {
+    match object {
+        NfObject::ListObject(object)
+        | NfObject::CmdObject(NfCmd::Add(object) | NfCmd::Create(object) | NfCmd::Insert(object)) => {
+            Some(object)
+        }
+        _ => None,
+    }
+}
+
+/// Collects the targets of the verdicts in an expression, and the names of
+/// the maps it refers to.
+fn expression_targets<'s>(
+    expr: &'s Expression,
+    targets: &mut Vec<(&'s str, JumpKind)>,
+    maps: &mut Vec<&'s str>,
+) {
+    match expr {
+        Expression::String(s) => maps.extend(s.strip_prefix('@')),
+        Expression::List(items) | Expression::Named(NamedExpression::Concat(items)) => items
+            .iter()
+            .for_each(|e| expression_targets(e, targets, maps)),
+        Expression::Named(NamedExpression::Set(items)) => {
+            for item in items {
+                match item {
+                    SetItem::Element(e) => expression_targets(e, targets, maps),
+                    SetItem::Mapping(_, value) => expression_targets(value, targets, maps),
+                    SetItem::MappingStatement(_, stmt) => statement_targets(stmt, targets, maps),
+                }
+            }
+        }
+        Expression::Named(NamedExpression::Elem(elem)) => {
+            expression_targets(&elem.val, targets, maps)
+        }
+        Expression::Verdict(Verdict::Jump(JumpTarget { target })) => {
+            targets.push((target, JumpKind::Jump))
+        }
+        Expression::Verdict(Verdict::Goto(JumpTarget { target })) => {
+            targets.push((target, JumpKind::Goto))
+        }
+        _ => {}
+    }
+}
+
+/// Collects the jump targets of a statement, and the names of the verdict
+/// maps it refers to.
+fn statement_targets<'s>(
+    stmt: &'s Statement,
+    targets: &mut Vec<(&'s str, JumpKind)>,
+    maps: &mut Vec<&'s str>,
+) {
+    match stmt {
+        Statement::Jump(JumpTarget { target }) => targets.push((target, JumpKind::Jump)),
+        Statement::Goto(JumpTarget { target }) => targets.push((target, JumpKind::Goto)),
+        Statement::VerdictMap(vmap) => expression_targets(&vmap.data, targets, maps),
+        Statement::Meter(meter) => statement_targets(&meter.stmt, targets, maps),
+        _ => {}
+    }
+}
+
+impl<'s> CallGraph<'s> {
+    /// Builds the call graph of the chains declared in a document.
+    pub fn new(nftables: &'s Nftables) -> Self {
+        let mut graph = CallGraph::default();
+        let mut index: HashMap<Key, usize> = HashMap::new();
+        // Verdicts in the elements of named maps.
+        let mut map_targets: HashMap<Key, Vec<(&str, JumpKind)>> = HashMap::new();
+        let mut rules: Vec<&Rule> = Vec::new();
+
+        for (object_index, object) in nftables.objects.iter().enumerate() {
+            let (family, table, name, elem) = match (object, list_object(object)) {
+                (_, Some(NfListObject::Chain(chain))) => {
+                    let key = (chain.family, &*chain.table, &*chain.name);
+                    let node = ChainNode {
+                        family: chain.family,
+                        table: &chain.table,
+                        name: &chain.name,
+                        hook: chain.hook,
+                        index: object_index,
+                    };
+                    match index.get(&key) {
+                        Some(&i) => graph.chains[i].hook = graph.chains[i].hook.or(node.hook),
+                        None => {
+                            index.insert(key, graph.chains.len());
+                            graph.chains.push(node);
+                        }
+                    }
+                    continue;
+                }
+                (_, Some(NfListObject::Rule(rule)))
+                | (NfObject::CmdObject(NfCmd::Replace(rule)), _) => {
+                    rules.push(rule);
+                    continue;
+                }
+                (_, Some(NfListObject::Map(map))) => match &map.elem {
+                    Some(elem) => (map.family, &map.table, &map.name, &elem[..]),
+                    None => continue,
+                },
+                (_, Some(NfListObject::Element(element))) => (
+                    element.family,
+                    &element.table,
+                    &element.name,
+                    &element.elem[..],
+                ),
+                _ => continue,
+            };
+            let targets = map_targets.entry((family, table, name)).or_default();
+            for e in elem {
+                expression_targets(e, targets, &mut Vec::new());
+            }
+        }
+
+        let mut edges = HashSet::new();
+        for rule in rules {
+            let Some(&from) = index.get(&(rule.family, &*rule.table, &*rule.chain)) else {
+                continue;
+            };
+            let (mut targets, mut maps) = (Vec::new(), Vec::new());
+            for stmt in rule.expr.iter() {
+                statement_targets(stmt, &mut targets, &mut maps);
+            }
+            for map in maps {
+                if let Some(map_targets) = map_targets.get(&(rule.family, &*rule.table, map)) {
+                    targets.extend(map_targets);
+                }
+            }
+            for (target, kind) in targets {
+                let Some(&to) = index.get(&(rule.family, &*rule.table, target)) else {
+                    continue;
+                };
+                let edge = Edge { from, to, kind };
+                if edges.insert(edge) {
+                    graph.edges.push(edge);
+                }
+            }
+        }
+        graph
+    }
+
+    /// Returns the targets of the jumps from a chain.
+    fn successors(&self, chain: usize) -> impl Iterator<Item = &Edge> {
+        self.edges.iter().filter(move |edge| edge.from == chain)
+    }
+
+    /// Returns the loops, each as the indices of the chains that can reach
+    /// each other, in ascending order.
+    ///
+    /// nft rejects rulesets with loops.
+    pub fn cycles(&self) -> Vec<Vec<usize>> {
+        // Tarjan's strongly connected components.
+        struct Tarjan<'g, 's> {
+            graph: &'g CallGraph<'s>,
+            next: usize,
+            index: Vec<Option<usize>>,
+            low: Vec<usize>,
+            stack: Vec<usize>,
+            on_stack: Vec<bool>,
+            components: Vec<Vec<usize>>,
+        }
+
+        impl Tarjan<'_, '_> {
+            fn visit(&mut self, v: usize) {
+                self.index[v] = Some(self.next);
+                self.low[v] = self.next;
+                self.next += 1;
+                self.stack.push(v);
+                self.on_stack[v] = true;
+                let successors: Vec<usize> = self.graph.successors(v).map(|e| e.to).collect();
+                for w in successors {
+                    match self.index[w] {
+                        None => {
+                            self.visit(w);
+                            self.low[v] = self.low[v].min(self.low[w]);
+                        }
+                        Some(index) if self.on_stack[w] => self.low[v] = self.low[v].min(index),
+                        Some(_) => {}
+                    }
+                }
+                if Some(self.low[v]) == self.index[v] {
+                    let mut component = Vec::new();
+                    while let Some(w) = self.stack.pop() {
+                        self.on_stack[w] = false;
+                        component.push(w);
+                        if w == v {
+                            break;
+                        }
+                    }
+                    self.components.push(component);
+                }
+            }
+        }
+
+        let n = self.chains.len();
+        let mut tarjan = Tarjan {
+            graph: self,
+            next: 0,
+            index: vec![None; n],
+            low: vec![0; n],
+            stack: Vec::new(),
+            on_stack: vec![false; n],
+            components: Vec::new(),
+        };
+        for v in 0..n {
+            if tarjan.index[v].is_none() {
+                tarjan.visit(v);
+            }
+        }
+        let mut cycles: Vec<Vec<usize>> = tarjan
+            .components
+            .into_iter()
+            .filter(|c| c.len() > 1 || self.successors(c[0]).any(|e| e.to == c[0]))
+            .map(|mut c| {
+                c.sort_unstable();
+                c
+            })
+            .collect();
+        cycles.sort_unstable();
+        cycles
+    }
+
+    /// Returns whether each chain can be reached from a base chain.
+    fn reachable(&self) -> Vec<bool> {
+        let mut reachable: Vec<bool> = self.chains.iter().map(|c| c.hook.is_some()).collect();
+        let mut stack: Vec<usize> = (0..self.chains.len()).filter(|&c| reachable[c]).collect();
+        while let Some(chain) = stack.pop() {
+            for edge in self.successors(chain) {
+                if !reachable[edge.to] {
+                    reachable[edge.to] = true;
+                    stack.push(edge.to);
+                }
+            }
+        }
+        reachable
+    }
+
+    /// Returns the indices of the regular chains no base chain can reach.
+    pub fn unreachable(&self) -> Vec<usize> {
+        let reachable = self.reachable();
+        (0..self.chains.len()).filter(|&c| !reachable[c]).collect()
+    }
+
+    /// Returns the maximum number of nested jumps starting at a base chain,
+    /// or `None` if a base chain can reach a loop.
+    ///
+    /// `goto` does not add to the depth, as it does not return to the calling
+    /// chain. The kernel limits the depth to 16.
+    pub fn max_depth(&self) -> Option<usize> {
+        let reachable = self.reachable();
+        let looping = self
+            .cycles()
+            .iter()
+            .any(|cycle| cycle.iter().any(|&c| reachable[c]));
+        if looping {
+            return None;
+        }
+        // Longest path in the acyclic reachable part, memoized per chain.
+        fn depth(graph: &CallGraph, chain: usize, memo: &mut Vec<Option<usize>>) -> usize {
+            if let Some(depth) = memo[chain] {
+                return depth;
+            }
+            let edges: Vec<Edge> = graph.successors(chain).copied().collect();
+            let result = edges
+                .into_iter()
+                .map(|edge| depth(graph, edge.to, memo) + usize::from(edge.kind == JumpKind::Jump))
+                .max()
+                .unwrap_or(0);
+            memo[chain] = Some(result);
+            result
+        }
+        let mut memo = vec![None; self.chains.len()];
+        Some(
+            (0..self.chains.len())
+                .filter(|&c| self.chains[c].hook.is_some())
+                .map(|c| depth(self, c, &mut memo))
+                .max()
+                .unwrap_or(0),
+        )
+    }
+
+    /// Renders the graph in the Graphviz DOT language.
+    ///
+    /// Chains are grouped by table and base chains are drawn as boxes with
+    /// their hook. `goto` edges are dashed, edges within loops red and
+    /// unreachable chains gray.
+    pub fn to_dot(&self) -> String {
+        fn quote(s: &str) -> String {
+            format!("\"{}\"", s.replace('"', "\\\""))
+        }
+        let id = |chain: &ChainNode| {
+            quote(&format!(
+                "{} {} {}",
+                lower(&chain.family),
+                chain.table,
+                chain.name
+            ))
+        };
+        let reachable = self.reachable();
+        let looping: HashSet<usize> = self.cycles().into_iter().flatten().collect();
+
+        let mut dot = String::from("digraph ruleset {\n");
+        let mut tables: Vec<(NfFamily, &str)> = Vec::new();
+        for chain in &self.chains {
+            if !tables.contains(&(chain.family, chain.table)) {
+                tables.push((chain.family, chain.table));
+            }
+        }
+        for (i, (family, table)) in tables.into_iter().enumerate() {
+            let label = quote(&format!("{} {table}", lower(&family)));
+            let _ = writeln!(dot, "\tsubgraph cluster_{i} {{\n\t\tlabel = {label};");
+            for (c, chain) in self.chains.iter().enumerate() {
+                if (chain.family, chain.table) != (family, table) {
+                    continue;
+                }
+                let mut attrs = match chain.hook {
+                    Some(hook) => format!(
+                        "label = {}, shape = box",
+                        quote(&format!("{}\\nhook {}", chain.name, lower(&hook)))
+                    ),
+                    None => format!("label = {}", quote(chain.name)),
+                };
+                if !reachable[c] {
+                    attrs.push_str(", color = gray, fontcolor = gray");
+                }
+                let _ = writeln!(dot, "\t\t{} [{attrs}];", id(chain));
+            }
+            dot.push_str("\t}\n");
+        }
+        for edge in &self.edges {
+            let mut attrs = Vec::new();
+            if edge.kind == JumpKind::Goto {
+                attrs.push("style = dashed");
+            }
+            if looping.contains(&edge.from) && looping.contains(&edge.to) {
+                attrs.push("color = red");
+            }
+            let attrs = match attrs.is_empty() {
+                true => String::new(),
+                false => format!(" [{}]", attrs.join(", ")),
+            };
+            let (from, to) = (&self.chains[edge.from], &self.chains[edge.to]);
+            let _ = writeln!(dot, "\t{} -> {}{attrs};", id(from), id(to));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
@@ -1,12 +1,18 @@
 use std::string::FromUtf8Error;
 use std::{
     ffi::{OsStr, OsString},
-    io::{self, Write},
-    process::{Command, Stdio},
+    fs::File,
+    io::{self, Read, Write},
+    os::{fd::AsRawFd, unix::process::CommandExt},
+    path::{Path, PathBuf},
+    process::{Child, Command, Output, Stdio},
+    thread,
+    time::{Duration, Instant},
 };
 
 use thiserror::Error;
 
+use crate::diagnostic::{parse_diagnostics, NftDiagnostic};
 use crate::schema::Nftables;
 
 /// Default `nft` executable.
@@ -39,16 +45,391 @@ pub enum NftablesError {
         hint: String,
         stdout: String,
         stderr: String,
+        /// Errors and warnings parsed from `stderr`.
+        diagnostics: Vec<NftDiagnostic>,
     },
+    #[error("{program:?} did not finish within {timeout:?} and was killed")]
+    NftTimeout {
+        program: OsString,
+        timeout: Duration,
+    },
+}
+
+/// Transport used by the helper functions to talk to nftables.
+///
+/// An executor receives the complete `nft` argument list (e.g. `-j list ruleset`
+/// or `-j -f -`) and, when applying a rule set, the JSON payload to be fed to
+/// the standard input. It returns the standard output on success.
+///
+/// [ProcessExecutor] is the default implementation, which spawns the `nft`
+/// executable. Custom implementations can record, fake or redirect invocations.
+pub trait NftExecutor {
+    /// Run `nft` with the given arguments and optional standard input.
+    fn execute(&self, args: &[&OsStr], stdin: Option<&str>) -> Result<String, NftablesError>;
+}
+
+/// Asynchronous counterpart of [NftExecutor].
+#[cfg(any(feature = "tokio", feature = "async-process"))]
+pub trait NftExecutorAsync {
+    /// Run `nft` asynchronously with the given arguments and optional standard input.
+    fn execute_async(
+        &self,
+        args: &[&OsStr],
+        stdin: Option<&str>,
+    ) -> impl std::future::Future<Output = Result<String, NftablesError>>;
+}
+
+/// Directory of named network namespaces, as managed by `ip netns`.
+const NETNS_RUN_DIR: &str = "/var/run/netns";
+
+/// A network namespace to run `nft` in.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum Netns {
+    /// A named namespace below `/var/run/netns`, as created by `ip netns add`.
+    Named(String),
+    /// A namespace file, e.g. a bind mount of `/proc/<pid>/ns/net`.
+    Path(PathBuf),
+    /// The namespace of the process with the given PID.
+    Pid(u32),
+}
+
+impl Netns {
+    /// The namespace file to be passed to `setns(2)`.
+    pub fn path(&self) -> PathBuf {
+        match self {
+            Netns::Named(name) => Path::new(NETNS_RUN_DIR).join(name),
+            Netns::Path(path) => path.clone(),
+            Netns::Pid(pid) => PathBuf::from(format!("/proc/{pid}/ns/net")),
+        }
+    }
+}
+
+/// Executor that spawns an `nft` subprocess for every invocation.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ProcessExecutor {
+    program: OsString,
+    netns: Option<Netns>,
+    timeout: Option<Duration>,
+}
+
+impl Default for ProcessExecutor {
+    fn default() -> Self {
+        ProcessExecutor {
+            program: NFT_EXECUTABLE.into(),
+            netns: None,
+            timeout: None,
+        }
+    }
+}
+
+impl ProcessExecutor {
+    /// Creates an executor calling `program`, or the default `nft` executable
+    /// if [DEFAULT_NFT] is passed.
+    pub fn new<P: AsRef<OsStr> + ?Sized>(program: Option<&P>) -> ProcessExecutor {
+        match program {
+            Some(program) => ProcessExecutor {
+                program: program.as_ref().into(),
+                netns: None,
+                timeout: None,
+            },
+            None => ProcessExecutor::default(),
+        }
+    }
+
+    /// Runs `nft` inside the given network namespace.
+    ///
+    /// The child process enters the namespace before executing `nft`, which
+    /// requires `CAP_SYS_ADMIN`. The calling process is not affected.
+    ///
+    /// This replaces wrapping `nft` in `ip netns exec <name>` and passing the
+    /// wrapper as `program` to the `*_with_args` and `*_raw` helpers: pass an
+    /// executor to their `*_with_executor` counterparts instead.
+    ///
+    /// ```no_run
+    /// use nftables::helper::{self, Netns, ProcessExecutor};
+    ///
+    /// // Previously: helper::get_current_ruleset_raw(Some("nft-in-blue"), helper::DEFAULT_ARGS)
+    /// // with `nft-in-blue` running `ip netns exec blue nft "$@"`.
+    /// let executor = ProcessExecutor::default().with_netns(Netns::Named("blue".to_string()));
+    /// let ruleset = helper::get_current_ruleset_raw_with_executor(&executor, helper::DEFAULT_ARGS)?;
+    /// helper::apply_ruleset_raw_with_executor(&ruleset, &executor, helper::DEFAULT_ARGS)?;
+    /// # Ok::<(), helper::NftablesError>(())
+    /// ```
+    pub fn with_netns(mut self, netns: Netns) -> ProcessExecutor {
+        self.netns = Some(netns);
+        self
+    }
+
+    /// Kills `nft` if it does not finish within the given duration.
+    ///
+    /// The invocation then fails with [NftablesError::NftTimeout]. Without a
+    /// timeout, which is the default, `nft` may run indefinitely; this also
+    /// applies to helpers that do not take an executor, such as [apply_ruleset].
+    /// Asynchronous invocations additionally kill `nft` when their future is
+    /// dropped.
+    pub fn with_timeout(mut self, timeout: Duration) -> ProcessExecutor {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// The program called by this executor.
+    pub fn program(&self) -> &OsStr {
+        &self.program
+    }
+
+    /// The network namespace `nft` is run in, if any.
+    pub fn netns(&self) -> Option<&Netns> {
+        self.netns.as_ref()
+    }
+
+    /// The deadline for `nft` to finish, if any.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Prepares the `nft` command, entering the network namespace if configured.
+    pub(crate) fn command(&self, args: &[&OsStr]) -> Result<Command, NftablesError> {
+        let mut nft_cmd = Command::new(&self.program);
+        nft_cmd.args(args);
+        if let Some(netns) = &self.netns {
+            let netns = File::open(netns.path()).map_err(|e| NftablesError::NftExecution {
+                program: self.program.clone(),
+                inner: e,
+            })?;
+            // SAFETY: setns(2) is async-signal-safe and only affects the child.
+            unsafe {
+                nft_cmd.pre_exec(move || {
+                    match libc::setns(netns.as_raw_fd(), libc::CLONE_NEWNET) {
+                        0 => Ok(()),
+                        _ => Err(io::Error::last_os_error()),
+                    }
+                });
+            }
+        }
+        Ok(nft_cmd)
+    }
+
+    /// Turns the output of a finished `nft` into the executor's result.
+    fn read_result(
+        &self,
+        args: &[&OsStr],
+        stdin: Option<&str>,
+        output: Output,
+    ) -> Result<String, NftablesError> {
+        let program = self.program.as_os_str();
+        let stdout = read_output(program, output.stdout)?;
+        if output.status.success() {
+            return Ok(stdout);
+        }
+
+        let stderr = read_output(program, output.stderr)?;
+        let hint = match stdin {
+            Some(_) => apply_hint(args),
+            None => "getting the current ruleset",
+        };
+        Err(NftablesError::NftFailed {
+            program: program.into(),
+            hint: hint.to_string(),
+            stdout,
+            diagnostics: parse_diagnostics(&stderr, stdin),
+            stderr,
+        })
+    }
+}
+
+/// Interval in which a child with a timeout is checked for termination.
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Feeds `stdin` to the child and collects its output, killing the child when
+/// `timeout` expires. Returns [None] if the child was killed.
+fn wait_with_timeout(
+    mut child: Child,
+    stdin: Option<&str>,
+    timeout: Duration,
+) -> io::Result<Option<Output>> {
+    fn read_all(mut pipe: impl Read) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        pipe.read_to_end(&mut buf).map(|_| buf)
+    }
+
+    let deadline = Instant::now() + timeout;
+    let child_stdin = child.stdin.take();
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+
+    thread::scope(|scope| {
+        let writer = scope.spawn(move || match (child_stdin, stdin) {
+            (Some(mut pipe), Some(payload)) => pipe.write_all(payload.as_bytes()),
+            _ => Ok(()),
+        });
+        let stdout = scope.spawn(move || read_all(stdout));
+        let stderr = scope.spawn(move || read_all(stderr));
+
+        let status = loop {
+            let status = child.try_wait();
+            let now = Instant::now();
+            match status {
+                Ok(Some(status)) => break Some(status),
+                Ok(None) if now < deadline => thread::sleep(WAIT_POLL_INTERVAL.min(deadline - now)),
+                Ok(None) => break None,
+                Err(e) => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(e);
+                }
+            }
+        };
+        let Some(status) = status else {
+            // Closes the pipes, which also terminates the helper threads.
+            child.kill()?;
+            child.wait()?;
+            return Ok(None);
+        };
+
+        writer.join().expect("stdin writer panicked")?;
+        Ok(Some(Output {
+            status,
+            stdout: stdout.join().expect("stdout reader panicked")?,
+            stderr: stderr.join().expect("stderr reader panicked")?,
+        }))
+    })
+}
+
+impl NftExecutor for ProcessExecutor {
+    fn execute(&self, args: &[&OsStr], stdin: Option<&str>) -> Result<String, NftablesError> {
+        let program = self.program.as_os_str();
+        let execution_error = |e| NftablesError::NftExecution {
+            program: program.into(),
+            inner: e,
+        };
+
+        let process = self
+            .command(args)?
+            .stdin(match stdin {
+                Some(_) => Stdio::piped(),
+                None => Stdio::null(),
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn();
+        let mut process = process.map_err(execution_error)?;
+
+        let output = match self.timeout {
+            Some(timeout) => wait_with_timeout(process, stdin, timeout)
+                .map_err(execution_error)?
+                .ok_or_else(|| NftablesError::NftTimeout {
+                    program: program.into(),
+                    timeout,
+                })?,
+            None => {
+                if let Some(payload) = stdin {
+                    let mut stdin = process.stdin.take().unwrap();
+                    stdin
+                        .write_all(payload.as_bytes())
+                        .map_err(execution_error)?;
+                    drop(stdin);
+                }
+                process.wait_with_output().map_err(execution_error)?
+            }
+        };
+        self.read_result(args, stdin, output)
+    }
+}
+
+#[cfg(any(feature = "tokio", feature = "async-process"))]
+impl NftExecutorAsync for ProcessExecutor {
+    async fn execute_async(
+        &self,
+        args: &[&OsStr],
+        stdin: Option<&str>,
+    ) -> Result<String, NftablesError> {
+        #[cfg(feature = "async-process")]
+        use async_process::Command;
+        #[cfg(feature = "async-process")]
+        use futures_lite::io::AsyncWriteExt;
+        #[cfg(feature = "tokio")]
+        use tokio::io::AsyncWriteExt;
+        #[cfg(feature = "tokio")]
+        use tokio::process::Command;
+
+        let program = self.program.as_os_str();
+        let execution_error = |e| NftablesError::NftExecution {
+            program: program.into(),
+            inner: e,
+        };
+
+        let mut nft_cmd = Command::from(self.command(args)?);
+        // Dropping the future (e.g. on timeout) kills `nft`.
+        nft_cmd.kill_on_drop(true);
+        let process = nft_cmd
+            .stdin(match stdin {
+                Some(_) => Stdio::piped(),
+                None => Stdio::null(),
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn();
+        let mut process = process.map_err(execution_error)?;
+
+        let run = async {
+            if let Some(payload) = stdin {
+                let mut stdin = process.stdin.take().unwrap();
+                stdin
+                    .write_all(payload.as_bytes())
+                    .await
+                    .map_err(execution_error)?;
+                drop(stdin);
+            }
+            #[cfg(feature = "tokio")]
+            let result = process.wait_with_output().await;
+            #[cfg(feature = "async-process")]
+            let result = process.output().await;
+            result.map_err(execution_error)
+        };
+
+        let output = match self.timeout {
+            #[cfg(feature = "tokio")]
+            Some(timeout) => tokio::time::timeout(timeout, run).await.map_err(|_| {
+                NftablesError::NftTimeout {
+                    program: program.into(),
+                    timeout,
+                }
+            })??,
+            #[cfg(feature = "async-process")]
+            Some(timeout) => {
+                futures_lite::future::or(run, async {
+                    async_io::Timer::after(timeout).await;
+                    Err(NftablesError::NftTimeout {
+                        program: program.into(),
+                        timeout,
+                    })
+                })
+                .await?
+            }
+            None => run.await?,
+        };
+        self.read_result(args, stdin, output)
+    }
 }
 
 /// Get the rule set that is currently active in the kernel.
 ///
-/// This is done by calling the default `nft` executable with default arguments.
+/// This is done by calling the default `nft` executable with default arguments,
+/// without a timeout. To limit how long `nft` may take, use
+/// [`get_current_ruleset_with_executor`] with [`ProcessExecutor::with_timeout`].
 pub fn get_current_ruleset() -> Result<Nftables<'static>, NftablesError> {
     get_current_ruleset_with_args(DEFAULT_NFT, DEFAULT_ARGS)
 }
 
+/// Get the rule set that is currently active in the kernel through the given
+/// [executor](NftExecutor).
+pub fn get_current_ruleset_with_executor<E: NftExecutor + ?Sized>(
+    executor: &E,
+) -> Result<Nftables<'static>, NftablesError> {
+    let output = list_raw(executor, &[])?;
+    serde_json::from_str(&output).map_err(NftablesError::NftInvalidJson)
+}
+
 /// Get the current rule set by calling a custom `nft` with custom arguments.
 ///
 /// If `program` is [Some], then this program will be called instead of the
@@ -59,6 +440,9 @@ pub fn get_current_ruleset() -> Result<Nftables<'static>, NftablesError> {
 /// default arguments `list` and `ruleset`.
 /// [DEFAULT_ARGS] can be passed to use the default arguments.
 /// Note that the argument `-j` is always added in front of `args`.
+///
+/// To run `nft` in a network namespace, use [`get_current_ruleset_with_executor`]
+/// with [`ProcessExecutor::with_netns`].
 pub fn get_current_ruleset_with_args<'a, P, A, I>(
     program: Option<&P>,
     args: I,
@@ -82,6 +466,9 @@ where
 /// default arguments `list` and `ruleset`.
 /// [DEFAULT_ARGS] can be passed to use the default arguments.
 /// Note that the argument `-j` is always added in front of `args`.
+///
+/// To run `nft` in a network namespace, use
+/// [`get_current_ruleset_raw_with_executor`] with [`ProcessExecutor::with_netns`].
 pub fn get_current_ruleset_raw<'a, P, A, I>(
     program: Option<&P>,
     args: I,
@@ -91,35 +478,27 @@ where
     A: AsRef<OsStr> + ?Sized + 'a,
     I: IntoIterator<Item = &'a A> + 'a,
 {
-    let program = program
-        .map(AsRef::as_ref)
-        .unwrap_or(NFT_EXECUTABLE.as_ref());
-    let mut nft_cmd = Command::new(program);
-    let nft_cmd = nft_cmd.arg("-j");
-    let mut args = args.into_iter();
-    let nft_cmd = match args.next() {
-        Some(arg) => nft_cmd.arg(arg).args(args),
-        None => nft_cmd.args(["list", "ruleset"]),
-    };
-    let process_result = nft_cmd.output();
-    let process_result = process_result.map_err(|e| NftablesError::NftExecution {
-        inner: e,
-        program: program.into(),
-    })?;
-
-    let stdout = read_output(program, process_result.stdout)?;
-
-    if !process_result.status.success() {
-        let stderr = read_output(program, process_result.stderr)?;
+    get_current_ruleset_raw_with_executor(&ProcessExecutor::new(program), args)
+}
 
-        return Err(NftablesError::NftFailed {
-            program: program.into(),
-            hint: "getting the current ruleset".to_string(),
-            stdout,
-            stderr,
-        });
-    }
-    Ok(stdout)
+/// Get the current raw rule set json through the given [executor](NftExecutor)
+/// with custom arguments.
+///
+/// This allows to configure a [ProcessExecutor] with a
+/// [timeout](ProcessExecutor::with_timeout) or a
+/// [network namespace](ProcessExecutor::with_netns) when reading the raw output.
+/// See [`get_current_ruleset_raw`] for the meaning of `args`.
+pub fn get_current_ruleset_raw_with_executor<'a, E, A, I>(
+    executor: &E,
+    args: I,
+) -> Result<String, NftablesError>
+where
+    E: NftExecutor + ?Sized,
+    A: AsRef<OsStr> + ?Sized + 'a,
+    I: IntoIterator<Item = &'a A> + 'a,
+{
+    let args: Vec<&OsStr> = args.into_iter().map(AsRef::as_ref).collect();
+    list_raw(executor, &args)
 }
 
 /// Get the rule set that is currently active in the kernel asynchronously.
@@ -130,6 +509,18 @@ pub async fn get_current_ruleset_async() -> Result<Nftables<'static>, NftablesEr
     get_current_ruleset_with_args_async(DEFAULT_NFT, DEFAULT_ARGS).await
 }
 
+/// Get the rule set that is currently active in the kernel asynchronously through
+/// the given [executor](NftExecutorAsync).
+///
+/// See the synchronous [`get_current_ruleset_with_executor`] for more information.
+#[cfg(any(feature = "tokio", feature = "async-process"))]
+pub async fn get_current_ruleset_with_executor_async<E: NftExecutorAsync>(
+    executor: &E,
+) -> Result<Nftables<'static>, NftablesError> {
+    let output = list_raw_async(executor, &[]).await?;
+    serde_json::from_str(&output).map_err(NftablesError::NftInvalidJson)
+}
+
 /// Get the current rule set asynchronously by calling a custom `nft` with custom arguments.
 ///
 /// See the synchronous [`get_current_ruleset_with_args`] for more information.
@@ -160,49 +551,46 @@ where
     A: AsRef<OsStr> + ?Sized + 'a,
     I: IntoIterator<Item = &'a A> + 'a,
 {
-    #[cfg(feature = "async-process")]
-    use async_process::Command;
-    #[cfg(feature = "tokio")]
-    use tokio::process::Command;
-
-    let program = program
-        .map(AsRef::as_ref)
-        .unwrap_or(NFT_EXECUTABLE.as_ref());
-    let mut nft_cmd = Command::new(program);
-    let nft_cmd = nft_cmd.arg("-j");
-    let mut args = args.into_iter();
-    let nft_cmd = match args.next() {
-        Some(arg) => nft_cmd.arg(arg).args(args),
-        None => nft_cmd.args(["list", "ruleset"]),
-    };
-    let process_result = nft_cmd.output().await;
-    let process_result = process_result.map_err(|e| NftablesError::NftExecution {
-        inner: e,
-        program: program.into(),
-    })?;
-
-    let stdout = read_output(program, process_result.stdout)?;
-
-    if !process_result.status.success() {
-        let stderr = read_output(program, process_result.stderr)?;
+    get_current_ruleset_raw_with_executor_async(&ProcessExecutor::new(program), args).await
+}
 
-        return Err(NftablesError::NftFailed {
-            program: program.into(),
-            hint: "getting the current ruleset".to_string(),
-            stdout,
-            stderr,
-        });
-    }
-    Ok(stdout)
+/// Get the current raw rule set json asynchronously through the given
+/// [executor](NftExecutorAsync) with custom arguments.
+///
+/// See the synchronous [`get_current_ruleset_raw_with_executor`] for more information.
+#[cfg(any(feature = "tokio", feature = "async-process"))]
+pub async fn get_current_ruleset_raw_with_executor_async<'a, E, A, I>(
+    executor: &E,
+    args: I,
+) -> Result<String, NftablesError>
+where
+    E: NftExecutorAsync,
+    A: AsRef<OsStr> + ?Sized + 'a,
+    I: IntoIterator<Item = &'a A> + 'a,
+{
+    let args: Vec<&OsStr> = args.into_iter().map(AsRef::as_ref).collect();
+    list_raw_async(executor, &args).await
 }
 
 /// Apply the given rule set to the kernel.
 ///
-/// This is done by calling the default `nft` executable with default arguments.
+/// This is done by calling the default `nft` executable with default arguments,
+/// without a timeout. To limit how long `nft` may take, use
+/// [`apply_ruleset_with_executor`] with [`ProcessExecutor::with_timeout`].
 pub fn apply_ruleset(nftables: &Nftables) -> Result<(), NftablesError> {
     apply_ruleset_with_args(nftables, DEFAULT_NFT, DEFAULT_ARGS)
 }
 
+/// Apply the given rule set to the kernel through the given [executor](NftExecutor).
+pub fn apply_ruleset_with_executor<E: NftExecutor + ?Sized>(
+    nftables: &Nftables,
+    executor: &E,
+) -> Result<(), NftablesError> {
+    let nftables = serde_json::to_string(nftables).expect("failed to serialize Nftables struct");
+    apply_raw(executor, &nftables, &[])?;
+    Ok(())
+}
+
 /// Apply the given rule set by calling a custom `nft` with custom arguments.
 ///
 /// If `program` is [Some], then this program will be called instead of the
@@ -211,6 +599,9 @@ pub fn apply_ruleset(nftables: &Nftables) -> Result<(), NftablesError> {
 ///
 /// If `args` is not empty, then these `nft` arguments will be added in front of the
 /// other arguments `-j` and `-f -` that are always required internally.
+///
+/// To run `nft` in a network namespace, use [`apply_ruleset_with_executor`]
+/// with [`ProcessExecutor::with_netns`].
 pub fn apply_ruleset_with_args<'a, P, A, I>(
     nftables: &Nftables,
     program: Option<&P>,
@@ -235,6 +626,19 @@ pub fn apply_and_return_ruleset(nftables: &Nftables) -> Result<Nftables<'static>
     apply_and_return_ruleset_with_args(nftables, DEFAULT_NFT, DEFAULT_ARGS)
 }
 
+/// Apply the given rule set through the given [executor](NftExecutor), and returns
+/// the processed rule set with extra information.
+///
+/// See [`apply_and_return_ruleset`] for more information.
+pub fn apply_and_return_ruleset_with_executor<E: NftExecutor + ?Sized>(
+    nftables: &Nftables,
+    executor: &E,
+) -> Result<Nftables<'static>, NftablesError> {
+    let nftables = serde_json::to_string(nftables).expect("failed to serialize Nftables struct");
+    let output = apply_raw(executor, &nftables, &[OsStr::new("--echo")])?;
+    serde_json::from_str(&output).map_err(NftablesError::NftInvalidJson)
+}
+
 /// Apply the given rule set by calling a custom `nft` with custom arguments, and
 /// returns the processed rule set with extra information.
 ///
@@ -269,6 +673,9 @@ where
 /// other arguments `-j` and `-f -` that are always required internally.
 ///
 /// The command's stdout is returned as a [`String`].
+///
+/// To run `nft` in a network namespace, use [`apply_ruleset_raw_with_executor`]
+/// with [`ProcessExecutor::with_netns`].
 pub fn apply_ruleset_raw<'a, P, A, I>(
     payload: &str,
     program: Option<&P>,
@@ -279,50 +686,75 @@ where
     A: AsRef<OsStr> + ?Sized + 'a,
     I: IntoIterator<Item = &'a A> + 'a,
 {
-    let program = program
-        .map(AsRef::as_ref)
-        .unwrap_or(NFT_EXECUTABLE.as_ref());
-    let mut nft_cmd = Command::new(program);
-    let default_args = ["-j", "-f", "-"];
-    let process = nft_cmd
-        .args(args)
-        .args(default_args)
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn();
-    let mut process = process.map_err(|e| NftablesError::NftExecution {
-        program: program.into(),
-        inner: e,
-    })?;
+    apply_ruleset_raw_with_executor(payload, &ProcessExecutor::new(program), args)
+}
 
-    let mut stdin = process.stdin.take().unwrap();
-    stdin
-        .write_all(payload.as_bytes())
-        .map_err(|e| NftablesError::NftExecution {
-            program: program.into(),
-            inner: e,
-        })?;
-    drop(stdin);
-
-    let result = process.wait_with_output();
-    match result {
-        Ok(output) if output.status.success() => read_output(program, output.stdout),
-        Ok(process_result) => {
-            let stdout = read_output(program, process_result.stdout)?;
-            let stderr = read_output(program, process_result.stderr)?;
-
-            Err(NftablesError::NftFailed {
-                program: program.into(),
-                hint: "applying ruleset".to_string(),
-                stdout,
-                stderr,
-            })
-        }
-        Err(e) => Err(NftablesError::NftExecution {
-            program: program.into(),
-            inner: e,
-        }),
-    }
+/// Apply the given raw rule set json through the given [executor](NftExecutor)
+/// with custom arguments.
+///
+/// This allows to configure a [ProcessExecutor] with a
+/// [timeout](ProcessExecutor::with_timeout) or a
+/// [network namespace](ProcessExecutor::with_netns) when applying a raw payload.
+/// See [`apply_ruleset_raw`] for the meaning of `args`.
+///
+/// The command's stdout is returned as a [`String`].
+pub fn apply_ruleset_raw_with_executor<'a, E, A, I>(
+    payload: &str,
+    executor: &E,
+    args: I,
+) -> Result<String, NftablesError>
+where
+    E: NftExecutor + ?Sized,
+    A: AsRef<OsStr> + ?Sized + 'a,
+    I: IntoIterator<Item = &'a A> + 'a,
+{
+    let args: Vec<&OsStr> = args.into_iter().map(AsRef::as_ref).collect();
+    apply_raw(executor, payload, &args)
+}
+
+/// Check the given rule set against the kernel without applying it.
+///
+/// This is done by using `nft --check`, which parses and evaluates the
+/// commands like [`apply_ruleset`] but does not commit them. The error
+/// returned for an invalid rule set is the same as when applying it.
+pub fn check_ruleset(nftables: &Nftables) -> Result<(), NftablesError> {
+    check_ruleset_with_args(nftables, DEFAULT_NFT, DEFAULT_ARGS)
+}
+
+/// Check the given rule set without applying it through the given
+/// [executor](NftExecutor).
+///
+/// See [`check_ruleset`] for more information.
+pub fn check_ruleset_with_executor<E: NftExecutor + ?Sized>(
+    nftables: &Nftables,
+    executor: &E,
+) -> Result<(), NftablesError> {
+    let nftables = serde_json::to_string(nftables).expect("failed to serialize Nftables struct");
+    apply_raw(executor, &nftables, &[OsStr::new("--check")])?;
+    Ok(())
+}
+
+/// Check the given rule set without applying it by calling a custom `nft`
+/// with custom arguments.
+///
+/// See [`apply_ruleset_with_args`] for the meaning of `program` and `args`.
+pub fn check_ruleset_with_args<'a, P, A, I>(
+    nftables: &Nftables,
+    program: Option<&P>,
+    args: I,
+) -> Result<(), NftablesError>
+where
+    P: AsRef<OsStr> + ?Sized,
+    A: AsRef<OsStr> + ?Sized + 'a,
+    I: IntoIterator<Item = &'a A> + 'a,
+{
+    let nftables = serde_json::to_string(nftables).expect("failed to serialize Nftables struct");
+    let args = args
+        .into_iter()
+        .map(AsRef::as_ref)
+        .chain(Some("--check".as_ref()));
+    apply_ruleset_raw(&nftables, program, args)?;
+    Ok(())
 }
 
 /// Apply the given rule set to the kernel asynchronously.
@@ -333,6 +765,20 @@ pub async fn apply_ruleset_async(nftables: &Nftables<'_>) -> Result<(), Nftables
     apply_ruleset_with_args_async(nftables, DEFAULT_NFT, DEFAULT_ARGS).await
 }
 
+/// Apply the given rule set to the kernel asynchronously through the given
+/// [executor](NftExecutorAsync).
+///
+/// See the synchronous [`apply_ruleset_with_executor`] for more information.
+#[cfg(any(feature = "tokio", feature = "async-process"))]
+pub async fn apply_ruleset_with_executor_async<E: NftExecutorAsync>(
+    nftables: &Nftables<'_>,
+    executor: &E,
+) -> Result<(), NftablesError> {
+    let nftables = serde_json::to_string(nftables).expect("failed to serialize Nftables struct");
+    apply_raw_async(executor, &nftables, &[]).await?;
+    Ok(())
+}
+
 /// Apply the given rule set asynchronously by calling a custom `nft` with custom arguments.
 ///
 /// See the synchronous [`apply_ruleset_with_args`] for more information.
@@ -363,6 +809,21 @@ pub async fn apply_and_return_ruleset_async(
     apply_and_return_ruleset_with_args_async(nftables, DEFAULT_NFT, DEFAULT_ARGS).await
 }
 
+/// Apply the given rule set asynchronously through the given
+/// [executor](NftExecutorAsync), and returns the processed rule set with extra
+/// information.
+///
+/// See the synchronous [`apply_and_return_ruleset`] for more information.
+#[cfg(any(feature = "tokio", feature = "async-process"))]
+pub async fn apply_and_return_ruleset_with_executor_async<E: NftExecutorAsync>(
+    nftables: &Nftables<'_>,
+    executor: &E,
+) -> Result<Nftables<'static>, NftablesError> {
+    let nftables = serde_json::to_string(nftables).expect("failed to serialize Nftables struct");
+    let output = apply_raw_async(executor, &nftables, &[OsStr::new("--echo")]).await?;
+    serde_json::from_str(&output).map_err(NftablesError::NftInvalidJson)
+}
+
 /// Apply the given rule set asynchronously by calling a custom `nft` with custom
 /// arguments, and returns the processed rule set with extra information.
 ///
@@ -387,6 +848,52 @@ where
     serde_json::from_str(&output).map_err(NftablesError::NftInvalidJson)
 }
 
+/// Check the given rule set against the kernel asynchronously without applying it.
+///
+/// See the synchronous [`check_ruleset`] for more information.
+#[cfg(any(feature = "tokio", feature = "async-process"))]
+pub async fn check_ruleset_async(nftables: &Nftables<'_>) -> Result<(), NftablesError> {
+    check_ruleset_with_args_async(nftables, DEFAULT_NFT, DEFAULT_ARGS).await
+}
+
+/// Check the given rule set asynchronously without applying it through the
+/// given [executor](NftExecutorAsync).
+///
+/// See the synchronous [`check_ruleset`] for more information.
+#[cfg(any(feature = "tokio", feature = "async-process"))]
+pub async fn check_ruleset_with_executor_async<E: NftExecutorAsync>(
+    nftables: &Nftables<'_>,
+    executor: &E,
+) -> Result<(), NftablesError> {
+    let nftables = serde_json::to_string(nftables).expect("failed to serialize Nftables struct");
+    apply_raw_async(executor, &nftables, &[OsStr::new("--check")]).await?;
+    Ok(())
+}
+
+/// Check the given rule set asynchronously without applying it by calling a
+/// custom `nft` with custom arguments.
+///
+/// See the synchronous [`check_ruleset_with_args`] for more information.
+#[cfg(any(feature = "tokio", feature = "async-process"))]
+pub async fn check_ruleset_with_args_async<'a, P, A, I>(
+    nftables: &Nftables<'_>,
+    program: Option<&P>,
+    args: I,
+) -> Result<(), NftablesError>
+where
+    P: AsRef<OsStr> + ?Sized,
+    A: AsRef<OsStr> + ?Sized + 'a,
+    I: IntoIterator<Item = &'a A> + 'a,
+{
+    let nftables = serde_json::to_string(nftables).expect("failed to serialize Nftables struct");
+    let args = args
+        .into_iter()
+        .map(AsRef::as_ref)
+        .chain(Some("--check".as_ref()));
+    apply_ruleset_raw_async(&nftables, program, args).await?;
+    Ok(())
+}
+
 /// Apply the given raw rule set json asynchronously by calling a custom `nft` with custom arguments.
 ///
 /// See the synchronous [`apply_ruleset_raw`] for more information.
@@ -401,63 +908,85 @@ where
     A: AsRef<OsStr> + ?Sized + 'a,
     I: IntoIterator<Item = &'a A> + 'a,
 {
-    #[cfg(feature = "async-process")]
-    use async_process::Command;
-    #[cfg(feature = "async-process")]
-    use futures_lite::io::AsyncWriteExt;
-    #[cfg(feature = "tokio")]
-    use tokio::io::AsyncWriteExt;
-    #[cfg(feature = "tokio")]
-    use tokio::process::Command;
-
-    let program = program
-        .map(AsRef::as_ref)
-        .unwrap_or(NFT_EXECUTABLE.as_ref());
-    let mut nft_cmd = Command::new(program);
-    let default_args = ["-j", "-f", "-"];
-    let process = nft_cmd
-        .args(args)
-        .args(default_args)
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn();
-    let mut process = process.map_err(|e| NftablesError::NftExecution {
-        program: program.into(),
-        inner: e,
-    })?;
+    apply_ruleset_raw_with_executor_async(payload, &ProcessExecutor::new(program), args).await
+}
 
-    let mut stdin = process.stdin.take().unwrap();
-    stdin
-        .write_all(payload.as_bytes())
-        .await
-        .map_err(|e| NftablesError::NftExecution {
-            program: program.into(),
-            inner: e,
-        })?;
-    drop(stdin);
-
-    #[cfg(feature = "tokio")]
-    let result = process.wait_with_output().await;
-    #[cfg(feature = "async-process")]
-    let result = process.output().await;
-    match result {
-        Ok(output) if output.status.success() => read_output(program, output.stdout),
-        Ok(process_result) => {
-            let stdout = read_output(program, process_result.stdout)?;
-            let stderr = read_output(program, process_result.stderr)?;
-
-            Err(NftablesError::NftFailed {
-                program: program.into(),
-                hint: "applying ruleset".to_string(),
-                stdout,
-                stderr,
-            })
-        }
-        Err(e) => Err(NftablesError::NftExecution {
-            program: program.into(),
-            inner: e,
-        }),
+/// Apply the given raw rule set json asynchronously through the given
+/// [executor](NftExecutorAsync) with custom arguments.
+///
+/// See the synchronous [`apply_ruleset_raw_with_executor`] for more information.
+#[cfg(any(feature = "tokio", feature = "async-process"))]
+pub async fn apply_ruleset_raw_with_executor_async<'a, E, A, I>(
+    payload: &str,
+    executor: &E,
+    args: I,
+) -> Result<String, NftablesError>
+where
+    E: NftExecutorAsync,
+    A: AsRef<OsStr> + ?Sized + 'a,
+    I: IntoIterator<Item = &'a A> + 'a,
+{
+    let args: Vec<&OsStr> = args.into_iter().map(AsRef::as_ref).collect();
+    apply_raw_async(executor, payload, &args).await
+}
+
+/// Arguments for listing: `-j` followed by `args`, or `-j list ruleset` if `args` is empty.
+fn list_args<'a>(args: &[&'a OsStr]) -> Vec<&'a OsStr> {
+    let mut nft_args = vec![OsStr::new("-j")];
+    match args {
+        [] => nft_args.extend(["list", "ruleset"].map(OsStr::new)),
+        args => nft_args.extend_from_slice(args),
     }
+    nft_args
+}
+
+/// Arguments for applying: `args` followed by `-j -f -`.
+fn apply_args<'a>(args: &[&'a OsStr]) -> Vec<&'a OsStr> {
+    let mut nft_args = args.to_vec();
+    nft_args.extend(["-j", "-f", "-"].map(OsStr::new));
+    nft_args
+}
+
+/// Describes an apply invocation for [NftablesError::NftFailed].
+fn apply_hint(args: &[&OsStr]) -> &'static str {
+    match args.iter().any(|arg| *arg == "-c" || *arg == "--check") {
+        true => "checking ruleset",
+        false => "applying ruleset",
+    }
+}
+
+fn list_raw<E: NftExecutor + ?Sized>(
+    executor: &E,
+    args: &[&OsStr],
+) -> Result<String, NftablesError> {
+    executor.execute(&list_args(args), None)
+}
+
+fn apply_raw<E: NftExecutor + ?Sized>(
+    executor: &E,
+    payload: &str,
+    args: &[&OsStr],
+) -> Result<String, NftablesError> {
+    executor.execute(&apply_args(args), Some(payload))
+}
+
+#[cfg(any(feature = "tokio", feature = "async-process"))]
+async fn list_raw_async<E: NftExecutorAsync>(
+    executor: &E,
+    args: &[&OsStr],
+) -> Result<String, NftablesError> {
+    executor.execute_async(&list_args(args), None).await
+}
+
+#[cfg(any(feature = "tokio", feature = "async-process"))]
+async fn apply_raw_async<E: NftExecutorAsync>(
+    executor: &E,
+    payload: &str,
+    args: &[&OsStr],
+) -> Result<String, NftablesError> {
+    executor
+        .execute_async(&apply_args(args), Some(payload))
+        .await
 }
 
 fn read_output(program: impl Into<OsString>, bytes: Vec<u8>) -> Result<String, NftablesError> {
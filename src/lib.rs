@@ -31,12 +31,65 @@ pub mod stmt;
 /// Contains common type definitions referred to in the schema.
 pub mod types;
 
+/// Contains fluent builders for rules, chains and sets.
+pub mod builder;
+
+/// Contains structured diagnostics parsed from the error output of `nft`.
+pub mod diagnostic;
+
 /// Contains methods to communicate with nftables JSON API.
 pub mod helper;
 
+/// Contains a semantic diff between two nftables documents.
+pub mod diff;
+
+/// Contains an offline validator for references and structural constraints.
+pub mod validate;
+
+/// Contains the call graph of the chains of a ruleset (`jump` and `goto`).
+pub mod graph;
+
+/// Contains an offline evaluator simulating how a ruleset treats a packet.
+pub mod eval;
+
+/// Contains a linter for shadowed, redundant and unreachable rules.
+pub mod lint;
+
+/// Contains a reconciler that converges the live ruleset onto a desired one.
+pub mod reconcile;
+
+/// Contains a subscription to ruleset change events (`nft monitor`).
+pub mod monitor;
+
+/// Contains packet trace events (`nft monitor trace`) and their grouping per packet.
+pub mod trace;
+
+/// Contains a parser for the native nft syntax (as read by `nft -f`).
+pub mod parser;
+
+/// Contains a printer rendering documents in the native nft syntax.
+pub mod printer;
+
+/// Contains an executor that drives libnftables in-process.
+#[cfg(feature = "libnftables")]
+pub mod libnftables;
+
 /// Contains node visitors for serde.
 pub mod visitor;
 
+/// Contains the [Visit](visit::Visit) trait walking a document by reference.
+pub mod visit;
+
+/// Contains the [VisitMut](visit_mut::VisitMut) trait walking a document by mutable reference.
+pub mod visit_mut;
+
+/// Contains the [Fold](fold::Fold) trait rebuilding a document by value.
+pub mod fold;
+
+/// Contains support code for the `nft_rule!` macro of `nftables-macros`.
+#[doc(hidden)]
+pub mod macro_support;
+
 /// Contains handling and parsing of command line arguments.
 pub mod cli;
 
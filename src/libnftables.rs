@@ -0,0 +1,260 @@
+use std::{
+    ffi::{c_char, c_int, c_uint, CStr, CString, OsStr, OsString},
+    io,
+    sync::Mutex,
+};
+
+use libloading::Library;
+
+use crate::diagnostic::parse_diagnostics;
+use crate::helper::{NftExecutor, NftablesError};
+
+/// Default soname of the nftables library.
+pub const LIBNFTABLES_SONAME: &str = "libnftables.so.1";
+
+const NFT_CTX_DEFAULT: c_uint = 0;
+const NFT_CTX_OUTPUT_HANDLE: c_uint = 1 << 3;
+const NFT_CTX_OUTPUT_JSON: c_uint = 1 << 4;
+const NFT_CTX_OUTPUT_ECHO: c_uint = 1 << 5;
+
+#[repr(C)]
+struct NftCtx {
+    _private: [u8; 0],
+}
+
+type NftCtxNew = unsafe extern "C" fn(c_uint) -> *mut NftCtx;
+type NftCtxFree = unsafe extern "C" fn(*mut NftCtx);
+type NftCtxOutputSetFlags = unsafe extern "C" fn(*mut NftCtx, c_uint);
+type NftCtxSetDryRun = unsafe extern "C" fn(*mut NftCtx, bool);
+type NftCtxBuffer = unsafe extern "C" fn(*mut NftCtx) -> c_int;
+type NftCtxGetBuffer = unsafe extern "C" fn(*mut NftCtx) -> *const c_char;
+type NftRunCmdFromBuffer = unsafe extern "C" fn(*mut NftCtx, *const c_char) -> c_int;
+
+/// Function pointers resolved from the loaded library.
+struct Symbols {
+    ctx_free: NftCtxFree,
+    output_set_flags: NftCtxOutputSetFlags,
+    set_dry_run: NftCtxSetDryRun,
+    get_output_buffer: NftCtxGetBuffer,
+    get_error_buffer: NftCtxGetBuffer,
+    run_cmd_from_buffer: NftRunCmdFromBuffer,
+}
+
+/// An nftables context owned by a [LibNftablesExecutor].
+struct Context {
+    ctx: *mut NftCtx,
+    symbols: Symbols,
+}
+
+// SAFETY: the context is only ever accessed while holding the executor's mutex.
+unsafe impl Send for Context {}
+
+impl Drop for Context {
+    fn drop(&mut self) {
+        // SAFETY: `ctx` was returned by `nft_ctx_new` and is freed exactly once.
+        unsafe { (self.symbols.ctx_free)(self.ctx) }
+    }
+}
+
+/// Executor that drives `libnftables` in-process instead of spawning `nft`.
+///
+/// The library is loaded at runtime, so no build-time dependency on the
+/// nftables headers is required. A single nftables context is kept for the
+/// lifetime of the executor and reused by all invocations.
+///
+/// Only the `nft` arguments used by the [helper](crate::helper) functions are
+/// understood: `-j`/`--json`, `-e`/`--echo`, `-a`/`--handle`, `-c`/`--check`
+/// and `-f -` (read the command from the standard input).
+/// All other arguments are passed to nftables as the command to run,
+/// e.g. `list ruleset`.
+pub struct LibNftablesExecutor {
+    name: OsString,
+    context: Mutex<Context>,
+    // Declared last so that the library outlives the context on drop.
+    _library: Library,
+}
+
+impl LibNftablesExecutor {
+    /// Loads the default nftables library ([LIBNFTABLES_SONAME]).
+    pub fn new() -> Result<LibNftablesExecutor, NftablesError> {
+        Self::with_library(LIBNFTABLES_SONAME)
+    }
+
+    /// Loads the nftables library from the given file name or path.
+    pub fn with_library<P: AsRef<OsStr> + ?Sized>(
+        library: &P,
+    ) -> Result<LibNftablesExecutor, NftablesError> {
+        let name: OsString = library.as_ref().into();
+        let load_error = |e: libloading::Error| NftablesError::NftExecution {
+            program: name.clone(),
+            inner: io::Error::other(e),
+        };
+
+        // SAFETY: loading libnftables runs no initialization routines with
+        // preconditions, and the symbol types match the libnftables(3) API.
+        unsafe {
+            let library = Library::new(&name).map_err(load_error)?;
+            let ctx_new = *library
+                .get::<NftCtxNew>(b"nft_ctx_new\0")
+                .map_err(load_error)?;
+            let buffer_output = *library
+                .get::<NftCtxBuffer>(b"nft_ctx_buffer_output\0")
+                .map_err(load_error)?;
+            let buffer_error = *library
+                .get::<NftCtxBuffer>(b"nft_ctx_buffer_error\0")
+                .map_err(load_error)?;
+            let symbols = Symbols {
+                ctx_free: *library.get(b"nft_ctx_free\0").map_err(load_error)?,
+                output_set_flags: *library
+                    .get(b"nft_ctx_output_set_flags\0")
+                    .map_err(load_error)?,
+                set_dry_run: *library.get(b"nft_ctx_set_dry_run\0").map_err(load_error)?,
+                get_output_buffer: *library
+                    .get(b"nft_ctx_get_output_buffer\0")
+                    .map_err(load_error)?,
+                get_error_buffer: *library
+                    .get(b"nft_ctx_get_error_buffer\0")
+                    .map_err(load_error)?,
+                run_cmd_from_buffer: *library
+                    .get(b"nft_run_cmd_from_buffer\0")
+                    .map_err(load_error)?,
+            };
+
+            let ctx = ctx_new(NFT_CTX_DEFAULT);
+            if ctx.is_null() {
+                return Err(NftablesError::NftExecution {
+                    program: name,
+                    inner: io::Error::other("nft_ctx_new returned NULL"),
+                });
+            }
+            let context = Context { ctx, symbols };
+            if buffer_output(ctx) != 0 || buffer_error(ctx) != 0 {
+                return Err(NftablesError::NftExecution {
+                    program: name,
+                    inner: io::Error::other("unable to buffer nftables output"),
+                });
+            }
+
+            Ok(LibNftablesExecutor {
+                name,
+                context: Mutex::new(context),
+                _library: library,
+            })
+        }
+    }
+
+    fn invalid_input(&self, msg: String) -> NftablesError {
+        NftablesError::NftExecution {
+            program: self.name.clone(),
+            inner: io::Error::new(io::ErrorKind::InvalidInput, msg),
+        }
+    }
+}
+
+impl std::fmt::Debug for LibNftablesExecutor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LibNftablesExecutor")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl NftExecutor for LibNftablesExecutor {
+    fn execute(&self, args: &[&OsStr], stdin: Option<&str>) -> Result<String, NftablesError> {
+        let mut flags = NFT_CTX_DEFAULT;
+        let mut dry_run = false;
+        let mut from_stdin = false;
+        let mut command: Vec<&str> = Vec::new();
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let arg = arg
+                .to_str()
+                .ok_or_else(|| self.invalid_input(format!("argument {arg:?} is not utf8")))?;
+            match arg {
+                "-j" | "--json" => flags |= NFT_CTX_OUTPUT_JSON,
+                "-e" | "--echo" => flags |= NFT_CTX_OUTPUT_ECHO,
+                "-a" | "--handle" => flags |= NFT_CTX_OUTPUT_HANDLE,
+                "-c" | "--check" => dry_run = true,
+                "-f" | "--file" => match args.next().map(|a| a.to_str()) {
+                    Some(Some("-")) => from_stdin = true,
+                    _ => return Err(self.invalid_input("only `-f -` is supported".into())),
+                },
+                arg if arg.starts_with('-') => {
+                    return Err(self.invalid_input(format!("unsupported argument `{arg}`")))
+                }
+                arg => command.push(arg),
+            }
+        }
+
+        let (buffer, hint) = match (from_stdin, stdin) {
+            (true, Some(payload)) if dry_run => (payload.to_owned(), "checking ruleset"),
+            (true, Some(payload)) => (payload.to_owned(), "applying ruleset"),
+            (false, None) => (command.join(" "), "getting the current ruleset"),
+            _ => {
+                return Err(self.invalid_input(
+                    "`-f -` must be given if and only if there is a payload".into(),
+                ))
+            }
+        };
+        let buffer = CString::new(buffer)
+            .map_err(|e| self.invalid_input(format!("payload contains a NUL byte: {e}")))?;
+
+        let context = self.context.lock().unwrap_or_else(|e| e.into_inner());
+        let Context { ctx, symbols } = &*context;
+        // SAFETY: the context is valid and exclusively borrowed through the
+        // mutex; the returned buffers stay valid until the next command.
+        let (rc, stdout, stderr) = unsafe {
+            (symbols.output_set_flags)(*ctx, flags);
+            (symbols.set_dry_run)(*ctx, dry_run);
+            let rc = (symbols.run_cmd_from_buffer)(*ctx, buffer.as_ptr());
+            let stdout = read_buffer((symbols.get_output_buffer)(*ctx));
+            let stderr = read_buffer((symbols.get_error_buffer)(*ctx));
+            (rc, stdout, stderr)
+        };
+        let stdout = read_output(&self.name, stdout)?;
+
+        if rc != 0 {
+            let stderr = read_output(&self.name, stderr)?;
+            return Err(NftablesError::NftFailed {
+                program: self.name.clone(),
+                hint: hint.to_string(),
+                stdout,
+                diagnostics: parse_diagnostics(&stderr, stdin),
+                stderr,
+            });
+        }
+        Ok(stdout)
+    }
+}
+
+#[cfg(any(feature = "tokio", feature = "async-process"))]
+/// Runs the command synchronously, as libnftables has no asynchronous API.
+impl crate::helper::NftExecutorAsync for LibNftablesExecutor {
+    async fn execute_async(
+        &self,
+        args: &[&OsStr],
+        stdin: Option<&str>,
+    ) -> Result<String, NftablesError> {
+        self.execute(args, stdin)
+    }
+}
+
+/// Copies a (possibly null) C string returned by libnftables.
+///
+/// # Safety
+///
+/// `buffer` must be null or point to a NUL-terminated string.
+unsafe fn read_buffer(buffer: *const c_char) -> Vec<u8> {
+    if buffer.is_null() {
+        return Vec::new();
+    }
+    CStr::from_ptr(buffer).to_bytes().to_vec()
+}
+
+fn read_output(name: &OsStr, bytes: Vec<u8>) -> Result<String, NftablesError> {
+    String::from_utf8(bytes).map_err(|e| NftablesError::NftOutputEncoding {
+        program: name.into(),
+        inner: e,
+    })
+}
@@ -0,0 +1,376 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    net::IpAddr,
+};
+
+use crate::expr::{Expression, NamedExpression, SetItem};
+use crate::schema::{NfCmd, NfListObject, NfObject, Nftables, Rule, SetFlag};
+use crate::stmt::{Match, Operator, Statement};
+use crate::types::NfFamily;
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+/// Configuration of [lint].
+pub struct LintConfig {
+    /// Chains whose rules must all have a counter, as family, table and
+    /// chain name.
+    pub require_counters: Vec<(NfFamily, String, String)>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+/// A finding of [lint].
+///
+/// Objects are referred to by their index in [objects](Nftables::objects).
+pub enum Lint {
+    /// A rule follows a rule of the same chain that ends with a verdict and
+    /// matches every packet.
+    Unreachable { chain: String, after: usize },
+    /// An earlier rule of the same chain ends with a verdict and matches all
+    /// packets the rule matches.
+    Shadowed { chain: String, by: usize },
+    /// A rule has the same statements as an earlier rule of the same chain.
+    Duplicate { chain: String, of: usize },
+    /// A set or map without the `interval` flag contains a prefix or range,
+    /// which nft rejects.
+    IntervalWithoutFlag { set: String, element: String },
+    /// Two elements of a set or map overlap.
+    OverlappingElements {
+        set: String,
+        first: String,
+        second: String,
+    },
+    /// A rule of a chain listed in [LintConfig::require_counters] has no
+    /// counter.
+    MissingCounter { chain: String },
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Lint::Unreachable { chain, after } => write!(
+                f,
+                "rule in chain `{chain}` is unreachable after the unconditional verdict of object {after}"
+            ),
+            Lint::Shadowed { chain, by } => {
+                write!(f, "rule in chain `{chain}` is shadowed by object {by}")
+            }
+            Lint::Duplicate { chain, of } => {
+                write!(f, "rule in chain `{chain}` duplicates object {of}")
+            }
+            Lint::IntervalWithoutFlag { set, element } => write!(
+                f,
+                "set `{set}` contains the interval `{element}` but lacks the interval flag"
+            ),
+            Lint::OverlappingElements { set, first, second } => write!(
+                f,
+                "set `{set}` contains the overlapping elements `{first}` and `{second}`"
+            ),
+            Lint::MissingCounter { chain } => write!(f, "rule in chain `{chain}` has no counter"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+/// A [lint] finding tied to an object of the document.
+pub struct Finding {
+    /// Index of the offending object in [objects](Nftables::objects).
+    pub index: usize,
+    /// Handle of the offending rule, set or map, if the document has one.
+    pub handle: Option<u32>,
+    /// What is suspicious.
+    pub lint: Lint,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "object {}", self.index)?;
+        if let Some(handle) = self.handle {
+            write!(f, " (handle {handle})")?;
+        }
+        write!(f, ": {}", self.lint)
+    }
+}
+
+/// Family, table and name of a chain, set or map.
+type Key<'s> = (NfFamily, &'s str, &'s str);
+
+/// An element of a set or map, with the index and handle of the object
+/// adding it.
+type Member<'s, 'a> = (usize, Option<u32>, &'s Expression<'a>);
+
+/// Finds rules that can never match or lack a required counter, and set
+/// elements nft rejects or that overlap.
+///
+/// Rules are compared with the earlier rules of their chain, in the order
+/// nft would evaluate them: listed objects and `add` and `create` commands
+/// append rules, `insert` commands prepend them. A rule shadows a later one
+/// if it ends with a verdict other than `jump` and each of its matches also
+/// appears in the later rule, or covers it with an anonymous set. Rules
+/// without any match make all later rules of the chain unreachable.
+///
+/// Overlaps are found between addresses, prefixes, numbers and ranges of
+/// numbers or addresses.
+pub fn lint(nftables: &Nftables, config: &LintConfig) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut chains: HashMap<Key, Vec<(usize, &Rule)>> = HashMap::new();
+    // Elements of each set or map, and whether it has the interval flag.
+    let mut sets: HashMap<Key, (bool, Vec<Member>)> = HashMap::new();
+
+    for (index, object) in nftables.objects.iter().enumerate() {
+        let (object, insert) = match object {
+            NfObject::ListObject(object)
+            | NfObject::CmdObject(NfCmd::Add(object) | NfCmd::Create(object)) => (object, false),
+            NfObject::CmdObject(NfCmd::Insert(object)) => (object, true),
+            _ => continue,
+        };
+        let (key, flags, elem, handle) = match object {
+            NfListObject::Rule(rule) => {
+                let rules = chains
+                    .entry((rule.family, &rule.table, &rule.chain))
+                    .or_default();
+                match insert {
+                    true => rules.insert(0, (index, rule)),
+                    false => rules.push((index, rule)),
+                }
+                continue;
+            }
+            NfListObject::Set(set) => (
+                (set.family, &*set.table, &*set.name),
+                Some(&set.flags),
+                set.elem.as_deref().unwrap_or_default(),
+                set.handle,
+            ),
+            NfListObject::Map(map) => (
+                (map.family, &*map.table, &*map.name),
+                Some(&map.flags),
+                map.elem.as_deref().unwrap_or_default(),
+                map.handle,
+            ),
+            NfListObject::Element(element) => (
+                (element.family, &*element.table, &*element.name),
+                None,
+                &element.elem[..],
+                None,
+            ),
+            _ => continue,
+        };
+        let (interval, elements) = sets.entry(key).or_default();
+        if let Some(flags) = flags {
+            *interval = flags
+                .as_ref()
+                .is_some_and(|flags| flags.contains(&SetFlag::Interval));
+        }
+        elements.extend(elem.iter().map(|elem| (index, handle, elem)));
+    }
+
+    let mut chains: Vec<_> = chains.into_iter().collect();
+    chains.sort_by_key(|(_, rules)| rules.first().map(|(index, _)| *index));
+    for ((family, table, chain), rules) in chains {
+        let counters = config
+            .require_counters
+            .iter()
+            .any(|(f, t, c)| (*f, &**t, &**c) == (family, table, chain));
+        lint_chain(chain, &rules, counters, &mut findings);
+    }
+
+    let mut sets: Vec<_> = sets.into_iter().collect();
+    sets.sort_by_key(|(_, (_, elements))| elements.first().map(|(index, ..)| *index));
+    for ((_, _, set), (interval, elements)) in sets {
+        lint_set(set, interval, &elements, &mut findings);
+    }
+
+    findings.sort_by_key(|finding| finding.index);
+    findings
+}
+
+fn lint_chain(chain: &str, rules: &[(usize, &Rule)], counters: bool, findings: &mut Vec<Finding>) {
+    let mut report = |(index, rule): (usize, &Rule), lint| {
+        findings.push(Finding {
+            index,
+            handle: rule.handle,
+            lint,
+        })
+    };
+    for (position, &(index, rule)) in rules.iter().enumerate() {
+        if counters
+            && !rule
+                .expr
+                .iter()
+                .any(|stmt| matches!(stmt, Statement::Counter(_)))
+        {
+            let chain = chain.to_string();
+            report((index, rule), Lint::MissingCounter { chain });
+        }
+        let chain = chain.to_string();
+        for &(earlier, first) in &rules[..position] {
+            let lint = if first.expr == rule.expr {
+                Lint::Duplicate { chain, of: earlier }
+            } else if let Some(matches) = terminal_matches(first) {
+                if matches.is_empty() {
+                    Lint::Unreachable {
+                        chain,
+                        after: earlier,
+                    }
+                } else if matches.iter().all(|m| covered(m, rule)) {
+                    Lint::Shadowed { chain, by: earlier }
+                } else {
+                    continue;
+                }
+            } else {
+                continue;
+            };
+            report((index, rule), lint);
+            break;
+        }
+    }
+}
+
+/// Returns the matches of a rule that ends with a verdict and does nothing
+/// else conditional, or `None` for other rules.
+fn terminal_matches<'r, 'a>(rule: &'r Rule<'a>) -> Option<Vec<&'r Match<'a>>> {
+    let mut matches = Vec::new();
+    for stmt in rule.expr.iter() {
+        match stmt {
+            Statement::Match(m) => matches.push(m),
+            Statement::Counter(_)
+            | Statement::Log(_)
+            | Statement::Mangle(_)
+            | Statement::Notrack
+            | Statement::CTHelper(_)
+            | Statement::CTTimeout(_)
+            | Statement::CTExpectation(_)
+            | Statement::Set(_)
+            | Statement::Dup(_) => {}
+            Statement::Accept(_)
+            | Statement::Drop(_)
+            | Statement::Reject(_)
+            | Statement::Return(_)
+            | Statement::Goto(_)
+            | Statement::Queue(_)
+            | Statement::SNAT(_)
+            | Statement::DNAT(_)
+            | Statement::Masquerade(_)
+            | Statement::Redirect(_) => return Some(matches),
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// Whether a match holds for every packet a rule matches.
+fn covered(m: &Match, rule: &Rule) -> bool {
+    rule.expr.iter().any(|stmt| match stmt {
+        Statement::Match(other) if other == m => true,
+        Statement::Match(other) if other.left == m.left => {
+            let Expression::Named(NamedExpression::Set(items)) = &m.right else {
+                return false;
+            };
+            let contains = |value: &Expression| {
+                items
+                    .iter()
+                    .any(|item| matches!(item, SetItem::Element(e) if e == value))
+            };
+            matches!(m.op, Operator::EQ | Operator::IN)
+                && matches!(other.op, Operator::EQ | Operator::IN)
+                && match &other.right {
+                    Expression::Named(NamedExpression::Set(others)) => {
+                        others.iter().all(|item| match item {
+                            SetItem::Element(e) => contains(e),
+                            _ => false,
+                        })
+                    }
+                    value => contains(value),
+                }
+        }
+        _ => false,
+    })
+}
+
+/// Closed interval of an element, or `None` if the element is no address,
+/// prefix, number or range.
+///
+/// Addresses of both families are mapped apart so that they never overlap.
+fn interval(elem: &Expression) -> Option<(u128, u128)> {
+    let point = |expr: &Expression| match expr {
+        Expression::Number(n) => Some(u128::from(*n)),
+        Expression::String(s) => match s.parse::<IpAddr>().ok()? {
+            IpAddr::V4(addr) => Some(1 << 64 | u128::from(u32::from(addr))),
+            IpAddr::V6(addr) => Some(u128::from(addr)),
+        },
+        _ => None,
+    };
+    match elem {
+        Expression::Named(NamedExpression::Elem(elem)) => interval(&elem.val),
+        Expression::Range(range) => Some((point(&range.range[0])?, point(&range.range[1])?)),
+        Expression::Named(NamedExpression::Prefix(prefix)) => {
+            let Expression::String(addr) = &*prefix.addr else {
+                return None;
+            };
+            let (base, bits) = match addr.parse::<IpAddr>().ok()? {
+                IpAddr::V4(addr) => (1 << 64 | u128::from(u32::from(addr)), 32),
+                IpAddr::V6(addr) => (u128::from(addr), 128),
+            };
+            let host = match bits - prefix.len.min(bits) {
+                128 => u128::MAX,
+                host => (1 << host) - 1,
+            };
+            Some((base & !host, base | host))
+        }
+        elem => point(elem).map(|p| (p, p)),
+    }
+}
+
+/// Returns the key of a map element.
+fn key<'e, 'a>(elem: &'e Expression<'a>) -> &'e Expression<'a> {
+    match elem {
+        Expression::List(pair) if pair.len() == 2 => &pair[0],
+        elem => elem,
+    }
+}
+
+fn lint_set(set: &str, interval_flag: bool, elements: &[Member], findings: &mut Vec<Finding>) {
+    let mut intervals = Vec::new();
+    let mut reported = HashSet::new();
+    for &(index, handle, elem) in elements {
+        let elem = key(elem);
+        let is_interval = matches!(
+            elem,
+            Expression::Range(_) | Expression::Named(NamedExpression::Prefix(_))
+        );
+        if is_interval && !interval_flag && reported.insert(index) {
+            findings.push(Finding {
+                index,
+                handle,
+                lint: Lint::IntervalWithoutFlag {
+                    set: set.to_string(),
+                    element: elem.to_string(),
+                },
+            });
+        }
+        if let Some((low, high)) = interval(elem) {
+            intervals.push((low, high, index, handle, elem));
+        }
+    }
+
+    intervals.sort_by_key(|&(low, high, ..)| (low, high));
+    // The element reaching furthest so far.
+    let mut furthest: Option<(u128, &Expression)> = None;
+    for (low, high, index, handle, elem) in intervals {
+        if let Some((end, first)) = furthest {
+            if low <= end {
+                findings.push(Finding {
+                    index,
+                    handle,
+                    lint: Lint::OverlappingElements {
+                        set: set.to_string(),
+                        first: first.to_string(),
+                        second: elem.to_string(),
+                    },
+                });
+            }
+        }
+        if furthest.map_or(true, |(end, _)| high > end) {
+            furthest = Some((high, elem));
+        }
+    }
+}
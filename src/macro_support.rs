@@ -0,0 +1,19 @@
+use std::borrow::Cow;
+
+use crate::expr::Expression;
+
+/// Converts a variable interpolated where the rule expects a value.
+pub fn value<'a>(value: impl Into<Expression<'a>>) -> Expression<'a> {
+    value.into()
+}
+
+/// Converts a variable interpolated where the rule expects a name, e.g. the
+/// target of a jump.
+pub fn name<'a>(name: impl Into<Cow<'a, str>>) -> Cow<'a, str> {
+    name.into()
+}
+
+/// Converts a variable interpolated as `@$name` into a set reference.
+pub fn set_reference<'a>(name: impl Into<Cow<'a, str>>) -> Cow<'a, str> {
+    Cow::Owned(format!("@{}", name.into()))
+}
@@ -0,0 +1,379 @@
+use std::{
+    ffi::{OsStr, OsString},
+    io::{self, BufRead, BufReader, Read},
+    process::{Child, ChildStdout, Stdio},
+    thread::{self, JoinHandle},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::diagnostic::parse_diagnostics;
+use crate::helper::{NftablesError, ProcessExecutor};
+use crate::schema::NfListObject;
+use crate::trace::Trace;
+
+/// Describes a monitor invocation for [NftablesError::NftFailed].
+const MONITOR_HINT: &str = "monitoring the ruleset";
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+/// A single event printed by `nft -j monitor`.
+pub enum MonitorEvent<'a> {
+    /// A ruleset element (table, chain, rule, set element, ...) was added.
+    Add(NfListObject<'a>),
+    /// A ruleset element was deleted.
+    Delete(NfListObject<'a>),
+    /// A packet traversed a chain with `meta nftrace set 1`.
+    Trace(Box<Trace<'a>>),
+}
+
+impl MonitorEvent<'static> {
+    /// Parses a single line of `nft -j monitor` output.
+    ///
+    /// Set elements are printed by nft as `"elem": {"set": [...]}`, which is
+    /// flattened into [Element::elem](crate::schema::Element::elem).
+    pub fn from_line(line: &str) -> Result<MonitorEvent<'static>, NftablesError> {
+        let mut value: Value = serde_json::from_str(line).map_err(NftablesError::NftInvalidJson)?;
+        for cmd in ["add", "delete"] {
+            if let Some(elem) = value.pointer_mut(&format!("/{cmd}/element/elem")) {
+                if let Some(set) = elem.get_mut("set").map(Value::take) {
+                    *elem = set;
+                }
+            }
+        }
+        serde_json::from_value(value).map_err(NftablesError::NftInvalidJson)
+    }
+}
+
+/// How many trailing bytes of nft's standard error are kept for [monitor_failed].
+const STDERR_TAIL: usize = 64 * 1024;
+
+/// Appends `data` to `tail`, keeping only the last [STDERR_TAIL] bytes.
+fn push_tail(tail: &mut Vec<u8>, data: &[u8]) {
+    tail.extend_from_slice(data);
+    if tail.len() > STDERR_TAIL {
+        tail.drain(..tail.len() - STDERR_TAIL);
+    }
+}
+
+/// Reads `pipe` until it is closed, keeping the tail of the output.
+fn drain_stderr(mut pipe: impl Read) -> io::Result<Vec<u8>> {
+    let mut tail = Vec::new();
+    let mut buf = [0; 4096];
+    loop {
+        match pipe.read(&mut buf) {
+            Ok(0) => return Ok(tail),
+            Ok(n) => push_tail(&mut tail, &buf[..n]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Arguments for monitoring: `-j monitor` followed by `args`, e.g. `rules` or `trace`.
+fn monitor_args<'a>(args: &[&'a OsStr]) -> Vec<&'a OsStr> {
+    let mut nft_args = ["-j", "monitor"].map(OsStr::new).to_vec();
+    nft_args.extend_from_slice(args);
+    nft_args
+}
+
+/// Error for an `nft monitor` that exited with a failure.
+fn monitor_failed(program: &OsStr, stderr: Vec<u8>) -> NftablesError {
+    let stderr = String::from_utf8_lossy(&stderr).into_owned();
+    NftablesError::NftFailed {
+        program: program.into(),
+        hint: MONITOR_HINT.to_string(),
+        stdout: String::new(),
+        diagnostics: parse_diagnostics(&stderr, None),
+        stderr,
+    }
+}
+
+/// Blocking iterator over the events of a running `nft -j monitor`.
+///
+/// Each line printed by nft is parsed into a [MonitorEvent]; lines that cannot
+/// be parsed are yielded as errors without ending the iteration. The iterator
+/// ends when nft exits, yielding a final [NftablesError::NftFailed] if it did
+/// not exit successfully. Dropping the monitor kills nft.
+#[derive(Debug)]
+pub struct Monitor {
+    program: OsString,
+    child: Child,
+    lines: Option<io::Lines<BufReader<ChildStdout>>>,
+    /// Drains standard error while nft runs, so it never blocks on a full pipe.
+    stderr: Option<JoinHandle<io::Result<Vec<u8>>>>,
+}
+
+impl Monitor {
+    /// Waits for the exited nft and checks its exit status.
+    fn finish(&mut self) -> Result<(), NftablesError> {
+        let execution_error = |e| NftablesError::NftExecution {
+            program: self.program.clone(),
+            inner: e,
+        };
+        let status = self.child.wait().map_err(execution_error)?;
+        if status.success() {
+            return Ok(());
+        }
+        let stderr = match self.stderr.take().map(JoinHandle::join) {
+            Some(Ok(stderr)) => stderr.map_err(execution_error)?,
+            Some(Err(_)) | None => Vec::new(),
+        };
+        Err(monitor_failed(&self.program, stderr))
+    }
+}
+
+impl Iterator for Monitor {
+    type Item = Result<MonitorEvent<'static>, NftablesError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let lines = self.lines.as_mut()?;
+        loop {
+            match lines.next() {
+                Some(Ok(line)) if line.trim().is_empty() => continue,
+                Some(Ok(line)) => return Some(MonitorEvent::from_line(&line)),
+                Some(Err(e)) => {
+                    self.lines = None;
+                    return Some(Err(NftablesError::NftExecution {
+                        program: self.program.clone(),
+                        inner: e,
+                    }));
+                }
+                None => {
+                    self.lines = None;
+                    return self.finish().err().map(Err);
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Monitor {
+    fn drop(&mut self) {
+        if let Ok(None) = self.child.try_wait() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+/// Starts monitoring all ruleset changes with the default `nft` executable.
+pub fn monitor() -> Result<Monitor, NftablesError> {
+    monitor_with_executor(&ProcessExecutor::default(), &[])
+}
+
+/// Starts `nft -j monitor` with the given additional arguments
+/// (e.g. `new rules` or `trace`).
+///
+/// The program and network namespace of the executor are honored, its
+/// [timeout](ProcessExecutor::with_timeout) is not, as nft runs until the
+/// monitor is dropped.
+pub fn monitor_with_executor(
+    executor: &ProcessExecutor,
+    args: &[&OsStr],
+) -> Result<Monitor, NftablesError> {
+    let program = executor.program().to_owned();
+    let mut child = executor
+        .command(&monitor_args(args))?
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| NftablesError::NftExecution {
+            program: program.clone(),
+            inner: e,
+        })?;
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+    let stderr = thread::Builder::new()
+        .name("nft monitor stderr".to_string())
+        .spawn(move || drain_stderr(stderr))
+        .map_err(|e| {
+            let _ = child.kill();
+            let _ = child.wait();
+            NftablesError::NftExecution {
+                program: program.clone(),
+                inner: e,
+            }
+        })?;
+    Ok(Monitor {
+        program,
+        child,
+        lines: Some(BufReader::new(stdout).lines()),
+        stderr: Some(stderr),
+    })
+}
+
+#[cfg(any(feature = "tokio", feature = "async-process"))]
+pub use self::stream::*;
+
+#[cfg(any(feature = "tokio", feature = "async-process"))]
+mod stream {
+    use std::{
+        ffi::{OsStr, OsString},
+        pin::Pin,
+        process::Stdio,
+        task::{Context, Poll},
+    };
+
+    #[cfg(feature = "async-process")]
+    use async_process::{Child, ChildStderr, ChildStdout, Command};
+    use futures_lite::{future, stream, Stream};
+    #[cfg(feature = "async-process")]
+    use futures_lite::{
+        io::{AsyncBufReadExt, AsyncReadExt, BufReader, Lines},
+        StreamExt,
+    };
+    #[cfg(feature = "tokio")]
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncReadExt, BufReader, Lines},
+        process::{Child, ChildStderr, ChildStdout, Command},
+    };
+
+    use super::{monitor_args, monitor_failed, push_tail, MonitorEvent};
+    use crate::helper::{NftablesError, ProcessExecutor};
+
+    type EventStream = dyn Stream<Item = Result<MonitorEvent<'static>, NftablesError>> + Send;
+
+    /// Asynchronous counterpart of [Monitor](super::Monitor), implementing [Stream].
+    ///
+    /// Dropping the stream kills nft.
+    pub struct AsyncMonitor {
+        events: Pin<Box<EventStream>>,
+    }
+
+    impl std::fmt::Debug for AsyncMonitor {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("AsyncMonitor").finish_non_exhaustive()
+        }
+    }
+
+    impl Stream for AsyncMonitor {
+        type Item = Result<MonitorEvent<'static>, NftablesError>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            self.events.as_mut().poll_next(cx)
+        }
+    }
+
+    /// A running nft, consumed once it exits.
+    struct Running {
+        program: OsString,
+        child: Child,
+        lines: Lines<BufReader<ChildStdout>>,
+        /// Standard error, drained while waiting for lines until it is closed.
+        stderr: Option<ChildStderr>,
+        /// The tail of standard error read so far.
+        stderr_tail: Vec<u8>,
+    }
+
+    /// Whichever of nft's outputs became ready first.
+    enum Output {
+        Line(Option<std::io::Result<String>>),
+        Stderr(std::io::Result<usize>),
+    }
+
+    impl Running {
+        async fn next(
+            mut self,
+        ) -> Option<(Result<MonitorEvent<'static>, NftablesError>, Option<Self>)> {
+            let execution_error = |program: &OsStr, e| NftablesError::NftExecution {
+                program: program.into(),
+                inner: e,
+            };
+            let mut buf = [0; 4096];
+            loop {
+                let (lines, stderr) = (&mut self.lines, &mut self.stderr);
+                let line = async {
+                    #[cfg(feature = "tokio")]
+                    let line = lines.next_line().await.transpose();
+                    #[cfg(feature = "async-process")]
+                    let line = lines.next().await;
+                    Output::Line(line)
+                };
+                let stderr = async {
+                    match stderr {
+                        Some(pipe) => Output::Stderr(pipe.read(&mut buf).await),
+                        None => future::pending().await,
+                    }
+                };
+                match future::or(line, stderr).await {
+                    Output::Line(Some(Ok(line))) if line.trim().is_empty() => continue,
+                    Output::Line(Some(Ok(line))) => {
+                        return Some((MonitorEvent::from_line(&line), Some(self)))
+                    }
+                    Output::Line(Some(Err(e))) => {
+                        return Some((Err(execution_error(&self.program, e)), None))
+                    }
+                    Output::Line(None) => break,
+                    Output::Stderr(Ok(0)) => self.stderr = None,
+                    Output::Stderr(Ok(n)) => push_tail(&mut self.stderr_tail, &buf[..n]),
+                    Output::Stderr(Err(e)) => {
+                        return Some((Err(execution_error(&self.program, e)), None))
+                    }
+                }
+            }
+
+            #[cfg(feature = "tokio")]
+            let status = self.child.wait().await;
+            #[cfg(feature = "async-process")]
+            let status = self.child.status().await;
+            match status {
+                Ok(status) if status.success() => return None,
+                Ok(_) => {}
+                Err(e) => return Some((Err(execution_error(&self.program, e)), None)),
+            }
+            if let Some(mut pipe) = self.stderr.take() {
+                loop {
+                    match pipe.read(&mut buf).await {
+                        Ok(0) => break,
+                        Ok(n) => push_tail(&mut self.stderr_tail, &buf[..n]),
+                        Err(e) => return Some((Err(execution_error(&self.program, e)), None)),
+                    }
+                }
+            }
+            Some((Err(monitor_failed(&self.program, self.stderr_tail)), None))
+        }
+    }
+
+    /// Starts monitoring all ruleset changes with the default `nft` executable.
+    pub fn monitor_async() -> Result<AsyncMonitor, NftablesError> {
+        monitor_with_executor_async(&ProcessExecutor::default(), &[])
+    }
+
+    /// Asynchronous counterpart of [monitor_with_executor](super::monitor_with_executor).
+    pub fn monitor_with_executor_async(
+        executor: &ProcessExecutor,
+        args: &[&OsStr],
+    ) -> Result<AsyncMonitor, NftablesError> {
+        let program = executor.program().to_owned();
+        let mut nft_cmd = Command::from(executor.command(&monitor_args(args))?);
+        nft_cmd.kill_on_drop(true);
+        let mut child = nft_cmd
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| NftablesError::NftExecution {
+                program: program.clone(),
+                inner: e,
+            })?;
+        let stdout = child.stdout.take().unwrap();
+        let stderr = child.stderr.take();
+        let running = Running {
+            program,
+            child,
+            lines: BufReader::new(stdout).lines(),
+            stderr,
+            stderr_tail: Vec::new(),
+        };
+        let events = stream::unfold(
+            Some(running),
+            |running| async move { running?.next().await },
+        );
+        Ok(AsyncMonitor {
+            events: Box::pin(events),
+        })
+    }
+}
@@ -0,0 +1,2510 @@
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fmt, fs, io,
+    net::IpAddr,
+    path::{Path, PathBuf},
+};
+
+use serde::de::DeserializeOwned;
+
+use crate::{
+    expr::{
+        self, BinaryOperation, Elem, Expression, Exthdr, Fib, FibFlag, JHash, Meta, MetaKey,
+        NamedExpression, Numgen, Payload, PayloadField, Prefix, Range, SctpChunk, SetItem, Socket,
+        SymHash, TcpOption, Verdict, CT, RT,
+    },
+    schema::{
+        self, CTExpectation, CTHelper, CTTimeout, Chain, Element, FlowTable, FlushObject, NfCmd,
+        NfListObject, NfObject, Nftables, Rule, SetType, SetTypeValue, Table,
+    },
+    stmt::{
+        self, AnonymousCounter, CTCount, Dup, Flow, JumpTarget, Log, LogFlag, Mangle, Match, Meter,
+        NATFamily, Operator, Queue, QueueFlag, QuotaOrQuotaRef, Reject, RejectType, SetOp,
+        Statement, TProxy, VerdictMap, FWD, NAT,
+    },
+    types::{NfChainPolicy, NfFamily},
+};
+
+/// Maximum nesting depth of variable expansions and includes.
+const MAX_DEPTH: u8 = 16;
+
+/// Protocols whose header fields are matched as `<protocol> <field>`.
+const PAYLOAD_PROTOCOLS: &[&str] = &[
+    "ether", "vlan", "arp", "ip", "icmp", "igmp", "ip6", "icmpv6", "tcp", "udp", "udplite", "sctp",
+    "dccp", "ah", "esp", "comp", "th", "gre",
+];
+
+/// IPv6 extension headers whose fields are matched as `<header> <field>`.
+const EXTHDRS: &[&str] = &["hbh", "frag", "dst", "mh", "srh"];
+
+/// Meta keys that may be used without the `meta` keyword.
+const UNQUALIFIED_META_KEYS: &[&str] = &[
+    "mark",
+    "iif",
+    "iifname",
+    "iiftype",
+    "iifgroup",
+    "oif",
+    "oifname",
+    "oiftype",
+    "oifgroup",
+    "skuid",
+    "skgid",
+    "nftrace",
+    "rtclassid",
+    "ibriport",
+    "obriport",
+    "ibridgename",
+    "obridgename",
+    "pkttype",
+    "cpu",
+    "cgroup",
+    "random",
+];
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// Error raised for nft syntax that cannot be parsed.
+pub struct ParseError {
+    /// The file containing the error, unless it is in the string passed to [parse].
+    pub path: Option<PathBuf>,
+    /// The line of the error, starting at 1.
+    pub line: usize,
+    /// The column of the error, starting at 1.
+    pub column: usize,
+    /// Description of the error.
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(path) = &self.path {
+            write!(f, "{}:", path.display())?;
+        }
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses a ruleset in nft syntax (as read by `nft -f`) into commands.
+///
+/// Tables, chains, sets, maps, flowtables and stateful objects declared in a
+/// `table` block are added before the rules of the block, so rules may refer
+/// to objects declared further down. Variables are substituted and includes
+/// are resolved relative to the current directory.
+pub fn parse(input: &str) -> Result<Nftables<'static>, ParseError> {
+    let mut parser = Parser::default();
+    parser.load(input, None, 0, 0)?;
+    parser.script()
+}
+
+/// Parses an nft script file, see [parse].
+///
+/// Includes are resolved relative to the directory of the including file.
+pub fn parse_file(path: impl AsRef<Path>) -> Result<Nftables<'static>, ParseError> {
+    let path = path.as_ref();
+    let input = fs::read_to_string(path).map_err(|err| ParseError {
+        path: Some(path.to_path_buf()),
+        line: 1,
+        column: 1,
+        message: format!("unable to read file: {err}"),
+    })?;
+    let mut parser = Parser::default();
+    parser.load(&input, Some(path.to_path_buf()), 0, 0)?;
+    parser.script()
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Location of a token.
+struct Pos {
+    /// Index into the files of the parser.
+    file: usize,
+    line: usize,
+    column: usize,
+    /// Number of variable expansions and includes the token went through.
+    depth: u8,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    /// A keyword, identifier, number, address or any other unquoted word.
+    Word(String),
+    /// A quoted string.
+    Str(String),
+    /// A variable reference (`$name`).
+    Var(String),
+    Punct(&'static str),
+    Newline,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    tok: Tok,
+    pos: Pos,
+}
+
+const PUNCTS: &[&str] = &[
+    "==", "!=", "<=", ">=", "<<", ">>", "<", ">", "=", "{", "}", "(", ")", ",", ";", "&", "|", "^",
+    ":", "+",
+];
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | ':' | '-' | '/' | '*' | '@' | '[' | ']')
+}
+
+/// Splits nft syntax into tokens, followed by a final newline.
+fn lex(input: &str, file: usize, depth: u8) -> Result<Vec<Token>, (Pos, String)> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let (mut i, mut line, mut column) = (0, 1, 1);
+    while i < chars.len() {
+        let start = i;
+        let pos = Pos {
+            file,
+            line,
+            column,
+            depth,
+        };
+        let c = chars[i];
+        let tok = match c {
+            '\n' => {
+                i += 1;
+                Some(Tok::Newline)
+            }
+            '\\' if chars.get(i + 1) == Some(&'\n') => {
+                i += 2;
+                None
+            }
+            '#' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                None
+            }
+            '"' => {
+                let mut value = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None | Some('\n') => return Err((pos, "unterminated string".into())),
+                        Some('"') => break,
+                        Some('\\') if matches!(chars.get(i + 1), Some('"' | '\\')) => {
+                            value.push(chars[i + 1]);
+                            i += 2;
+                        }
+                        Some(c) => {
+                            value.push(*c);
+                            i += 1;
+                        }
+                    }
+                }
+                i += 1;
+                Some(Tok::Str(value))
+            }
+            '$' => {
+                i += 1;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                if i == start + 1 {
+                    return Err((pos, "expected variable name after `$`".into()));
+                }
+                Some(Tok::Var(chars[start + 1..i].iter().collect()))
+            }
+            c if c.is_whitespace() => {
+                i += 1;
+                None
+            }
+            c if is_word_char(c)
+                && (c != ':' || chars.get(i + 1).is_some_and(|c| is_word_char(*c))) =>
+            {
+                while i < chars.len() && is_word_char(chars[i]) {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                // A colon directly after a map key, as in `80: 10.0.0.1`.
+                if word.len() > 1 && word.ends_with(':') && !word.ends_with("::") {
+                    tokens.push(Token {
+                        tok: Tok::Word(word[..word.len() - 1].to_owned()),
+                        pos,
+                    });
+                    Some(Tok::Punct(":"))
+                } else {
+                    Some(Tok::Word(word))
+                }
+            }
+            _ => {
+                let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+                let Some(punct) = PUNCTS.iter().find(|p| rest.starts_with(**p)) else {
+                    return Err((pos, format!("unexpected character `{c}`")));
+                };
+                i += punct.len();
+                Some(Tok::Punct(punct))
+            }
+        };
+        for c in &chars[start..i] {
+            if *c == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+        if let Some(tok) = tok {
+            tokens.push(Token { tok, pos });
+        }
+    }
+    tokens.push(Token {
+        tok: Tok::Newline,
+        pos: Pos {
+            file,
+            line,
+            column,
+            depth,
+        },
+    });
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+/// What a word in an expression stands for.
+enum Context {
+    /// A selector such as `ip saddr` (left hand side of a match).
+    Selector,
+    /// A constant such as `22` or `established` (right hand side of a match).
+    Value,
+    /// A selector if the word is a selector keyword, a constant otherwise.
+    Any,
+}
+
+/// Contents of a `table` block, in the order they are added.
+#[derive(Default)]
+struct TableBlock {
+    objects: Vec<NfListObject<'static>>,
+    chains: Vec<NfListObject<'static>>,
+    rules: Vec<NfListObject<'static>>,
+}
+
+#[derive(Default)]
+struct Parser {
+    tokens: Vec<Token>,
+    next: usize,
+    files: Vec<Option<PathBuf>>,
+    defines: HashMap<String, Vec<Token>>,
+    commands: Vec<NfCmd<'static>>,
+}
+
+impl Parser {
+    /// Inserts the tokens of `input` at token index `at`.
+    fn load(
+        &mut self,
+        input: &str,
+        path: Option<PathBuf>,
+        depth: u8,
+        at: usize,
+    ) -> Result<(), ParseError> {
+        let file = self.files.len();
+        self.files.push(path);
+        let tokens = lex(input, file, depth).map_err(|(pos, msg)| self.error_at(pos, msg))?;
+        self.tokens.splice(at..at, tokens);
+        Ok(())
+    }
+
+    fn script(mut self) -> Result<Nftables<'static>, ParseError> {
+        loop {
+            self.skip_separators();
+            if self.peek().is_none() {
+                break;
+            }
+            self.command()?;
+        }
+        let objects: Vec<NfObject<'static>> =
+            self.commands.into_iter().map(NfObject::CmdObject).collect();
+        Ok(Nftables {
+            objects: objects.into(),
+        })
+    }
+
+    // Token access
+
+    /// Returns the next token, substituting defined variables.
+    fn peek(&mut self) -> Option<&Tok> {
+        while let Some(Token {
+            tok: Tok::Var(name),
+            pos,
+        }) = self.tokens.get(self.next)
+        {
+            let Some(value) = self.defines.get(name).filter(|_| pos.depth < MAX_DEPTH) else {
+                break;
+            };
+            let depth = pos.depth + 1;
+            let value: Vec<Token> = value
+                .iter()
+                .map(|token| Token {
+                    tok: token.tok.clone(),
+                    pos: Pos { depth, ..token.pos },
+                })
+                .collect();
+            self.tokens.splice(self.next..=self.next, value);
+        }
+        self.tokens.get(self.next).map(|token| &token.tok)
+    }
+
+    /// Returns the word `offset` tokens ahead without substituting variables.
+    fn lookahead(&self, offset: usize) -> Option<&str> {
+        match self.tokens.get(self.next + offset) {
+            Some(Token {
+                tok: Tok::Word(word),
+                ..
+            }) => Some(word),
+            _ => None,
+        }
+    }
+
+    fn pos(&mut self) -> Pos {
+        self.peek();
+        self.tokens
+            .get(self.next)
+            .or(self.tokens.last())
+            .map(|token| token.pos)
+            .unwrap_or(Pos {
+                file: 0,
+                line: 1,
+                column: 1,
+                depth: 0,
+            })
+    }
+
+    fn peek_word(&mut self) -> Option<&str> {
+        match self.peek() {
+            Some(Tok::Word(word)) => Some(word),
+            _ => None,
+        }
+    }
+
+    fn is_word(&mut self, word: &str) -> bool {
+        self.peek_word() == Some(word)
+    }
+
+    fn eat_word(&mut self, word: &str) -> bool {
+        let found = self.is_word(word);
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    fn eat_punct(&mut self, punct: &str) -> bool {
+        let found = matches!(self.peek(), Some(Tok::Punct(p)) if *p == punct);
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    fn expect_punct(&mut self, punct: &str) -> Result<(), ParseError> {
+        if self.eat_punct(punct) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("`{punct}`")))
+        }
+    }
+
+    fn expect_keyword(&mut self, word: &str) -> Result<(), ParseError> {
+        if self.eat_word(word) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("`{word}`")))
+        }
+    }
+
+    fn expect_word(&mut self, what: &str) -> Result<String, ParseError> {
+        match self.peek() {
+            Some(Tok::Word(word)) => {
+                let word = word.clone();
+                self.next += 1;
+                Ok(word)
+            }
+            _ => Err(self.unexpected(what)),
+        }
+    }
+
+    /// Consumes a word or a quoted string.
+    fn string(&mut self, what: &str) -> Result<String, ParseError> {
+        match self.peek() {
+            Some(Tok::Word(value) | Tok::Str(value)) => {
+                let value = value.clone();
+                self.next += 1;
+                Ok(value)
+            }
+            _ => Err(self.unexpected(what)),
+        }
+    }
+
+    fn number<T: TryFrom<u64>>(&mut self, what: &str) -> Result<T, ParseError> {
+        let pos = self.pos();
+        let word = self.expect_word(what)?;
+        parse_integer(&word)
+            .and_then(|n| T::try_from(n).ok())
+            .ok_or_else(|| self.error_at(pos, format!("invalid {what} `{word}`")))
+    }
+
+    /// Consumes a keyword named like the serialized variant of `T`.
+    fn keyword<T: DeserializeOwned>(&mut self, what: &str) -> Result<T, ParseError> {
+        let pos = self.pos();
+        let word = self.expect_word(what)?;
+        keyword(&word).ok_or_else(|| self.error_at(pos, format!("unknown {what} `{word}`")))
+    }
+
+    /// Consumes a comma separated list.
+    fn list<T>(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<Vec<T>, ParseError> {
+        let mut items = vec![item(self)?];
+        while self.eat_punct(",") {
+            items.push(item(self)?);
+        }
+        Ok(items)
+    }
+
+    /// Consumes a time value like `1h30m` and returns it in milliseconds.
+    fn duration(&mut self) -> Result<u64, ParseError> {
+        let pos = self.pos();
+        let word = self.expect_word("time")?;
+        parse_duration(&word).ok_or_else(|| self.error_at(pos, format!("invalid time `{word}`")))
+    }
+
+    fn seconds<T: TryFrom<u64>>(&mut self) -> Result<T, ParseError> {
+        let pos = self.pos();
+        let seconds = self.duration()? / 1000;
+        T::try_from(seconds).map_err(|_| self.error_at(pos, "time is out of range"))
+    }
+
+    fn milliseconds<T: TryFrom<u64>>(&mut self) -> Result<T, ParseError> {
+        let pos = self.pos();
+        let milliseconds = self.duration()?;
+        T::try_from(milliseconds).map_err(|_| self.error_at(pos, "time is out of range"))
+    }
+
+    /// Consumes a byte amount like `10 mbytes`.
+    fn bytes(&mut self) -> Result<(u64, String), ParseError> {
+        let amount = self.number("amount")?;
+        let pos = self.pos();
+        let unit = self.expect_word("byte unit")?;
+        if byte_unit_shift(&unit).is_none() {
+            return Err(self.error_at(pos, format!("unknown byte unit `{unit}`")));
+        }
+        Ok((amount, unit))
+    }
+
+    /// Returns whether the current statement ends here.
+    fn at_end(&mut self) -> bool {
+        matches!(
+            self.peek(),
+            None | Some(Tok::Newline | Tok::Punct(";") | Tok::Punct("}"))
+        )
+    }
+
+    fn expect_end(&mut self) -> Result<(), ParseError> {
+        if self.at_end() {
+            Ok(())
+        } else {
+            Err(self.unexpected("end of statement"))
+        }
+    }
+
+    fn skip_separators(&mut self) {
+        while matches!(self.peek(), Some(Tok::Newline | Tok::Punct(";"))) {
+            self.next += 1;
+        }
+    }
+
+    fn skip_newlines(&mut self) {
+        while self.peek() == Some(&Tok::Newline) {
+            self.next += 1;
+        }
+    }
+
+    fn error_at(&self, pos: Pos, message: impl Into<String>) -> ParseError {
+        ParseError {
+            path: self.files.get(pos.file).cloned().flatten(),
+            line: pos.line,
+            column: pos.column,
+            message: message.into(),
+        }
+    }
+
+    fn error(&mut self, message: impl Into<String>) -> ParseError {
+        let pos = self.pos();
+        self.error_at(pos, message)
+    }
+
+    fn unexpected(&mut self, expected: &str) -> ParseError {
+        let found = match self.peek() {
+            None => "end of input".to_owned(),
+            Some(Tok::Word(word)) => format!("`{word}`"),
+            Some(Tok::Str(value)) => format!("\"{value}\""),
+            Some(Tok::Var(name)) => {
+                let message = format!("undefined variable `${name}`");
+                return self.error(message);
+            }
+            Some(Tok::Punct(punct)) => format!("`{punct}`"),
+            Some(Tok::Newline) => "end of line".to_owned(),
+        };
+        self.error(format!("expected {expected}, found {found}"))
+    }
+
+    // Commands
+
+    fn command(&mut self) -> Result<(), ParseError> {
+        let pos = self.pos();
+        let verb = self.expect_word("command")?;
+        match verb.as_str() {
+            "include" => self.include(pos),
+            "define" | "redefine" => self.define(),
+            "undefine" => {
+                let name = self.expect_word("variable name")?;
+                self.defines.remove(&name);
+                self.expect_end()
+            }
+            "table" => self.table("add"),
+            "add" | "create" | "insert" | "replace" | "delete" | "destroy" => {
+                self.object_command(&verb)
+            }
+            "flush" => self.flush(),
+            _ => Err(self.error_at(pos, format!("unknown command `{verb}`"))),
+        }
+    }
+
+    fn include(&mut self, pos: Pos) -> Result<(), ParseError> {
+        let name = self.string("file name")?;
+        self.expect_end()?;
+        if pos.depth >= MAX_DEPTH {
+            return Err(self.error_at(pos, "includes are nested too deeply"));
+        }
+        let path = match &self.files[pos.file] {
+            Some(file) => file.parent().unwrap_or(Path::new("")).join(&name),
+            None => PathBuf::from(&name),
+        };
+        let paths = include_paths(&path)
+            .map_err(|err| self.error_at(pos, format!("unable to read {name}: {err}")))?;
+        // Insert in reverse so that the files end up in order.
+        for path in paths.into_iter().rev() {
+            let input = fs::read_to_string(&path).map_err(|err| {
+                self.error_at(pos, format!("unable to read {}: {err}", path.display()))
+            })?;
+            self.load(&input, Some(path), pos.depth + 1, self.next)?;
+        }
+        Ok(())
+    }
+
+    fn define(&mut self) -> Result<(), ParseError> {
+        let name = self.expect_word("variable name")?;
+        self.expect_punct("=")?;
+        let mut value = Vec::new();
+        let mut braces = 0usize;
+        loop {
+            match self.peek() {
+                None => break,
+                Some(Tok::Newline | Tok::Punct(";")) if braces == 0 => break,
+                Some(Tok::Punct("{")) => braces += 1,
+                Some(Tok::Punct("}")) if braces == 0 => break,
+                Some(Tok::Punct("}")) => braces -= 1,
+                _ => {}
+            }
+            value.push(self.tokens[self.next].clone());
+            self.next += 1;
+        }
+        if value.is_empty() {
+            return Err(self.unexpected("value"));
+        }
+        self.defines.insert(name, value);
+        Ok(())
+    }
+
+    /// Consumes an optional family and a table name.
+    fn table_spec(&mut self) -> Result<Table<'static>, ParseError> {
+        let family = self.family();
+        let name = self.string("table name")?;
+        Ok(Table {
+            family,
+            name: name.into(),
+            handle: None,
+            flags: None,
+            comment: None,
+        })
+    }
+
+    /// Consumes a family keyword if it is followed by a name, defaulting to
+    /// `ip` like nft does.
+    fn family(&mut self) -> NfFamily {
+        let family = self.peek_word().and_then(keyword::<NfFamily>);
+        let named = matches!(
+            self.tokens.get(self.next + 1).map(|token| &token.tok),
+            Some(Tok::Word(_) | Tok::Str(_))
+        );
+        match family {
+            Some(family) if named => {
+                self.next += 1;
+                family
+            }
+            _ => NfFamily::IP,
+        }
+    }
+
+    fn table(&mut self, verb: &str) -> Result<(), ParseError> {
+        let table = self.table_spec()?;
+        let mut block = TableBlock::default();
+        if self.eat_punct("{") {
+            self.table_body(&table, &mut block)?;
+        } else {
+            self.expect_end()?;
+        }
+        let cmd = command(verb, NfListObject::Table(table));
+        self.commands.push(cmd);
+        self.push_block(block);
+        Ok(())
+    }
+
+    fn push_block(&mut self, block: TableBlock) {
+        let objects = block.objects.into_iter();
+        let objects = objects.chain(block.chains).chain(block.rules);
+        self.commands.extend(objects.map(NfCmd::Add));
+    }
+
+    fn table_body(&mut self, table: &Table, block: &mut TableBlock) -> Result<(), ParseError> {
+        loop {
+            self.skip_separators();
+            if self.eat_punct("}") {
+                return Ok(());
+            }
+            let pos = self.pos();
+            let word = self.expect_word("table content")?;
+            match word.as_str() {
+                "chain" => {
+                    let name = self.string("chain name")?;
+                    self.expect_punct("{")?;
+                    let chain = self.chain_body(table, name, &mut block.rules)?;
+                    block.chains.push(NfListObject::Chain(chain));
+                }
+                "set" | "map" => {
+                    let name = self.string("set name")?;
+                    self.expect_punct("{")?;
+                    let set = self.set_body(table, name, word == "map")?;
+                    block.objects.push(set);
+                }
+                "counter" | "quota" | "limit" | "synproxy" | "flowtable" => {
+                    block.objects.push(self.object(table, &word)?);
+                }
+                "ct" => {
+                    let pos = self.pos();
+                    let kind = self.expect_word("ct object type")?;
+                    if !matches!(kind.as_str(), "helper" | "expectation" | "timeout") {
+                        return Err(self.error_at(pos, format!("unknown ct object `{kind}`")));
+                    }
+                    block
+                        .objects
+                        .push(self.object(table, &format!("ct {kind}"))?);
+                }
+                "define" | "redefine" => self.define()?,
+                _ => return Err(self.error_at(pos, format!("unsupported table content `{word}`"))),
+            }
+            self.expect_end()?;
+        }
+    }
+
+    fn chain_body(
+        &mut self,
+        table: &Table,
+        name: String,
+        rules: &mut Vec<NfListObject<'static>>,
+    ) -> Result<Chain<'static>, ParseError> {
+        let mut chain = Chain {
+            family: table.family,
+            table: table.name.to_string().into(),
+            name: name.into(),
+            ..Default::default()
+        };
+        loop {
+            self.skip_separators();
+            if self.eat_punct("}") {
+                break;
+            }
+            if self.peek().is_none() {
+                return Err(self.unexpected("`}`"));
+            }
+            if self.eat_word("type") {
+                chain._type = Some(self.keyword("chain type")?);
+                self.expect_keyword("hook")?;
+                chain.hook = Some(self.keyword("hook")?);
+                if self.eat_word("device") {
+                    chain.dev = Some(vec![self.string("device")?.into()].into());
+                } else if self.eat_word("devices") {
+                    chain.dev = Some(self.devices()?.into_iter().map(Cow::from).collect());
+                }
+                self.expect_keyword("priority")?;
+                chain.prio = Some(self.priority(chain.family)?);
+            } else if self.eat_word("policy") {
+                chain.policy = Some(self.keyword("chain policy")?);
+            } else if self.eat_word("flags") {
+                let flags = self.list(|p| p.keyword("chain flag"))?;
+                chain.flags = Some(flags.into_iter().collect());
+            } else if self.eat_word("comment") {
+                chain.comment = Some(self.string("comment")?.into());
+            } else {
+                let rule = self.rule(chain.family, &chain.table, &chain.name)?;
+                rules.push(NfListObject::Rule(rule));
+            }
+            self.expect_end()?;
+        }
+        // The kernel reports the default policy for base chains.
+        if chain.hook.is_some() && chain.policy.is_none() {
+            chain.policy = Some(NfChainPolicy::Accept);
+        }
+        Ok(chain)
+    }
+
+    /// Consumes a priority, which is a number or a standard priority name
+    /// with an optional offset (`filter + 10`).
+    fn priority(&mut self, family: NfFamily) -> Result<i32, ParseError> {
+        let pos = self.pos();
+        let word = self.expect_word("priority")?;
+        if let Ok(priority) = word.parse() {
+            return Ok(priority);
+        }
+        let base = standard_priority(family, &word)
+            .ok_or_else(|| self.error_at(pos, format!("unknown priority `{word}`")))?;
+        let sign = if self.eat_punct("+") {
+            1
+        } else if self.eat_word("-") {
+            -1
+        } else {
+            return Ok(base);
+        };
+        Ok(base + sign * self.number::<i32>("priority offset")?)
+    }
+
+    /// Consumes the device list following `devices`: `= { eth0, eth1 }` or
+    /// `= eth0`.
+    fn devices(&mut self) -> Result<Vec<String>, ParseError> {
+        self.expect_punct("=")?;
+        if !self.eat_punct("{") {
+            return Ok(vec![self.string("device")?]);
+        }
+        let devices = self.list(|p| {
+            p.skip_newlines();
+            p.string("device")
+        })?;
+        self.skip_newlines();
+        self.expect_punct("}")?;
+        Ok(devices)
+    }
+
+    fn set_body(
+        &mut self,
+        table: &Table,
+        name: String,
+        is_map: bool,
+    ) -> Result<NfListObject<'static>, ParseError> {
+        let mut set = schema::Set {
+            family: table.family,
+            table: table.name.to_string().into(),
+            name: name.into(),
+            ..Default::default()
+        };
+        let mut set_type = None;
+        let mut data = None;
+        loop {
+            self.skip_separators();
+            if self.eat_punct("}") {
+                break;
+            }
+            let pos = self.pos();
+            let word = self.expect_word("set property")?;
+            match word.as_str() {
+                "type" => {
+                    set_type = Some(self.set_type()?);
+                    if is_map {
+                        self.expect_punct(":")?;
+                        data = Some(self.set_type()?);
+                    }
+                }
+                "typeof" => {
+                    set_type = Some(SetTypeValue::Typeof(self.expression(Context::Selector)?));
+                    if is_map {
+                        self.expect_punct(":")?;
+                        data = Some(if self.eat_word("verdict") {
+                            SetTypeValue::Single(SetType::Verdict)
+                        } else {
+                            SetTypeValue::Typeof(self.expression(Context::Selector)?)
+                        });
+                    }
+                }
+                "flags" => {
+                    let flags = self.list(|p| p.keyword("set flag"))?;
+                    set.flags = Some(flags.into_iter().collect());
+                }
+                "timeout" => set.timeout = Some(self.seconds()?),
+                "gc-interval" => set.gc_interval = Some(self.seconds()?),
+                "size" => set.size = Some(self.number("set size")?),
+                "policy" => set.policy = Some(self.keyword("set policy")?),
+                "comment" => set.comment = Some(self.string("comment")?.into()),
+                "elements" => {
+                    self.expect_punct("=")?;
+                    self.expect_punct("{")?;
+                    let items = self.set_items(Context::Value)?;
+                    set.elem = Some(items.into_iter().map(element_expression).collect());
+                }
+                _ => return Err(self.error_at(pos, format!("unsupported set property `{word}`"))),
+            }
+            self.expect_end()?;
+        }
+        let Some(set_type) = set_type else {
+            return Err(self.error(format!("set `{}` has no type", set.name)));
+        };
+        set.set_type = set_type;
+        Ok(match data {
+            Some(map) => NfListObject::Map(Box::new(schema::Map {
+                family: set.family,
+                table: set.table,
+                name: set.name,
+                handle: None,
+                set_type: set.set_type,
+                map,
+                policy: set.policy,
+                flags: set.flags,
+                elem: set.elem,
+                timeout: set.timeout,
+                gc_interval: set.gc_interval,
+                size: set.size,
+                comment: set.comment,
+            })),
+            None => NfListObject::Set(Box::new(set)),
+        })
+    }
+
+    /// Consumes a data type, concatenated with `.`.
+    fn set_type(&mut self) -> Result<SetTypeValue<'static>, ParseError> {
+        let mut types: Vec<SetType> = vec![self.keyword("data type")?];
+        while self.eat_word(".") {
+            types.push(self.keyword("data type")?);
+        }
+        Ok(match types.as_slice() {
+            [single] => SetTypeValue::Single(single.clone()),
+            _ => SetTypeValue::Concatenated(types.into()),
+        })
+    }
+
+    /// Consumes the properties of an object within braces.
+    ///
+    /// `property` is called with each property keyword and returns whether
+    /// it consumed the property.
+    fn properties(
+        &mut self,
+        what: &str,
+        mut property: impl FnMut(&mut Self, &str) -> Result<bool, ParseError>,
+    ) -> Result<(), ParseError> {
+        self.expect_punct("{")?;
+        loop {
+            self.skip_separators();
+            if self.eat_punct("}") {
+                return Ok(());
+            }
+            let pos = self.pos();
+            let word = self.expect_word(&format!("{what} property"))?;
+            if !property(self, &word)? {
+                return Err(self.error_at(pos, format!("unsupported {what} property `{word}`")));
+            }
+        }
+    }
+
+    /// Consumes a stateful object or flowtable of the given kind, starting
+    /// at its name.
+    fn object(&mut self, table: &Table, kind: &str) -> Result<NfListObject<'static>, ParseError> {
+        let family = table.family;
+        let table: Cow<'static, str> = table.name.to_string().into();
+        let name: Cow<'static, str> = self.string(&format!("{kind} name"))?.into();
+        Ok(match kind {
+            "counter" => {
+                let mut counter = schema::Counter {
+                    family,
+                    table,
+                    name,
+                    ..Default::default()
+                };
+                if !self.at_end() {
+                    self.properties(kind, |p, word| {
+                        match word {
+                            "packets" => counter.packets = Some(p.number("packets")?),
+                            "bytes" => counter.bytes = Some(p.number("bytes")?),
+                            "comment" => counter.comment = Some(p.string("comment")?.into()),
+                            _ => return Ok(false),
+                        }
+                        Ok(true)
+                    })?;
+                }
+                NfListObject::Counter(counter)
+            }
+            "quota" => {
+                let mut quota = schema::Quota {
+                    family,
+                    table,
+                    name,
+                    ..Default::default()
+                };
+                self.properties(kind, |p, word| {
+                    match word {
+                        "over" => quota.inv = Some(true),
+                        "until" => quota.inv = Some(false),
+                        "used" => quota.used = Some(p.byte_amount()?),
+                        _ => {
+                            p.next -= 1;
+                            quota.bytes = Some(p.byte_amount()?);
+                        }
+                    }
+                    Ok(true)
+                })?;
+                NfListObject::Quota(quota)
+            }
+            "limit" => {
+                let mut limit = schema::Limit {
+                    family,
+                    table,
+                    name,
+                    ..Default::default()
+                };
+                self.properties(kind, |p, word| {
+                    match word {
+                        "rate" => {
+                            if p.eat_word("over") {
+                                limit.inv = Some(true);
+                            }
+                            let pos = p.pos();
+                            let (rate, unit, per) = p.rate()?;
+                            let shift = unit.as_deref().map_or(Some(0), byte_unit_shift);
+                            limit.rate = shift
+                                .and_then(|shift| rate.checked_mul(1 << shift))
+                                .map(Some)
+                                .ok_or_else(|| p.error_at(pos, "rate is out of range"))?;
+                            limit.unit = Some(match unit {
+                                Some(_) => schema::LimitUnit::Bytes,
+                                None => schema::LimitUnit::Packets,
+                            });
+                            limit.per = Some(keyword(&per).ok_or_else(|| {
+                                p.error_at(pos, format!("unknown time unit `{per}`"))
+                            })?);
+                        }
+                        "burst" => {
+                            limit.burst = Some(p.number("burst")?);
+                            if !p.eat_word("packets") {
+                                p.eat_word("bytes");
+                            }
+                        }
+                        _ => return Ok(false),
+                    }
+                    Ok(true)
+                })?;
+                NfListObject::Limit(limit)
+            }
+            "ct helper" => {
+                let mut helper = CTHelper {
+                    family,
+                    table,
+                    name,
+                    handle: None,
+                    _type: "".into(),
+                    protocol: None,
+                    l3proto: None,
+                };
+                self.properties(kind, |p, word| {
+                    match word {
+                        "type" => helper._type = p.string("helper type")?.into(),
+                        "protocol" => helper.protocol = Some(p.expect_word("protocol")?.into()),
+                        "l3proto" => helper.l3proto = Some(p.expect_word("protocol")?.into()),
+                        _ => return Ok(false),
+                    }
+                    Ok(true)
+                })?;
+                NfListObject::CTHelper(helper)
+            }
+            "ct expectation" => {
+                let mut expectation = CTExpectation {
+                    family,
+                    table,
+                    name,
+                    handle: None,
+                    l3proto: None,
+                    protocol: None,
+                    dport: None,
+                    timeout: None,
+                    size: None,
+                };
+                self.properties(kind, |p, word| {
+                    match word {
+                        "protocol" => expectation.protocol = Some(p.keyword("protocol")?),
+                        "l3proto" => expectation.l3proto = Some(p.expect_word("protocol")?.into()),
+                        "dport" => expectation.dport = Some(p.number("port")?),
+                        "timeout" => expectation.timeout = Some(p.milliseconds()?),
+                        "size" => expectation.size = Some(p.number("size")?),
+                        _ => return Ok(false),
+                    }
+                    Ok(true)
+                })?;
+                NfListObject::CTExpectation(expectation)
+            }
+            "ct timeout" => {
+                let mut timeout = CTTimeout {
+                    family,
+                    table,
+                    name,
+                    ..Default::default()
+                };
+                self.properties(kind, |p, word| {
+                    match word {
+                        "protocol" => timeout.protocol = Some(p.keyword("protocol")?),
+                        "l3proto" => timeout.l3proto = Some(p.expect_word("protocol")?.into()),
+                        "policy" => {
+                            p.expect_punct("=")?;
+                            p.expect_punct("{")?;
+                            p.skip_newlines();
+                            timeout.state = Some(p.expect_word("conntrack state")?.into());
+                            p.expect_punct(":")?;
+                            timeout.value = Some(p.seconds()?);
+                            p.skip_newlines();
+                            if !p.eat_punct("}") {
+                                let message =
+                                    "ct timeouts with more than one state are not supported";
+                                return Err(p.error(message));
+                            }
+                        }
+                        _ => return Ok(false),
+                    }
+                    Ok(true)
+                })?;
+                NfListObject::CTTimeout(timeout)
+            }
+            "synproxy" => {
+                let mut synproxy = schema::SynProxy {
+                    family,
+                    table,
+                    name,
+                    handle: None,
+                    mss: None,
+                    wscale: None,
+                    flags: None,
+                };
+                self.properties(kind, |p, word| {
+                    match word {
+                        "mss" => synproxy.mss = Some(p.number("mss")?),
+                        "wscale" => synproxy.wscale = Some(p.number("wscale")?),
+                        _ => match keyword(word) {
+                            Some(flag) => {
+                                synproxy.flags.get_or_insert_with(HashSet::new).insert(flag);
+                            }
+                            None => return Ok(false),
+                        },
+                    }
+                    Ok(true)
+                })?;
+                NfListObject::SynProxy(synproxy)
+            }
+            _ => {
+                let mut flowtable = FlowTable {
+                    family,
+                    table,
+                    name,
+                    ..Default::default()
+                };
+                self.properties(kind, |p, word| {
+                    match word {
+                        "hook" => flowtable.hook = Some(p.keyword("hook")?),
+                        "priority" => {
+                            let pos = p.pos();
+                            let priority = p.priority(family)?;
+                            let priority = u32::try_from(priority).map_err(|_| {
+                                p.error_at(pos, "negative flowtable priorities are not supported")
+                            })?;
+                            flowtable.prio = Some(priority);
+                        }
+                        "devices" => {
+                            let devices = p.devices()?;
+                            flowtable.dev = Some(devices.into_iter().map(Cow::from).collect());
+                        }
+                        _ => return Ok(false),
+                    }
+                    Ok(true)
+                })?;
+                NfListObject::FlowTable(flowtable)
+            }
+        })
+    }
+
+    /// Consumes a byte amount and returns it in bytes.
+    fn byte_amount(&mut self) -> Result<u64, ParseError> {
+        let pos = self.pos();
+        let (amount, unit) = self.bytes()?;
+        byte_unit_shift(&unit)
+            .and_then(|shift| amount.checked_mul(1 << shift))
+            .ok_or_else(|| self.error_at(pos, "amount is out of range"))
+    }
+
+    /// Consumes a rate like `10/second` or `10 mbytes/second` and returns
+    /// the rate, the byte unit if any and the time unit.
+    fn rate(&mut self) -> Result<(u32, Option<String>, String), ParseError> {
+        let pos = self.pos();
+        let word = self.expect_word("rate")?;
+        let invalid = |p: &Self| p.error_at(pos, format!("invalid rate `{word}`"));
+        if let Some((rate, per)) = word.split_once('/') {
+            let rate = parse_integer(rate).and_then(|rate| u32::try_from(rate).ok());
+            return Ok((rate.ok_or_else(|| invalid(self))?, None, per.to_owned()));
+        }
+        let rate = parse_integer(&word).and_then(|rate| u32::try_from(rate).ok());
+        let rate = rate.ok_or_else(|| invalid(self))?;
+        let pos = self.pos();
+        let unit = self.expect_word("rate unit")?;
+        match unit.split_once('/') {
+            Some((unit, per)) if byte_unit_shift(unit).is_some() => {
+                Ok((rate, Some(unit.to_owned()), per.to_owned()))
+            }
+            _ => Err(self.error_at(pos, format!("invalid rate unit `{unit}`"))),
+        }
+    }
+
+    fn object_command(&mut self, verb: &str) -> Result<(), ParseError> {
+        let pos = self.pos();
+        let kind = self.expect_word("object type")?;
+        if matches!(verb, "insert" | "replace") && kind != "rule" {
+            return Err(self.error_at(pos, format!("cannot {verb} a {kind}")));
+        }
+        let removal = matches!(verb, "delete" | "destroy");
+        match kind.as_str() {
+            "table" if removal => {
+                let table = self.table_spec()?;
+                self.push_command(command(verb, NfListObject::Table(table)))
+            }
+            "table" => self.table(verb),
+            "chain" => {
+                let table = self.table_spec()?;
+                let name = self.string("chain name")?;
+                if removal || !self.eat_punct("{") {
+                    let chain = Chain {
+                        family: table.family,
+                        table: table.name,
+                        name: name.into(),
+                        ..Default::default()
+                    };
+                    return self.push_command(command(verb, NfListObject::Chain(chain)));
+                }
+                let mut block = TableBlock::default();
+                let chain = self.chain_body(&table, name, &mut block.rules)?;
+                self.commands
+                    .push(command(verb, NfListObject::Chain(chain)));
+                self.push_block(block);
+                self.expect_end()
+            }
+            "rule" => self.rule_command(verb),
+            "set" | "map" => {
+                let table = self.table_spec()?;
+                let name = self.string("set name")?;
+                if removal {
+                    let set = schema::Set {
+                        family: table.family,
+                        table: table.name,
+                        name: name.into(),
+                        ..Default::default()
+                    };
+                    return self.push_command(command(verb, NfListObject::Set(Box::new(set))));
+                }
+                self.expect_punct("{")?;
+                let set = self.set_body(&table, name, kind == "map")?;
+                self.push_command(command(verb, set))
+            }
+            "element" => {
+                let table = self.table_spec()?;
+                let name = self.string("set name")?;
+                self.expect_punct("{")?;
+                let items = self.set_items(Context::Value)?;
+                let element = Element {
+                    family: table.family,
+                    table: table.name,
+                    name: name.into(),
+                    elem: items.into_iter().map(element_expression).collect(),
+                };
+                self.push_command(command(verb, NfListObject::Element(element)))
+            }
+            "counter" | "quota" | "limit" | "synproxy" | "flowtable" | "ct" if !removal => {
+                let kind = if kind == "ct" {
+                    format!("ct {}", self.expect_word("ct object type")?)
+                } else {
+                    kind
+                };
+                let table = self.table_spec()?;
+                let object = self.object(&table, &kind)?;
+                self.push_command(command(verb, object))
+            }
+            _ => Err(self.error_at(pos, format!("cannot {verb} a {kind}"))),
+        }
+    }
+
+    fn push_command(&mut self, cmd: NfCmd<'static>) -> Result<(), ParseError> {
+        self.commands.push(cmd);
+        self.expect_end()
+    }
+
+    fn rule_command(&mut self, verb: &str) -> Result<(), ParseError> {
+        let table = self.table_spec()?;
+        let chain = self.string("chain name")?;
+        let (mut handle, mut index) = (None, None);
+        if self.eat_word("handle") || self.eat_word("position") {
+            handle = Some(self.number("rule handle")?);
+        } else if self.eat_word("index") {
+            index = Some(self.number("rule index")?);
+        }
+        if matches!(verb, "delete" | "destroy" | "replace") && handle.is_none() {
+            return Err(self.unexpected("`handle`"));
+        }
+        let mut rule = if matches!(verb, "delete" | "destroy") {
+            Rule {
+                family: table.family,
+                table: table.name,
+                chain: chain.into(),
+                ..Default::default()
+            }
+        } else {
+            self.rule(table.family, &table.name, &chain)?
+        };
+        rule.handle = handle;
+        rule.index = index;
+        let cmd = match verb {
+            "replace" => NfCmd::Replace(rule),
+            _ => command(verb, NfListObject::Rule(rule)),
+        };
+        self.push_command(cmd)
+    }
+
+    fn flush(&mut self) -> Result<(), ParseError> {
+        let pos = self.pos();
+        let kind = self.expect_word("object type")?;
+        let object = match kind.as_str() {
+            "ruleset" => FlushObject::Ruleset(None),
+            "table" => FlushObject::Table(self.table_spec()?),
+            "chain" => {
+                let table = self.table_spec()?;
+                FlushObject::Chain(Chain {
+                    family: table.family,
+                    table: table.name,
+                    name: self.string("chain name")?.into(),
+                    ..Default::default()
+                })
+            }
+            "set" | "map" => {
+                let table = self.table_spec()?;
+                let name = self.string("set name")?.into();
+                if kind == "set" {
+                    FlushObject::Set(Box::new(schema::Set {
+                        family: table.family,
+                        table: table.name,
+                        name,
+                        ..Default::default()
+                    }))
+                } else {
+                    FlushObject::Map(Box::new(schema::Map {
+                        family: table.family,
+                        table: table.name,
+                        name,
+                        ..Default::default()
+                    }))
+                }
+            }
+            _ => return Err(self.error_at(pos, format!("cannot flush a {kind}"))),
+        };
+        self.push_command(NfCmd::Flush(object))
+    }
+
+    // Rules
+
+    fn rule(
+        &mut self,
+        family: NfFamily,
+        table: &str,
+        chain: &str,
+    ) -> Result<Rule<'static>, ParseError> {
+        let mut expr = Vec::new();
+        let mut comment = None;
+        while !self.at_end() {
+            if self.eat_word("comment") {
+                comment = Some(self.string("comment")?.into());
+            } else {
+                expr.push(self.statement()?);
+            }
+        }
+        Ok(Rule {
+            family,
+            table: table.to_owned().into(),
+            chain: chain.to_owned().into(),
+            expr: expr.into(),
+            handle: None,
+            index: None,
+            comment,
+        })
+    }
+
+    fn statement(&mut self) -> Result<Statement<'static>, ParseError> {
+        let Some(word) = self.peek_word().map(str::to_owned) else {
+            return self.relation();
+        };
+        let statement = match word.as_str() {
+            "accept" => Statement::Accept(None),
+            "drop" => Statement::Drop(None),
+            "continue" => Statement::Continue(None),
+            "return" => Statement::Return(None),
+            "notrack" => Statement::Notrack,
+            "jump" | "goto" => {
+                self.next += 1;
+                let target = JumpTarget {
+                    target: self.string("chain name")?.into(),
+                };
+                return Ok(if word == "jump" {
+                    Statement::Jump(target)
+                } else {
+                    Statement::Goto(target)
+                });
+            }
+            "counter" => return self.counter(),
+            "limit" => return self.limit(),
+            "log" => return self.log(),
+            "snat" | "dnat" | "masquerade" | "redirect" => return self.nat(&word),
+            "tproxy" => return self.tproxy(),
+            "reject" => return self.reject(),
+            "queue" => return self.queue(),
+            "quota" => return self.quota(),
+            "synproxy" => return self.synproxy(),
+            "flow" => return self.flow(),
+            "dup" => return self.dup(),
+            "fwd" => return self.fwd(),
+            "meter" => return self.meter(),
+            "add" | "update" => return self.set_statement(),
+            "ct" => match (self.lookahead(1), self.lookahead(2)) {
+                (Some("count"), _) => return self.ct_count(),
+                (Some("helper" | "expectation" | "timeout"), Some("set")) => {
+                    return self.ct_object_statement()
+                }
+                _ => return self.relation(),
+            },
+            _ => return self.relation(),
+        };
+        self.next += 1;
+        Ok(statement)
+    }
+
+    /// Consumes a match, a mangle statement (`<selector> set <value>`) or a
+    /// verdict map statement (`<selector> vmap <map>`).
+    fn relation(&mut self) -> Result<Statement<'static>, ParseError> {
+        let left = self.expression(Context::Selector)?;
+        if self.eat_word("set") {
+            let value = self.expression(Context::Any)?;
+            return Ok(Statement::Mangle(Mangle { key: left, value }));
+        }
+        if self.eat_word("vmap") {
+            let data = self.map_data()?;
+            return Ok(Statement::VerdictMap(VerdictMap { key: left, data }));
+        }
+        let op = self.operator();
+        if self.at_end() {
+            return Err(self.unexpected("value"));
+        }
+        let right = self.values()?;
+        Ok(Statement::Match(relation(left, op, right)))
+    }
+
+    fn operator(&mut self) -> Option<Operator> {
+        // The schema names of `<` and `>` are swapped.
+        let op = match self.peek()? {
+            Tok::Punct("==") => Operator::EQ,
+            Tok::Punct("!=") => Operator::NEQ,
+            Tok::Punct("<") => Operator::GT,
+            Tok::Punct(">") => Operator::LT,
+            Tok::Punct("<=") => Operator::LEQ,
+            Tok::Punct(">=") => Operator::GEQ,
+            Tok::Word(word) => match word.as_str() {
+                "eq" => Operator::EQ,
+                "ne" => Operator::NEQ,
+                "lt" => Operator::GT,
+                "gt" => Operator::LT,
+                "le" => Operator::LEQ,
+                "ge" => Operator::GEQ,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        self.next += 1;
+        Some(op)
+    }
+
+    fn counter(&mut self) -> Result<Statement<'static>, ParseError> {
+        self.next += 1;
+        if self.eat_word("name") {
+            let name = self.string("counter name")?;
+            return Ok(Statement::Counter(stmt::Counter::Named(name.into())));
+        }
+        Ok(Statement::Counter(self.counter_values()?))
+    }
+
+    /// Consumes the optional values of an anonymous counter.
+    fn counter_values(&mut self) -> Result<stmt::Counter<'static>, ParseError> {
+        if !self.is_word("packets") && !self.is_word("bytes") {
+            return Ok(stmt::Counter::Anonymous(None));
+        }
+        let mut counter = AnonymousCounter::default();
+        if self.eat_word("packets") {
+            counter.packets = Some(self.number("packets")?);
+        }
+        if self.eat_word("bytes") {
+            counter.bytes = Some(self.number("bytes")?);
+        }
+        Ok(stmt::Counter::Anonymous(Some(counter)))
+    }
+
+    fn limit(&mut self) -> Result<Statement<'static>, ParseError> {
+        self.next += 1;
+        self.expect_keyword("rate")?;
+        let inv = self.eat_word("over").then_some(true);
+        let (rate, rate_unit, per) = self.rate()?;
+        let mut limit = stmt::Limit {
+            rate,
+            rate_unit: rate_unit.map(Cow::from),
+            per: Some(per.into()),
+            burst: None,
+            burst_unit: None,
+            inv,
+        };
+        if self.eat_word("burst") {
+            limit.burst = Some(self.number("burst")?);
+            match self.peek_word().map(str::to_owned) {
+                Some(unit) if unit == "packets" => self.next += 1,
+                Some(unit) if byte_unit_shift(&unit).is_some() => {
+                    self.next += 1;
+                    limit.burst_unit = Some(unit.into());
+                }
+                _ => {}
+            }
+        } else if limit.rate_unit.is_none() {
+            // The default burst of packet limits, as reported by the kernel.
+            limit.burst = Some(5);
+        }
+        Ok(Statement::Limit(limit))
+    }
+
+    fn log(&mut self) -> Result<Statement<'static>, ParseError> {
+        self.next += 1;
+        let mut log = Log::new(None);
+        let mut options = false;
+        while let Some(word) = self.peek_word().map(str::to_owned) {
+            match word.as_str() {
+                "prefix" | "level" | "group" | "snaplen" | "queue-threshold" | "flags" => {
+                    self.next += 1
+                }
+                _ => break,
+            }
+            match word.as_str() {
+                "prefix" => log.prefix = Some(self.string("log prefix")?.into()),
+                "level" => log.level = Some(self.keyword("log level")?),
+                "group" => log.group = Some(self.number("log group")?),
+                "snaplen" => log.snaplen = Some(self.number("snaplen")?),
+                "queue-threshold" => log.queue_threshold = Some(self.number("queue threshold")?),
+                _ => {
+                    let flags = log.flags.get_or_insert_with(HashSet::new);
+                    self.log_flags(flags)?;
+                }
+            }
+            options = true;
+        }
+        Ok(Statement::Log(options.then_some(log)))
+    }
+
+    /// Consumes log flags such as `tcp sequence,options` or `skuid`.
+    fn log_flags(&mut self, flags: &mut HashSet<LogFlag>) -> Result<(), ParseError> {
+        loop {
+            let pos = self.pos();
+            let word = self.expect_word("log flag")?;
+            match word.as_str() {
+                "tcp" => loop {
+                    let pos = self.pos();
+                    flags.insert(match self.expect_word("tcp log flag")?.as_str() {
+                        "sequence" => LogFlag::TCPSequence,
+                        "options" => LogFlag::TCPOptions,
+                        flag => {
+                            return Err(self.error_at(pos, format!("unknown log flag `tcp {flag}`")))
+                        }
+                    });
+                    let next = self.lookahead(1);
+                    let more = matches!(next, Some("sequence" | "options"));
+                    if !more || !self.eat_punct(",") {
+                        break;
+                    }
+                },
+                "ip" => {
+                    self.expect_keyword("options")?;
+                    flags.insert(LogFlag::IPOptions);
+                }
+                _ => {
+                    let flag = keyword(&word)
+                        .ok_or_else(|| self.error_at(pos, format!("unknown log flag `{word}`")))?;
+                    flags.insert(flag);
+                }
+            }
+            if !self.eat_punct(",") {
+                return Ok(());
+            }
+        }
+    }
+
+    fn nat(&mut self, kind: &str) -> Result<Statement<'static>, ParseError> {
+        self.next += 1;
+        let mut nat = NAT {
+            addr: None,
+            family: None,
+            port: None,
+            flags: None,
+        };
+        let with_addr = matches!(kind, "snat" | "dnat");
+        if with_addr {
+            nat.family = match self.peek_word() {
+                Some("ip") => Some(NATFamily::IP),
+                Some("ip6") => Some(NATFamily::IP6),
+                _ => None,
+            };
+            if nat.family.is_some() {
+                self.next += 1;
+            }
+            self.expect_keyword("to")?;
+            self.nat_target(&mut nat, true)?;
+        } else if self.eat_word("to") {
+            self.nat_target(&mut nat, false)?;
+        }
+        if let Some(word) = self.peek_word() {
+            if keyword::<stmt::NATFlag>(word).is_some() {
+                let flags = self.list(|p| p.keyword("nat flag"))?;
+                nat.flags = Some(flags.into_iter().collect());
+            }
+        }
+        let options = nat.addr.is_some() || nat.port.is_some() || nat.flags.is_some();
+        Ok(match kind {
+            "snat" => Statement::SNAT(Some(nat)),
+            "dnat" => Statement::DNAT(Some(nat)),
+            "masquerade" => Statement::Masquerade(options.then_some(nat)),
+            _ => Statement::Redirect(options.then_some(nat)),
+        })
+    }
+
+    /// Consumes the address and port of a NAT statement.
+    fn nat_target(&mut self, nat: &mut NAT<'static>, with_addr: bool) -> Result<(), ParseError> {
+        if let Some(word) = self.peek_word().map(str::to_owned) {
+            let pos = self.pos();
+            if let Some((addr, port)) = split_addr_port(&word) {
+                self.next += 1;
+                if !addr.is_empty() {
+                    nat.addr = Some(self.value(addr, pos)?);
+                }
+                if !port.is_empty() {
+                    nat.port = Some(self.value(port, pos)?);
+                }
+                return Ok(());
+            }
+            if !with_addr {
+                self.next += 1;
+                nat.port = Some(self.value(&word, pos)?);
+                return Ok(());
+            }
+        }
+        nat.addr = Some(self.expression(Context::Any)?);
+        Ok(())
+    }
+
+    fn tproxy(&mut self) -> Result<Statement<'static>, ParseError> {
+        self.next += 1;
+        let family = match self.peek_word() {
+            Some(family @ ("ip" | "ip6")) => Some(family.to_owned()),
+            _ => None,
+        };
+        if family.is_some() {
+            self.next += 1;
+        }
+        self.expect_keyword("to")?;
+        let pos = self.pos();
+        let word = self.expect_word("tproxy address")?;
+        let (addr, port) = split_addr_port(&word).unwrap_or((&word, ""));
+        let port = port
+            .parse()
+            .map_err(|_| self.error_at(pos, format!("expected port in `{word}`")))?;
+        Ok(Statement::TProxy(TProxy {
+            family: family.map(Cow::from),
+            port,
+            addr: (!addr.is_empty()).then(|| addr.to_owned().into()),
+        }))
+    }
+
+    fn reject(&mut self) -> Result<Statement<'static>, ParseError> {
+        self.next += 1;
+        if !self.eat_word("with") {
+            return Ok(Statement::Reject(None));
+        }
+        let pos = self.pos();
+        let reject = match self.expect_word("reject type")?.as_str() {
+            "tcp" => {
+                self.expect_keyword("reset")?;
+                Reject::new(Some(RejectType::TCPReset), None)
+            }
+            kind @ ("icmp" | "icmpv6" | "icmpx") => {
+                let _type = match kind {
+                    "icmp" => RejectType::ICMP,
+                    "icmpv6" => RejectType::ICMPv6,
+                    _ => RejectType::ICMPX,
+                };
+                self.eat_word("type");
+                Reject::new(Some(_type), Some(self.keyword("reject code")?))
+            }
+            kind => return Err(self.error_at(pos, format!("unknown reject type `{kind}`"))),
+        };
+        Ok(Statement::Reject(Some(reject)))
+    }
+
+    fn queue(&mut self) -> Result<Statement<'static>, ParseError> {
+        self.next += 1;
+        let mut flags: HashSet<QueueFlag> = HashSet::new();
+        let mut num = None;
+        loop {
+            if self.eat_word("flags") {
+                flags.extend(self.list(|p| p.keyword::<QueueFlag>("queue flag"))?);
+            } else if self.eat_word("num") || self.eat_word("to") {
+                num = Some(self.range(Context::Value)?);
+            } else if let Some(flag) = self.peek_word().and_then(keyword) {
+                // Flags after the queue number, as in `queue num 1 bypass`.
+                self.next += 1;
+                flags.insert(flag);
+                self.eat_punct(",");
+            } else {
+                break;
+            }
+        }
+        Ok(Statement::Queue(Queue {
+            num: num.unwrap_or(Expression::Number(0)),
+            flags: (!flags.is_empty()).then_some(flags),
+        }))
+    }
+
+    fn quota(&mut self) -> Result<Statement<'static>, ParseError> {
+        self.next += 1;
+        if self.eat_word("name") {
+            let name = self.string("quota name")?;
+            return Ok(Statement::Quota(QuotaOrQuotaRef::QuotaRef(name.into())));
+        }
+        let inv = self.eat_word("over").then_some(true);
+        let (val, val_unit) = self.bytes()?;
+        let mut quota = stmt::Quota {
+            val,
+            val_unit: val_unit.into(),
+            used: None,
+            used_unit: None,
+            inv,
+        };
+        if self.eat_word("used") {
+            let (used, used_unit) = self.bytes()?;
+            quota.used = Some(used);
+            quota.used_unit = Some(used_unit.into());
+        }
+        Ok(Statement::Quota(QuotaOrQuotaRef::Quota(quota)))
+    }
+
+    fn synproxy(&mut self) -> Result<Statement<'static>, ParseError> {
+        self.next += 1;
+        if self.is_word("name") {
+            return Err(self.error("references to named synproxies are not supported"));
+        }
+        let mut synproxy = stmt::SynProxy {
+            mss: None,
+            wscale: None,
+            flags: None,
+        };
+        while let Some(word) = self.peek_word().map(str::to_owned) {
+            if word == "mss" {
+                self.next += 1;
+                synproxy.mss = Some(self.number("mss")?);
+            } else if word == "wscale" {
+                self.next += 1;
+                synproxy.wscale = Some(self.number("wscale")?);
+            } else if let Some(flag) = keyword(&word) {
+                self.next += 1;
+                synproxy.flags.get_or_insert_with(HashSet::new).insert(flag);
+            } else {
+                break;
+            }
+        }
+        Ok(Statement::SynProxy(synproxy))
+    }
+
+    fn flow(&mut self) -> Result<Statement<'static>, ParseError> {
+        self.next += 1;
+        if !self.eat_word("add") {
+            self.expect_keyword("offload")?;
+        }
+        let flowtable = self.set_reference("flowtable reference")?;
+        Ok(Statement::Flow(Flow {
+            op: SetOp::Add,
+            flowtable: flowtable.into(),
+        }))
+    }
+
+    /// Consumes a reference (`@name`) to a set, map or flowtable.
+    fn set_reference(&mut self, what: &str) -> Result<String, ParseError> {
+        match self.peek_word() {
+            Some(word) if word.starts_with('@') => self.expect_word(what),
+            _ => Err(self.unexpected(what)),
+        }
+    }
+
+    fn dup(&mut self) -> Result<Statement<'static>, ParseError> {
+        self.next += 1;
+        self.expect_keyword("to")?;
+        let addr = self.expression(Context::Any)?;
+        let dev = if self.eat_word("device") {
+            Some(self.expression(Context::Any)?)
+        } else {
+            None
+        };
+        Ok(Statement::Dup(Dup { addr, dev }))
+    }
+
+    fn fwd(&mut self) -> Result<Statement<'static>, ParseError> {
+        self.next += 1;
+        let family = match self.peek_word() {
+            Some("ip") => Some(stmt::FWDFamily::IP),
+            Some("ip6") => Some(stmt::FWDFamily::IP6),
+            _ => None,
+        };
+        if family.is_some() {
+            self.next += 1;
+        }
+        self.expect_keyword("to")?;
+        let target = self.expression(Context::Any)?;
+        let fwd = match family {
+            Some(_) => {
+                self.expect_keyword("device")?;
+                FWD {
+                    dev: Some(self.expression(Context::Any)?),
+                    family,
+                    addr: Some(target),
+                }
+            }
+            None => FWD {
+                dev: Some(target),
+                family: None,
+                addr: None,
+            },
+        };
+        Ok(Statement::FWD(Some(fwd)))
+    }
+
+    fn meter(&mut self) -> Result<Statement<'static>, ParseError> {
+        self.next += 1;
+        let name = self.string("meter name")?;
+        self.expect_punct("{")?;
+        let key = self.expression(Context::Selector)?;
+        let stmt = self.statement()?;
+        self.expect_punct("}")?;
+        Ok(Statement::Meter(Meter {
+            name: name.into(),
+            key,
+            stmt: Box::new(stmt),
+        }))
+    }
+
+    /// Consumes `add @set { <element> }` or `update @set { <element> }`.
+    fn set_statement(&mut self) -> Result<Statement<'static>, ParseError> {
+        let op = self.keyword("set operation")?;
+        let set = self.set_reference("set reference")?;
+        self.expect_punct("{")?;
+        let elem = self.element(Context::Any)?;
+        self.expect_punct("}")?;
+        Ok(Statement::Set(stmt::Set {
+            op,
+            elem,
+            set: set.into(),
+        }))
+    }
+
+    fn ct_count(&mut self) -> Result<Statement<'static>, ParseError> {
+        self.next += 2;
+        let inv = self.eat_word("over").then_some(true);
+        let val = Expression::Number(self.number("connection count")?);
+        Ok(Statement::CTCount(CTCount { val, inv }))
+    }
+
+    /// Consumes `ct helper|expectation|timeout set <name>`.
+    fn ct_object_statement(&mut self) -> Result<Statement<'static>, ParseError> {
+        let kind = self.lookahead(1).unwrap_or_default().to_owned();
+        self.next += 3;
+        let name: Cow<'static, str> = self.string(&format!("ct {kind} name"))?.into();
+        Ok(match kind.as_str() {
+            "helper" => Statement::CTHelper(name),
+            "expectation" => Statement::CTExpectation(Expression::String(name)),
+            _ => Statement::CTTimeout(Expression::String(name)),
+        })
+    }
+
+    // Expressions
+
+    /// Consumes a concatenation of binary operations, optionally looked up
+    /// in a map (`<key> map <map>`).
+    fn expression(&mut self, ctx: Context) -> Result<Expression<'static>, ParseError> {
+        let mut items = vec![self.or(ctx)?];
+        while self.eat_word(".") {
+            items.push(self.or(ctx)?);
+        }
+        let key = match items.len() {
+            1 => items.swap_remove(0),
+            _ => Expression::Named(NamedExpression::Concat(items)),
+        };
+        if self.eat_word("map") {
+            let data = self.map_data()?;
+            return Ok(Expression::Named(NamedExpression::Map(Box::new(
+                expr::Map { key, data },
+            ))));
+        }
+        Ok(key)
+    }
+
+    /// Consumes a map reference or an anonymous map.
+    fn map_data(&mut self) -> Result<Expression<'static>, ParseError> {
+        if self.eat_punct("{") {
+            let items = self.set_items(Context::Value)?;
+            return Ok(Expression::Named(NamedExpression::Set(items)));
+        }
+        Ok(Expression::String(self.set_reference("map")?.into()))
+    }
+
+    fn or(&mut self, ctx: Context) -> Result<Expression<'static>, ParseError> {
+        let mut left = self.xor(ctx)?;
+        while self.eat_punct("|") || self.eat_word("or") {
+            let right = self.xor(Context::Value)?;
+            left = binary(BinaryOperation::OR(vec![left, right]));
+        }
+        Ok(left)
+    }
+
+    fn xor(&mut self, ctx: Context) -> Result<Expression<'static>, ParseError> {
+        let mut left = self.and(ctx)?;
+        while self.eat_punct("^") || self.eat_word("xor") {
+            let right = self.and(Context::Value)?;
+            left = binary(BinaryOperation::XOR(left, right));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self, ctx: Context) -> Result<Expression<'static>, ParseError> {
+        let mut left = self.shift(ctx)?;
+        while self.eat_punct("&") || self.eat_word("and") {
+            let right = self.shift(Context::Value)?;
+            left = binary(BinaryOperation::AND(left, right));
+        }
+        Ok(left)
+    }
+
+    fn shift(&mut self, ctx: Context) -> Result<Expression<'static>, ParseError> {
+        let mut left = self.primary(ctx)?;
+        loop {
+            if self.eat_punct("<<") || self.eat_word("lshift") {
+                let right = self.primary(Context::Value)?;
+                left = binary(BinaryOperation::LSHIFT(left, right));
+            } else if self.eat_punct(">>") || self.eat_word("rshift") {
+                let right = self.primary(Context::Value)?;
+                left = binary(BinaryOperation::RSHIFT(left, right));
+            } else {
+                return Ok(left);
+            }
+        }
+    }
+
+    fn primary(&mut self, ctx: Context) -> Result<Expression<'static>, ParseError> {
+        let pos = self.pos();
+        match self.peek().cloned() {
+            Some(Tok::Punct("(")) => {
+                self.next += 1;
+                let expr = self.expression(ctx)?;
+                self.expect_punct(")")?;
+                Ok(expr)
+            }
+            Some(Tok::Punct("{")) => {
+                self.next += 1;
+                let ctx = if ctx == Context::Any {
+                    Context::Any
+                } else {
+                    Context::Value
+                };
+                Ok(Expression::Named(NamedExpression::Set(
+                    self.set_items(ctx)?,
+                )))
+            }
+            Some(Tok::Str(value)) => {
+                self.next += 1;
+                Ok(Expression::String(value.into()))
+            }
+            Some(Tok::Word(word)) => {
+                if ctx != Context::Value {
+                    if let Some(selector) = self.selector(&word)? {
+                        return Ok(selector);
+                    }
+                    if ctx == Context::Selector {
+                        let message = format!("unknown statement or selector `{word}`");
+                        return Err(self.error_at(pos, message));
+                    }
+                }
+                self.next += 1;
+                self.value(&word, pos)
+            }
+            _ => Err(self.unexpected("expression")),
+        }
+    }
+
+    /// Consumes a selector starting with `word`, if it is one.
+    fn selector(&mut self, word: &str) -> Result<Option<Expression<'static>>, ParseError> {
+        let named = match word {
+            "meta" => {
+                self.next += 1;
+                NamedExpression::Meta(Meta {
+                    key: self.keyword("meta key")?,
+                })
+            }
+            _ if UNQUALIFIED_META_KEYS.contains(&word) => {
+                self.next += 1;
+                NamedExpression::Meta(Meta {
+                    key: keyword::<MetaKey>(word).expect("valid meta key"),
+                })
+            }
+            "ct" => {
+                self.next += 1;
+                let dir = self.peek_word().and_then(keyword);
+                if dir.is_some() {
+                    self.next += 1;
+                }
+                let family = match self.lookahead(1) {
+                    Some(_) => self.peek_word().and_then(keyword),
+                    None => None,
+                };
+                if family.is_some() {
+                    self.next += 1;
+                }
+                NamedExpression::CT(CT {
+                    key: self.expect_word("ct key")?.into(),
+                    family,
+                    dir,
+                })
+            }
+            "rt" => {
+                self.next += 1;
+                let family = match self.lookahead(1) {
+                    Some(_) => self.peek_word().and_then(keyword),
+                    None => None,
+                };
+                if family.is_some() {
+                    self.next += 1;
+                }
+                let pos = self.pos();
+                let key = self.expect_word("rt key")?;
+                match keyword(&key) {
+                    Some(key) => NamedExpression::RT(RT { key, family }),
+                    // The IPv6 routing header shares the keyword.
+                    None if family.is_none() => NamedExpression::Exthdr(Exthdr {
+                        name: "rt".into(),
+                        field: Some(key.into()),
+                        offset: None,
+                    }),
+                    None => return Err(self.error_at(pos, format!("unknown rt key `{key}`"))),
+                }
+            }
+            "tcp" if self.lookahead(1) == Some("option") => {
+                self.next += 2;
+                let name = self.expect_word("tcp option")?;
+                let field = match self.peek_word() {
+                    Some("exists" | "missing" | "set" | "eq" | "ne") | None => None,
+                    Some(field) => Some(field.to_owned()),
+                };
+                if field.is_some() {
+                    self.next += 1;
+                }
+                NamedExpression::TcpOption(TcpOption {
+                    name: name.into(),
+                    field: field.map(Cow::from),
+                })
+            }
+            "sctp" if self.lookahead(1) == Some("chunk") => {
+                self.next += 2;
+                NamedExpression::SctpChunk(SctpChunk {
+                    name: self.expect_word("sctp chunk")?.into(),
+                    field: self.expect_word("sctp chunk field")?.into(),
+                })
+            }
+            "exthdr" => {
+                self.next += 1;
+                NamedExpression::Exthdr(Exthdr {
+                    name: self.expect_word("extension header")?.into(),
+                    field: None,
+                    offset: None,
+                })
+            }
+            _ if EXTHDRS.contains(&word) && self.lookahead(1).is_some() => {
+                self.next += 1;
+                NamedExpression::Exthdr(Exthdr {
+                    name: word.to_owned().into(),
+                    field: Some(self.expect_word("extension header field")?.into()),
+                    offset: None,
+                })
+            }
+            _ if PAYLOAD_PROTOCOLS.contains(&word) && self.lookahead(1).is_some() => {
+                self.next += 1;
+                NamedExpression::Payload(Payload::PayloadField(PayloadField {
+                    protocol: word.to_owned().into(),
+                    field: self.expect_word("header field")?.into(),
+                }))
+            }
+            "numgen" => {
+                self.next += 1;
+                let mode = self.keyword("numgen mode")?;
+                self.expect_keyword("mod")?;
+                let ng_mod = self.number("modulus")?;
+                let offset = self.offset()?;
+                NamedExpression::Numgen(Numgen {
+                    mode,
+                    ng_mod,
+                    offset,
+                })
+            }
+            "jhash" => {
+                self.next += 1;
+                let expr = self.expression(Context::Selector)?;
+                self.expect_keyword("mod")?;
+                let hash_mod = self.number("modulus")?;
+                let seed = if self.eat_word("seed") {
+                    Some(self.number("seed")?)
+                } else {
+                    None
+                };
+                NamedExpression::JHash(JHash {
+                    hash_mod,
+                    offset: self.offset()?,
+                    expr: Box::new(expr),
+                    seed,
+                })
+            }
+            "symhash" => {
+                self.next += 1;
+                self.expect_keyword("mod")?;
+                NamedExpression::SymHash(SymHash {
+                    hash_mod: self.number("modulus")?,
+                    offset: self.offset()?,
+                })
+            }
+            "fib" => {
+                self.next += 1;
+                let mut flags = HashSet::from([self.keyword::<FibFlag>("fib flag")?]);
+                while self.eat_word(".") {
+                    flags.insert(self.keyword("fib flag")?);
+                }
+                NamedExpression::Fib(Fib {
+                    result: self.keyword("fib result")?,
+                    flags,
+                })
+            }
+            "socket" => {
+                self.next += 1;
+                NamedExpression::Socket(Socket {
+                    key: Cow::Owned(self.keyword("socket key")?),
+                })
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(Expression::Named(named)))
+    }
+
+    fn offset(&mut self) -> Result<Option<u32>, ParseError> {
+        if self.eat_word("offset") {
+            Ok(Some(self.number("offset")?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Converts a constant word into an expression.
+    fn value(&self, word: &str, pos: Pos) -> Result<Expression<'static>, ParseError> {
+        match word {
+            "exists" => return Ok(Expression::Boolean(true)),
+            "missing" => return Ok(Expression::Boolean(false)),
+            _ => {}
+        }
+        if word.starts_with(|c: char| c.is_ascii_digit()) {
+            if let Some(n) = parse_integer(word) {
+                return Ok(Expression::Number(n));
+            }
+            if word.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(self.error_at(pos, format!("number `{word}` is out of range")));
+            }
+        }
+        if let Some(prefix) = prefix(word) {
+            return Ok(prefix);
+        }
+        if let Some((low, high)) = word.split_once('-') {
+            let scalar = |s: &str| parse_integer(s).is_some() || s.parse::<IpAddr>().is_ok();
+            if scalar(low) && scalar(high) {
+                return Ok(Expression::Range(Box::new(Range {
+                    range: [self.value(low, pos)?, self.value(high, pos)?],
+                })));
+            }
+        }
+        Ok(Expression::String(word.to_owned().into()))
+    }
+
+    /// Consumes the right hand side of a match, which may be a comma
+    /// separated list.
+    fn values(&mut self) -> Result<Expression<'static>, ParseError> {
+        let mut items = self.list(|p| p.range(Context::Value))?;
+        Ok(match items.len() {
+            1 => items.swap_remove(0),
+            _ => Expression::List(items),
+        })
+    }
+
+    /// Consumes an expression, or a range of two expressions separated by
+    /// ` - `.
+    fn range(&mut self, ctx: Context) -> Result<Expression<'static>, ParseError> {
+        let low = self.expression(ctx)?;
+        if self.eat_word("-") {
+            let high = self.expression(ctx)?;
+            return Ok(Expression::Range(Box::new(Range { range: [low, high] })));
+        }
+        Ok(low)
+    }
+
+    /// Consumes the items of a set or map up to the closing brace.
+    fn set_items(&mut self, ctx: Context) -> Result<Vec<SetItem<'static>>, ParseError> {
+        let mut items = Vec::new();
+        loop {
+            self.skip_newlines();
+            if self.eat_punct("}") {
+                return Ok(items);
+            }
+            let key = self.element(ctx)?;
+            items.push(if self.eat_punct(":") {
+                SetItem::Mapping(key, self.mapping_value()?)
+            } else {
+                SetItem::Element(key)
+            });
+            self.skip_newlines();
+            if !self.eat_punct(",") {
+                self.expect_punct("}")?;
+                return Ok(items);
+            }
+        }
+    }
+
+    /// Consumes a set element with optional timeout, expiry, comment and
+    /// counter.
+    fn element(&mut self, ctx: Context) -> Result<Expression<'static>, ParseError> {
+        let val = self.range(ctx)?;
+        let mut elem = Elem {
+            val: Box::new(val),
+            timeout: None,
+            expires: None,
+            comment: None,
+            counter: None,
+        };
+        let mut options = false;
+        loop {
+            if self.eat_word("timeout") {
+                elem.timeout = Some(self.seconds()?);
+            } else if self.eat_word("expires") {
+                elem.expires = Some(self.seconds()?);
+            } else if self.eat_word("comment") {
+                elem.comment = Some(self.string("comment")?.into());
+            } else if self.eat_word("counter") {
+                elem.counter = Some(self.counter_values()?);
+            } else {
+                break;
+            }
+            options = true;
+        }
+        Ok(match options {
+            true => Expression::Named(NamedExpression::Elem(elem)),
+            false => *elem.val,
+        })
+    }
+
+    /// Consumes the value of a map element, which may be a verdict.
+    fn mapping_value(&mut self) -> Result<Expression<'static>, ParseError> {
+        let verdict = match self.peek_word() {
+            Some("accept") => Verdict::Accept,
+            Some("drop") => Verdict::Drop,
+            Some("continue") => Verdict::Continue,
+            Some("return") => Verdict::Return,
+            Some(word @ ("jump" | "goto")) => {
+                let jump = word == "jump";
+                self.next += 1;
+                let target = JumpTarget {
+                    target: self.string("chain name")?.into(),
+                };
+                return Ok(Expression::Verdict(if jump {
+                    Verdict::Jump(target)
+                } else {
+                    Verdict::Goto(target)
+                }));
+            }
+            _ => return self.range(Context::Value),
+        };
+        self.next += 1;
+        Ok(Expression::Verdict(verdict))
+    }
+}
+
+fn command(verb: &str, object: NfListObject<'static>) -> NfCmd<'static> {
+    match verb {
+        "create" => NfCmd::Create(object),
+        "insert" => NfCmd::Insert(object),
+        "delete" => NfCmd::Delete(object),
+        "destroy" => NfCmd::Destroy(object),
+        _ => NfCmd::Add(object),
+    }
+}
+
+fn binary(op: BinaryOperation<'static>) -> Expression<'static> {
+    Expression::BinaryOperation(Box::new(op))
+}
+
+/// Builds a match the way nft evaluates it: flags combined with `|` become
+/// a list, a set of one element becomes the element and the implicit
+/// operator is `in` for lists and bitmasks and `==` otherwise.
+fn relation(
+    mut left: Expression<'static>,
+    op: Option<Operator>,
+    right: Expression<'static>,
+) -> Match<'static> {
+    let mut right = right;
+    if matches!(op, None | Some(Operator::EQ | Operator::NEQ)) {
+        right = flag_list(right);
+        if let Expression::BinaryOperation(binop) = &mut left {
+            if let BinaryOperation::AND(_, mask) = binop.as_mut() {
+                *mask = flag_list(mask.clone());
+            }
+        }
+    }
+    if let Expression::Named(NamedExpression::Set(items)) = &right {
+        if let [SetItem::Element(item)] = items.as_slice() {
+            right = item.clone();
+        }
+    }
+    let op = op.unwrap_or(match &right {
+        Expression::Named(NamedExpression::Set(_)) => Operator::EQ,
+        Expression::List(_) => Operator::IN,
+        _ if is_bitmask(&left) => Operator::IN,
+        _ => Operator::EQ,
+    });
+    Match { left, right, op }
+}
+
+/// Turns `a | b | c` of symbolic constants into a list of flags.
+fn flag_list(expr: Expression<'static>) -> Expression<'static> {
+    fn flags(expr: &Expression<'static>, out: &mut Vec<Expression<'static>>) -> bool {
+        match expr {
+            Expression::String(_) => {
+                out.push(expr.clone());
+                true
+            }
+            Expression::BinaryOperation(op) => match op.as_ref() {
+                BinaryOperation::OR(items) => items.iter().all(|item| flags(item, out)),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+    let mut out = Vec::new();
+    match &expr {
+        Expression::BinaryOperation(_) if flags(&expr, &mut out) => Expression::List(out),
+        _ => expr,
+    }
+}
+
+/// Returns whether a selector is a bitmask, which nft matches with `in`
+/// unless an operator is given.
+fn is_bitmask(expr: &Expression) -> bool {
+    match expr {
+        Expression::Named(NamedExpression::CT(ct)) => {
+            matches!(ct.key.as_ref(), "state" | "status" | "event")
+        }
+        Expression::Named(NamedExpression::Payload(Payload::PayloadField(field))) => {
+            field.protocol == "tcp" && field.field == "flags"
+        }
+        Expression::BinaryOperation(op) => {
+            matches!(op.as_ref(), BinaryOperation::AND(left, _) if is_bitmask(left))
+        }
+        _ => false,
+    }
+}
+
+/// Converts an item of a named set or map into an element expression.
+fn element_expression(item: SetItem<'static>) -> Expression<'static> {
+    match item {
+        SetItem::Element(expr) => expr,
+        SetItem::Mapping(key, value) => Expression::List(vec![key, value]),
+        SetItem::MappingStatement(key, _) => key,
+    }
+}
+
+/// Deserializes a keyword into the variant of `T` serialized as it.
+fn keyword<T: DeserializeOwned>(word: &str) -> Option<T> {
+    serde_json::from_value(serde_json::Value::String(word.to_owned())).ok()
+}
+
+fn parse_integer(word: &str) -> Option<u64> {
+    match word.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => word.parse().ok(),
+    }
+}
+
+/// Parses a time value like `1d`, `1h30m` or `500ms` into milliseconds.
+/// Plain numbers are seconds.
+fn parse_duration(word: &str) -> Option<u64> {
+    if let Ok(seconds) = word.parse::<u64>() {
+        return seconds.checked_mul(1000);
+    }
+    let mut total = 0u64;
+    let mut rest = word;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+        let value: u64 = rest[..digits].parse().ok()?;
+        rest = &rest[digits..];
+        let unit = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let factor = match &rest[..unit] {
+            "ms" => 1,
+            "s" => 1000,
+            "m" => 60 * 1000,
+            "h" => 60 * 60 * 1000,
+            "d" => 24 * 60 * 60 * 1000,
+            _ => return None,
+        };
+        total = total.checked_add(value.checked_mul(factor)?)?;
+        rest = &rest[unit..];
+    }
+    Some(total)
+}
+
+/// Returns the power of 1024 (as a shift) of a byte unit.
+fn byte_unit_shift(unit: &str) -> Option<u32> {
+    match unit {
+        "bytes" => Some(0),
+        "kbytes" => Some(10),
+        "mbytes" => Some(20),
+        "gbytes" => Some(30),
+        _ => None,
+    }
+}
+
+/// Resolves a standard priority name.
+///
+/// See *PRIORITY* in *nft(8)*.
+fn standard_priority(family: NfFamily, name: &str) -> Option<i32> {
+    match (family, name) {
+        (NfFamily::Bridge, "dstnat") => Some(-300),
+        (NfFamily::Bridge, "filter") => Some(-200),
+        (NfFamily::Bridge, "out") => Some(100),
+        (NfFamily::Bridge, "srcnat") => Some(300),
+        (NfFamily::Bridge, _) => None,
+        (_, "raw") => Some(-300),
+        (_, "mangle") => Some(-150),
+        (_, "dstnat") => Some(-100),
+        (_, "filter") => Some(0),
+        (_, "security") => Some(50),
+        (_, "srcnat") => Some(100),
+        _ => None,
+    }
+}
+
+/// Parses an address prefix like `10.0.0.1/8`, clearing the host bits like
+/// nft does. A prefix covering the whole address becomes the address.
+fn prefix(word: &str) -> Option<Expression<'static>> {
+    let (addr, len) = word.split_once('/')?;
+    let addr: IpAddr = addr.parse().ok()?;
+    let len: u32 = len.parse().ok()?;
+    let addr = match addr {
+        IpAddr::V4(addr) if len <= 32 => {
+            let mask = u32::MAX.checked_shl(32 - len).unwrap_or(0);
+            IpAddr::V4((u32::from(addr) & mask).into())
+        }
+        IpAddr::V6(addr) if len <= 128 => {
+            let mask = u128::MAX.checked_shl(128 - len).unwrap_or(0);
+            IpAddr::V6((u128::from(addr) & mask).into())
+        }
+        _ => return None,
+    };
+    let full = if addr.is_ipv4() { 32 } else { 128 };
+    let addr = Expression::String(addr.to_string().into());
+    Some(match len == full {
+        true => addr,
+        false => Expression::Named(NamedExpression::Prefix(Prefix {
+            addr: Box::new(addr),
+            len,
+        })),
+    })
+}
+
+/// Splits `addr:port`, `[addr]:port` or `:port` into address and port.
+fn split_addr_port(word: &str) -> Option<(&str, &str)> {
+    if let Some(rest) = word.strip_prefix('[') {
+        let (addr, rest) = rest.split_once(']')?;
+        return Some((addr, rest.strip_prefix(':').unwrap_or(rest)));
+    }
+    match word.matches(':').count() {
+        1 => word.split_once(':'),
+        _ => None,
+    }
+}
+
+/// Expands the wildcards `*` and `?` in the file name of an include.
+fn include_paths(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let pattern = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("");
+    if !pattern.contains(['*', '?']) {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir.unwrap_or(Path::new(".")))? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let matched = name.to_str().is_some_and(|name| {
+            !name.starts_with('.') && wildcard_match(pattern.as_bytes(), name.as_bytes())
+        });
+        if matched && entry.file_type()?.is_file() {
+            paths.push(entry.path());
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+fn wildcard_match(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => {
+            wildcard_match(&pattern[1..], name)
+                || (!name.is_empty() && wildcard_match(pattern, &name[1..]))
+        }
+        (Some(b'?'), Some(_)) => wildcard_match(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) if p == n => wildcard_match(&pattern[1..], &name[1..]),
+        _ => false,
+    }
+}
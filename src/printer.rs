@@ -0,0 +1,1458 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display, Formatter, Write},
+};
+
+use serde::Serialize;
+
+use crate::{
+    expr::{BinaryOperation, Expression, MetaKey, NamedExpression, Payload, SetItem, Verdict, CT},
+    schema::{
+        Chain, FlushObject, ListObject, NfCmd, NfListObject, NfObject, Nftables, ObjectFilter,
+        ResetObject, Rule, SetTypeValue,
+    },
+    stmt::{self, Counter, Match, Operator, QuotaOrQuotaRef, Statement, NAT},
+    types::{NfFamily, NfHook},
+};
+
+/// Standard chain priorities of all families but bridge, see *PRIORITY* in
+/// *nft(8)*.
+const STANDARD_PRIORITIES: &[(&str, i32)] = &[
+    ("raw", -300),
+    ("mangle", -150),
+    ("dstnat", -100),
+    ("filter", 0),
+    ("security", 50),
+    ("srcnat", 100),
+];
+
+/// Standard chain priorities of the bridge family.
+const BRIDGE_PRIORITIES: &[(&str, i32)] = &[
+    ("dstnat", -300),
+    ("filter", -200),
+    ("out", 100),
+    ("srcnat", 300),
+];
+
+/// Renders the document like `nft list ruleset` does.
+///
+/// Runs of ruleset elements (plain objects and `add` commands) are printed as
+/// `table` blocks, other commands as single command lines in between.
+impl Display for Nftables<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut run = Vec::new();
+        for object in self.objects.iter() {
+            let object = match object {
+                NfObject::ListObject(NfListObject::MetainfoObject(_)) => continue,
+                NfObject::ListObject(NfListObject::Element(element))
+                | NfObject::CmdObject(NfCmd::Add(NfListObject::Element(element))) => {
+                    write_tables(f, &run)?;
+                    run.clear();
+                    writeln!(f, "add {}", NfListObject::Element(element.clone()))?;
+                    continue;
+                }
+                NfObject::ListObject(object) | NfObject::CmdObject(NfCmd::Add(object)) => object,
+                NfObject::CmdObject(cmd) => {
+                    write_tables(f, &run)?;
+                    run.clear();
+                    writeln!(f, "{cmd}")?;
+                    continue;
+                }
+            };
+            run.push(object);
+        }
+        write_tables(f, &run)
+    }
+}
+
+/// Renders a command as a line of an nft script.
+impl Display for NfCmd<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            NfCmd::Add(object) => write!(f, "add {object}"),
+            NfCmd::Create(object) => write!(f, "create {object}"),
+            NfCmd::Insert(object) => write!(f, "insert {object}"),
+            NfCmd::Replace(rule) => write!(f, "replace {}", NfListObject::Rule(rule.clone())),
+            NfCmd::Delete(object) => write!(f, "delete {}", Identity(object)),
+            NfCmd::Destroy(object) => write!(f, "destroy {}", Identity(object)),
+            NfCmd::List(object) => {
+                let (kind, filter) = match object {
+                    ListObject::Ruleset(filter) => ("ruleset", filter),
+                    ListObject::Tables(filter) => ("tables", filter),
+                    ListObject::Chains(filter) => ("chains", filter),
+                    ListObject::Sets(filter) => ("sets", filter),
+                    ListObject::Maps(filter) => ("maps", filter),
+                    ListObject::FlowTables(filter) => ("flowtables", filter),
+                    ListObject::Counters(filter) => ("counters", filter),
+                    ListObject::Quotas(filter) => ("quotas", filter),
+                    ListObject::Limits(filter) => ("limits", filter),
+                    ListObject::Meters(filter) => ("meters", filter),
+                    ListObject::CTHelpers(filter) => ("ct helpers", filter),
+                    ListObject::CTTimeouts(filter) => ("ct timeouts", filter),
+                    ListObject::CTExpectations(filter) => ("ct expectations", filter),
+                    ListObject::SynProxys(filter) => ("synproxys", filter),
+                    ListObject::Object(object) => {
+                        return write!(f, "list {}", Identity(object));
+                    }
+                };
+                write!(f, "list {kind}{}", Filter(filter))
+            }
+            NfCmd::Reset(object) => {
+                let (kind, objects) = match object {
+                    ResetObject::Counter(counter) => {
+                        ("counter", vec![NfListObject::Counter(counter.clone())])
+                    }
+                    ResetObject::Counters(counters) => (
+                        "counters",
+                        counters
+                            .iter()
+                            .cloned()
+                            .map(NfListObject::Counter)
+                            .collect(),
+                    ),
+                    ResetObject::Quota(quota) => {
+                        ("quota", vec![NfListObject::Quota(quota.clone())])
+                    }
+                    ResetObject::Quotas(quotas) => (
+                        "quotas",
+                        quotas.iter().cloned().map(NfListObject::Quota).collect(),
+                    ),
+                    ResetObject::Rule(rule) if rule.handle.is_none() => {
+                        let chain = NfListObject::Chain(Chain {
+                            family: rule.family,
+                            table: rule.table.clone(),
+                            name: rule.chain.clone(),
+                            ..Default::default()
+                        });
+                        return write!(f, "reset rules {}", Identity(&chain));
+                    }
+                    ResetObject::Rule(rule) => {
+                        let rule = NfListObject::Rule(rule.clone());
+                        return write!(f, "reset {}", Identity(&rule));
+                    }
+                    ResetObject::Rules(filter) => {
+                        return write!(f, "reset rules{}", Filter(filter));
+                    }
+                    ResetObject::Set(set) => {
+                        let set = NfListObject::Set(set.clone());
+                        return write!(f, "reset {}", Identity(&set));
+                    }
+                    ResetObject::Map(map) => {
+                        let map = NfListObject::Map(map.clone());
+                        return write!(f, "reset {}", Identity(&map));
+                    }
+                    ResetObject::Element(element) => {
+                        let element = NfListObject::Element(element.clone());
+                        return write!(f, "reset {element}");
+                    }
+                    ResetObject::Table(table) => {
+                        let table = NfListObject::Table(table.clone());
+                        return write!(f, "reset {}", Identity(&table));
+                    }
+                };
+                if objects.is_empty() {
+                    return write!(f, "reset {kind}");
+                }
+                for (i, object) in objects.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "reset {}", Identity(object))?;
+                }
+                Ok(())
+            }
+            NfCmd::Flush(object) => match object {
+                FlushObject::Ruleset(_) => write!(f, "flush ruleset"),
+                FlushObject::Table(table) => {
+                    let table = NfListObject::Table(table.clone());
+                    write!(f, "flush {}", Identity(&table))
+                }
+                FlushObject::Chain(chain) => {
+                    let chain = NfListObject::Chain(chain.clone());
+                    write!(f, "flush {}", Identity(&chain))
+                }
+                FlushObject::Set(set) => {
+                    let set = NfListObject::Set(set.clone());
+                    write!(f, "flush {}", Identity(&set))
+                }
+                FlushObject::Map(map) => {
+                    let map = NfListObject::Map(map.clone());
+                    write!(f, "flush {}", Identity(&map))
+                }
+                FlushObject::Meter(meter) => write!(f, "flush meter {}", Word(&meter.name)),
+            },
+            NfCmd::Rename(chain) => {
+                let newname = chain.newname.as_deref().unwrap_or_default();
+                let object = NfListObject::Chain(chain.clone());
+                write!(f, "rename {} {}", Identity(&object), Word(newname))
+            }
+        }
+    }
+}
+
+/// Renders a ruleset element in the form taken by `add` commands, such as
+/// `rule inet filter input tcp dport 22 accept`.
+impl Display for NfListObject<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            NfListObject::MetainfoObject(info) => {
+                write!(f, "# nftables")?;
+                if let Some(version) = &info.version {
+                    write!(f, " v{version}")?;
+                }
+                if let Some(release_name) = &info.release_name {
+                    write!(f, " ({release_name})")?;
+                }
+                Ok(())
+            }
+            NfListObject::Rule(rule) => {
+                write!(f, "{}", Identity(self))?;
+                if let Some(index) = rule.index {
+                    write!(f, " index {index}")?;
+                }
+                if !rule.expr.is_empty() || rule.comment.is_some() {
+                    write!(f, " {}", RuleBody(rule))?;
+                }
+                Ok(())
+            }
+            NfListObject::Element(element) => {
+                write!(f, "{} {{ ", Identity(self))?;
+                write_list(f, element.elem.iter(), ", ", |f, elem| {
+                    write_element(f, elem, false)
+                })?;
+                write!(f, " }}")
+            }
+            _ => {
+                write!(f, "{}", Identity(self))?;
+                let lines = body(self);
+                if !lines.is_empty() {
+                    write!(f, " {{ ")?;
+                    for line in lines {
+                        let separator = if line.ends_with(';') { " " } else { "; " };
+                        write!(f, "{line}{separator}")?;
+                    }
+                    write!(f, "}}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Renders a statement as nft prints it within a rule.
+impl Display for Statement<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Statement::Accept(_) => write!(f, "accept"),
+            Statement::Drop(_) => write!(f, "drop"),
+            Statement::Continue(_) => write!(f, "continue"),
+            Statement::Return(_) => write!(f, "return"),
+            Statement::Jump(target) => write!(f, "jump {}", Word(&target.target)),
+            Statement::Goto(target) => write!(f, "goto {}", Word(&target.target)),
+            Statement::Match(m) => write_match(f, m),
+            Statement::Counter(counter) => write_counter(f, counter),
+            Statement::Mangle(mangle) => write!(f, "{} set {}", mangle.key, mangle.value),
+            Statement::Quota(QuotaOrQuotaRef::QuotaRef(name)) => {
+                write!(f, "quota name {}", Quoted(name))
+            }
+            Statement::Quota(QuotaOrQuotaRef::Quota(quota)) => {
+                write!(f, "quota ")?;
+                if quota.inv == Some(true) {
+                    write!(f, "over ")?;
+                }
+                write!(f, "{} {}", quota.val, quota.val_unit)?;
+                if let (Some(used), Some(unit)) = (quota.used, &quota.used_unit) {
+                    write!(f, " used {used} {unit}")?;
+                }
+                Ok(())
+            }
+            Statement::Limit(limit) => write_limit(f, limit),
+            Statement::Flow(flow) => write!(f, "flow {} {}", name(&flow.op), flow.flowtable),
+            Statement::FWD(None) => write!(f, "fwd"),
+            Statement::FWD(Some(fwd)) => {
+                write!(f, "fwd")?;
+                if let Some(family) = &fwd.family {
+                    write!(f, " {}", name(family))?;
+                }
+                match (&fwd.addr, &fwd.dev) {
+                    (Some(addr), Some(dev)) => {
+                        write!(f, " to {addr} device {}", Expr::quoted(dev))
+                    }
+                    (Some(addr), None) => write!(f, " to {addr}"),
+                    (None, Some(dev)) => write!(f, " to {}", Expr::quoted(dev)),
+                    (None, None) => Ok(()),
+                }
+            }
+            Statement::Notrack => write!(f, "notrack"),
+            Statement::Dup(dup) => {
+                write!(f, "dup to {}", dup.addr)?;
+                if let Some(dev) = &dup.dev {
+                    write!(f, " device {}", Expr::quoted(dev))?;
+                }
+                Ok(())
+            }
+            Statement::SNAT(nat) => write_nat(f, "snat", nat.as_ref()),
+            Statement::DNAT(nat) => write_nat(f, "dnat", nat.as_ref()),
+            Statement::Masquerade(nat) => write_nat(f, "masquerade", nat.as_ref()),
+            Statement::Redirect(nat) => write_nat(f, "redirect", nat.as_ref()),
+            Statement::Reject(reject) => {
+                write!(f, "reject")?;
+                let Some(reject) = reject else {
+                    return Ok(());
+                };
+                match (&reject._type, &reject.expr) {
+                    (Some(stmt::RejectType::TCPReset), _) => write!(f, " with tcp reset"),
+                    (Some(_type), Some(code)) => {
+                        write!(f, " with {} {}", name(_type), name(code))
+                    }
+                    (None, Some(code)) => write!(f, " with icmpx {}", name(code)),
+                    (_, None) => Ok(()),
+                }
+            }
+            Statement::Set(set) => {
+                write!(f, "{} {} {{ {} }}", name(&set.op), set.set, set.elem)
+            }
+            Statement::Log(log) => {
+                write!(f, "log")?;
+                let Some(log) = log else {
+                    return Ok(());
+                };
+                if let Some(prefix) = &log.prefix {
+                    write!(f, " prefix {}", Quoted(prefix))?;
+                }
+                if let Some(group) = log.group {
+                    write!(f, " group {group}")?;
+                }
+                if let Some(snaplen) = log.snaplen {
+                    write!(f, " snaplen {snaplen}")?;
+                }
+                if let Some(queue_threshold) = log.queue_threshold {
+                    write!(f, " queue-threshold {queue_threshold}")?;
+                }
+                if let Some(level) = &log.level {
+                    write!(f, " level {}", name(level))?;
+                }
+                if let Some(flags) = &log.flags {
+                    write_log_flags(f, flags)?;
+                }
+                Ok(())
+            }
+            Statement::CTHelper(helper) => write!(f, "ct helper set {}", Quoted(helper)),
+            Statement::Meter(meter) => write!(
+                f,
+                "meter {} {{ {} {} }}",
+                Word(&meter.name),
+                meter.key,
+                meter.stmt
+            ),
+            Statement::Queue(queue) => {
+                write!(f, "queue")?;
+                if let Some(flags) = &queue.flags {
+                    write!(f, " flags ")?;
+                    write_list(f, sorted(flags, |flag| *flag as u8), ",", |f, flag| {
+                        write!(f, "{}", name(flag))
+                    })?;
+                }
+                if queue.num != Expression::Number(0) {
+                    write!(f, " to {}", queue.num)?;
+                }
+                Ok(())
+            }
+            Statement::VerdictMap(vmap) => write!(f, "{} vmap {}", vmap.key, vmap.data),
+            Statement::CTCount(count) => {
+                write!(f, "ct count ")?;
+                if count.inv == Some(true) {
+                    write!(f, "over ")?;
+                }
+                write!(f, "{}", count.val)
+            }
+            Statement::CTTimeout(timeout) => {
+                write!(f, "ct timeout set {}", Expr::quoted(timeout))
+            }
+            Statement::CTExpectation(expectation) => {
+                write!(f, "ct expectation set {}", Expr::quoted(expectation))
+            }
+            Statement::XT(xt) => {
+                write!(f, "xt")?;
+                let field = |key| xt.as_ref().and_then(|xt| xt.get(key)?.as_str());
+                for value in [field("type"), field("name")].into_iter().flatten() {
+                    write!(f, " {}", Word(value))?;
+                }
+                Ok(())
+            }
+            Statement::SynProxy(synproxy) => {
+                write!(f, "synproxy")?;
+                if let Some(mss) = synproxy.mss {
+                    write!(f, " mss {mss}")?;
+                }
+                if let Some(wscale) = synproxy.wscale {
+                    write!(f, " wscale {wscale}")?;
+                }
+                if let Some(flags) = &synproxy.flags {
+                    for flag in sorted(flags, |flag| flag.clone() as u8) {
+                        write!(f, " {}", name(flag))?;
+                    }
+                }
+                Ok(())
+            }
+            Statement::TProxy(tproxy) => {
+                write!(f, "tproxy ")?;
+                if let Some(family) = &tproxy.family {
+                    write!(f, "{family} ")?;
+                }
+                write!(f, "to ")?;
+                match tproxy.addr.as_deref() {
+                    Some(addr) if addr.contains(':') => write!(f, "[{addr}]")?,
+                    Some(addr) => write!(f, "{addr}")?,
+                    None => {}
+                }
+                write!(f, ":{}", tproxy.port)
+            }
+        }
+    }
+}
+
+/// Renders an expression as nft prints it.
+impl Display for Expression<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_expr(f, self, false)
+    }
+}
+
+/// A string printed as a word, quoted only where nft would not read it as a
+/// single word.
+struct Word<'s>(&'s str);
+
+impl Display for Word<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let plain = !self.0.is_empty()
+            && self.0.chars().all(|c| {
+                c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | ':' | '-' | '/' | '*' | '@')
+            });
+        if plain {
+            f.write_str(self.0)
+        } else {
+            Quoted(self.0).fmt(f)
+        }
+    }
+}
+
+/// A string printed in double quotes.
+struct Quoted<'s>(&'s str);
+
+impl Display for Quoted<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_char('"')?;
+        for c in self.0.chars() {
+            if matches!(c, '"' | '\\') {
+                f.write_char('\\')?;
+            }
+            f.write_char(c)?;
+        }
+        f.write_char('"')
+    }
+}
+
+/// An expression whose strings are quoted, like nft prints values of string
+/// types such as interface names.
+struct Expr<'e, 'a> {
+    expr: &'e Expression<'a>,
+    quote: bool,
+}
+
+impl<'e, 'a> Expr<'e, 'a> {
+    fn quoted(expr: &'e Expression<'a>) -> Self {
+        Expr { expr, quote: true }
+    }
+}
+
+impl Display for Expr<'_, '_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_expr(f, self.expr, self.quote)
+    }
+}
+
+/// The kind, family, table and name (or handle) of a ruleset element.
+struct Identity<'o, 'a>(&'o NfListObject<'a>);
+
+impl Display for Identity<'_, '_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let (kind, family, table, object_name, handle) = match self.0 {
+            NfListObject::Table(o) => {
+                write!(f, "table {} ", name(&o.family))?;
+                return write_name(f, &o.name, o.handle);
+            }
+            NfListObject::Rule(o) => {
+                write!(
+                    f,
+                    "rule {} {} {}",
+                    name(&o.family),
+                    Word(&o.table),
+                    Word(&o.chain)
+                )?;
+                if let Some(handle) = o.handle {
+                    write!(f, " handle {handle}")?;
+                }
+                return Ok(());
+            }
+            NfListObject::MetainfoObject(_) => return Ok(()),
+            NfListObject::Chain(o) => ("chain", o.family, &o.table, &o.name, o.handle),
+            NfListObject::Set(o) => ("set", o.family, &o.table, &o.name, o.handle),
+            NfListObject::Map(o) => ("map", o.family, &o.table, &o.name, o.handle),
+            NfListObject::Element(o) => ("element", o.family, &o.table, &o.name, None),
+            NfListObject::FlowTable(o) => ("flowtable", o.family, &o.table, &o.name, o.handle),
+            NfListObject::Counter(o) => ("counter", o.family, &o.table, &o.name, o.handle),
+            NfListObject::Quota(o) => ("quota", o.family, &o.table, &o.name, o.handle),
+            NfListObject::CTHelper(o) => ("ct helper", o.family, &o.table, &o.name, o.handle),
+            NfListObject::Limit(o) => ("limit", o.family, &o.table, &o.name, o.handle),
+            NfListObject::CTTimeout(o) => ("ct timeout", o.family, &o.table, &o.name, o.handle),
+            NfListObject::CTExpectation(o) => {
+                ("ct expectation", o.family, &o.table, &o.name, o.handle)
+            }
+            NfListObject::SynProxy(o) => ("synproxy", o.family, &o.table, &o.name, o.handle),
+        };
+        write!(f, "{kind} {} {} ", name(&family), Word(table))?;
+        write_name(f, object_name, handle)
+    }
+}
+
+/// The family and table a plural command is restricted to, e.g.
+/// ` table inet filter` in `list sets table inet filter`.
+struct Filter<'o, 'a>(&'o ObjectFilter<'a>);
+
+impl Display for Filter<'_, '_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.0.table.is_some() {
+            write!(f, " table")?;
+        }
+        if let Some(family) = &self.0.family {
+            write!(f, " {}", name(family))?;
+        }
+        if let Some(table) = &self.0.table {
+            write!(f, " {}", Word(table))?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes the name of an object, or its handle if it has no name.
+fn write_name(f: &mut Formatter<'_>, name: &str, handle: Option<u32>) -> fmt::Result {
+    match handle {
+        Some(handle) if name.is_empty() => write!(f, "handle {handle}"),
+        _ => write!(f, "{}", Word(name)),
+    }
+}
+
+/// The statements and comment of a rule.
+struct RuleBody<'r, 'a>(&'r Rule<'a>);
+
+impl Display for RuleBody<'_, '_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_list(f, self.0.expr.iter(), " ", |f, stmt| write!(f, "{stmt}"))?;
+        if let Some(comment) = &self.0.comment {
+            if !self.0.expr.is_empty() {
+                write!(f, " ")?;
+            }
+            write!(f, "comment {}", Quoted(comment))?;
+        }
+        Ok(())
+    }
+}
+
+/// Ruleset elements of one table, in the order `nft list ruleset` prints
+/// them.
+struct TableBlock<'o, 'a> {
+    family: NfFamily,
+    name: &'o str,
+    objects: Vec<&'o NfListObject<'a>>,
+    sets: Vec<&'o NfListObject<'a>>,
+    flowtables: Vec<&'o NfListObject<'a>>,
+    chains: Vec<(&'o str, Option<&'o Chain<'a>>, Vec<&'o Rule<'a>>)>,
+}
+
+/// Writes ruleset elements grouped into `table` blocks.
+fn write_tables(f: &mut Formatter<'_>, objects: &[&NfListObject]) -> fmt::Result {
+    let mut tables: Vec<TableBlock> = Vec::new();
+    let mut positions: HashMap<(NfFamily, &str), usize> = HashMap::new();
+    for object in objects {
+        let (family, table) = match object {
+            NfListObject::Table(o) => (o.family, &o.name),
+            NfListObject::Chain(o) => (o.family, &o.table),
+            NfListObject::Rule(o) => (o.family, &o.table),
+            NfListObject::Set(o) => (o.family, &o.table),
+            NfListObject::Map(o) => (o.family, &o.table),
+            NfListObject::Element(o) => (o.family, &o.table),
+            NfListObject::FlowTable(o) => (o.family, &o.table),
+            NfListObject::Counter(o) => (o.family, &o.table),
+            NfListObject::Quota(o) => (o.family, &o.table),
+            NfListObject::CTHelper(o) => (o.family, &o.table),
+            NfListObject::Limit(o) => (o.family, &o.table),
+            NfListObject::CTTimeout(o) => (o.family, &o.table),
+            NfListObject::CTExpectation(o) => (o.family, &o.table),
+            NfListObject::SynProxy(o) => (o.family, &o.table),
+            NfListObject::MetainfoObject(_) => continue,
+        };
+        let position = *positions.entry((family, table)).or_insert_with(|| {
+            tables.push(TableBlock {
+                family,
+                name: table,
+                objects: Vec::new(),
+                sets: Vec::new(),
+                flowtables: Vec::new(),
+                chains: Vec::new(),
+            });
+            tables.len() - 1
+        });
+        let block = &mut tables[position];
+        match object {
+            NfListObject::Table(_) => {}
+            NfListObject::Set(_) | NfListObject::Map(_) => block.sets.push(object),
+            NfListObject::FlowTable(_) => block.flowtables.push(object),
+            NfListObject::Chain(chain) => {
+                match block
+                    .chains
+                    .iter_mut()
+                    .find(|(name, ..)| *name == chain.name)
+                {
+                    Some(entry) => entry.1 = Some(chain),
+                    None => block.chains.push((&chain.name, Some(chain), Vec::new())),
+                }
+            }
+            NfListObject::Rule(rule) => {
+                match block
+                    .chains
+                    .iter_mut()
+                    .find(|(name, ..)| *name == rule.chain)
+                {
+                    Some(entry) => entry.2.push(rule),
+                    None => block.chains.push((&rule.chain, None, vec![rule])),
+                }
+            }
+            _ => block.objects.push(object),
+        }
+    }
+    for table in tables {
+        writeln!(f, "table {} {} {{", name(&table.family), Word(table.name))?;
+        let mut first = true;
+        let mut separate = |f: &mut Formatter<'_>| {
+            if !std::mem::take(&mut first) {
+                writeln!(f)?;
+            }
+            Ok::<_, fmt::Error>(())
+        };
+        for object in table
+            .objects
+            .iter()
+            .chain(&table.sets)
+            .chain(&table.flowtables)
+        {
+            separate(f)?;
+            write_block(f, object)?;
+        }
+        for (chain, declaration, rules) in &table.chains {
+            separate(f)?;
+            writeln!(f, "\tchain {} {{", Word(chain))?;
+            if let Some(declaration) = declaration {
+                for line in chain_body(declaration) {
+                    writeln!(f, "\t\t{line}")?;
+                }
+            }
+            for rule in rules {
+                writeln!(f, "\t\t{}", RuleBody(rule))?;
+            }
+            writeln!(f, "\t}}")?;
+        }
+        writeln!(f, "}}")?;
+    }
+    Ok(())
+}
+
+/// Writes a set, map, flowtable or stateful object as a block within its
+/// table.
+fn write_block(f: &mut Formatter<'_>, object: &NfListObject) -> fmt::Result {
+    let (kind, name) = match object {
+        NfListObject::Set(o) => ("set", &o.name),
+        NfListObject::Map(o) => ("map", &o.name),
+        NfListObject::FlowTable(o) => ("flowtable", &o.name),
+        NfListObject::Counter(o) => ("counter", &o.name),
+        NfListObject::Quota(o) => ("quota", &o.name),
+        NfListObject::CTHelper(o) => ("ct helper", &o.name),
+        NfListObject::Limit(o) => ("limit", &o.name),
+        NfListObject::CTTimeout(o) => ("ct timeout", &o.name),
+        NfListObject::CTExpectation(o) => ("ct expectation", &o.name),
+        NfListObject::SynProxy(o) => ("synproxy", &o.name),
+        _ => return Ok(()),
+    };
+    writeln!(f, "\t{kind} {} {{", Word(name))?;
+    for line in body(object) {
+        writeln!(f, "\t\t{line}")?;
+    }
+    writeln!(f, "\t}}")
+}
+
+/// Returns the lines of the block declaring an object.
+fn body(object: &NfListObject) -> Vec<String> {
+    let mut lines = Vec::new();
+    match object {
+        NfListObject::Chain(chain) => lines = chain_body(chain),
+        NfListObject::Set(set) => {
+            set_body(
+                &mut lines,
+                SetBody {
+                    set_type: &set.set_type,
+                    map: None,
+                    policy: set.policy.as_ref().map(name),
+                    flags: set.flags.as_ref(),
+                    timeout: set.timeout,
+                    gc_interval: set.gc_interval,
+                    size: set.size,
+                    comment: set.comment.as_deref(),
+                    elem: set.elem.as_deref(),
+                },
+            );
+        }
+        NfListObject::Map(map) => {
+            set_body(
+                &mut lines,
+                SetBody {
+                    set_type: &map.set_type,
+                    map: Some(&map.map),
+                    policy: map.policy.as_ref().map(name),
+                    flags: map.flags.as_ref(),
+                    timeout: map.timeout,
+                    gc_interval: map.gc_interval,
+                    size: map.size,
+                    comment: map.comment.as_deref(),
+                    elem: map.elem.as_deref(),
+                },
+            );
+        }
+        NfListObject::FlowTable(flowtable) => {
+            if let (Some(hook), Some(prio)) = (&flowtable.hook, flowtable.prio) {
+                let prio = priority(flowtable.family, Some(hook), prio as i32);
+                lines.push(format!("hook {} priority {prio}", name(hook)));
+            }
+            if let Some(devices) = &flowtable.dev {
+                let devices: Vec<String> =
+                    devices.iter().map(|dev| Word(dev).to_string()).collect();
+                lines.push(format!("devices = {{ {} }}", devices.join(", ")));
+            }
+        }
+        NfListObject::Counter(counter) => {
+            if let Some(comment) = &counter.comment {
+                lines.push(format!("comment {}", Quoted(comment)));
+            }
+            if counter.packets.is_some() || counter.bytes.is_some() {
+                lines.push(format!(
+                    "packets {} bytes {}",
+                    counter.packets.unwrap_or_default(),
+                    counter.bytes.unwrap_or_default()
+                ));
+            }
+        }
+        NfListObject::Quota(quota) => {
+            let mut line = String::new();
+            if quota.inv == Some(true) {
+                line.push_str("over ");
+            }
+            line.push_str(&byte_amount(quota.bytes.unwrap_or_default()));
+            if let Some(used) = quota.used.filter(|used| *used != 0) {
+                line.push_str(&format!(" used {}", byte_amount(used)));
+            }
+            lines.push(line);
+        }
+        NfListObject::CTHelper(helper) => {
+            let mut line = format!("type {}", Quoted(&helper._type));
+            if let Some(protocol) = &helper.protocol {
+                line.push_str(&format!(" protocol {protocol}"));
+            }
+            lines.push(line);
+            if let Some(l3proto) = &helper.l3proto {
+                lines.push(format!("l3proto {l3proto}"));
+            }
+        }
+        NfListObject::Limit(limit) => {
+            let mut line = String::from("rate ");
+            if limit.inv == Some(true) {
+                line.push_str("over ");
+            }
+            let rate = limit.rate.unwrap_or_default();
+            let per = limit.per.as_ref().map_or("second".into(), name);
+            let burst = limit.burst.unwrap_or_default();
+            if limit.unit == Some(crate::schema::LimitUnit::Bytes) {
+                line.push_str(&format!("{}/{per}", byte_amount(rate.into())));
+                if burst != 0 {
+                    line.push_str(&format!(" burst {}", byte_amount(burst.into())));
+                }
+            } else {
+                line.push_str(&format!("{rate}/{per}"));
+                if burst != 0 && burst != 5 {
+                    line.push_str(&format!(" burst {burst} packets"));
+                }
+            }
+            lines.push(line);
+        }
+        NfListObject::CTTimeout(timeout) => {
+            if let Some(protocol) = &timeout.protocol {
+                lines.push(format!("protocol {}", name(protocol)));
+            }
+            if let Some(l3proto) = &timeout.l3proto {
+                lines.push(format!("l3proto {l3proto}"));
+            }
+            if let (Some(state), Some(value)) = (&timeout.state, timeout.value) {
+                let value = duration(value.saturating_mul(1000));
+                lines.push(format!("policy = {{ {} : {value} }}", Word(state)));
+            }
+        }
+        NfListObject::CTExpectation(expectation) => {
+            if let Some(protocol) = &expectation.protocol {
+                lines.push(format!("protocol {}", name(protocol)));
+            }
+            if let Some(dport) = expectation.dport {
+                lines.push(format!("dport {dport}"));
+            }
+            if let Some(timeout) = expectation.timeout {
+                lines.push(format!("timeout {}", duration(timeout)));
+            }
+            if let Some(size) = expectation.size {
+                lines.push(format!("size {size}"));
+            }
+            if let Some(l3proto) = &expectation.l3proto {
+                lines.push(format!("l3proto {l3proto}"));
+            }
+        }
+        NfListObject::SynProxy(synproxy) => {
+            if let Some(mss) = synproxy.mss {
+                lines.push(format!("mss {mss}"));
+            }
+            if let Some(wscale) = synproxy.wscale {
+                lines.push(format!("wscale {wscale}"));
+            }
+            if let Some(flags) = synproxy.flags.as_ref().filter(|flags| !flags.is_empty()) {
+                let flags: Vec<String> = sorted(flags, |flag| flag.clone() as u8)
+                    .into_iter()
+                    .map(name)
+                    .collect();
+                lines.push(flags.join(" "));
+            }
+        }
+        NfListObject::Table(_)
+        | NfListObject::Rule(_)
+        | NfListObject::Element(_)
+        | NfListObject::MetainfoObject(_) => {}
+    }
+    lines
+}
+
+/// Returns the comment, the declaration of a base chain and the flags of a
+/// chain.
+fn chain_body(chain: &Chain) -> Vec<String> {
+    let mut lines = Vec::new();
+    if let Some(comment) = &chain.comment {
+        lines.push(format!("comment {}", Quoted(comment)));
+    }
+    if let (Some(_type), Some(hook)) = (&chain._type, &chain.hook) {
+        let mut line = format!("type {} hook {}", name(_type), name(hook));
+        match chain.dev.as_deref() {
+            None | Some([]) => {}
+            Some([dev]) => line.push_str(&format!(" device {}", Quoted(dev))),
+            Some(devices) => {
+                let devices: Vec<String> =
+                    devices.iter().map(|dev| Word(dev).to_string()).collect();
+                line.push_str(&format!(" devices = {{ {} }}", devices.join(", ")));
+            }
+        }
+        let prio = priority(chain.family, Some(hook), chain.prio.unwrap_or_default());
+        line.push_str(&format!(" priority {prio};"));
+        if let Some(policy) = &chain.policy {
+            line.push_str(&format!(" policy {};", name(policy)));
+        }
+        lines.push(line);
+    }
+    if let Some(flags) = chain.flags.as_ref().filter(|flags| !flags.is_empty()) {
+        let flags: Vec<String> = sorted(flags, |flag| *flag as u8)
+            .into_iter()
+            .map(name)
+            .collect();
+        lines.push(format!("flags {};", flags.join(",")));
+    }
+    lines
+}
+
+/// Properties shared by sets and maps.
+struct SetBody<'s, 'a> {
+    set_type: &'s SetTypeValue<'a>,
+    map: Option<&'s SetTypeValue<'a>>,
+    policy: Option<String>,
+    flags: Option<&'s HashSet<crate::schema::SetFlag>>,
+    timeout: Option<u64>,
+    gc_interval: Option<u32>,
+    size: Option<u32>,
+    comment: Option<&'s str>,
+    elem: Option<&'s [Expression<'a>]>,
+}
+
+fn set_body(lines: &mut Vec<String>, set: SetBody) {
+    let keyword = match set.set_type {
+        SetTypeValue::Typeof(_) => "typeof",
+        _ => "type",
+    };
+    let mut line = format!("{keyword} {}", set_type(set.set_type));
+    if let Some(map) = set.map {
+        line.push_str(&format!(" : {}", set_type(map)));
+    }
+    lines.push(line);
+    if let Some(policy) = set.policy {
+        lines.push(format!("policy {policy}"));
+    }
+    if let Some(flags) = set.flags.filter(|flags| !flags.is_empty()) {
+        let flags: Vec<String> = sorted(flags, |flag| *flag as u8)
+            .into_iter()
+            .map(name)
+            .collect();
+        lines.push(format!("flags {}", flags.join(",")));
+    }
+    if let Some(timeout) = set.timeout {
+        lines.push(format!(
+            "timeout {}",
+            duration(timeout.saturating_mul(1000))
+        ));
+    }
+    if let Some(gc_interval) = set.gc_interval {
+        lines.push(format!(
+            "gc-interval {}",
+            duration(u64::from(gc_interval) * 1000)
+        ));
+    }
+    if let Some(size) = set.size {
+        lines.push(format!("size {size}"));
+    }
+    if let Some(comment) = set.comment {
+        lines.push(format!("comment {}", Quoted(comment)));
+    }
+    if let Some(elem) = set.elem.filter(|elem| !elem.is_empty()) {
+        let quote = *set.set_type == SetTypeValue::Single(crate::schema::SetType::Ifname);
+        let elements: Vec<String> = elem
+            .iter()
+            .map(|elem| match (elem, set.map) {
+                (Expression::List(pair), Some(map)) if pair.len() == 2 => {
+                    let quote_data = *map == SetTypeValue::Single(crate::schema::SetType::Ifname);
+                    format!(
+                        "{} : {}",
+                        ElementDisplay(&pair[0], quote),
+                        ElementDisplay(&pair[1], quote_data)
+                    )
+                }
+                _ => ElementDisplay(elem, quote).to_string(),
+            })
+            .collect();
+        lines.push(format!("elements = {{ {} }}", elements.join(", ")));
+    }
+}
+
+/// A set element, with its options.
+struct ElementDisplay<'e, 'a>(&'e Expression<'a>, bool);
+
+impl Display for ElementDisplay<'_, '_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_element(f, self.0, self.1)
+    }
+}
+
+fn write_element(f: &mut Formatter<'_>, elem: &Expression, quote: bool) -> fmt::Result {
+    match elem {
+        Expression::List(pair) if pair.len() == 2 => {
+            write_expr(f, &pair[0], quote)?;
+            write!(f, " : ")?;
+            write_expr(f, &pair[1], quote)
+        }
+        _ => write_expr(f, elem, quote),
+    }
+}
+
+fn set_type(set_type: &SetTypeValue) -> String {
+    match set_type {
+        SetTypeValue::Single(single) => name(single),
+        SetTypeValue::Concatenated(types) => {
+            let types: Vec<String> = types.iter().map(name).collect();
+            types.join(" . ")
+        }
+        SetTypeValue::Typeof(expr) => expr.to_string(),
+    }
+}
+
+fn write_match(f: &mut Formatter<'_>, m: &Match) -> fmt::Result {
+    write!(f, "{}", m.left)?;
+    let op = match m.op {
+        Operator::IN => None,
+        Operator::EQ
+            if !matches!(m.left, Expression::BinaryOperation(_))
+                && !matches!(m.right, Expression::List(_)) =>
+        {
+            None
+        }
+        op => Some(name(&op)),
+    };
+    if let Some(op) = &op {
+        write!(f, " {op}")?;
+    }
+    write!(f, " ")?;
+    let quote = is_string_typed(&m.left);
+    match &m.right {
+        // Flags combined with `|`, such as `syn | ack`.
+        Expression::List(items) if op.is_some() => {
+            write_list(f, items.iter(), " | ", |f, item| write_expr(f, item, quote))
+        }
+        right => write_expr(f, right, quote),
+    }
+}
+
+/// Returns whether values compared to `expr` are strings which nft quotes.
+fn is_string_typed(expr: &Expression) -> bool {
+    match expr {
+        Expression::Named(NamedExpression::Meta(meta)) => matches!(
+            meta.key,
+            MetaKey::Iif
+                | MetaKey::Oif
+                | MetaKey::Iifname
+                | MetaKey::Oifname
+                | MetaKey::Iifkind
+                | MetaKey::Oifkind
+                | MetaKey::Ibridgename
+                | MetaKey::Obridgename
+        ),
+        Expression::Named(NamedExpression::CT(CT { key, .. })) => key == "helper",
+        Expression::Named(NamedExpression::Fib(fib)) => {
+            fib.result == crate::expr::FibResult::Oifname
+        }
+        Expression::Named(NamedExpression::Osf(_)) => true,
+        _ => false,
+    }
+}
+
+fn write_counter(f: &mut Formatter<'_>, counter: &Counter) -> fmt::Result {
+    match counter {
+        Counter::Named(name) => write!(f, "counter name {}", Quoted(name)),
+        Counter::Anonymous(None) => write!(f, "counter"),
+        Counter::Anonymous(Some(counter)) => write!(
+            f,
+            "counter packets {} bytes {}",
+            counter.packets.unwrap_or_default(),
+            counter.bytes.unwrap_or_default()
+        ),
+    }
+}
+
+fn write_limit(f: &mut Formatter<'_>, limit: &stmt::Limit) -> fmt::Result {
+    write!(f, "limit rate ")?;
+    if limit.inv == Some(true) {
+        write!(f, "over ")?;
+    }
+    let per = limit.per.as_deref().unwrap_or("second");
+    match limit.rate_unit.as_deref() {
+        Some(unit) if unit != "packets" => {
+            write!(f, "{} {unit}/{per}", limit.rate)?;
+            if let Some(burst) = limit.burst.filter(|burst| *burst != 0) {
+                let unit = limit.burst_unit.as_deref().unwrap_or("bytes");
+                write!(f, " burst {burst} {unit}")?;
+            }
+        }
+        _ => {
+            write!(f, "{}/{per}", limit.rate)?;
+            if let Some(burst) = limit.burst.filter(|burst| *burst != 0 && *burst != 5) {
+                write!(f, " burst {burst} packets")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_log_flags(f: &mut Formatter<'_>, flags: &HashSet<stmt::LogFlag>) -> fmt::Result {
+    use stmt::LogFlag;
+    if flags.contains(&LogFlag::All) {
+        return write!(f, " flags all");
+    }
+    match (
+        flags.contains(&LogFlag::TCPSequence),
+        flags.contains(&LogFlag::TCPOptions),
+    ) {
+        (true, true) => write!(f, " flags tcp sequence,options")?,
+        (true, false) => write!(f, " flags tcp sequence")?,
+        (false, true) => write!(f, " flags tcp options")?,
+        (false, false) => {}
+    }
+    if flags.contains(&LogFlag::IPOptions) {
+        write!(f, " flags ip options")?;
+    }
+    if flags.contains(&LogFlag::Skuid) {
+        write!(f, " flags skuid")?;
+    }
+    if flags.contains(&LogFlag::Ether) {
+        write!(f, " flags ether")?;
+    }
+    Ok(())
+}
+
+fn write_nat(f: &mut Formatter<'_>, kind: &str, nat: Option<&NAT>) -> fmt::Result {
+    write!(f, "{kind}")?;
+    let Some(nat) = nat else {
+        return Ok(());
+    };
+    if let Some(family) = &nat.family {
+        write!(f, " {}", name(family))?;
+    }
+    match (&nat.addr, &nat.port) {
+        (Some(Expression::String(addr)), Some(port)) if addr.contains(':') => {
+            write!(f, " to [{addr}]:{port}")?
+        }
+        (Some(addr), Some(port)) => write!(f, " to {addr}:{port}")?,
+        (Some(addr), None) => write!(f, " to {addr}")?,
+        (None, Some(port)) => write!(f, " to :{port}")?,
+        (None, None) => {}
+    }
+    if let Some(flags) = nat.flags.as_ref().filter(|flags| !flags.is_empty()) {
+        write!(f, " ")?;
+        write_list(f, sorted(flags, |flag| *flag as u8), ",", |f, flag| {
+            write!(f, "{}", name(flag))
+        })?;
+    }
+    Ok(())
+}
+
+/// Binding strength of an expression, to parenthesize operands.
+fn precedence(expr: &Expression) -> u8 {
+    match expr {
+        Expression::BinaryOperation(op) => match op.as_ref() {
+            BinaryOperation::OR(_) => 1,
+            BinaryOperation::XOR(..) => 2,
+            BinaryOperation::AND(..) => 3,
+            BinaryOperation::LSHIFT(..) | BinaryOperation::RSHIFT(..) => 4,
+        },
+        // Flags, printed as `a | b` within binary operations.
+        Expression::List(_) => 1,
+        Expression::Named(NamedExpression::Concat(_) | NamedExpression::Map(_)) => 0,
+        _ => 5,
+    }
+}
+
+/// Writes the operand of a binary operation, in parentheses if it binds
+/// less than `min`.
+fn write_operand(f: &mut Formatter<'_>, expr: &Expression, min: u8) -> fmt::Result {
+    let parens = precedence(expr) < min;
+    if parens {
+        write!(f, "(")?;
+    }
+    match expr {
+        Expression::List(items) => {
+            write_list(f, items.iter(), " | ", |f, item| write_operand(f, item, 2))?
+        }
+        _ => write_expr(f, expr, false)?,
+    }
+    if parens {
+        write!(f, ")")?;
+    }
+    Ok(())
+}
+
+/// Operands of a `|` chain; nested chains are printed flat like nft does.
+fn or_operands<'e, 'a>(items: &'e [Expression<'a>]) -> Vec<&'e Expression<'a>> {
+    let mut operands = Vec::new();
+    for item in items {
+        match item {
+            Expression::BinaryOperation(op) => match op.as_ref() {
+                BinaryOperation::OR(nested) => operands.extend(or_operands(nested)),
+                _ => operands.push(item),
+            },
+            _ => operands.push(item),
+        }
+    }
+    operands
+}
+
+fn write_expr(f: &mut Formatter<'_>, expr: &Expression, quote: bool) -> fmt::Result {
+    match expr {
+        Expression::String(s) if quote => write!(f, "{}", Quoted(s)),
+        Expression::String(s) => write!(f, "{}", Word(s)),
+        Expression::Number(n) => write!(f, "{n}"),
+        Expression::SignedNumber(n) => write!(f, "{n}"),
+        Expression::Boolean(true) => write!(f, "exists"),
+        Expression::Boolean(false) => write!(f, "missing"),
+        Expression::List(items) => {
+            write_list(f, items.iter(), ",", |f, item| write_expr(f, item, quote))
+        }
+        Expression::BinaryOperation(op) => match op.as_ref() {
+            BinaryOperation::AND(left, right) => {
+                write_operand(f, left, 3)?;
+                write!(f, " & ")?;
+                write_operand(f, right, 4)
+            }
+            BinaryOperation::OR(items) => write_list(f, or_operands(items), " | ", |f, item| {
+                write_operand(f, item, 2)
+            }),
+            BinaryOperation::XOR(left, right) => {
+                write_operand(f, left, 2)?;
+                write!(f, " ^ ")?;
+                write_operand(f, right, 3)
+            }
+            BinaryOperation::LSHIFT(left, right) => {
+                write_operand(f, left, 4)?;
+                write!(f, " << ")?;
+                write_operand(f, right, 5)
+            }
+            BinaryOperation::RSHIFT(left, right) => {
+                write_operand(f, left, 4)?;
+                write!(f, " >> ")?;
+                write_operand(f, right, 5)
+            }
+        },
+        Expression::Range(range) => {
+            write_expr(f, &range.range[0], quote)?;
+            write!(f, "-")?;
+            write_expr(f, &range.range[1], quote)
+        }
+        Expression::Verdict(verdict) => match verdict {
+            Verdict::Accept => write!(f, "accept"),
+            Verdict::Drop => write!(f, "drop"),
+            Verdict::Continue => write!(f, "continue"),
+            Verdict::Return => write!(f, "return"),
+            Verdict::Jump(target) => write!(f, "jump {}", Word(&target.target)),
+            Verdict::Goto(target) => write!(f, "goto {}", Word(&target.target)),
+        },
+        Expression::Named(named) => write_named(f, named, quote),
+    }
+}
+
+fn write_named(f: &mut Formatter<'_>, expr: &NamedExpression, quote: bool) -> fmt::Result {
+    match expr {
+        NamedExpression::Concat(items) => {
+            write_list(f, items.iter(), " . ", |f, item| write_expr(f, item, quote))
+        }
+        NamedExpression::Set(items) => {
+            write!(f, "{{ ")?;
+            write_list(f, items.iter(), ", ", |f, item| match item {
+                // Mappings deserialize as elements holding a `[key, value]` list.
+                SetItem::Element(elem) => write_element(f, elem, quote),
+                SetItem::Mapping(key, value) => {
+                    write_expr(f, key, quote)?;
+                    write!(f, " : ")?;
+                    write_expr(f, value, false)
+                }
+                SetItem::MappingStatement(key, stmt) => {
+                    write_expr(f, key, quote)?;
+                    write!(f, " : {stmt}")
+                }
+            })?;
+            write!(f, " }}")
+        }
+        NamedExpression::Map(map) => write!(f, "{} map {}", map.key, map.data),
+        NamedExpression::Prefix(prefix) => {
+            write_expr(f, &prefix.addr, quote)?;
+            write!(f, "/{}", prefix.len)
+        }
+        NamedExpression::Payload(Payload::PayloadField(field)) => {
+            write!(f, "{} {}", field.protocol, field.field)
+        }
+        NamedExpression::Payload(Payload::PayloadRaw(raw)) => {
+            write!(f, "@{},{},{}", name(&raw.base), raw.offset, raw.len)
+        }
+        NamedExpression::Exthdr(exthdr) => match &exthdr.field {
+            Some(field) => write!(f, "{} {field}", exthdr.name),
+            None => write!(f, "exthdr {}", exthdr.name),
+        },
+        NamedExpression::TcpOption(option) => {
+            write!(f, "tcp option {}", option.name)?;
+            if let Some(field) = &option.field {
+                write!(f, " {field}")?;
+            }
+            Ok(())
+        }
+        NamedExpression::SctpChunk(chunk) => {
+            write!(f, "sctp chunk {} {}", chunk.name, chunk.field)
+        }
+        NamedExpression::Meta(meta) => {
+            // Interface keys are printed without the `meta` keyword.
+            let unqualified = matches!(
+                meta.key,
+                MetaKey::Iif
+                    | MetaKey::Oif
+                    | MetaKey::Iifname
+                    | MetaKey::Oifname
+                    | MetaKey::Iifgroup
+                    | MetaKey::Oifgroup
+            );
+            if !unqualified {
+                write!(f, "meta ")?;
+            }
+            write!(f, "{}", name(&meta.key))
+        }
+        NamedExpression::RT(rt) => {
+            write!(f, "rt ")?;
+            if let Some(family) = &rt.family {
+                write!(f, "{} ", name(family))?;
+            }
+            write!(f, "{}", name(&rt.key))
+        }
+        NamedExpression::CT(ct) => {
+            write!(f, "ct ")?;
+            if let Some(dir) = &ct.dir {
+                write!(f, "{} ", name(dir))?;
+            }
+            if let Some(family) = &ct.family {
+                write!(f, "{} ", name(family))?;
+            }
+            write!(f, "{}", ct.key)
+        }
+        NamedExpression::Numgen(numgen) => {
+            write!(f, "numgen {} mod {}", name(&numgen.mode), numgen.ng_mod)?;
+            if let Some(offset) = numgen.offset {
+                write!(f, " offset {offset}")?;
+            }
+            Ok(())
+        }
+        NamedExpression::JHash(jhash) => {
+            write!(f, "jhash {} mod {}", jhash.expr, jhash.hash_mod)?;
+            if let Some(seed) = jhash.seed {
+                write!(f, " seed 0x{seed:x}")?;
+            }
+            if let Some(offset) = jhash.offset {
+                write!(f, " offset {offset}")?;
+            }
+            Ok(())
+        }
+        NamedExpression::SymHash(symhash) => {
+            write!(f, "symhash mod {}", symhash.hash_mod)?;
+            if let Some(offset) = symhash.offset {
+                write!(f, " offset {offset}")?;
+            }
+            Ok(())
+        }
+        NamedExpression::Fib(fib) => {
+            write!(f, "fib ")?;
+            write_list(
+                f,
+                sorted(&fib.flags, |flag| *flag as u8),
+                " . ",
+                |f, flag| write!(f, "{}", name(flag)),
+            )?;
+            write!(f, " {}", name(&fib.result))
+        }
+        NamedExpression::Elem(elem) => {
+            write_expr(f, &elem.val, quote)?;
+            if let Some(timeout) = elem.timeout {
+                write!(f, " timeout {}", duration(timeout.saturating_mul(1000)))?;
+            }
+            if let Some(expires) = elem.expires {
+                write!(f, " expires {}", duration(expires.saturating_mul(1000)))?;
+            }
+            if let Some(counter) = &elem.counter {
+                write!(f, " ")?;
+                write_counter(f, counter)?;
+            }
+            if let Some(comment) = &elem.comment {
+                write!(f, " comment {}", Quoted(comment))?;
+            }
+            Ok(())
+        }
+        NamedExpression::Socket(socket) => write!(f, "socket {}", name(socket.key.as_ref())),
+        NamedExpression::Osf(osf) => write!(f, "osf ttl {} {}", name(&osf.ttl), osf.key),
+    }
+}
+
+/// Writes items separated by `separator`.
+fn write_list<T>(
+    f: &mut Formatter<'_>,
+    items: impl IntoIterator<Item = T>,
+    separator: &str,
+    mut item: impl FnMut(&mut Formatter<'_>, T) -> fmt::Result,
+) -> fmt::Result {
+    for (i, value) in items.into_iter().enumerate() {
+        if i > 0 {
+            f.write_str(separator)?;
+        }
+        item(f, value)?;
+    }
+    Ok(())
+}
+
+/// Returns flags in the order nft prints them, which is the order of their
+/// declaration.
+fn sorted<T>(flags: &HashSet<T>, order: impl Fn(&T) -> u8) -> Vec<&T> {
+    let mut flags: Vec<&T> = flags.iter().collect();
+    flags.sort_by_key(|flag| order(flag));
+    flags
+}
+
+/// Returns the name `value` is serialized as.
+fn name<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => String::new(),
+    }
+}
+
+/// Formats a chain priority, using the name of a standard priority within
+/// 10 of it like nft does.
+fn priority(family: NfFamily, hook: Option<&NfHook>, prio: i32) -> String {
+    let priorities = match family {
+        NfFamily::Bridge => BRIDGE_PRIORITIES,
+        _ => STANDARD_PRIORITIES,
+    };
+    let Some((standard, value)) = priorities
+        .iter()
+        .find(|(_, value)| (prio - value).abs() <= 10)
+    else {
+        return prio.to_string();
+    };
+    let compatible = match (*standard, hook) {
+        ("dstnat", Some(hook)) => *hook == NfHook::Prerouting,
+        ("srcnat", Some(hook)) => *hook == NfHook::Postrouting,
+        ("out", Some(hook)) => *hook == NfHook::Output,
+        (standard, _) => {
+            standard == "filter" || !matches!(family, NfFamily::NetDev | NfFamily::ARP)
+        }
+    };
+    match prio - value {
+        _ if !compatible => prio.to_string(),
+        0 => standard.to_string(),
+        offset if offset > 0 => format!("{standard} + {offset}"),
+        offset => format!("{standard} - {}", -offset),
+    }
+}
+
+/// Formats a time like `1d2h`, from milliseconds.
+fn duration(ms: u64) -> String {
+    if ms == 0 {
+        return "0s".to_owned();
+    }
+    let mut out = String::new();
+    let mut rest = ms;
+    for (unit, size) in [
+        ("d", 86_400_000),
+        ("h", 3_600_000),
+        ("m", 60_000),
+        ("s", 1000),
+        ("ms", 1),
+    ] {
+        if rest >= size {
+            out.push_str(&format!("{}{unit}", rest / size));
+            rest %= size;
+        }
+    }
+    out
+}
+
+/// Formats a byte amount in the largest unit dividing it, like
+/// `25 mbytes`.
+fn byte_amount(bytes: u64) -> String {
+    let mut amount = bytes;
+    let mut units = ["bytes", "kbytes", "mbytes", "gbytes"].iter().peekable();
+    let mut unit = units.next().unwrap();
+    while amount != 0 && amount % 1024 == 0 {
+        let Some(next) = units.next() else {
+            break;
+        };
+        amount /= 1024;
+        unit = next;
+    }
+    format!("{amount} {unit}")
+}
@@ -0,0 +1,632 @@
+use std::{borrow::Cow, ffi::OsStr};
+
+use crate::batch::Batch;
+use crate::diff::{list_objects, normalize_object, normalize_rule};
+use crate::expr::Expression;
+use crate::helper::{self, NftExecutor, NftablesError, ProcessExecutor};
+use crate::schema::{
+    Chain, Element, FlushObject, Map, NfCmd, NfListObject, Nftables, Rule, Set, Table, TableFlag,
+};
+use crate::types::NfFamily;
+
+/// A table's identity: its family and name.
+pub type TableId<'a> = (NfFamily, &'a str);
+
+/// Contents of a single table, in document order.
+struct TableState<'s, 'a> {
+    table: Table<'a>,
+    chains: Vec<&'s Chain<'a>>,
+    rules: Vec<&'s Rule<'a>>,
+    sets: Vec<&'s Set<'a>>,
+    maps: Vec<&'s Map<'a>>,
+    elements: Vec<&'s Element<'a>>,
+    /// Flowtables and stateful objects (counters, quotas, ...).
+    objects: Vec<&'s NfListObject<'a>>,
+}
+
+impl<'s, 'a> TableState<'s, 'a> {
+    fn new(family: NfFamily, name: &Cow<'a, str>) -> Self {
+        TableState {
+            table: Table {
+                family,
+                name: name.clone(),
+                ..Table::default()
+            },
+            chains: Vec::new(),
+            rules: Vec::new(),
+            sets: Vec::new(),
+            maps: Vec::new(),
+            elements: Vec::new(),
+            objects: Vec::new(),
+        }
+    }
+
+    fn id(&self) -> TableId<'_> {
+        (self.table.family, &self.table.name)
+    }
+
+    fn chain(&self, name: &str) -> Option<&'s Chain<'a>> {
+        self.chains.iter().copied().find(|c| c.name == name)
+    }
+
+    fn rules_of(&self, chain: &str) -> Vec<&'s Rule<'a>> {
+        self.rules
+            .iter()
+            .copied()
+            .filter(|r| r.chain == chain)
+            .collect()
+    }
+
+    /// Elements of a set or map, including those of separate element objects.
+    fn elements_of(
+        &self,
+        name: &str,
+        inline: Option<&'s [Expression<'a>]>,
+    ) -> Vec<&'s Expression<'a>> {
+        let mut elements: Vec<&'s Expression<'a>> = inline.into_iter().flatten().collect();
+        for element in self.elements.iter().filter(|e| e.name == name) {
+            elements.extend(element.elem.iter());
+        }
+        elements
+    }
+}
+
+/// Splits a document into its tables.
+///
+/// Plain ruleset elements and the elements of `add` and `create` commands are
+/// taken into account; all other commands are ignored.
+fn tables<'s, 'a>(nftables: &'s Nftables<'a>) -> Vec<TableState<'s, 'a>> {
+    let mut tables: Vec<TableState<'s, 'a>> = Vec::new();
+    let mut state = |family: NfFamily, name: &Cow<'a, str>| -> usize {
+        match tables
+            .iter()
+            .position(|t| t.table.family == family && t.table.name == *name)
+        {
+            Some(i) => i,
+            None => {
+                tables.push(TableState::new(family, name));
+                tables.len() - 1
+            }
+        }
+    };
+    let mut entries: Vec<(usize, &'s NfListObject<'a>)> = Vec::new();
+    for object in list_objects(nftables) {
+        let i = match object {
+            NfListObject::Table(t) => state(t.family, &t.name),
+            NfListObject::Chain(c) => state(c.family, &c.table),
+            NfListObject::Rule(r) => state(r.family, &r.table),
+            NfListObject::Set(s) => state(s.family, &s.table),
+            NfListObject::Map(m) => state(m.family, &m.table),
+            NfListObject::Element(e) => state(e.family, &e.table),
+            NfListObject::FlowTable(o) => state(o.family, &o.table),
+            NfListObject::Counter(o) => state(o.family, &o.table),
+            NfListObject::Quota(o) => state(o.family, &o.table),
+            NfListObject::CTHelper(o) => state(o.family, &o.table),
+            NfListObject::Limit(o) => state(o.family, &o.table),
+            NfListObject::CTTimeout(o) => state(o.family, &o.table),
+            NfListObject::CTExpectation(o) => state(o.family, &o.table),
+            NfListObject::SynProxy(o) => state(o.family, &o.table),
+            NfListObject::MetainfoObject(_) => continue,
+        };
+        entries.push((i, object));
+    }
+    for (i, object) in entries {
+        let table = &mut tables[i];
+        match object {
+            NfListObject::Table(t) => {
+                table.table.flags.clone_from(&t.flags);
+                table.table.comment.clone_from(&t.comment);
+            }
+            NfListObject::MetainfoObject(_) => {}
+            NfListObject::Chain(c) => table.chains.push(c),
+            NfListObject::Rule(r) => table.rules.push(r),
+            NfListObject::Set(s) => table.sets.push(s),
+            NfListObject::Map(m) => table.maps.push(m),
+            NfListObject::Element(e) => table.elements.push(e),
+            object => table.objects.push(object),
+        }
+    }
+    tables
+}
+
+/// Kind and name of a flowtable or stateful object.
+fn object_id<'s>(object: &'s NfListObject) -> (&'static str, &'s str) {
+    match object {
+        NfListObject::FlowTable(o) => ("flowtable", &o.name),
+        NfListObject::Counter(o) => ("counter", &o.name),
+        NfListObject::Quota(o) => ("quota", &o.name),
+        NfListObject::CTHelper(o) => ("ct helper", &o.name),
+        NfListObject::Limit(o) => ("limit", &o.name),
+        NfListObject::CTTimeout(o) => ("ct timeout", &o.name),
+        NfListObject::CTExpectation(o) => ("ct expectation", &o.name),
+        NfListObject::SynProxy(o) => ("synproxy", &o.name),
+        _ => unreachable!("not a named object"),
+    }
+}
+
+fn normalize_table<'a>(table: &Table<'a>) -> Table<'a> {
+    Table {
+        handle: None,
+        flags: table.flags.clone().filter(|flags| !flags.is_empty()),
+        ..table.clone()
+    }
+}
+
+fn normalize_chain<'a>(chain: &Chain<'a>) -> Chain<'a> {
+    Chain {
+        handle: None,
+        newname: None,
+        ..chain.clone()
+    }
+}
+
+fn normalize_set<'a>(set: &Set<'a>) -> Set<'a> {
+    Set {
+        handle: None,
+        elem: None,
+        ..set.clone()
+    }
+}
+
+fn normalize_map<'a>(map: &Map<'a>) -> Map<'a> {
+    Map {
+        handle: None,
+        elem: None,
+        ..map.clone()
+    }
+}
+
+/// A rule to be added at the end of its chain, or inserted before the rule
+/// with the given handle.
+fn positioned_rule<'a>(rule: &Rule<'a>, before: Option<u32>) -> NfCmd<'a> {
+    let rule = Rule {
+        handle: before,
+        index: None,
+        ..rule.clone()
+    };
+    match before {
+        Some(_) => NfCmd::Insert(NfListObject::Rule(rule)),
+        None => NfCmd::Add(NfListObject::Rule(rule)),
+    }
+}
+
+/// Pairs of indices into `current` and `desired` of a longest common
+/// subsequence of equal rules.
+///
+/// Rules are equal if they have the same statements (ignoring the state of
+/// counters and quotas) and comment.
+fn common_rules<'a>(current: &[&Rule<'a>], desired: &[&Rule<'a>]) -> Vec<(usize, usize)> {
+    let normalize = |rules: &[&Rule<'a>]| -> Vec<_> {
+        rules
+            .iter()
+            .map(|r| {
+                let rule = normalize_rule(r);
+                (rule.expr, rule.comment)
+            })
+            .collect()
+    };
+    let (current, desired) = (normalize(current), normalize(desired));
+    let (n, m) = (current.len(), desired.len());
+    let mut lengths = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i][j] = match current[i] == desired[j] {
+                true => lengths[i + 1][j + 1] + 1,
+                false => lengths[i + 1][j].max(lengths[i][j + 1]),
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut pairs = Vec::new();
+    while i < n && j < m {
+        if current[i] == desired[j] {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+/// Commands converging the rules of an existing chain.
+///
+/// Unchanged rules are kept. Between two kept rules, changed rules are
+/// replaced in place, surplus rules are deleted and missing rules are
+/// inserted before the next kept rule (or appended to the chain).
+/// Returns [None] if a current rule has no handle.
+fn diff_rules<'a>(current: &[&Rule<'a>], desired: &[&Rule<'a>]) -> Option<Vec<NfCmd<'a>>> {
+    let mut cmds = Vec::new();
+    if current.iter().any(|r| r.handle.is_none()) {
+        return None;
+    }
+    if desired.is_empty() && !current.is_empty() {
+        let chain = Chain {
+            family: current[0].family,
+            table: current[0].table.clone(),
+            name: current[0].chain.clone(),
+            ..Chain::default()
+        };
+        cmds.push(NfCmd::Flush(FlushObject::Chain(chain)));
+        return Some(cmds);
+    }
+
+    let mut pairs = common_rules(current, desired);
+    pairs.push((current.len(), desired.len()));
+    let (mut i, mut j) = (0, 0);
+    for (next_i, next_j) in pairs {
+        let removed = &current[i..next_i];
+        let added = &desired[j..next_j];
+        let replaced = removed.len().min(added.len());
+        for (old, new) in removed.iter().zip(added) {
+            cmds.push(NfCmd::Replace(Rule {
+                handle: old.handle,
+                index: None,
+                ..(*new).clone()
+            }));
+        }
+        for old in &removed[replaced..] {
+            cmds.push(NfCmd::Delete(NfListObject::Rule(Rule {
+                expr: [][..].into(),
+                index: None,
+                comment: None,
+                ..(*old).clone()
+            })));
+        }
+        let before = current.get(next_i).and_then(|r| r.handle);
+        for new in &added[replaced..] {
+            cmds.push(positioned_rule(new, before));
+        }
+        (i, j) = (next_i + 1, next_j + 1);
+    }
+    Some(cmds)
+}
+
+/// Commands creating a table and all of its contents.
+///
+/// Chains are added before sets and maps, whose elements may jump to them.
+fn create_table<'a>(desired: &TableState<'_, 'a>, cmds: &mut Vec<NfCmd<'a>>) {
+    cmds.push(NfCmd::Add(NfListObject::Table(desired.table.clone())));
+    for object in &desired.objects {
+        cmds.push(NfCmd::Add(normalize_object(object)));
+    }
+    for chain in &desired.chains {
+        cmds.push(NfCmd::Add(NfListObject::Chain(normalize_chain(chain))));
+    }
+    for set in &desired.sets {
+        let set = Set {
+            handle: None,
+            ..(*set).clone()
+        };
+        cmds.push(NfCmd::Add(NfListObject::Set(Box::new(set))));
+    }
+    for map in &desired.maps {
+        let map = Map {
+            handle: None,
+            ..(*map).clone()
+        };
+        cmds.push(NfCmd::Add(NfListObject::Map(Box::new(map))));
+    }
+    for element in &desired.elements {
+        cmds.push(NfCmd::Add(NfListObject::Element((*element).clone())));
+    }
+    for rule in &desired.rules {
+        cmds.push(positioned_rule(rule, None));
+    }
+}
+
+/// Commands converging the elements of an existing set or map.
+fn diff_elements<'a>(
+    family: NfFamily,
+    table: Cow<'a, str>,
+    name: Cow<'a, str>,
+    current: &[&Expression<'a>],
+    desired: &[&Expression<'a>],
+    cmds: &mut Vec<NfCmd<'a>>,
+) {
+    let element = |elem: Vec<Expression<'a>>| Element {
+        family,
+        table: table.clone(),
+        name: name.clone(),
+        elem: elem.into(),
+    };
+    let removed: Vec<Expression<'a>> = current
+        .iter()
+        .filter(|e| !desired.contains(e))
+        .map(|e| (*e).clone())
+        .collect();
+    let added: Vec<Expression<'a>> = desired
+        .iter()
+        .filter(|e| !current.contains(e))
+        .map(|e| (*e).clone())
+        .collect();
+    if !removed.is_empty() {
+        cmds.push(NfCmd::Delete(NfListObject::Element(element(removed))));
+    }
+    if !added.is_empty() {
+        cmds.push(NfCmd::Add(NfListObject::Element(element(added))));
+    }
+}
+
+/// Commands converging an existing table.
+///
+/// Returns [None] if the table cannot be updated in place, e.g. because the
+/// type or hook of a chain or the type of a set changed.
+fn diff_table<'a>(
+    current: &TableState<'_, 'a>,
+    desired: &TableState<'_, 'a>,
+) -> Option<Vec<NfCmd<'a>>> {
+    let mut added = Vec::new();
+    let mut elements = Vec::new();
+    let mut removed = Vec::new();
+
+    // An existing table can be made dormant in place. Waking it up or
+    // changing its other flags or comment requires re-creating it.
+    let (old, new) = (
+        normalize_table(&current.table),
+        normalize_table(&desired.table),
+    );
+    if old != new {
+        let dormant = |table: &Table| {
+            table
+                .flags
+                .as_ref()
+                .is_some_and(|flags| flags.contains(&TableFlag::Dormant))
+        };
+        let mut asleep = old.clone();
+        asleep
+            .flags
+            .get_or_insert_with(Default::default)
+            .insert(TableFlag::Dormant);
+        if dormant(&old) || asleep != new {
+            return None;
+        }
+        added.push(NfCmd::Add(NfListObject::Table(new)));
+    }
+
+    // Flowtables and stateful objects.
+    for object in &desired.objects {
+        let id = object_id(object);
+        match current.objects.iter().find(|o| object_id(o) == id) {
+            None => added.push(NfCmd::Add(normalize_object(object))),
+            Some(old) if normalize_object(old) != normalize_object(object) => return None,
+            Some(_) => {}
+        }
+    }
+    for object in &current.objects {
+        let id = object_id(object);
+        if !desired.objects.iter().any(|o| object_id(o) == id) {
+            removed.push(NfCmd::Delete((*object).clone()));
+        }
+    }
+
+    // Sets and maps, including their elements.
+    for set in &desired.sets {
+        let desired_elements = desired.elements_of(&set.name, set.elem.as_deref());
+        let current_elements = match current.sets.iter().find(|s| s.name == set.name) {
+            None => {
+                let set = Set {
+                    handle: None,
+                    elem: None,
+                    ..(*set).clone()
+                };
+                added.push(NfCmd::Add(NfListObject::Set(Box::new(set))));
+                Vec::new()
+            }
+            Some(old) if normalize_set(old) != normalize_set(set) => return None,
+            Some(old) => current.elements_of(&old.name, old.elem.as_deref()),
+        };
+        diff_elements(
+            desired.table.family,
+            desired.table.name.clone(),
+            set.name.clone(),
+            &current_elements,
+            &desired_elements,
+            &mut elements,
+        );
+    }
+    for set in &current.sets {
+        if desired.sets.iter().all(|s| s.name != set.name) {
+            removed.push(NfCmd::Delete(NfListObject::Set(Box::new((*set).clone()))));
+        }
+    }
+    for map in &desired.maps {
+        let desired_elements = desired.elements_of(&map.name, map.elem.as_deref());
+        let current_elements = match current.maps.iter().find(|m| m.name == map.name) {
+            None => {
+                let map = Map {
+                    handle: None,
+                    elem: None,
+                    ..(*map).clone()
+                };
+                added.push(NfCmd::Add(NfListObject::Map(Box::new(map))));
+                Vec::new()
+            }
+            Some(old) if normalize_map(old) != normalize_map(map) => return None,
+            Some(old) => current.elements_of(&old.name, old.elem.as_deref()),
+        };
+        diff_elements(
+            desired.table.family,
+            desired.table.name.clone(),
+            map.name.clone(),
+            &current_elements,
+            &desired_elements,
+            &mut elements,
+        );
+    }
+    for map in &current.maps {
+        if desired.maps.iter().all(|m| m.name != map.name) {
+            removed.push(NfCmd::Delete(NfListObject::Map(Box::new((*map).clone()))));
+        }
+    }
+
+    // Chains must exist before rules or map elements jump to them, and
+    // rules must be gone before the chains they jump to are deleted.
+    let mut rules = Vec::new();
+    for chain in &desired.chains {
+        let desired_rules = desired.rules_of(&chain.name);
+        match current.chain(&chain.name) {
+            None => {
+                added.push(NfCmd::Add(NfListObject::Chain(normalize_chain(chain))));
+                rules.extend(desired_rules.iter().map(|r| positioned_rule(r, None)));
+            }
+            Some(old) => {
+                let (old, new) = (normalize_chain(old), normalize_chain(chain));
+                if old.policy != new.policy {
+                    if (Chain {
+                        policy: new.policy,
+                        ..old
+                    }) != new
+                    {
+                        return None;
+                    }
+                    added.push(NfCmd::Add(NfListObject::Chain(new)));
+                } else if old != new {
+                    return None;
+                }
+                rules.extend(diff_rules(&current.rules_of(&chain.name), &desired_rules)?);
+            }
+        }
+    }
+    // Removed chains are flushed first, so that no rule of another removed
+    // chain jumps to them when they are deleted.
+    let mut removed_chains = Vec::new();
+    for chain in &current.chains {
+        if desired.chain(&chain.name).is_none() {
+            let chain = Chain {
+                family: chain.family,
+                table: chain.table.clone(),
+                name: chain.name.clone(),
+                ..Chain::default()
+            };
+            rules.push(NfCmd::Flush(FlushObject::Chain(chain.clone())));
+            removed_chains.push(NfCmd::Delete(NfListObject::Chain(chain)));
+        }
+    }
+
+    added.append(&mut elements);
+    added.append(&mut rules);
+    added.append(&mut removed_chains);
+    added.append(&mut removed);
+    Some(added)
+}
+
+/// Computes the commands that converge `current` onto `desired`.
+///
+/// Only tables contained in `desired` or listed in `owned` are touched; all
+/// other tables of `current` are left alone. Owned tables missing from
+/// `desired` are deleted.
+///
+/// Within an existing table, rules are matched by their statements and
+/// comment (ignoring handles), so unchanged rules are kept along with their
+/// counters. Changes that cannot be applied in place, such as a different
+/// chain hook or set type, delete and re-create the whole table. So do
+/// changes of a table's flags or comment, except for making it dormant.
+///
+/// `current` is expected to be the output of `nft -j list ruleset` (with
+/// handles). As nft may print statements differently than they were
+/// submitted, rules should be written the way nft lists them to be
+/// recognized as unchanged.
+pub fn diff<'a>(
+    current: &Nftables<'a>,
+    desired: &Nftables<'a>,
+    owned: &[TableId],
+) -> Vec<NfCmd<'a>> {
+    let current = tables(current);
+    let desired = tables(desired);
+    let mut cmds = Vec::new();
+
+    for table in &current {
+        let id = table.id();
+        if owned.contains(&id) && desired.iter().all(|t| t.id() != id) {
+            cmds.push(NfCmd::Delete(NfListObject::Table(table.table.clone())));
+        }
+    }
+    for table in &desired {
+        match current.iter().find(|t| t.id() == table.id()) {
+            None => create_table(table, &mut cmds),
+            Some(old) => match diff_table(old, table) {
+                Some(mut table_cmds) => cmds.append(&mut table_cmds),
+                None => {
+                    cmds.push(NfCmd::Delete(NfListObject::Table(old.table.clone())));
+                    create_table(table, &mut cmds);
+                }
+            },
+        }
+    }
+    cmds
+}
+
+/// Converges the live ruleset onto `desired` using the default `nft` executable.
+///
+/// See [reconcile_with_executor].
+pub fn reconcile<'a>(
+    desired: &Nftables<'a>,
+    owned: &[TableId],
+) -> Result<Vec<NfCmd<'a>>, NftablesError> {
+    reconcile_with_executor(desired, owned, &ProcessExecutor::default())
+}
+
+/// Reads the live ruleset, computes the [diff] towards `desired` and applies
+/// it in a single transaction.
+///
+/// Returns the applied commands, which are empty if the ruleset already
+/// matched.
+pub fn reconcile_with_executor<'a, E: NftExecutor + ?Sized>(
+    desired: &Nftables<'a>,
+    owned: &[TableId],
+    executor: &E,
+) -> Result<Vec<NfCmd<'a>>, NftablesError> {
+    let current = parse_ruleset(&executor.execute(&list_ruleset_args(), None)?)?;
+    let cmds = diff(&current, desired, owned);
+    if !cmds.is_empty() {
+        helper::apply_ruleset_with_executor(&to_nftables(&cmds), executor)?;
+    }
+    Ok(cmds)
+}
+
+/// Asynchronous counterpart of [reconcile].
+#[cfg(any(feature = "tokio", feature = "async-process"))]
+pub async fn reconcile_async<'a>(
+    desired: &Nftables<'a>,
+    owned: &[TableId<'_>],
+) -> Result<Vec<NfCmd<'a>>, NftablesError> {
+    reconcile_with_executor_async(desired, owned, &ProcessExecutor::default()).await
+}
+
+/// Asynchronous counterpart of [reconcile_with_executor].
+#[cfg(any(feature = "tokio", feature = "async-process"))]
+pub async fn reconcile_with_executor_async<'a, E: helper::NftExecutorAsync>(
+    desired: &Nftables<'a>,
+    owned: &[TableId<'_>],
+    executor: &E,
+) -> Result<Vec<NfCmd<'a>>, NftablesError> {
+    let output = executor.execute_async(&list_ruleset_args(), None).await?;
+    let current = parse_ruleset(&output)?;
+    let cmds = diff(&current, desired, owned);
+    if !cmds.is_empty() {
+        helper::apply_ruleset_with_executor_async(&to_nftables(&cmds), executor).await?;
+    }
+    Ok(cmds)
+}
+
+fn list_ruleset_args() -> [&'static OsStr; 3] {
+    ["-j", "list", "ruleset"].map(OsStr::new)
+}
+
+/// Parses the live ruleset with the lifetime of the desired one, as
+/// [Nftables] is invariant over its lifetime.
+fn parse_ruleset<'a>(output: &str) -> Result<Nftables<'a>, NftablesError> {
+    serde_json::from_str(output).map_err(NftablesError::NftInvalidJson)
+}
+
+fn to_nftables<'a>(cmds: &[NfCmd<'a>]) -> Nftables<'a> {
+    let mut batch = Batch::new();
+    for cmd in cmds {
+        batch.add_cmd(cmd.clone());
+    }
+    batch.to_nftables()
+}
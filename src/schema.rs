@@ -103,11 +103,16 @@ pub enum NfCmd<'a> {
     /// For most ruleset elements, this is **family** and **table** plus either
     /// **handle** or **name** (except rules since they don’t have a name).
     Delete(NfListObject<'a>), // TODO: ADD_OBJECT is subset of NfListObject
+    /// Delete an object from the ruleset if it exists.
+    ///
+    /// Identical to [delete command](NfCmd::Delete), but does not return an
+    /// error if the object does not exist. Requires nftables 1.0.8 or later.
+    Destroy(NfListObject<'a>),
     /// List ruleset elements.
     ///
     /// The plural forms are used to list all objects of that kind,
     /// optionally filtered by family and for some, also table.
-    List(NfListObject<'a>),
+    List(ListObject<'a>),
     /// Reset state in suitable objects, i.e. zero their internal counter.
     Reset(ResetObject<'a>),
     /// Empty contents in given object, e.g. remove all chains from given table
@@ -131,6 +136,80 @@ pub enum ResetObject<'a> {
     Quota(Quota<'a>),
     /// A list of quotas to reset.
     Quotas(Cow<'a, [Quota<'a>]>),
+    /// A rule whose stateful statements to reset.
+    ///
+    /// Without a **handle**, all rules of the rule’s chain are reset.
+    Rule(Rule<'a>),
+    /// All rules to reset, optionally filtered by family and table.
+    Rules(ObjectFilter<'a>),
+    /// A set whose element counters and quotas to reset.
+    Set(Box<Set<'a>>),
+    /// A map whose element counters and quotas to reset.
+    Map(Box<Map<'a>>),
+    /// Set elements whose counters and quotas to reset.
+    Element(Element<'a>),
+    /// A table whose rules, sets, maps and stateful objects to reset.
+    Table(Table<'a>),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+/// Ruleset elements to [list](NfCmd::List).
+pub enum ListObject<'a> {
+    /// The whole ruleset.
+    Ruleset(ObjectFilter<'a>),
+    /// All tables.
+    Tables(ObjectFilter<'a>),
+    /// All chains.
+    Chains(ObjectFilter<'a>),
+    /// All sets.
+    Sets(ObjectFilter<'a>),
+    /// All maps.
+    Maps(ObjectFilter<'a>),
+    /// All flow tables.
+    FlowTables(ObjectFilter<'a>),
+    /// All counters.
+    Counters(ObjectFilter<'a>),
+    /// All quotas.
+    Quotas(ObjectFilter<'a>),
+    /// All limits.
+    Limits(ObjectFilter<'a>),
+    /// All meters.
+    Meters(ObjectFilter<'a>),
+    #[serde(rename = "ct helpers")]
+    /// All conntrack helpers.
+    CTHelpers(ObjectFilter<'a>),
+    #[serde(rename = "ct timeouts")]
+    /// All conntrack timeouts.
+    CTTimeouts(ObjectFilter<'a>),
+    #[serde(rename = "ct expectations")]
+    /// All conntrack expectations.
+    CTExpectations(ObjectFilter<'a>),
+    /// All synproxy objects.
+    SynProxys(ObjectFilter<'a>),
+    #[serde(untagged)]
+    /// A single ruleset element.
+    Object(NfListObject<'a>),
+}
+
+impl<'a> From<NfListObject<'a>> for ListObject<'a> {
+    fn from(object: NfListObject<'a>) -> Self {
+        ListObject::Object(object)
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
+/// Restricts a plural [list](ListObject) or [reset](ResetObject) command to
+/// the objects of a family or table.
+pub struct ObjectFilter<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// The [family](NfFamily) of the objects, all families if omitted.
+    pub family: Option<NfFamily>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// The table of the objects, all tables if omitted.
+    ///
+    /// Not supported when listing the ruleset, tables or chains.
+    pub table: Option<Cow<'a, str>>,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
@@ -166,6 +245,20 @@ pub struct Table<'a> {
     /// In input, it is used only in [delete command](NfCmd::Delete) as
     /// alternative to **name**.
     pub handle: Option<u32>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_optional_flags",
+        default
+    )]
+    /// The table’s flags.
+    pub flags: Option<HashSet<TableFlag>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Optional table comment.
+    ///
+    /// The comment is kept in the table’s userdata, which nftables does not
+    /// expose otherwise.
+    /// Table comment attribute requires at least nftables 0.9.7 and kernel 5.10
+    pub comment: Option<Cow<'a, str>>,
 }
 
 /// Default table.
@@ -175,10 +268,29 @@ impl Default for Table<'_> {
             family: DEFAULT_FAMILY,
             name: DEFAULT_TABLE.into(),
             handle: None,
+            flags: None,
+            comment: None,
         }
     }
 }
 
+#[derive(
+    Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize, EnumString, Hash, JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+/// Describes a [table](Table)’s flags.
+pub enum TableFlag {
+    /// The table is not evaluated: its base chains are unregistered.
+    Dormant,
+    /// The table is owned by the process that created it and removed when
+    /// that process closes its netlink socket.
+    Owner,
+    /// The table is kept after its owner closes its netlink socket, which
+    /// releases the ownership instead.
+    Persist,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
 /// This object describes a chain.
 pub struct Chain<'a> {
@@ -213,18 +325,38 @@ pub struct Chain<'a> {
     ///
     /// (Base chains): <https://wiki.nftables.org/wiki-nftables/index.php/Configuring_chains#Adding_base_chains>
     pub prio: Option<i32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    /// The chain’s bound interface (if in the netdev family).
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "single_string_to_option_vec"
+    )]
+    /// The chain’s bound interface(s) (if in the netdev family).
     /// Required for [base chains](Base chains).
     ///
+    /// A netdev base chain may span several devices, e.g. the members of a
+    /// bond.
+    /// Cow slice of device names, e.g. `vec!["eth0".into(), "eth1".into()].into()`.
+    ///
     /// (Base chains): <https://wiki.nftables.org/wiki-nftables/index.php/Configuring_chains#Adding_base_chains>
-    pub dev: Option<Cow<'a, str>>,
+    pub dev: Option<Cow<'a, [Cow<'a, str>]>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     /// The chain’s [policy](NfChainPolicy).
     /// Required for [base chains](Base chains).
     ///
     /// (Base chains): <https://wiki.nftables.org/wiki-nftables/index.php/Configuring_chains#Adding_base_chains>
     pub policy: Option<NfChainPolicy>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_optional_flags",
+        default
+    )]
+    /// The chain’s flags.
+    pub flags: Option<HashSet<ChainFlag>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Optional chain comment.
+    ///
+    /// Chain comment attribute requires at least nftables 0.9.7 and kernel 5.10
+    pub comment: Option<Cow<'a, str>>,
 }
 
 /// Default Chain.
@@ -241,10 +373,23 @@ impl Default for Chain<'_> {
             prio: None,
             dev: None,
             policy: None,
+            flags: None,
+            comment: None,
         }
     }
 }
 
+#[derive(
+    Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize, EnumString, Hash, JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+/// Describes a [chain](Chain)’s flags.
+pub enum ChainFlag {
+    /// The base chain is offloaded to the hardware of its device(s).
+    Offload,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
 /// This object describes a rule.
 ///
@@ -307,7 +452,8 @@ pub struct Set<'a> {
     #[serde(rename = "type")]
     /// The set’s datatype.
     ///
-    /// The set type might be a string, such as `"ipv4_addr"` or an array consisting of strings (for concatenated types).
+    /// The set type might be a string, such as `"ipv4_addr"`, an array consisting of strings (for concatenated types)
+    /// or the expression of a `typeof` declaration.
     pub set_type: SetTypeValue<'a>,
     #[serde(skip_serializing_if = "Option::is_none")]
     /// The set’s policy.
@@ -327,7 +473,7 @@ pub struct Set<'a> {
     pub elem: Option<Cow<'a, [Expression<'a>]>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     /// Element timeout in seconds.
-    pub timeout: Option<u32>,
+    pub timeout: Option<u64>,
     #[serde(rename = "gc-interval", skip_serializing_if = "Option::is_none")]
     /// Garbage collector interval in seconds.
     pub gc_interval: Option<u32>,
@@ -378,7 +524,8 @@ pub struct Map<'a> {
     /// The map set’s datatype.
     ///
     /// The set type might be a string, such as `"ipv4_addr"`` or an array
-    /// consisting of strings (for concatenated types).
+    /// consisting of strings (for concatenated types) or the expression of a
+    /// `typeof` declaration.
     pub set_type: SetTypeValue<'a>,
     /// Type of values this set maps to (i.e. this set is a map).
     pub map: SetTypeValue<'a>,
@@ -400,7 +547,7 @@ pub struct Map<'a> {
     pub elem: Option<Cow<'a, [Expression<'a>]>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     /// Element timeout in seconds.
-    pub timeout: Option<u32>,
+    pub timeout: Option<u64>,
     #[serde(rename = "gc-interval", skip_serializing_if = "Option::is_none")]
     /// Garbage collector interval in seconds.
     pub gc_interval: Option<u32>,
@@ -437,21 +584,73 @@ impl Default for Map<'_> {
 
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
-/// Wrapper for single or concatenated set types.
-/// The set type might be a string, such as `"ipv4_addr"` or an array consisting of strings (for concatenated types).
+/// Wrapper for single, concatenated or `typeof` set types.
+/// The set type might be a string, such as `"ipv4_addr"`, an array consisting of strings (for concatenated types)
+/// or an object with the expression whose type is used, such as `{"typeof": {"payload": {"protocol": "ip", "field": "saddr"}}}`.
 pub enum SetTypeValue<'a> {
     /// Single set type.
     Single(SetType),
     /// Concatenated set types.
     Concatenated(Cow<'a, [SetType]>),
+    /// Type of an expression, e.g. `typeof ip saddr . tcp dport`.
+    ///
+    /// Concatenations are given as [concat](crate::expr::NamedExpression::Concat) expression.
+    #[serde(with = "typeof_expression")]
+    #[schemars(with = "TypeofExpression")]
+    Typeof(Expression<'a>),
 }
 
-#[derive(
-    Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize, Deserialize, EnumString, JsonSchema,
-)]
+#[derive(Serialize, Deserialize, JsonSchema)]
+/// The JSON representation of [SetTypeValue::Typeof].
+struct TypeofExpression<'a> {
+    #[serde(rename = "typeof")]
+    expr: Expression<'a>,
+}
+
+mod typeof_expression {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::TypeofExpression;
+    use crate::expr::Expression;
+
+    pub fn serialize<S: Serializer>(expr: &Expression, serializer: S) -> Result<S::Ok, S::Error> {
+        TypeofExpression { expr: expr.clone() }.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, 'a, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Expression<'a>, D::Error> {
+        TypeofExpression::deserialize(deserializer).map(|typeof_expr| typeof_expr.expr)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize, EnumString, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 /// Describes a set’s datatype.
+///
+/// These are the datatypes listed by `nft describe`. Datatypes unknown to
+/// this library, e.g. from newer nftables versions, are kept as [SetType::Unknown].
 pub enum SetType {
+    #[serde(rename = "verdict")]
+    #[strum(serialize = "verdict")]
+    /// Verdict, e.g. `accept` or `jump chain`.
+    Verdict,
+    #[serde(rename = "nf_proto")]
+    #[strum(serialize = "nf_proto")]
+    /// Netfilter protocol (`meta nfproto`).
+    NfProto,
+    #[serde(rename = "integer")]
+    #[strum(serialize = "integer")]
+    /// Plain integer.
+    Integer,
+    #[serde(rename = "string")]
+    #[strum(serialize = "string")]
+    /// Plain string.
+    String,
+    #[serde(rename = "lladdr")]
+    #[strum(serialize = "lladdr")]
+    /// Link layer address.
+    LlAddr,
     #[serde(rename = "ipv4_addr")]
     #[strum(serialize = "ipv4_addr")]
     /// IPv4 address.
@@ -464,6 +663,14 @@ pub enum SetType {
     #[strum(serialize = "ether_addr")]
     /// Ethernet address.
     EtherAddr,
+    #[serde(rename = "ether_type")]
+    #[strum(serialize = "ether_type")]
+    /// Ethernet protocol type.
+    EtherType,
+    #[serde(rename = "arp_op")]
+    #[strum(serialize = "arp_op")]
+    /// ARP operation.
+    ArpOp,
     #[serde(rename = "inet_proto")]
     #[strum(serialize = "inet_proto")]
     /// Internet protocol type.
@@ -472,14 +679,142 @@ pub enum SetType {
     #[strum(serialize = "inet_service")]
     /// Internet service.
     InetService,
+    #[serde(rename = "icmp_type")]
+    #[strum(serialize = "icmp_type")]
+    /// ICMP type.
+    IcmpType,
+    #[serde(rename = "icmp_code")]
+    #[strum(serialize = "icmp_code")]
+    /// ICMP code.
+    IcmpCode,
+    #[serde(rename = "icmpv6_type")]
+    #[strum(serialize = "icmpv6_type")]
+    /// ICMPv6 type.
+    Icmpv6Type,
+    #[serde(rename = "icmpv6_code")]
+    #[strum(serialize = "icmpv6_code")]
+    /// ICMPv6 code.
+    Icmpv6Code,
+    #[serde(rename = "icmpx_code")]
+    #[strum(serialize = "icmpx_code")]
+    /// ICMP code shared by IPv4 and IPv6 (`reject with icmpx`).
+    IcmpxCode,
+    #[serde(rename = "igmp_type")]
+    #[strum(serialize = "igmp_type")]
+    /// IGMP type.
+    IgmpType,
+    #[serde(rename = "tcp_flag")]
+    #[strum(serialize = "tcp_flag")]
+    /// TCP flag.
+    TcpFlag,
+    #[serde(rename = "dccp_pkttype")]
+    #[strum(serialize = "dccp_pkttype")]
+    /// DCCP packet type.
+    DccpPktType,
+    #[serde(rename = "mh_type")]
+    #[strum(serialize = "mh_type")]
+    /// Mobility header type.
+    MhType,
+    #[serde(rename = "time")]
+    #[strum(serialize = "time")]
+    /// Relative time, e.g. `1h30m`.
+    Time,
+    #[serde(rename = "day")]
+    #[strum(serialize = "day")]
+    /// Day of the week.
+    Day,
+    #[serde(rename = "hour")]
+    #[strum(serialize = "hour")]
+    /// Time of the day.
+    Hour,
     #[serde(rename = "mark")]
     #[strum(serialize = "mark")]
     /// Mark type.
     Mark,
+    #[serde(rename = "iface_index")]
+    #[strum(serialize = "iface_index")]
+    /// Network interface index.
+    IfaceIndex,
+    #[serde(rename = "iface_type")]
+    #[strum(serialize = "iface_type")]
+    /// Network interface type.
+    IfaceType,
     #[serde(rename = "ifname")]
     #[strum(serialize = "ifname")]
     /// Network interface name (eth0, eth1..).
     Ifname,
+    #[serde(rename = "realm")]
+    #[strum(serialize = "realm")]
+    /// Routing realm.
+    Realm,
+    #[serde(rename = "classid")]
+    #[strum(serialize = "classid")]
+    /// Traffic control class id.
+    ClassId,
+    #[serde(rename = "uid")]
+    #[strum(serialize = "uid")]
+    /// User id.
+    Uid,
+    #[serde(rename = "gid")]
+    #[strum(serialize = "gid")]
+    /// Group id.
+    Gid,
+    #[serde(rename = "pkttype")]
+    #[strum(serialize = "pkttype")]
+    /// Packet type.
+    PktType,
+    #[serde(rename = "devgroup")]
+    #[strum(serialize = "devgroup")]
+    /// Network device group.
+    DevGroup,
+    #[serde(rename = "dscp")]
+    #[strum(serialize = "dscp")]
+    /// Differentiated services code point.
+    Dscp,
+    #[serde(rename = "ecn")]
+    #[strum(serialize = "ecn")]
+    /// Explicit congestion notification.
+    Ecn,
+    #[serde(rename = "fib_addrtype")]
+    #[strum(serialize = "fib_addrtype")]
+    /// Address type of a FIB lookup.
+    FibAddrType,
+    #[serde(rename = "boolean")]
+    #[strum(serialize = "boolean")]
+    /// Boolean.
+    Boolean,
+    #[serde(rename = "ct_state")]
+    #[strum(serialize = "ct_state")]
+    /// Conntrack state.
+    CtState,
+    #[serde(rename = "ct_dir")]
+    #[strum(serialize = "ct_dir")]
+    /// Conntrack direction.
+    CtDir,
+    #[serde(rename = "ct_status")]
+    #[strum(serialize = "ct_status")]
+    /// Conntrack status.
+    CtStatus,
+    #[serde(rename = "ct_event")]
+    #[strum(serialize = "ct_event")]
+    /// Conntrack event.
+    CtEvent,
+    #[serde(rename = "ct_label")]
+    #[strum(serialize = "ct_label")]
+    /// Conntrack label.
+    CtLabel,
+    #[serde(rename = "ipsec_spi")]
+    #[strum(serialize = "ipsec_spi")]
+    /// IPsec security parameter index.
+    IpsecSpi,
+    #[serde(rename = "cgroupsv2")]
+    #[strum(serialize = "cgroupsv2")]
+    /// Control group (v2) path.
+    CgroupsV2,
+    #[serde(untagged)]
+    #[strum(default)]
+    /// Datatype unknown to this library.
+    Unknown(String),
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize, Deserialize, JsonSchema)]
@@ -613,9 +948,12 @@ pub struct Counter<'a> {
     pub handle: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     /// Packet counter value.
-    pub packets: Option<u32>,
+    pub packets: Option<u64>,
     /// Byte counter value.
-    pub bytes: Option<u32>,
+    pub bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// The counter’s comment.
+    pub comment: Option<Cow<'a, str>>,
 }
 
 /// Default [counter](Counter) named "mycounter".
@@ -628,6 +966,7 @@ impl Default for Counter<'_> {
             handle: None,
             packets: None,
             bytes: None,
+            comment: None,
         }
     }
 }
@@ -656,10 +995,10 @@ pub struct Quota<'a> {
     pub handle: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     /// Quota threshold.
-    pub bytes: Option<u32>,
+    pub bytes: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     /// Quota used so far.
-    pub used: Option<u32>,
+    pub used: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     /// If `true`, match if the quota has been exceeded (i.e., "invert" the quota).
     pub inv: Option<bool>,
@@ -859,8 +1198,8 @@ pub struct CTTimeout<'a> {
     /// The connection state name, e.g. "established", "syn_sent", "close" or "close_wait", for which the timeout value has to be updated.
     pub state: Option<Cow<'a, str>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    /// The updated timeout value for the specified connection state.
-    pub value: Option<u32>,
+    /// The updated timeout value in seconds for the specified connection state.
+    pub value: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     /// The ct timeout object’s layer 3 protocol, e.g. "ip" or "ip6".
     pub l3proto: Option<Cow<'a, str>>,
@@ -907,7 +1246,7 @@ pub struct CTExpectation<'a> {
     pub dport: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     /// The time in millisecond that this expectation will live.
-    pub timeout: Option<u32>,
+    pub timeout: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     /// The maximum count of expectations to be living in the same time.
     pub size: Option<u32>,
@@ -140,10 +140,10 @@ pub enum Counter<'a> {
 pub struct AnonymousCounter {
     /// Packets counted.
     #[serde(serialize_with = "crate::visitor::serialize_none_to_zero")]
-    pub packets: Option<usize>,
+    pub packets: Option<u64>,
     /// Bytes counted.
     #[serde(serialize_with = "crate::visitor::serialize_none_to_zero")]
-    pub bytes: Option<usize>,
+    pub bytes: Option<u64>,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
@@ -169,12 +169,12 @@ pub enum QuotaOrQuotaRef<'a> {
 /// Creates an anonymous quota which lives in the rule it appears in.
 pub struct Quota<'a> {
     /// Quota value.
-    pub val: u32,
+    pub val: u64,
     /// Unit of `val`, e.g. `"kbytes"` or `"mbytes"`. If omitted, defaults to `"bytes"`.
     pub val_unit: Cow<'a, str>,
     #[serde(skip_serializing_if = "Option::is_none")]
     /// Quota used so far. Optional on input. If given, serves as initial value.
-    pub used: Option<u32>,
+    pub used: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     /// Unit of `used`. Defaults to `"bytes"`.
     pub used_unit: Option<Cow<'a, str>>,
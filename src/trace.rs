@@ -0,0 +1,143 @@
+use std::{borrow::Cow, collections::HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::stmt::Statement;
+use crate::types::{NfChainPolicy, NfFamily};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+/// Reason for which a [trace event](Trace) was emitted.
+pub enum TraceType {
+    /// A rule matched the packet.
+    Rule,
+    /// The end of a non-base chain was reached and evaluation returns to the caller.
+    Return,
+    /// The end of a base chain was reached and its policy was applied.
+    Policy,
+    /// The kernel did not set a type.
+    Unspec,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+/// A single event printed by `nft -j monitor trace` for a packet that has
+/// `meta nftrace` set.
+///
+/// All events of one packet share the same [id](Trace::id).
+/// Packet fields such as [iif](Trace::iif) are usually only included in the
+/// first event of a packet and are [None] otherwise.
+pub struct Trace<'a> {
+    /// Trace id shared by all events of the same packet.
+    pub id: u32,
+    /// The table’s family.
+    pub family: NfFamily,
+    #[serde(rename = "type")]
+    /// The reason for this event.
+    pub _type: TraceType,
+    /// The table’s name.
+    pub table: Cow<'a, str>,
+    /// The chain’s name.
+    pub chain: Cow<'a, str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// The handle of the matching rule (for [TraceType::Rule]).
+    pub handle: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// The verdict of the matching rule, e.g. `accept` or `jump`.
+    pub verdict: Option<Statement<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// The applied chain policy (for [TraceType::Policy]).
+    pub policy: Option<NfChainPolicy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// The input interface.
+    pub iif: Option<Cow<'a, str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// The output interface.
+    pub oif: Option<Cow<'a, str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// The packet mark.
+    pub mark: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// The link layer header as a hex string.
+    pub ll_header: Option<Cow<'a, str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// The network header as a hex string.
+    pub network_header: Option<Cow<'a, str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// The transport header as a hex string.
+    pub transport_header: Option<Cow<'a, str>>,
+}
+
+impl Trace<'_> {
+    /// The packet's fate decided by this event, if any.
+    ///
+    /// This is the policy for [TraceType::Policy] events, and the verdict of
+    /// rules that `accept` or `drop` the packet.
+    pub fn decision(&self) -> Option<NfChainPolicy> {
+        match (self._type, &self.verdict) {
+            (TraceType::Policy, _) => self.policy,
+            (TraceType::Rule, Some(Statement::Accept(_))) => Some(NfChainPolicy::Accept),
+            (TraceType::Rule, Some(Statement::Drop(_))) => Some(NfChainPolicy::Drop),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+/// Path of a single packet through the ruleset.
+pub struct TracePath<'a> {
+    /// Trace id of the packet.
+    pub id: u32,
+    /// Events of the packet in the order they were emitted.
+    pub events: Vec<Trace<'a>>,
+}
+
+impl<'a> TracePath<'a> {
+    /// The (table, chain) pairs traversed by the packet, without repetitions
+    /// of consecutive events in the same chain.
+    pub fn chains(&self) -> Vec<(&str, &str)> {
+        let mut chains: Vec<(&str, &str)> = Vec::new();
+        for event in &self.events {
+            let chain = (event.table.as_ref(), event.chain.as_ref());
+            if chains.last() != Some(&chain) {
+                chains.push(chain);
+            }
+        }
+        chains
+    }
+
+    /// The event that decided the packet's fate, i.e. the last one with a
+    /// [decision](Trace::decision).
+    pub fn decided_by(&self) -> Option<&Trace<'a>> {
+        self.events.iter().rev().find(|e| e.decision().is_some())
+    }
+
+    /// Whether the packet was accepted or dropped, if known.
+    pub fn decision(&self) -> Option<NfChainPolicy> {
+        self.decided_by().and_then(Trace::decision)
+    }
+}
+
+/// Groups trace events by [trace id](Trace::id).
+///
+/// Paths are returned in the order of their first event, and events keep
+/// their relative order within a path.
+pub fn group_traces<'a, I>(traces: I) -> Vec<TracePath<'a>>
+where
+    I: IntoIterator<Item = Trace<'a>>,
+{
+    let mut paths: Vec<TracePath<'a>> = Vec::new();
+    let mut index: HashMap<u32, usize> = HashMap::new();
+    for trace in traces {
+        match index.get(&trace.id) {
+            Some(&i) => paths[i].events.push(trace),
+            None => {
+                index.insert(trace.id, paths.len());
+                paths.push(TracePath {
+                    id: trace.id,
+                    events: vec![trace],
+                });
+            }
+        }
+    }
+    paths
+}
@@ -1,6 +1,9 @@
+use std::{fmt, str::FromStr};
+
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use strum_macros::EnumString;
+use thiserror::Error;
 
 /// Families in nftables.
 ///
@@ -121,3 +124,39 @@ pub enum NfTimeUnit {
     /// A week (604800 seconds).
     Week,
 }
+
+/// A MAC address, written as `00:11:22:aa:bb:cc` in nftables.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub struct MacAddr(pub [u8; 6]);
+
+impl fmt::Display for MacAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(f, "{a:02x}:{b:02x}:{c:02x}:{d:02x}:{e:02x}:{g:02x}")
+    }
+}
+
+/// Error returned when parsing a [MacAddr] fails.
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+#[error("invalid MAC address syntax")]
+pub struct MacAddrParseError;
+
+impl FromStr for MacAddr {
+    type Err = MacAddrParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut addr = [0; 6];
+        let mut parts = s.split(':');
+        for byte in &mut addr {
+            let part = parts.next().ok_or(MacAddrParseError)?;
+            if !(1..=2).contains(&part.len()) || !part.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(MacAddrParseError);
+            }
+            *byte = u8::from_str_radix(part, 16).map_err(|_| MacAddrParseError)?;
+        }
+        match parts.next() {
+            Some(_) => Err(MacAddrParseError),
+            None => Ok(MacAddr(addr)),
+        }
+    }
+}
@@ -0,0 +1,581 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use crate::diagnostic::Severity;
+use crate::expr::{BinaryOperation, Expression, NamedExpression, SetItem, Verdict};
+use crate::graph::CallGraph;
+use crate::schema::{Chain, NfCmd, NfListObject, NfObject, Nftables, Rule};
+use crate::stmt::{Counter, QuotaOrQuotaRef, Statement};
+use crate::types::{NfChainType, NfFamily, NfHook};
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+/// A problem found by [validate].
+pub enum Problem {
+    /// An object refers to a table the document does not declare.
+    UnknownTable { family: NfFamily, table: String },
+    /// A rule refers to a chain the document does not declare.
+    UnknownChain { table: String, chain: String },
+    /// A `jump` or `goto` targets a chain its table does not declare.
+    UnknownJumpTarget { chain: String },
+    /// A `jump` or `goto` targets a base chain.
+    JumpToBaseChain { chain: String },
+    /// An `@name` reference or element names a set or map the table does not declare.
+    UnknownSet { name: String },
+    /// A `flow add @name` statement names a flowtable the table does not declare.
+    UnknownFlowtable { name: String },
+    /// A statement refers to a named stateful object (e.g. `"counter"`) the
+    /// table does not declare.
+    UnknownObject { kind: &'static str, name: String },
+    /// A base chain lacks its type, hook, priority or device.
+    IncompleteBaseChain {
+        chain: String,
+        missing: &'static str,
+    },
+    /// The chain type does not exist in the table family.
+    UnsupportedChainType {
+        family: NfFamily,
+        chain_type: NfChainType,
+    },
+    /// The chain type cannot be attached to the hook in the table family.
+    UnsupportedHook {
+        family: NfFamily,
+        chain_type: NfChainType,
+        hook: NfHook,
+    },
+    /// A statement cannot be used in a base chain of this type and hook.
+    StatementNotAllowed {
+        statement: &'static str,
+        chain_type: NfChainType,
+        hook: NfHook,
+    },
+    /// A regular chain sets a policy, which nft ignores.
+    PolicyIgnored { chain: String },
+    /// A statement follows a verdict that always ends the rule.
+    StatementAfterVerdict,
+    /// Chains jump to each other in a loop, which nft rejects.
+    Loop { chains: Vec<String> },
+}
+
+/// Lowercase name of a fieldless enum variant, as nft writes it.
+pub(crate) fn lower(value: &impl fmt::Debug) -> String {
+    format!("{value:?}").to_lowercase()
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::UnknownTable { family, table } => {
+                write!(f, "unknown table `{} {table}`", lower(family))
+            }
+            Problem::UnknownChain { table, chain } => {
+                write!(f, "unknown chain `{chain}` in table `{table}`")
+            }
+            Problem::UnknownJumpTarget { chain } => write!(f, "jump to unknown chain `{chain}`"),
+            Problem::JumpToBaseChain { chain } => write!(f, "jump to base chain `{chain}`"),
+            Problem::UnknownSet { name } => write!(f, "unknown set or map `{name}`"),
+            Problem::UnknownFlowtable { name } => write!(f, "unknown flowtable `{name}`"),
+            Problem::UnknownObject { kind, name } => write!(f, "unknown {kind} `{name}`"),
+            Problem::IncompleteBaseChain { chain, missing } => {
+                write!(f, "base chain `{chain}` is missing its {missing}")
+            }
+            Problem::UnsupportedChainType { family, chain_type } => write!(
+                f,
+                "chain type {} is not supported in family {}",
+                lower(chain_type),
+                lower(family)
+            ),
+            Problem::UnsupportedHook {
+                family,
+                chain_type,
+                hook,
+            } => write!(
+                f,
+                "{} chains in family {} cannot use hook {}",
+                lower(chain_type),
+                lower(family),
+                lower(hook)
+            ),
+            Problem::StatementNotAllowed {
+                statement,
+                chain_type,
+                hook,
+            } => write!(
+                f,
+                "`{statement}` is not allowed in {} chains at hook {}",
+                lower(chain_type),
+                lower(hook)
+            ),
+            Problem::PolicyIgnored { chain } => {
+                write!(f, "policy of regular chain `{chain}` is ignored")
+            }
+            Problem::StatementAfterVerdict => write!(f, "statement after verdict has no effect"),
+            Problem::Loop { chains } => write!(f, "loop between chains `{}`", chains.join("`, `")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+/// A [problem](Problem) with an object of a document, as found by [validate].
+pub struct Diagnostic {
+    /// Whether nft would reject the document or merely behave unexpectedly.
+    pub severity: Severity,
+    /// Index of the offending object in [objects](Nftables::objects).
+    pub index: usize,
+    /// What is wrong.
+    pub problem: Problem,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "object {}: {severity}: {}", self.index, self.problem)
+    }
+}
+
+/// Names declared in a table.
+#[derive(Default)]
+struct Declarations<'s> {
+    /// Whether the document declares the table itself.
+    declared: bool,
+    /// Chains with their type and hook, if they are base chains.
+    chains: HashMap<&'s str, Option<(NfChainType, NfHook)>>,
+    /// Sets, maps (as `"set"`), flowtables and named stateful objects by kind.
+    names: HashSet<(&'static str, &'s str)>,
+}
+
+/// Hooks the chain type can attach to in the family, empty if the family
+/// does not support the chain type.
+fn hooks(family: NfFamily, chain_type: NfChainType) -> &'static [NfHook] {
+    use NfHook::*;
+    match (chain_type, family) {
+        (NfChainType::Filter, NfFamily::IP | NfFamily::IP6 | NfFamily::Bridge) => {
+            &[Prerouting, Input, Forward, Output, Postrouting]
+        }
+        (NfChainType::Filter, NfFamily::INet) => {
+            &[Ingress, Prerouting, Input, Forward, Output, Postrouting]
+        }
+        (NfChainType::Filter, NfFamily::ARP) => &[Input, Output],
+        (NfChainType::Filter, NfFamily::NetDev) => &[Ingress, Egress],
+        (NfChainType::NAT, NfFamily::IP | NfFamily::IP6 | NfFamily::INet) => {
+            &[Prerouting, Input, Output, Postrouting]
+        }
+        (NfChainType::Route, NfFamily::IP | NfFamily::IP6 | NfFamily::INet) => &[Output],
+        _ => &[],
+    }
+}
+
+/// Name of a NAT statement and the hooks of nat chains it may be used at.
+fn nat_hooks(stmt: &Statement) -> Option<(&'static str, &'static [NfHook])> {
+    match stmt {
+        Statement::SNAT(_) => Some(("snat", &[NfHook::Postrouting, NfHook::Input])),
+        Statement::Masquerade(_) => Some(("masquerade", &[NfHook::Postrouting])),
+        Statement::DNAT(_) => Some(("dnat", &[NfHook::Prerouting, NfHook::Output])),
+        Statement::Redirect(_) => Some(("redirect", &[NfHook::Prerouting, NfHook::Output])),
+        _ => None,
+    }
+}
+
+/// Collects the tables of a document with the names declared in them.
+fn declarations<'s>(nftables: &'s Nftables) -> HashMap<(NfFamily, &'s str), Declarations<'s>> {
+    let mut tables: HashMap<_, Declarations> = HashMap::new();
+    for object in nftables.objects.iter() {
+        let object = match object {
+            NfObject::ListObject(object)
+            | NfObject::CmdObject(
+                NfCmd::Add(object) | NfCmd::Create(object) | NfCmd::Insert(object),
+            ) => object,
+            _ => continue,
+        };
+        let (family, table, name, kind) = match object {
+            NfListObject::Table(table) => {
+                tables
+                    .entry((table.family, &*table.name))
+                    .or_default()
+                    .declared = true;
+                continue;
+            }
+            NfListObject::Chain(chain) => {
+                tables
+                    .entry((chain.family, &*chain.table))
+                    .or_default()
+                    .chains
+                    .insert(&chain.name, chain._type.zip(chain.hook));
+                continue;
+            }
+            NfListObject::Set(o) => (o.family, &o.table, &o.name, "set"),
+            NfListObject::Map(o) => (o.family, &o.table, &o.name, "set"),
+            NfListObject::FlowTable(o) => (o.family, &o.table, &o.name, "flowtable"),
+            NfListObject::Counter(o) => (o.family, &o.table, &o.name, "counter"),
+            NfListObject::Quota(o) => (o.family, &o.table, &o.name, "quota"),
+            NfListObject::CTHelper(o) => (o.family, &o.table, &o.name, "ct helper"),
+            NfListObject::Limit(o) => (o.family, &o.table, &o.name, "limit"),
+            NfListObject::CTTimeout(o) => (o.family, &o.table, &o.name, "ct timeout"),
+            NfListObject::CTExpectation(o) => (o.family, &o.table, &o.name, "ct expectation"),
+            NfListObject::SynProxy(o) => (o.family, &o.table, &o.name, "synproxy"),
+            _ => continue,
+        };
+        tables
+            .entry((family, &**table))
+            .or_default()
+            .names
+            .insert((kind, &**name));
+    }
+    tables
+}
+
+/// Checks a document offline for mistakes nft would only report by
+/// rejecting the whole batch.
+///
+/// The document is expected to be complete, e.g. the output of
+/// `nft -j list ruleset` or a batch that creates everything it uses: objects
+/// and rules must refer to tables, chains, sets, maps, flowtables and named
+/// stateful objects the document declares, and jumps must target regular
+/// chains of the same table without forming a loop (see
+/// [CallGraph::cycles]). Base chains are checked for a complete and
+/// supported combination of family, chain type and hook, and NAT statements
+/// for being used in nat base chains at a suitable hook.
+///
+/// Named limits cannot be referenced by [statements](Statement) and are not
+/// checked.
+pub fn validate(nftables: &Nftables) -> Vec<Diagnostic> {
+    let tables = declarations(nftables);
+    let mut validator = Validator {
+        tables: &tables,
+        index: 0,
+        diagnostics: Vec::new(),
+    };
+    for (index, object) in nftables.objects.iter().enumerate() {
+        validator.index = index;
+        match object {
+            NfObject::ListObject(object)
+            | NfObject::CmdObject(
+                NfCmd::Add(object) | NfCmd::Create(object) | NfCmd::Insert(object),
+            ) => validator.object(object),
+            NfObject::CmdObject(NfCmd::Replace(rule)) => validator.rule(rule),
+            _ => {}
+        }
+    }
+    let graph = CallGraph::new(nftables);
+    for cycle in graph.cycles() {
+        let chains = cycle.iter().map(|&c| &graph.chains[c]);
+        // Jumps to base chains are reported on their own.
+        if chains.clone().any(|c| c.hook.is_some()) {
+            continue;
+        }
+        validator.index = chains.clone().map(|c| c.index).min().unwrap_or_default();
+        let chains = chains.map(|c| c.name.to_string()).collect();
+        validator.report(Severity::Error, Problem::Loop { chains });
+    }
+    validator.diagnostics.sort_by_key(|d| d.index);
+    validator.diagnostics
+}
+
+struct Validator<'s> {
+    tables: &'s HashMap<(NfFamily, &'s str), Declarations<'s>>,
+    /// Index of the object being checked.
+    index: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'s> Validator<'s> {
+    fn report(&mut self, severity: Severity, problem: Problem) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            index: self.index,
+            problem,
+        });
+    }
+
+    /// Returns the declarations of a table the document declares.
+    fn table(&mut self, family: NfFamily, table: &'s str) -> Option<&'s Declarations<'s>> {
+        let tables = self.tables;
+        match tables.get(&(family, table)) {
+            Some(declarations) if declarations.declared => Some(declarations),
+            _ => {
+                self.report(
+                    Severity::Error,
+                    Problem::UnknownTable {
+                        family,
+                        table: table.to_string(),
+                    },
+                );
+                None
+            }
+        }
+    }
+
+    fn object(&mut self, object: &'s NfListObject) {
+        let (family, table) = match object {
+            NfListObject::Table(_) | NfListObject::MetainfoObject(_) => return,
+            NfListObject::Chain(chain) => return self.chain(chain),
+            NfListObject::Rule(rule) => return self.rule(rule),
+            NfListObject::Set(set) => {
+                if let Some(table) = self.table(set.family, &set.table) {
+                    set.elem
+                        .iter()
+                        .flat_map(|elem| elem.iter())
+                        .for_each(|e| self.expression(table, e));
+                }
+                return;
+            }
+            NfListObject::Map(map) => {
+                if let Some(table) = self.table(map.family, &map.table) {
+                    map.elem
+                        .iter()
+                        .flat_map(|elem| elem.iter())
+                        .for_each(|e| self.expression(table, e));
+                }
+                return;
+            }
+            NfListObject::Element(element) => {
+                if let Some(table) = self.table(element.family, &element.table) {
+                    self.name(table, "set", &element.name);
+                    element.elem.iter().for_each(|e| self.expression(table, e));
+                }
+                return;
+            }
+            NfListObject::FlowTable(o) => (o.family, &o.table),
+            NfListObject::Counter(o) => (o.family, &o.table),
+            NfListObject::Quota(o) => (o.family, &o.table),
+            NfListObject::CTHelper(o) => (o.family, &o.table),
+            NfListObject::Limit(o) => (o.family, &o.table),
+            NfListObject::CTTimeout(o) => (o.family, &o.table),
+            NfListObject::CTExpectation(o) => (o.family, &o.table),
+            NfListObject::SynProxy(o) => (o.family, &o.table),
+        };
+        self.table(family, table);
+    }
+
+    fn chain(&mut self, chain: &'s Chain) {
+        if self.table(chain.family, &chain.table).is_none() {
+            return;
+        }
+        let name = || chain.name.to_string();
+        if chain._type.is_none() && chain.hook.is_none() && chain.prio.is_none() {
+            if chain.policy.is_some() {
+                self.report(Severity::Warning, Problem::PolicyIgnored { chain: name() });
+            }
+            return;
+        }
+        let (Some(chain_type), Some(hook), Some(_)) = (chain._type, chain.hook, chain.prio) else {
+            let missing = match (chain._type, chain.hook) {
+                (None, _) => "type",
+                (_, None) => "hook",
+                _ => "priority",
+            };
+            let problem = Problem::IncompleteBaseChain {
+                chain: name(),
+                missing,
+            };
+            return self.report(Severity::Error, problem);
+        };
+        let hooks = hooks(chain.family, chain_type);
+        let problem = if hooks.is_empty() {
+            Problem::UnsupportedChainType {
+                family: chain.family,
+                chain_type,
+            }
+        } else if !hooks.contains(&hook) {
+            Problem::UnsupportedHook {
+                family: chain.family,
+                chain_type,
+                hook,
+            }
+        } else if chain.dev.as_ref().map_or(true, |dev| dev.is_empty())
+            && (chain.family == NfFamily::NetDev || hook == NfHook::Ingress)
+        {
+            Problem::IncompleteBaseChain {
+                chain: name(),
+                missing: "device",
+            }
+        } else {
+            return;
+        };
+        self.report(Severity::Error, problem);
+    }
+
+    fn rule(&mut self, rule: &'s Rule) {
+        let Some(table) = self.table(rule.family, &rule.table) else {
+            return;
+        };
+        let Some(base) = table.chains.get(&*rule.chain) else {
+            let problem = Problem::UnknownChain {
+                table: rule.table.to_string(),
+                chain: rule.chain.to_string(),
+            };
+            return self.report(Severity::Error, problem);
+        };
+        let mut verdict = false;
+        for stmt in rule.expr.iter() {
+            if verdict {
+                self.report(Severity::Error, Problem::StatementAfterVerdict);
+                break;
+            }
+            if let (Some((statement, allowed)), Some((chain_type, hook))) = (nat_hooks(stmt), base)
+            {
+                if *chain_type != NfChainType::NAT || !allowed.contains(hook) {
+                    let problem = Problem::StatementNotAllowed {
+                        statement,
+                        chain_type: *chain_type,
+                        hook: *hook,
+                    };
+                    self.report(Severity::Error, problem);
+                }
+            }
+            self.statement(table, stmt);
+            verdict = matches!(
+                stmt,
+                Statement::Accept(_)
+                    | Statement::Drop(_)
+                    | Statement::Continue(_)
+                    | Statement::Return(_)
+                    | Statement::Jump(_)
+                    | Statement::Goto(_)
+                    | Statement::Reject(_)
+            );
+        }
+    }
+
+    fn statement(&mut self, table: &'s Declarations<'s>, stmt: &Statement) {
+        match stmt {
+            Statement::Jump(target) | Statement::Goto(target) => self.jump(table, &target.target),
+            Statement::Match(m) => {
+                self.expression(table, &m.left);
+                self.expression(table, &m.right);
+            }
+            Statement::Mangle(m) => {
+                self.expression(table, &m.key);
+                self.expression(table, &m.value);
+            }
+            Statement::Counter(Counter::Named(name)) => self.name(table, "counter", name),
+            Statement::Quota(QuotaOrQuotaRef::QuotaRef(name)) => self.name(table, "quota", name),
+            Statement::CTHelper(name) => self.name(table, "ct helper", name),
+            Statement::CTTimeout(Expression::String(name)) => self.name(table, "ct timeout", name),
+            Statement::CTExpectation(Expression::String(name)) => {
+                self.name(table, "ct expectation", name)
+            }
+            Statement::Flow(flow) => {
+                let name = flow.flowtable.strip_prefix('@').unwrap_or(&flow.flowtable);
+                self.name(table, "flowtable", name);
+            }
+            Statement::Set(set) => {
+                self.name(table, "set", set.set.strip_prefix('@').unwrap_or(&set.set));
+                self.expression(table, &set.elem);
+            }
+            Statement::VerdictMap(vmap) => {
+                self.expression(table, &vmap.key);
+                self.expression(table, &vmap.data);
+            }
+            Statement::Meter(meter) => {
+                self.expression(table, &meter.key);
+                self.statement(table, &meter.stmt);
+            }
+            Statement::SNAT(Some(nat))
+            | Statement::DNAT(Some(nat))
+            | Statement::Masquerade(Some(nat))
+            | Statement::Redirect(Some(nat)) => {
+                nat.addr
+                    .iter()
+                    .chain(&nat.port)
+                    .for_each(|e| self.expression(table, e));
+            }
+            Statement::Dup(dup) => {
+                self.expression(table, &dup.addr);
+                dup.dev.iter().for_each(|e| self.expression(table, e));
+            }
+            Statement::FWD(Some(fwd)) => {
+                fwd.dev
+                    .iter()
+                    .chain(&fwd.addr)
+                    .for_each(|e| self.expression(table, e));
+            }
+            Statement::Queue(queue) => self.expression(table, &queue.num),
+            Statement::CTCount(count) => self.expression(table, &count.val),
+            _ => {}
+        }
+    }
+
+    fn expression(&mut self, table: &'s Declarations<'s>, expr: &Expression) {
+        match expr {
+            Expression::String(s) => {
+                if let Some(name) = s.strip_prefix('@') {
+                    self.name(table, "set", name);
+                }
+            }
+            Expression::List(items) => items.iter().for_each(|e| self.expression(table, e)),
+            Expression::BinaryOperation(op) => match &**op {
+                BinaryOperation::AND(a, b)
+                | BinaryOperation::XOR(a, b)
+                | BinaryOperation::LSHIFT(a, b)
+                | BinaryOperation::RSHIFT(a, b) => {
+                    self.expression(table, a);
+                    self.expression(table, b);
+                }
+                BinaryOperation::OR(items) => items.iter().for_each(|e| self.expression(table, e)),
+            },
+            Expression::Range(range) => range.range.iter().for_each(|e| self.expression(table, e)),
+            Expression::Named(named) => match named {
+                NamedExpression::Concat(items) => {
+                    items.iter().for_each(|e| self.expression(table, e))
+                }
+                NamedExpression::Set(items) => {
+                    for item in items {
+                        match item {
+                            SetItem::Element(e) => self.expression(table, e),
+                            SetItem::Mapping(key, value) => {
+                                self.expression(table, key);
+                                self.expression(table, value);
+                            }
+                            SetItem::MappingStatement(key, stmt) => {
+                                self.expression(table, key);
+                                self.statement(table, stmt);
+                            }
+                        }
+                    }
+                }
+                NamedExpression::Map(map) => {
+                    self.expression(table, &map.key);
+                    self.expression(table, &map.data);
+                }
+                NamedExpression::Prefix(prefix) => self.expression(table, &prefix.addr),
+                NamedExpression::Elem(elem) => self.expression(table, &elem.val),
+                _ => {}
+            },
+            Expression::Verdict(Verdict::Jump(target) | Verdict::Goto(target)) => {
+                self.jump(table, &target.target)
+            }
+            _ => {}
+        }
+    }
+
+    fn jump(&mut self, table: &Declarations, target: &str) {
+        let problem = match table.chains.get(target) {
+            None => Problem::UnknownJumpTarget {
+                chain: target.to_string(),
+            },
+            Some(Some(_)) => Problem::JumpToBaseChain {
+                chain: target.to_string(),
+            },
+            Some(None) => return,
+        };
+        self.report(Severity::Error, problem);
+    }
+
+    fn name(&mut self, table: &Declarations, kind: &'static str, name: &str) {
+        if table.names.contains(&(kind, name)) {
+            return;
+        }
+        let name = name.to_string();
+        let problem = match kind {
+            "set" => Problem::UnknownSet { name },
+            "flowtable" => Problem::UnknownFlowtable { name },
+            kind => Problem::UnknownObject { kind, name },
+        };
+        self.report(Severity::Error, problem);
+    }
+}
@@ -0,0 +1,531 @@
+use crate::expr::{BinaryOperation, Expression, NamedExpression, SetItem, Verdict};
+use crate::schema::{
+    CTExpectation, CTHelper, CTTimeout, Chain, Counter, Element, FlowTable, FlushObject, Limit,
+    ListObject, Map, MetainfoObject, NfCmd, NfListObject, NfObject, Nftables, Quota, ResetObject,
+    Rule, Set, SetTypeValue, SynProxy, Table,
+};
+use crate::stmt::{JumpTarget, Match, Statement, NAT};
+
+/// Walks a document by reference, in the style of `syn::visit`.
+///
+/// Each method visits one type of node. Its default implementation calls
+/// the function of the same name in this module, which visits the children of
+/// the node. Override the methods of the nodes of interest and call the
+/// function to keep walking into their children.
+///
+/// ```
+/// use std::collections::BTreeSet;
+///
+/// use nftables::{expr::Expression, parser::parse, visit::{self, Visit}};
+///
+/// /// Collects the names of the sets referenced by `@name`.
+/// #[derive(Default)]
+/// struct SetNames<'ast>(BTreeSet<&'ast str>);
+///
+/// impl<'ast, 'a> Visit<'ast, 'a> for SetNames<'ast> {
+///     fn visit_expression(&mut self, node: &'ast Expression<'a>) {
+///         if let Expression::String(s) = node {
+///             self.0.extend(s.strip_prefix('@'));
+///         }
+///         visit::visit_expression(self, node);
+///     }
+/// }
+///
+/// let ruleset = parse("add rule inet filter input ip saddr @blocked tcp dport @ports drop").unwrap();
+/// let mut names = SetNames::default();
+/// names.visit_nftables(&ruleset);
+/// assert_eq!(names.0, BTreeSet::from(["blocked", "ports"]));
+/// ```
+pub trait Visit<'ast, 'a> {
+    fn visit_nftables(&mut self, node: &'ast Nftables<'a>) {
+        visit_nftables(self, node);
+    }
+
+    fn visit_object(&mut self, node: &'ast NfObject<'a>) {
+        visit_object(self, node);
+    }
+
+    fn visit_cmd(&mut self, node: &'ast NfCmd<'a>) {
+        visit_cmd(self, node);
+    }
+
+    fn visit_list_object(&mut self, node: &'ast NfListObject<'a>) {
+        visit_list_object(self, node);
+    }
+
+    fn visit_reset_object(&mut self, node: &'ast ResetObject<'a>) {
+        visit_reset_object(self, node);
+    }
+
+    fn visit_flush_object(&mut self, node: &'ast FlushObject<'a>) {
+        visit_flush_object(self, node);
+    }
+
+    fn visit_table(&mut self, node: &'ast Table<'a>) {
+        visit_table(self, node);
+    }
+
+    fn visit_chain(&mut self, node: &'ast Chain<'a>) {
+        visit_chain(self, node);
+    }
+
+    fn visit_rule(&mut self, node: &'ast Rule<'a>) {
+        visit_rule(self, node);
+    }
+
+    fn visit_set(&mut self, node: &'ast Set<'a>) {
+        visit_set(self, node);
+    }
+
+    fn visit_map(&mut self, node: &'ast Map<'a>) {
+        visit_map(self, node);
+    }
+
+    fn visit_element(&mut self, node: &'ast Element<'a>) {
+        visit_element(self, node);
+    }
+
+    fn visit_flowtable(&mut self, node: &'ast FlowTable<'a>) {
+        visit_flowtable(self, node);
+    }
+
+    /// Visits a named counter object, not a [counter statement](Statement::Counter).
+    fn visit_counter(&mut self, node: &'ast Counter<'a>) {
+        visit_counter(self, node);
+    }
+
+    /// Visits a named quota object, not a [quota statement](Statement::Quota).
+    fn visit_quota(&mut self, node: &'ast Quota<'a>) {
+        visit_quota(self, node);
+    }
+
+    fn visit_ct_helper(&mut self, node: &'ast CTHelper<'a>) {
+        visit_ct_helper(self, node);
+    }
+
+    /// Visits a named limit object, not a [limit statement](Statement::Limit).
+    fn visit_limit(&mut self, node: &'ast Limit<'a>) {
+        visit_limit(self, node);
+    }
+
+    fn visit_metainfo(&mut self, node: &'ast MetainfoObject<'a>) {
+        visit_metainfo(self, node);
+    }
+
+    fn visit_ct_timeout(&mut self, node: &'ast CTTimeout<'a>) {
+        visit_ct_timeout(self, node);
+    }
+
+    fn visit_ct_expectation(&mut self, node: &'ast CTExpectation<'a>) {
+        visit_ct_expectation(self, node);
+    }
+
+    /// Visits a named synproxy object, not a [synproxy statement](Statement::SynProxy).
+    fn visit_synproxy(&mut self, node: &'ast SynProxy<'a>) {
+        visit_synproxy(self, node);
+    }
+
+    fn visit_statement(&mut self, node: &'ast Statement<'a>) {
+        visit_statement(self, node);
+    }
+
+    fn visit_match(&mut self, node: &'ast Match<'a>) {
+        visit_match(self, node);
+    }
+
+    /// Visits the target of a `jump` or `goto` statement or verdict.
+    fn visit_jump_target(&mut self, node: &'ast JumpTarget<'a>) {
+        visit_jump_target(self, node);
+    }
+
+    fn visit_expression(&mut self, node: &'ast Expression<'a>) {
+        visit_expression(self, node);
+    }
+
+    fn visit_named_expression(&mut self, node: &'ast NamedExpression<'a>) {
+        visit_named_expression(self, node);
+    }
+
+    fn visit_set_item(&mut self, node: &'ast SetItem<'a>) {
+        visit_set_item(self, node);
+    }
+
+    fn visit_binary_operation(&mut self, node: &'ast BinaryOperation<'a>) {
+        visit_binary_operation(self, node);
+    }
+}
+
+pub fn visit_nftables<'ast, 'a, V>(v: &mut V, node: &'ast Nftables<'a>)
+where
+    V: Visit<'ast, 'a> + ?Sized,
+{
+    for object in node.objects.iter() {
+        v.visit_object(object);
+    }
+}
+
+pub fn visit_object<'ast, 'a, V>(v: &mut V, node: &'ast NfObject<'a>)
+where
+    V: Visit<'ast, 'a> + ?Sized,
+{
+    match node {
+        NfObject::CmdObject(cmd) => v.visit_cmd(cmd),
+        NfObject::ListObject(object) => v.visit_list_object(object),
+    }
+}
+
+pub fn visit_cmd<'ast, 'a, V>(v: &mut V, node: &'ast NfCmd<'a>)
+where
+    V: Visit<'ast, 'a> + ?Sized,
+{
+    match node {
+        NfCmd::Add(object)
+        | NfCmd::Create(object)
+        | NfCmd::Insert(object)
+        | NfCmd::Delete(object)
+        | NfCmd::Destroy(object)
+        | NfCmd::List(ListObject::Object(object)) => v.visit_list_object(object),
+        NfCmd::List(_) => {}
+        NfCmd::Replace(rule) => v.visit_rule(rule),
+        NfCmd::Reset(object) => v.visit_reset_object(object),
+        NfCmd::Flush(object) => v.visit_flush_object(object),
+        NfCmd::Rename(chain) => v.visit_chain(chain),
+    }
+}
+
+pub fn visit_list_object<'ast, 'a, V>(v: &mut V, node: &'ast NfListObject<'a>)
+where
+    V: Visit<'ast, 'a> + ?Sized,
+{
+    match node {
+        NfListObject::Table(table) => v.visit_table(table),
+        NfListObject::Chain(chain) => v.visit_chain(chain),
+        NfListObject::Rule(rule) => v.visit_rule(rule),
+        NfListObject::Set(set) => v.visit_set(set),
+        NfListObject::Map(map) => v.visit_map(map),
+        NfListObject::Element(element) => v.visit_element(element),
+        NfListObject::FlowTable(flowtable) => v.visit_flowtable(flowtable),
+        NfListObject::Counter(counter) => v.visit_counter(counter),
+        NfListObject::Quota(quota) => v.visit_quota(quota),
+        NfListObject::CTHelper(helper) => v.visit_ct_helper(helper),
+        NfListObject::Limit(limit) => v.visit_limit(limit),
+        NfListObject::MetainfoObject(metainfo) => v.visit_metainfo(metainfo),
+        NfListObject::CTTimeout(timeout) => v.visit_ct_timeout(timeout),
+        NfListObject::CTExpectation(expectation) => v.visit_ct_expectation(expectation),
+        NfListObject::SynProxy(synproxy) => v.visit_synproxy(synproxy),
+    }
+}
+
+pub fn visit_reset_object<'ast, 'a, V>(v: &mut V, node: &'ast ResetObject<'a>)
+where
+    V: Visit<'ast, 'a> + ?Sized,
+{
+    match node {
+        ResetObject::Counter(counter) => v.visit_counter(counter),
+        ResetObject::Counters(counters) => {
+            for counter in counters.iter() {
+                v.visit_counter(counter);
+            }
+        }
+        ResetObject::Quota(quota) => v.visit_quota(quota),
+        ResetObject::Quotas(quotas) => {
+            for quota in quotas.iter() {
+                v.visit_quota(quota);
+            }
+        }
+        ResetObject::Rule(rule) => v.visit_rule(rule),
+        ResetObject::Rules(_) => {}
+        ResetObject::Set(set) => v.visit_set(set),
+        ResetObject::Map(map) => v.visit_map(map),
+        ResetObject::Element(element) => v.visit_element(element),
+        ResetObject::Table(table) => v.visit_table(table),
+    }
+}
+
+pub fn visit_flush_object<'ast, 'a, V>(v: &mut V, node: &'ast FlushObject<'a>)
+where
+    V: Visit<'ast, 'a> + ?Sized,
+{
+    match node {
+        FlushObject::Table(table) => v.visit_table(table),
+        FlushObject::Chain(chain) => v.visit_chain(chain),
+        FlushObject::Set(set) => v.visit_set(set),
+        FlushObject::Map(map) => v.visit_map(map),
+        FlushObject::Meter(meter) => {
+            v.visit_expression(&meter.key);
+            v.visit_statement(&meter.stmt);
+        }
+        FlushObject::Ruleset(_) => {}
+    }
+}
+
+pub fn visit_table<'ast, 'a, V>(_v: &mut V, _node: &'ast Table<'a>)
+where
+    V: Visit<'ast, 'a> + ?Sized,
+{
+}
+
+pub fn visit_chain<'ast, 'a, V>(_v: &mut V, _node: &'ast Chain<'a>)
+where
+    V: Visit<'ast, 'a> + ?Sized,
+{
+}
+
+pub fn visit_rule<'ast, 'a, V>(v: &mut V, node: &'ast Rule<'a>)
+where
+    V: Visit<'ast, 'a> + ?Sized,
+{
+    for stmt in node.expr.iter() {
+        v.visit_statement(stmt);
+    }
+}
+
+pub fn visit_set<'ast, 'a, V>(v: &mut V, node: &'ast Set<'a>)
+where
+    V: Visit<'ast, 'a> + ?Sized,
+{
+    visit_set_type(v, &node.set_type);
+    for elem in node.elem.iter().flat_map(|elem| elem.iter()) {
+        v.visit_expression(elem);
+    }
+}
+
+pub fn visit_map<'ast, 'a, V>(v: &mut V, node: &'ast Map<'a>)
+where
+    V: Visit<'ast, 'a> + ?Sized,
+{
+    visit_set_type(v, &node.set_type);
+    visit_set_type(v, &node.map);
+    for elem in node.elem.iter().flat_map(|elem| elem.iter()) {
+        v.visit_expression(elem);
+    }
+}
+
+/// Visits the expression of a `typeof` set type.
+fn visit_set_type<'ast, 'a, V>(v: &mut V, node: &'ast SetTypeValue<'a>)
+where
+    V: Visit<'ast, 'a> + ?Sized,
+{
+    if let SetTypeValue::Typeof(expr) = node {
+        v.visit_expression(expr);
+    }
+}
+
+pub fn visit_element<'ast, 'a, V>(v: &mut V, node: &'ast Element<'a>)
+where
+    V: Visit<'ast, 'a> + ?Sized,
+{
+    for elem in node.elem.iter() {
+        v.visit_expression(elem);
+    }
+}
+
+pub fn visit_flowtable<'ast, 'a, V>(_v: &mut V, _node: &'ast FlowTable<'a>)
+where
+    V: Visit<'ast, 'a> + ?Sized,
+{
+}
+
+pub fn visit_counter<'ast, 'a, V>(_v: &mut V, _node: &'ast Counter<'a>)
+where
+    V: Visit<'ast, 'a> + ?Sized,
+{
+}
+
+pub fn visit_quota<'ast, 'a, V>(_v: &mut V, _node: &'ast Quota<'a>)
+where
+    V: Visit<'ast, 'a> + ?Sized,
+{
+}
+
+pub fn visit_ct_helper<'ast, 'a, V>(_v: &mut V, _node: &'ast CTHelper<'a>)
+where
+    V: Visit<'ast, 'a> + ?Sized,
+{
+}
+
+pub fn visit_limit<'ast, 'a, V>(_v: &mut V, _node: &'ast Limit<'a>)
+where
+    V: Visit<'ast, 'a> + ?Sized,
+{
+}
+
+pub fn visit_metainfo<'ast, 'a, V>(_v: &mut V, _node: &'ast MetainfoObject<'a>)
+where
+    V: Visit<'ast, 'a> + ?Sized,
+{
+}
+
+pub fn visit_ct_timeout<'ast, 'a, V>(_v: &mut V, _node: &'ast CTTimeout<'a>)
+where
+    V: Visit<'ast, 'a> + ?Sized,
+{
+}
+
+pub fn visit_ct_expectation<'ast, 'a, V>(_v: &mut V, _node: &'ast CTExpectation<'a>)
+where
+    V: Visit<'ast, 'a> + ?Sized,
+{
+}
+
+pub fn visit_synproxy<'ast, 'a, V>(_v: &mut V, _node: &'ast SynProxy<'a>)
+where
+    V: Visit<'ast, 'a> + ?Sized,
+{
+}
+
+pub fn visit_statement<'ast, 'a, V>(v: &mut V, node: &'ast Statement<'a>)
+where
+    V: Visit<'ast, 'a> + ?Sized,
+{
+    match node {
+        Statement::Jump(target) | Statement::Goto(target) => v.visit_jump_target(target),
+        Statement::Match(m) => v.visit_match(m),
+        Statement::Mangle(mangle) => {
+            v.visit_expression(&mangle.key);
+            v.visit_expression(&mangle.value);
+        }
+        Statement::FWD(Some(fwd)) => {
+            for expr in [&fwd.dev, &fwd.addr].into_iter().flatten() {
+                v.visit_expression(expr);
+            }
+        }
+        Statement::Dup(dup) => {
+            v.visit_expression(&dup.addr);
+            if let Some(dev) = &dup.dev {
+                v.visit_expression(dev);
+            }
+        }
+        Statement::SNAT(Some(nat))
+        | Statement::DNAT(Some(nat))
+        | Statement::Masquerade(Some(nat))
+        | Statement::Redirect(Some(nat)) => visit_nat(v, nat),
+        Statement::Set(set) => v.visit_expression(&set.elem),
+        Statement::Meter(meter) => {
+            v.visit_expression(&meter.key);
+            v.visit_statement(&meter.stmt);
+        }
+        Statement::Queue(queue) => v.visit_expression(&queue.num),
+        Statement::VerdictMap(vmap) => {
+            v.visit_expression(&vmap.key);
+            v.visit_expression(&vmap.data);
+        }
+        Statement::CTCount(count) => v.visit_expression(&count.val),
+        Statement::CTTimeout(expr) | Statement::CTExpectation(expr) => v.visit_expression(expr),
+        _ => {}
+    }
+}
+
+fn visit_nat<'ast, 'a, V>(v: &mut V, node: &'ast NAT<'a>)
+where
+    V: Visit<'ast, 'a> + ?Sized,
+{
+    for expr in [&node.addr, &node.port].into_iter().flatten() {
+        v.visit_expression(expr);
+    }
+}
+
+pub fn visit_match<'ast, 'a, V>(v: &mut V, node: &'ast Match<'a>)
+where
+    V: Visit<'ast, 'a> + ?Sized,
+{
+    v.visit_expression(&node.left);
+    v.visit_expression(&node.right);
+}
+
+pub fn visit_jump_target<'ast, 'a, V>(_v: &mut V, _node: &'ast JumpTarget<'a>)
+where
+    V: Visit<'ast, 'a> + ?Sized,
+{
+}
+
+pub fn visit_expression<'ast, 'a, V>(v: &mut V, node: &'ast Expression<'a>)
+where
+    V: Visit<'ast, 'a> + ?Sized,
+{
+    match node {
+        Expression::List(exprs) => {
+            for expr in exprs {
+                v.visit_expression(expr);
+            }
+        }
+        Expression::BinaryOperation(op) => v.visit_binary_operation(op),
+        Expression::Range(range) => {
+            for expr in &range.range {
+                v.visit_expression(expr);
+            }
+        }
+        Expression::Named(named) => v.visit_named_expression(named),
+        Expression::Verdict(Verdict::Jump(target) | Verdict::Goto(target)) => {
+            v.visit_jump_target(target)
+        }
+        Expression::String(_)
+        | Expression::Number(_)
+        | Expression::SignedNumber(_)
+        | Expression::Boolean(_)
+        | Expression::Verdict(_) => {}
+    }
+}
+
+pub fn visit_named_expression<'ast, 'a, V>(v: &mut V, node: &'ast NamedExpression<'a>)
+where
+    V: Visit<'ast, 'a> + ?Sized,
+{
+    match node {
+        NamedExpression::Concat(exprs) => {
+            for expr in exprs {
+                v.visit_expression(expr);
+            }
+        }
+        NamedExpression::Set(items) => {
+            for item in items {
+                v.visit_set_item(item);
+            }
+        }
+        NamedExpression::Map(map) => {
+            v.visit_expression(&map.key);
+            v.visit_expression(&map.data);
+        }
+        NamedExpression::Prefix(prefix) => v.visit_expression(&prefix.addr),
+        NamedExpression::JHash(hash) => v.visit_expression(&hash.expr),
+        NamedExpression::Elem(elem) => v.visit_expression(&elem.val),
+        _ => {}
+    }
+}
+
+pub fn visit_set_item<'ast, 'a, V>(v: &mut V, node: &'ast SetItem<'a>)
+where
+    V: Visit<'ast, 'a> + ?Sized,
+{
+    match node {
+        SetItem::Element(expr) => v.visit_expression(expr),
+        SetItem::Mapping(key, value) => {
+            v.visit_expression(key);
+            v.visit_expression(value);
+        }
+        SetItem::MappingStatement(key, stmt) => {
+            v.visit_expression(key);
+            v.visit_statement(stmt);
+        }
+    }
+}
+
+pub fn visit_binary_operation<'ast, 'a, V>(v: &mut V, node: &'ast BinaryOperation<'a>)
+where
+    V: Visit<'ast, 'a> + ?Sized,
+{
+    match node {
+        BinaryOperation::AND(left, right)
+        | BinaryOperation::XOR(left, right)
+        | BinaryOperation::LSHIFT(left, right)
+        | BinaryOperation::RSHIFT(left, right) => {
+            v.visit_expression(left);
+            v.visit_expression(right);
+        }
+        BinaryOperation::OR(exprs) => {
+            for expr in exprs {
+                v.visit_expression(expr);
+            }
+        }
+    }
+}
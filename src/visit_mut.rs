@@ -0,0 +1,542 @@
+use crate::expr::{BinaryOperation, Expression, NamedExpression, SetItem, Verdict};
+use crate::schema::{
+    CTExpectation, CTHelper, CTTimeout, Chain, Counter, Element, FlowTable, FlushObject, Limit,
+    ListObject, Map, MetainfoObject, NfCmd, NfListObject, NfObject, Nftables, Quota, ResetObject,
+    Rule, Set, SetTypeValue, SynProxy, Table,
+};
+use crate::stmt::{JumpTarget, Match, Statement, NAT};
+
+/// Walks a document by mutable reference, in the style of `syn::visit_mut`.
+///
+/// Each method visits one type of node. Its default implementation calls
+/// the function of the same name in this module, which visits the children of
+/// the node. Override the methods of the nodes of interest and call the
+/// function to keep walking into their children.
+///
+/// Borrowed lists of objects, statements and elements are cloned when walked.
+///
+/// ```
+/// use nftables::{
+///     parser::parse,
+///     schema::{Rule, Table},
+///     visit_mut::{self, VisitMut},
+/// };
+///
+/// /// Renames the table `filter` to `firewall`.
+/// struct RenameTable;
+///
+/// impl<'a> VisitMut<'a> for RenameTable {
+///     fn visit_table_mut(&mut self, node: &mut Table<'a>) {
+///         if node.name == "filter" {
+///             node.name = "firewall".into();
+///         }
+///     }
+///
+///     fn visit_rule_mut(&mut self, node: &mut Rule<'a>) {
+///         if node.table == "filter" {
+///             node.table = "firewall".into();
+///         }
+///         visit_mut::visit_rule_mut(self, node);
+///     }
+/// }
+///
+/// let mut ruleset = parse("add table inet filter\nadd rule inet filter input accept").unwrap();
+/// RenameTable.visit_nftables_mut(&mut ruleset);
+/// assert_eq!(
+///     ruleset.to_string(),
+///     parse("add table inet firewall\nadd rule inet firewall input accept").unwrap().to_string()
+/// );
+/// ```
+pub trait VisitMut<'a> {
+    fn visit_nftables_mut(&mut self, node: &mut Nftables<'a>) {
+        visit_nftables_mut(self, node);
+    }
+
+    fn visit_object_mut(&mut self, node: &mut NfObject<'a>) {
+        visit_object_mut(self, node);
+    }
+
+    fn visit_cmd_mut(&mut self, node: &mut NfCmd<'a>) {
+        visit_cmd_mut(self, node);
+    }
+
+    fn visit_list_object_mut(&mut self, node: &mut NfListObject<'a>) {
+        visit_list_object_mut(self, node);
+    }
+
+    fn visit_reset_object_mut(&mut self, node: &mut ResetObject<'a>) {
+        visit_reset_object_mut(self, node);
+    }
+
+    fn visit_flush_object_mut(&mut self, node: &mut FlushObject<'a>) {
+        visit_flush_object_mut(self, node);
+    }
+
+    fn visit_table_mut(&mut self, node: &mut Table<'a>) {
+        visit_table_mut(self, node);
+    }
+
+    fn visit_chain_mut(&mut self, node: &mut Chain<'a>) {
+        visit_chain_mut(self, node);
+    }
+
+    fn visit_rule_mut(&mut self, node: &mut Rule<'a>) {
+        visit_rule_mut(self, node);
+    }
+
+    fn visit_set_mut(&mut self, node: &mut Set<'a>) {
+        visit_set_mut(self, node);
+    }
+
+    fn visit_map_mut(&mut self, node: &mut Map<'a>) {
+        visit_map_mut(self, node);
+    }
+
+    fn visit_element_mut(&mut self, node: &mut Element<'a>) {
+        visit_element_mut(self, node);
+    }
+
+    fn visit_flowtable_mut(&mut self, node: &mut FlowTable<'a>) {
+        visit_flowtable_mut(self, node);
+    }
+
+    /// Visits a named counter object, not a [counter statement](Statement::Counter).
+    fn visit_counter_mut(&mut self, node: &mut Counter<'a>) {
+        visit_counter_mut(self, node);
+    }
+
+    /// Visits a named quota object, not a [quota statement](Statement::Quota).
+    fn visit_quota_mut(&mut self, node: &mut Quota<'a>) {
+        visit_quota_mut(self, node);
+    }
+
+    fn visit_ct_helper_mut(&mut self, node: &mut CTHelper<'a>) {
+        visit_ct_helper_mut(self, node);
+    }
+
+    /// Visits a named limit object, not a [limit statement](Statement::Limit).
+    fn visit_limit_mut(&mut self, node: &mut Limit<'a>) {
+        visit_limit_mut(self, node);
+    }
+
+    fn visit_metainfo_mut(&mut self, node: &mut MetainfoObject<'a>) {
+        visit_metainfo_mut(self, node);
+    }
+
+    fn visit_ct_timeout_mut(&mut self, node: &mut CTTimeout<'a>) {
+        visit_ct_timeout_mut(self, node);
+    }
+
+    fn visit_ct_expectation_mut(&mut self, node: &mut CTExpectation<'a>) {
+        visit_ct_expectation_mut(self, node);
+    }
+
+    /// Visits a named synproxy object, not a [synproxy statement](Statement::SynProxy).
+    fn visit_synproxy_mut(&mut self, node: &mut SynProxy<'a>) {
+        visit_synproxy_mut(self, node);
+    }
+
+    fn visit_statement_mut(&mut self, node: &mut Statement<'a>) {
+        visit_statement_mut(self, node);
+    }
+
+    fn visit_match_mut(&mut self, node: &mut Match<'a>) {
+        visit_match_mut(self, node);
+    }
+
+    /// Visits the target of a `jump` or `goto` statement or verdict.
+    fn visit_jump_target_mut(&mut self, node: &mut JumpTarget<'a>) {
+        visit_jump_target_mut(self, node);
+    }
+
+    fn visit_expression_mut(&mut self, node: &mut Expression<'a>) {
+        visit_expression_mut(self, node);
+    }
+
+    fn visit_named_expression_mut(&mut self, node: &mut NamedExpression<'a>) {
+        visit_named_expression_mut(self, node);
+    }
+
+    fn visit_set_item_mut(&mut self, node: &mut SetItem<'a>) {
+        visit_set_item_mut(self, node);
+    }
+
+    fn visit_binary_operation_mut(&mut self, node: &mut BinaryOperation<'a>) {
+        visit_binary_operation_mut(self, node);
+    }
+}
+
+pub fn visit_nftables_mut<'a, V>(v: &mut V, node: &mut Nftables<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+    for object in node.objects.to_mut() {
+        v.visit_object_mut(object);
+    }
+}
+
+pub fn visit_object_mut<'a, V>(v: &mut V, node: &mut NfObject<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+    match node {
+        NfObject::CmdObject(cmd) => v.visit_cmd_mut(cmd),
+        NfObject::ListObject(object) => v.visit_list_object_mut(object),
+    }
+}
+
+pub fn visit_cmd_mut<'a, V>(v: &mut V, node: &mut NfCmd<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+    match node {
+        NfCmd::Add(object)
+        | NfCmd::Create(object)
+        | NfCmd::Insert(object)
+        | NfCmd::Delete(object)
+        | NfCmd::Destroy(object)
+        | NfCmd::List(ListObject::Object(object)) => v.visit_list_object_mut(object),
+        NfCmd::List(_) => {}
+        NfCmd::Replace(rule) => v.visit_rule_mut(rule),
+        NfCmd::Reset(object) => v.visit_reset_object_mut(object),
+        NfCmd::Flush(object) => v.visit_flush_object_mut(object),
+        NfCmd::Rename(chain) => v.visit_chain_mut(chain),
+    }
+}
+
+pub fn visit_list_object_mut<'a, V>(v: &mut V, node: &mut NfListObject<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+    match node {
+        NfListObject::Table(table) => v.visit_table_mut(table),
+        NfListObject::Chain(chain) => v.visit_chain_mut(chain),
+        NfListObject::Rule(rule) => v.visit_rule_mut(rule),
+        NfListObject::Set(set) => v.visit_set_mut(set),
+        NfListObject::Map(map) => v.visit_map_mut(map),
+        NfListObject::Element(element) => v.visit_element_mut(element),
+        NfListObject::FlowTable(flowtable) => v.visit_flowtable_mut(flowtable),
+        NfListObject::Counter(counter) => v.visit_counter_mut(counter),
+        NfListObject::Quota(quota) => v.visit_quota_mut(quota),
+        NfListObject::CTHelper(helper) => v.visit_ct_helper_mut(helper),
+        NfListObject::Limit(limit) => v.visit_limit_mut(limit),
+        NfListObject::MetainfoObject(metainfo) => v.visit_metainfo_mut(metainfo),
+        NfListObject::CTTimeout(timeout) => v.visit_ct_timeout_mut(timeout),
+        NfListObject::CTExpectation(expectation) => v.visit_ct_expectation_mut(expectation),
+        NfListObject::SynProxy(synproxy) => v.visit_synproxy_mut(synproxy),
+    }
+}
+
+pub fn visit_reset_object_mut<'a, V>(v: &mut V, node: &mut ResetObject<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+    match node {
+        ResetObject::Counter(counter) => v.visit_counter_mut(counter),
+        ResetObject::Counters(counters) => {
+            for counter in counters.to_mut() {
+                v.visit_counter_mut(counter);
+            }
+        }
+        ResetObject::Quota(quota) => v.visit_quota_mut(quota),
+        ResetObject::Quotas(quotas) => {
+            for quota in quotas.to_mut() {
+                v.visit_quota_mut(quota);
+            }
+        }
+        ResetObject::Rule(rule) => v.visit_rule_mut(rule),
+        ResetObject::Rules(_) => {}
+        ResetObject::Set(set) => v.visit_set_mut(set),
+        ResetObject::Map(map) => v.visit_map_mut(map),
+        ResetObject::Element(element) => v.visit_element_mut(element),
+        ResetObject::Table(table) => v.visit_table_mut(table),
+    }
+}
+
+pub fn visit_flush_object_mut<'a, V>(v: &mut V, node: &mut FlushObject<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+    match node {
+        FlushObject::Table(table) => v.visit_table_mut(table),
+        FlushObject::Chain(chain) => v.visit_chain_mut(chain),
+        FlushObject::Set(set) => v.visit_set_mut(set),
+        FlushObject::Map(map) => v.visit_map_mut(map),
+        FlushObject::Meter(meter) => {
+            v.visit_expression_mut(&mut meter.key);
+            v.visit_statement_mut(&mut meter.stmt);
+        }
+        FlushObject::Ruleset(_) => {}
+    }
+}
+
+pub fn visit_table_mut<'a, V>(_v: &mut V, _node: &mut Table<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+}
+
+pub fn visit_chain_mut<'a, V>(_v: &mut V, _node: &mut Chain<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+}
+
+pub fn visit_rule_mut<'a, V>(v: &mut V, node: &mut Rule<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+    for stmt in node.expr.to_mut() {
+        v.visit_statement_mut(stmt);
+    }
+}
+
+pub fn visit_set_mut<'a, V>(v: &mut V, node: &mut Set<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+    visit_set_type_mut(v, &mut node.set_type);
+    for elem in node.elem.iter_mut().flat_map(|elem| elem.to_mut()) {
+        v.visit_expression_mut(elem);
+    }
+}
+
+pub fn visit_map_mut<'a, V>(v: &mut V, node: &mut Map<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+    visit_set_type_mut(v, &mut node.set_type);
+    visit_set_type_mut(v, &mut node.map);
+    for elem in node.elem.iter_mut().flat_map(|elem| elem.to_mut()) {
+        v.visit_expression_mut(elem);
+    }
+}
+
+/// Visits the expression of a `typeof` set type.
+fn visit_set_type_mut<'a, V>(v: &mut V, node: &mut SetTypeValue<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+    if let SetTypeValue::Typeof(expr) = node {
+        v.visit_expression_mut(expr);
+    }
+}
+
+pub fn visit_element_mut<'a, V>(v: &mut V, node: &mut Element<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+    for elem in node.elem.to_mut() {
+        v.visit_expression_mut(elem);
+    }
+}
+
+pub fn visit_flowtable_mut<'a, V>(_v: &mut V, _node: &mut FlowTable<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+}
+
+pub fn visit_counter_mut<'a, V>(_v: &mut V, _node: &mut Counter<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+}
+
+pub fn visit_quota_mut<'a, V>(_v: &mut V, _node: &mut Quota<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+}
+
+pub fn visit_ct_helper_mut<'a, V>(_v: &mut V, _node: &mut CTHelper<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+}
+
+pub fn visit_limit_mut<'a, V>(_v: &mut V, _node: &mut Limit<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+}
+
+pub fn visit_metainfo_mut<'a, V>(_v: &mut V, _node: &mut MetainfoObject<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+}
+
+pub fn visit_ct_timeout_mut<'a, V>(_v: &mut V, _node: &mut CTTimeout<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+}
+
+pub fn visit_ct_expectation_mut<'a, V>(_v: &mut V, _node: &mut CTExpectation<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+}
+
+pub fn visit_synproxy_mut<'a, V>(_v: &mut V, _node: &mut SynProxy<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+}
+
+pub fn visit_statement_mut<'a, V>(v: &mut V, node: &mut Statement<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+    match node {
+        Statement::Jump(target) | Statement::Goto(target) => v.visit_jump_target_mut(target),
+        Statement::Match(m) => v.visit_match_mut(m),
+        Statement::Mangle(mangle) => {
+            v.visit_expression_mut(&mut mangle.key);
+            v.visit_expression_mut(&mut mangle.value);
+        }
+        Statement::FWD(Some(fwd)) => {
+            for expr in [&mut fwd.dev, &mut fwd.addr].into_iter().flatten() {
+                v.visit_expression_mut(expr);
+            }
+        }
+        Statement::Dup(dup) => {
+            v.visit_expression_mut(&mut dup.addr);
+            if let Some(dev) = &mut dup.dev {
+                v.visit_expression_mut(dev);
+            }
+        }
+        Statement::SNAT(Some(nat))
+        | Statement::DNAT(Some(nat))
+        | Statement::Masquerade(Some(nat))
+        | Statement::Redirect(Some(nat)) => visit_nat_mut(v, nat),
+        Statement::Set(set) => v.visit_expression_mut(&mut set.elem),
+        Statement::Meter(meter) => {
+            v.visit_expression_mut(&mut meter.key);
+            v.visit_statement_mut(&mut meter.stmt);
+        }
+        Statement::Queue(queue) => v.visit_expression_mut(&mut queue.num),
+        Statement::VerdictMap(vmap) => {
+            v.visit_expression_mut(&mut vmap.key);
+            v.visit_expression_mut(&mut vmap.data);
+        }
+        Statement::CTCount(count) => v.visit_expression_mut(&mut count.val),
+        Statement::CTTimeout(expr) | Statement::CTExpectation(expr) => v.visit_expression_mut(expr),
+        _ => {}
+    }
+}
+
+fn visit_nat_mut<'a, V>(v: &mut V, node: &mut NAT<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+    for expr in [&mut node.addr, &mut node.port].into_iter().flatten() {
+        v.visit_expression_mut(expr);
+    }
+}
+
+pub fn visit_match_mut<'a, V>(v: &mut V, node: &mut Match<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+    v.visit_expression_mut(&mut node.left);
+    v.visit_expression_mut(&mut node.right);
+}
+
+pub fn visit_jump_target_mut<'a, V>(_v: &mut V, _node: &mut JumpTarget<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+}
+
+pub fn visit_expression_mut<'a, V>(v: &mut V, node: &mut Expression<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+    match node {
+        Expression::List(exprs) => {
+            for expr in exprs {
+                v.visit_expression_mut(expr);
+            }
+        }
+        Expression::BinaryOperation(op) => v.visit_binary_operation_mut(op),
+        Expression::Range(range) => {
+            for expr in &mut range.range {
+                v.visit_expression_mut(expr);
+            }
+        }
+        Expression::Named(named) => v.visit_named_expression_mut(named),
+        Expression::Verdict(Verdict::Jump(target) | Verdict::Goto(target)) => {
+            v.visit_jump_target_mut(target)
+        }
+        Expression::String(_)
+        | Expression::Number(_)
+        | Expression::SignedNumber(_)
+        | Expression::Boolean(_)
+        | Expression::Verdict(_) => {}
+    }
+}
+
+pub fn visit_named_expression_mut<'a, V>(v: &mut V, node: &mut NamedExpression<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+    match node {
+        NamedExpression::Concat(exprs) => {
+            for expr in exprs {
+                v.visit_expression_mut(expr);
+            }
+        }
+        NamedExpression::Set(items) => {
+            for item in items {
+                v.visit_set_item_mut(item);
+            }
+        }
+        NamedExpression::Map(map) => {
+            v.visit_expression_mut(&mut map.key);
+            v.visit_expression_mut(&mut map.data);
+        }
+        NamedExpression::Prefix(prefix) => v.visit_expression_mut(&mut prefix.addr),
+        NamedExpression::JHash(hash) => v.visit_expression_mut(&mut hash.expr),
+        NamedExpression::Elem(elem) => v.visit_expression_mut(&mut elem.val),
+        _ => {}
+    }
+}
+
+pub fn visit_set_item_mut<'a, V>(v: &mut V, node: &mut SetItem<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+    match node {
+        SetItem::Element(expr) => v.visit_expression_mut(expr),
+        SetItem::Mapping(key, value) => {
+            v.visit_expression_mut(key);
+            v.visit_expression_mut(value);
+        }
+        SetItem::MappingStatement(key, stmt) => {
+            v.visit_expression_mut(key);
+            v.visit_statement_mut(stmt);
+        }
+    }
+}
+
+pub fn visit_binary_operation_mut<'a, V>(v: &mut V, node: &mut BinaryOperation<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+    match node {
+        BinaryOperation::AND(left, right)
+        | BinaryOperation::XOR(left, right)
+        | BinaryOperation::LSHIFT(left, right)
+        | BinaryOperation::RSHIFT(left, right) => {
+            v.visit_expression_mut(left);
+            v.visit_expression_mut(right);
+        }
+        BinaryOperation::OR(exprs) => {
+            for expr in exprs {
+                v.visit_expression_mut(expr);
+            }
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use nftables::{
+    builder::BuildError,
+    expr::Prefix,
+    schema::{Chain, ChainFlag, NfListObject, Rule, Set, SetFlag, SetType},
+    types::{NfChainPolicy, NfChainType, NfFamily, NfHook},
+};
+use serde_json::{json, Value};
+
+fn list_object(object: Value) -> NfListObject<'static> {
+    serde_json::from_value(object).unwrap()
+}
+
+#[test]
+/// Builds a rule from typed matches and statements.
+fn test_build_rule() {
+    let rule = Rule::builder(NfFamily::INet, "filter", "input")
+        .match_ip_saddr(Prefix::new("10.0.0.0", 8))
+        .match_tcp_dport(22..=23)
+        .match_ct_state(["established", "related"])
+        .counter()
+        .comment("ssh")
+        .accept()
+        .build()
+        .unwrap();
+    let expected = list_object(json!({"rule": {
+    "family": "inet", "table": "filter", "chain": "input", "comment": "ssh", "expr": [
+        {"match": {"op": "==", "left": {"payload": {"protocol": "ip", "field": "saddr"}},
+            "right": {"prefix": {"addr": "10.0.0.0", "len": 8}}}},
+        {"match": {"op": "==", "left": {"payload": {"protocol": "tcp", "field": "dport"}},
+            "right": {"range": [22, 23]}}},
+        {"match": {"op": "in", "left": {"ct": {"key": "state"}},
+            "right": ["established", "related"]}},
+        {"counter": null},
+        {"accept": null},
+    ]}}));
+    assert_eq!(NfListObject::Rule(rule), expected);
+
+    let rule = Rule::builder(NfFamily::IP, "nat", "postrouting")
+        .match_oifname("wan0")
+        .masquerade()
+        .build()
+        .unwrap();
+    let expected = list_object(json!({"rule": {
+    "family": "ip", "table": "nat", "chain": "postrouting", "expr": [
+        {"match": {"op": "==", "left": {"meta": {"key": "oifname"}}, "right": "wan0"}},
+        {"masquerade": null},
+    ]}}));
+    assert_eq!(NfListObject::Rule(rule), expected);
+
+    // Unsuffixed integer literals are accepted as values.
+    let rule = Rule::builder(NfFamily::INet, "filter", "input")
+        .match_tcp_dport(22)
+        .accept()
+        .build()
+        .unwrap();
+    let expected = list_object(json!({"rule": {
+    "family": "inet", "table": "filter", "chain": "input", "expr": [
+        {"match": {"op": "==", "left": {"payload": {"protocol": "tcp", "field": "dport"}},
+            "right": 22}},
+        {"accept": null},
+    ]}}));
+    assert_eq!(NfListObject::Rule(rule), expected);
+}
+
+#[test]
+/// Rules without statements are rejected.
+fn test_build_empty_rule() {
+    let err = Rule::builder(NfFamily::INet, "filter", "input")
+        .comment("nothing")
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        err,
+        BuildError::EmptyRule {
+            chain: "input".to_string()
+        }
+    );
+}
+
+#[test]
+/// Builds regular and base chains and checks base chain settings.
+fn test_build_chain() {
+    let chain = Chain::builder(NfFamily::INet, "filter", "input")
+        .base(NfChainType::Filter, NfHook::Input, 0)
+        .policy(NfChainPolicy::Drop)
+        .build()
+        .unwrap();
+    let expected = list_object(json!({"chain": {
+        "family": "inet", "table": "filter", "name": "input",
+        "type": "filter", "hook": "input", "prio": 0, "policy": "drop"}}));
+    assert_eq!(NfListObject::Chain(chain), expected);
+
+    let chain = Chain::builder(NfFamily::INet, "filter", "services")
+        .build()
+        .unwrap();
+    let expected = list_object(json!({"chain": {
+        "family": "inet", "table": "filter", "name": "services"}}));
+    assert_eq!(NfListObject::Chain(chain), expected);
+
+    let err = Chain::builder(NfFamily::INet, "filter", "input")
+        .policy(NfChainPolicy::Drop)
+        .build()
+        .unwrap_err();
+    assert_eq!(err.to_string(), "chain `input` is missing its hook");
+
+    let err = Chain::builder(NfFamily::INet, "filter", "input")
+        .hook(NfHook::Input)
+        .priority(0)
+        .build()
+        .unwrap_err();
+    assert_eq!(err.to_string(), "chain `input` is missing its type");
+
+    let err = Chain::builder(NfFamily::NetDev, "filter", "ingress")
+        .base(NfChainType::Filter, NfHook::Ingress, -500)
+        .build()
+        .unwrap_err();
+    assert_eq!(err.to_string(), "chain `ingress` is missing its device");
+
+    let chain = Chain::builder(NfFamily::NetDev, "filter", "ingress")
+        .base(NfChainType::Filter, NfHook::Ingress, -500)
+        .dev("eth0")
+        .dev("eth1")
+        .flag(ChainFlag::Offload)
+        .comment("bond members")
+        .build()
+        .unwrap();
+    let expected = list_object(json!({"chain": {
+        "family": "netdev", "table": "filter", "name": "ingress", "type": "filter",
+        "hook": "ingress", "prio": -500, "dev": ["eth0", "eth1"], "flags": "offload",
+        "comment": "bond members"}}));
+    assert_eq!(NfListObject::Chain(chain), expected);
+}
+
+#[test]
+/// Builds sets and checks their type and flags.
+fn test_build_set() {
+    let set = Set::builder(NfFamily::INet, "filter", "blocked")
+        .set_type(SetType::Ipv4Addr)
+        .flag(SetFlag::Interval)
+        .timeout(90)
+        .element("10.0.0.1")
+        .element(Prefix::new("10.1.0.0", 16))
+        .build()
+        .unwrap();
+    let expected = list_object(json!({"set": {
+        "family": "inet", "table": "filter", "name": "blocked", "type": "ipv4_addr",
+        "flags": ["interval"], "timeout": 90,
+        "elem": ["10.0.0.1", {"prefix": {"addr": "10.1.0.0", "len": 16}}]}}));
+    assert_eq!(NfListObject::Set(Box::new(set)), expected);
+
+    let err = Set::builder(NfFamily::INet, "filter", "blocked")
+        .elements(["10.0.0.1", "10.0.0.2"])
+        .build()
+        .unwrap_err();
+    assert_eq!(err.to_string(), "set `blocked` is missing its type");
+
+    let err = Set::builder(NfFamily::INet, "filter", "ports")
+        .set_type(SetType::InetService)
+        .element(1024..=65535)
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        err,
+        BuildError::MissingIntervalFlag {
+            name: "ports".to_string()
+        }
+    );
+}
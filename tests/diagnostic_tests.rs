@@ -0,0 +1,63 @@
+use nftables::diagnostic::{parse_diagnostics, DiagnosticKind, ErrorCategory, Severity};
+
+#[test]
+/// Parses a netlink error that references the failing JSON command.
+fn test_parse_netlink_error() {
+    let payload = r#"{"nftables":[{"add":{"table":{"family":"ip","name":"a"}}},{"delete":{"table":{"family":"ip6","name":"i-do-not-exist"}}}]}"#;
+    let stderr = concat!(
+        "Error: Could not process rule: No such file or directory\n",
+        r#"{"delete":{"table":{"family":"ip6","name":"i-do-not-exist"}}}"#,
+        "\n",
+        "^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^\n",
+    );
+
+    let diagnostics = parse_diagnostics(stderr, Some(payload));
+    assert_eq!(1, diagnostics.len());
+    let diagnostic = &diagnostics[0];
+    assert_eq!(Severity::Error, diagnostic.severity);
+    assert_eq!(DiagnosticKind::Netlink, diagnostic.kind);
+    assert_eq!(Some(ErrorCategory::NotFound), diagnostic.category);
+    assert_eq!(Some(1), diagnostic.command_index);
+    assert_eq!(Some(0..63), diagnostic.span);
+    assert_eq!(None, diagnostic.location);
+}
+
+#[test]
+/// Parses several located messages as printed for nft syntax input.
+fn test_parse_located_errors() {
+    let stderr = "\
+/dev/stdin:2:1-30: Error: Could not process rule: Device or resource busy
+delete chain inet filter input
+^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
+/dev/stdin:3:17-22: Error: syntax error, unexpected string
+add rule inet filter foo bar
+                ^^^^^^
+";
+
+    let diagnostics = parse_diagnostics(stderr, None);
+    assert_eq!(2, diagnostics.len());
+
+    let busy = &diagnostics[0];
+    assert_eq!(Some(ErrorCategory::Busy), busy.category);
+    assert_eq!(None, busy.command_index);
+    let location = busy.location.as_ref().unwrap();
+    assert_eq!("/dev/stdin", location.file);
+    assert_eq!(2, location.line);
+    assert_eq!(1..31, location.columns);
+    assert_eq!(
+        Some("delete chain inet filter input"),
+        busy.context.as_deref()
+    );
+
+    let syntax = &diagnostics[1];
+    assert_eq!(DiagnosticKind::Syntax, syntax.kind);
+    assert_eq!(None, syntax.category);
+    assert_eq!(Some(16..22), syntax.span);
+}
+
+#[test]
+/// Ignores output that does not belong to a message.
+fn test_parse_no_diagnostics() {
+    assert!(parse_diagnostics("", None).is_empty());
+    assert!(parse_diagnostics("netlink: something odd\n", None).is_empty());
+}
@@ -0,0 +1,132 @@
+use nftables::{
+    diff::{diff, Change},
+    schema::{NfListObject, Nftables},
+};
+use serde_json::json;
+
+fn nftables(objects: serde_json::Value) -> Nftables<'static> {
+    serde_json::from_value(json!({ "nftables": objects })).unwrap()
+}
+
+fn old() -> Nftables<'static> {
+    nftables(json!([
+        {"table": {"family": "inet", "name": "filter", "handle": 1}},
+        {"chain": {"family": "inet", "table": "filter", "name": "input", "handle": 1,
+            "type": "filter", "hook": "input", "prio": 0, "policy": "accept"}},
+        {"chain": {"family": "inet", "table": "filter", "name": "old", "handle": 2}},
+        {"rule": {"family": "inet", "table": "filter", "chain": "input", "handle": 3,
+            "expr": [{"counter": {"packets": 10, "bytes": 800}}, {"accept": null}], "comment": "ssh"}},
+        {"rule": {"family": "inet", "table": "filter", "chain": "input", "handle": 4,
+            "expr": [{"drop": null}]}},
+        {"set": {"family": "inet", "table": "filter", "name": "blocked", "handle": 5,
+            "type": "ipv4_addr", "elem": ["10.0.0.1", "10.0.0.2"]}},
+    ]))
+}
+
+#[test]
+/// Ignores handles, counter values and the order of set elements.
+fn test_diff_equivalent() {
+    let new = nftables(json!([
+        {"table": {"family": "inet", "name": "filter"}},
+        {"chain": {"family": "inet", "table": "filter", "name": "input",
+            "type": "filter", "hook": "input", "prio": 0, "policy": "accept"}},
+        {"chain": {"family": "inet", "table": "filter", "name": "old"}},
+        {"rule": {"family": "inet", "table": "filter", "chain": "input",
+            "expr": [{"counter": null}, {"accept": null}], "comment": "ssh"}},
+        {"rule": {"family": "inet", "table": "filter", "chain": "input",
+            "expr": [{"drop": null}]}},
+        {"set": {"family": "inet", "table": "filter", "name": "blocked",
+            "type": "ipv4_addr", "elem": ["10.0.0.2"]}},
+        {"element": {"family": "inet", "table": "filter", "name": "blocked", "elem": ["10.0.0.1"]}},
+    ]));
+    let diff = diff(&old(), &new);
+    assert!(diff.is_empty(), "{diff}");
+}
+
+#[test]
+/// Reports added, removed and modified objects and elements.
+fn test_diff_changes() {
+    let new = nftables(json!([
+        {"table": {"family": "inet", "name": "filter"}},
+        {"chain": {"family": "inet", "table": "filter", "name": "input",
+            "type": "filter", "hook": "input", "prio": 0, "policy": "drop"}},
+        {"rule": {"family": "inet", "table": "filter", "chain": "input",
+            "expr": [{"accept": null}]}},
+        {"rule": {"family": "inet", "table": "filter", "chain": "input",
+            "expr": [{"counter": null}, {"accept": null}], "comment": "ssh"}},
+        {"set": {"family": "inet", "table": "filter", "name": "blocked",
+            "type": "ipv4_addr", "elem": ["10.0.0.1", "10.0.0.3"]}},
+    ]));
+    let diff = diff(&old(), &new);
+
+    assert_eq!(5, diff.changes.len(), "{diff}");
+    assert!(matches!(
+        &diff.changes[0],
+        Change::Removed(NfListObject::Chain(chain)) if chain.name == "old"
+    ));
+    assert!(matches!(
+        &diff.changes[1],
+        Change::Removed(NfListObject::Element(element)) if element.name == "blocked"
+    ));
+    assert!(matches!(
+        &diff.changes[2],
+        Change::Modified { new: NfListObject::Chain(chain), .. } if chain.name == "input"
+    ));
+    assert!(matches!(
+        &diff.changes[3],
+        Change::Modified { new: NfListObject::Rule(rule), .. } if rule.comment.is_none()
+    ));
+
+    assert_eq!(
+        "\
+- chain inet filter old
+- element inet filter blocked { \"10.0.0.2\" }
+~ chain inet filter input
+    policy: \"accept\" -> \"drop\"
+~ rule inet filter input: [{\"accept\":null}]
+    was: rule inet filter input: [{\"drop\":null}]
++ element inet filter blocked { \"10.0.0.3\" }
+",
+        diff.to_string()
+    );
+
+    let json = serde_json::to_value(&diff).unwrap();
+    assert_eq!(
+        json!({"added": {"element": {"family": "inet", "table": "filter", "name": "blocked", "elem": ["10.0.0.3"]}}}),
+        json[4]
+    );
+    assert_eq!("old", json[0]["removed"]["chain"]["name"]);
+    assert_eq!("drop", json[2]["modified"]["new"]["chain"]["policy"]);
+}
+
+#[test]
+/// Tells apart rules of a chain that share a comment.
+fn test_diff_shared_comment() {
+    let rule = |handle: u32, verdict: &str| {
+        json!({"rule": {"family": "inet", "table": "filter", "chain": "input", "handle": handle,
+            "expr": [{verdict: null}], "comment": "lan"}})
+    };
+    let chain = json!({"chain": {"family": "inet", "table": "filter", "name": "input"}});
+    let old = nftables(json!([chain, rule(1, "accept"), rule(2, "drop")]));
+
+    let removed = nftables(json!([chain, rule(1, "accept")]));
+    let changes = diff(&old, &removed);
+    assert_eq!(1, changes.changes.len(), "{changes}");
+    assert!(matches!(
+        &changes.changes[0],
+        Change::Removed(NfListObject::Rule(rule)) if rule.handle == Some(2)
+    ));
+
+    let added = nftables(json!([
+        chain,
+        rule(1, "accept"),
+        rule(2, "drop"),
+        rule(3, "continue")
+    ]));
+    let diff = diff(&old, &added);
+    assert_eq!(1, diff.changes.len(), "{diff}");
+    assert!(matches!(
+        &diff.changes[0],
+        Change::Added(NfListObject::Rule(_))
+    ));
+}
@@ -0,0 +1,183 @@
+use std::net::IpAddr;
+
+use nftables::{
+    eval::{evaluate, CtState, Decision, EvalError, Packet},
+    parser::parse,
+    schema::Nftables,
+    types::{NfFamily, NfHook},
+};
+
+const RULESET: &str = r#"
+table inet filter {
+    set trusted {
+        type ipv4_addr
+        flags interval
+        elements = { 10.0.0.0/8 }
+    }
+    chain input {
+        type filter hook input priority 0; policy drop;
+        ct state established,related accept
+        iifname "lo" accept
+        meta l4proto vmap { tcp : jump tcp_in, udp : drop }
+    }
+    chain tcp_in {
+        tcp dport 22 ip saddr @trusted accept
+        tcp dport { 80, 443 } counter accept
+        tcp dport 1024-2048 reject
+    }
+}
+table inet marks {
+    chain input {
+        type filter hook input priority 10;
+        meta mark 1 drop
+    }
+}
+"#;
+
+/// Returns a new TCP packet to the input hook.
+fn tcp(saddr: &str, dport: u16) -> Packet {
+    Packet {
+        iifname: Some("eth0".into()),
+        saddr: Some(saddr.parse().unwrap()),
+        daddr: Some(IpAddr::from([192, 0, 2, 1])),
+        l4proto: Some(6),
+        sport: Some(40000),
+        dport: Some(dport),
+        ct_state: Some(CtState::New),
+        ..Packet::new(NfFamily::IP, NfHook::Input)
+    }
+}
+
+/// Returns the decision and the chains of the matched rules.
+fn decide(ruleset: &Nftables, packet: &Packet) -> (Decision, Vec<String>) {
+    let evaluation = evaluate(ruleset, packet).unwrap();
+    let chains = evaluation
+        .matched
+        .iter()
+        .map(|rule| rule.chain.to_string())
+        .collect();
+    (evaluation.decision, chains)
+}
+
+#[test]
+/// Follows verdict maps and jumps, and falls back to the chain policy.
+fn test_evaluate_verdicts() {
+    let ruleset = parse(RULESET).unwrap();
+
+    assert_eq!(
+        decide(&ruleset, &tcp("10.1.2.3", 22)),
+        (Decision::Accept, vec!["input".into(), "tcp_in".into()])
+    );
+    assert_eq!(
+        decide(&ruleset, &tcp("10.1.2.3", 443)),
+        (Decision::Accept, vec!["input".into(), "tcp_in".into()])
+    );
+    // Not trusted: returns from tcp_in and reaches the policy of input.
+    assert_eq!(
+        decide(&ruleset, &tcp("192.168.1.1", 22)),
+        (Decision::Drop, vec!["input".into()])
+    );
+    assert_eq!(
+        decide(&ruleset, &tcp("192.168.1.1", 1500)).0,
+        Decision::Reject
+    );
+    let udp = Packet {
+        l4proto: Some(17),
+        ..tcp("10.1.2.3", 53)
+    };
+    assert_eq!(
+        decide(&ruleset, &udp),
+        (Decision::Drop, vec!["input".into()])
+    );
+}
+
+#[test]
+/// Runs every base chain of the hook in priority order.
+fn test_evaluate_base_chains() {
+    let ruleset = parse(RULESET).unwrap();
+    let established = Packet {
+        ct_state: Some(CtState::Established),
+        ..tcp("192.168.1.1", 4000)
+    };
+    assert_eq!(
+        decide(&ruleset, &established),
+        (Decision::Accept, vec!["input".into()])
+    );
+    let marked = Packet {
+        mark: 1,
+        ..established
+    };
+    assert_eq!(
+        decide(&ruleset, &marked),
+        (Decision::Drop, vec!["input".into(), "input".into()])
+    );
+    // Chains of ip6 packets do not see ip headers.
+    let ipv6 = Packet {
+        family: NfFamily::IP6,
+        saddr: Some("2001:db8::1".parse().unwrap()),
+        daddr: Some("2001:db8::2".parse().unwrap()),
+        ..tcp("10.1.2.3", 22)
+    };
+    assert_eq!(
+        decide(&ruleset, &ipv6),
+        (Decision::Drop, vec!["input".into()])
+    );
+}
+
+#[test]
+/// Reports what cannot be evaluated instead of guessing.
+fn test_evaluate_errors() {
+    let ruleset = parse(RULESET).unwrap();
+    let unknown = Packet {
+        ct_state: None,
+        ..tcp("10.1.2.3", 22)
+    };
+    assert_eq!(
+        evaluate(&ruleset, &unknown),
+        Err(EvalError::Unspecified {
+            chain: "input".into(),
+            field: "ct state"
+        })
+    );
+
+    let ruleset = parse(
+        r#"
+        table ip filter {
+            chain input {
+                type filter hook input priority 0;
+                tcp flags syn limit rate 10/second accept
+            }
+        }
+        "#,
+    )
+    .unwrap();
+    let err = evaluate(&ruleset, &tcp("10.1.2.3", 22)).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "chain `input`: cannot evaluate `tcp flags`"
+    );
+
+    let ruleset = parse(
+        r#"
+        table ip filter {
+            chain input {
+                type filter hook input priority 0;
+                jump a
+            }
+            chain a {
+                jump b
+            }
+            chain b {
+                goto a
+            }
+        }
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        evaluate(&ruleset, &tcp("10.1.2.3", 22)),
+        Err(EvalError::Loop {
+            chains: vec!["a".into(), "b".into()]
+        })
+    );
+}
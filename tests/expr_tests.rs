@@ -0,0 +1,116 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    ops::RangeInclusive,
+};
+
+use nftables::{
+    expr::{Expression, TryFromExpressionError},
+    types::MacAddr,
+};
+use serde_json::json;
+
+fn expression(value: serde_json::Value) -> Expression<'static> {
+    serde_json::from_value(value).unwrap()
+}
+
+#[test]
+/// Converts addresses to string expressions and back.
+fn test_convert_addresses() {
+    let v4 = Ipv4Addr::new(192, 168, 1, 1);
+    let v6: Ipv6Addr = "fe80::1".parse().unwrap();
+    assert_eq!(Expression::from(v4), expression(json!("192.168.1.1")));
+    assert_eq!(
+        Expression::from(IpAddr::V6(v6)),
+        expression(json!("fe80::1"))
+    );
+    assert_eq!(
+        Ipv4Addr::try_from(&expression(json!("192.168.1.1"))),
+        Ok(v4)
+    );
+    assert_eq!(
+        IpAddr::try_from(expression(json!("fe80::1"))),
+        Ok(IpAddr::V6(v6))
+    );
+    assert_eq!(
+        Ipv6Addr::try_from(&expression(json!("192.168.1.1"))),
+        Err(TryFromExpressionError {
+            expected: "an IPv6 address"
+        })
+    );
+    assert!(IpAddr::try_from(&expression(json!("@blocked"))).is_err());
+    assert!(IpAddr::try_from(&expression(json!(80))).is_err());
+
+    let socket: SocketAddr = "10.0.0.1:8080".parse().unwrap();
+    let concat = expression(json!({"concat": ["10.0.0.1", 8080]}));
+    assert_eq!(Expression::from(socket), concat);
+    assert_eq!(SocketAddr::try_from(&concat), Ok(socket));
+    assert!(SocketAddr::try_from(&expression(json!({"concat": ["10.0.0.1", 70000]}))).is_err());
+}
+
+#[test]
+/// Converts integers to number expressions like deserialization does.
+fn test_convert_numbers() {
+    assert_eq!(Expression::from(22u32), expression(json!(22)));
+    assert_eq!(Expression::from(u64::MAX), expression(json!(u64::MAX)));
+    assert_eq!(Expression::from(3600i64), expression(json!(3600)));
+    assert_eq!(Expression::from(-1i64), expression(json!(-1)));
+    assert_eq!(Expression::from(22), expression(json!(22)));
+    assert_eq!(Expression::from(-1), expression(json!(-1)));
+}
+
+#[test]
+/// Converts port ranges to range expressions and back.
+fn test_convert_port_range() {
+    let range = expression(json!({"range": [1024, 65535]}));
+    assert_eq!(Expression::from(1024..=65535), range);
+    assert_eq!(RangeInclusive::<u16>::try_from(&range), Ok(1024..=65535));
+    assert!(RangeInclusive::<u16>::try_from(&expression(json!({"range": [0, 70000]}))).is_err());
+    assert!(RangeInclusive::<u16>::try_from(&expression(json!(22))).is_err());
+}
+
+#[test]
+/// Formats and parses MAC addresses as nft does.
+fn test_convert_mac_address() {
+    let mac = MacAddr([0x00, 0x11, 0x22, 0xaa, 0xbb, 0xcc]);
+    assert_eq!(mac.to_string(), "00:11:22:aa:bb:cc");
+    assert_eq!("0:11:22:AA:bb:cc".parse(), Ok(mac));
+    assert_eq!(
+        Expression::from(mac),
+        expression(json!("00:11:22:aa:bb:cc"))
+    );
+    assert_eq!(
+        MacAddr::try_from(expression(json!("00:11:22:aa:bb:cc"))),
+        Ok(mac)
+    );
+    for invalid in [
+        "00:11:22:aa:bb",
+        "00:11:22:aa:bb:cc:dd",
+        "00:11:22:aa:bb:+c",
+        "001:1:22:aa:bb:cc",
+    ] {
+        assert!(invalid.parse::<MacAddr>().is_err(), "{invalid}");
+    }
+}
+
+#[cfg(feature = "ipnet")]
+#[test]
+/// Converts networks to prefixes and reads prefixes and addresses back.
+fn test_convert_ipnet() {
+    use ipnet::{IpNet, Ipv4Net, Ipv6Net};
+    use nftables::expr::Prefix;
+
+    let net: Ipv4Net = "10.1.2.3/8".parse().unwrap();
+    let prefix = expression(json!({"prefix": {"addr": "10.0.0.0", "len": 8}}));
+    assert_eq!(Expression::from(net), prefix);
+    assert_eq!(Expression::from(net), Prefix::new("10.0.0.0", 8).into());
+    assert_eq!(Ipv4Net::try_from(&prefix), Ok(net.trunc()));
+    assert_eq!(
+        IpNet::try_from(&expression(json!("fe80::1"))),
+        Ok("fe80::1/128".parse().unwrap())
+    );
+    assert!(Ipv6Net::try_from(&prefix).is_err());
+    assert!(IpNet::try_from(&expression(
+        json!({"prefix": {"addr": "10.0.0.0", "len": 33}})
+    ))
+    .is_err());
+}
@@ -1,6 +1,10 @@
 use std::{fs::File, io::BufReader};
 
-use nftables::schema::Nftables;
+use nftables::{
+    expr::Expression,
+    schema::{NfListObject, NfObject, Nftables, SetType, SetTypeValue},
+    stmt::{AnonymousCounter, Counter, Statement},
+};
 
 // nft 1.1.4 changed behavior where the flag is printed as single string instead of array
 // As such this lib should be able to parse both and return the same result.
@@ -43,3 +47,96 @@ fn test_parse_set_map_flags() {
         "resources/test/fixtures/set-map-flag-2.json",
     );
 }
+
+#[test]
+fn test_parse_table_flags() {
+    parse_and_compare_files(
+        "resources/test/fixtures/table-flag-1.json",
+        "resources/test/fixtures/table-flag-2.json",
+    );
+}
+
+#[test]
+fn test_parse_chain_devices() {
+    parse_and_compare_files(
+        "resources/test/fixtures/chain-dev-1.json",
+        "resources/test/fixtures/chain-dev-2.json",
+    );
+}
+
+#[test]
+fn test_parse_set_typeof() {
+    let path = "resources/test/fixtures/set-typeof.json";
+    let json: serde_json::Value =
+        serde_json::from_reader(BufReader::new(File::open(path).expect("Cannot open file")))
+            .expect("failed to read json");
+    let parsed: Nftables = serde_json::from_value(json.clone()).expect("failed to parse json");
+
+    let types: Vec<(&str, &SetTypeValue, Option<&SetTypeValue>)> = parsed
+        .objects
+        .iter()
+        .filter_map(|object| match object {
+            NfObject::ListObject(NfListObject::Set(set)) => Some((&*set.name, &set.set_type, None)),
+            NfObject::ListObject(NfListObject::Map(map)) => {
+                Some((&*map.name, &map.set_type, Some(&map.map)))
+            }
+            _ => None,
+        })
+        .collect();
+    let SetTypeValue::Typeof(expr) = types[0].1 else {
+        panic!("set1 is not declared with typeof: {:?}", types[0]);
+    };
+    assert_eq!(expr.to_string(), "ip saddr . tcp dport");
+    assert!(matches!(types[1].1, SetTypeValue::Typeof(_)));
+    assert_eq!(types[1].2, Some(&SetTypeValue::Single(SetType::Verdict)));
+    assert_eq!(
+        types[2].1,
+        &SetTypeValue::Concatenated(vec![SetType::CtState, SetType::IcmpType].into())
+    );
+    assert_eq!(types[2].2, Some(&SetTypeValue::Single(SetType::Time)));
+    assert_eq!(types[3].1, &SetTypeValue::Single(SetType::CgroupsV2));
+    assert_eq!(
+        types[4].1,
+        &SetTypeValue::Concatenated(
+            vec![SetType::IpsecSpi, SetType::Unknown("future_type".into())].into()
+        )
+    );
+
+    assert_eq!(serde_json::to_value(&parsed).unwrap(), json);
+}
+
+#[test]
+fn test_parse_large_counters() {
+    let path = "resources/test/fixtures/counter-large.json";
+    let json: serde_json::Value =
+        serde_json::from_reader(BufReader::new(File::open(path).expect("Cannot open file")))
+            .expect("failed to read json");
+    let parsed: Nftables = serde_json::from_value(json.clone()).expect("failed to parse json");
+
+    let NfObject::ListObject(NfListObject::Counter(counter)) = &parsed.objects[2] else {
+        panic!("not a counter: {:?}", parsed.objects[2]);
+    };
+    assert_eq!(counter.packets, Some(8_589_934_592));
+    assert_eq!(counter.bytes, Some(12_884_901_888_000));
+    let NfObject::ListObject(NfListObject::Quota(quota)) = &parsed.objects[3] else {
+        panic!("not a quota: {:?}", parsed.objects[3]);
+    };
+    assert_eq!(quota.bytes, Some(10_737_418_240));
+    assert_eq!(quota.used, Some(5_368_709_120));
+    let NfObject::ListObject(NfListObject::Rule(rule)) = &parsed.objects[5] else {
+        panic!("not a rule: {:?}", parsed.objects[5]);
+    };
+    let Statement::Match(mark) = &rule.expr[0] else {
+        panic!("not a match: {:?}", rule.expr[0]);
+    };
+    assert_eq!(mark.right, Expression::Number(4_294_967_296));
+    assert_eq!(
+        rule.expr[1],
+        Statement::Counter(Counter::Anonymous(Some(AnonymousCounter {
+            packets: Some(4_294_967_297),
+            bytes: Some(6_442_450_944_000),
+        })))
+    );
+
+    assert_eq!(serde_json::to_value(&parsed).unwrap(), json);
+}
@@ -0,0 +1,141 @@
+use nftables::{
+    graph::{CallGraph, Edge, JumpKind},
+    schema::Nftables,
+    validate::{validate, Problem},
+};
+use serde_json::{json, Value};
+
+fn nftables(objects: Value) -> Nftables<'static> {
+    serde_json::from_value(json!({ "nftables": objects })).unwrap()
+}
+
+/// Returns a filter table with a base chain `input` and the given regular chains.
+fn ruleset(chains: &[&str], rules: Value) -> Nftables<'static> {
+    let mut objects = vec![
+        json!({"table": {"family": "inet", "name": "filter"}}),
+        json!({"chain": {"family": "inet", "table": "filter", "name": "input",
+            "type": "filter", "hook": "input", "prio": 0}}),
+    ];
+    for chain in chains {
+        objects.push(json!({"chain": {"family": "inet", "table": "filter", "name": chain}}));
+    }
+    for rule in rules.as_array().unwrap() {
+        let (chain, expr) = (&rule[0], &rule[1]);
+        objects.push(
+            json!({"rule": {"family": "inet", "table": "filter", "chain": chain,
+            "expr": expr}}),
+        );
+    }
+    nftables(Value::Array(objects))
+}
+
+#[test]
+/// Collects jumps from statements, anonymous verdict maps and named maps.
+fn test_graph_edges() {
+    let mut document = ruleset(
+        &["tcp", "udp", "ssh", "dns"],
+        json!([
+            ["input", [{"vmap": {"key": {"meta": {"key": "l4proto"}},
+                "data": {"set": [["tcp", {"jump": {"target": "tcp"}}],
+                                 ["udp", {"goto": {"target": "udp"}}]]}}}]],
+            ["tcp", [{"jump": {"target": "ssh"}}]],
+            ["tcp", [{"jump": {"target": "ssh"}}]],
+            ["udp", [{"vmap": {"key": {"payload": {"protocol": "udp", "field": "dport"}},
+                "data": "@ports"}}]],
+            ["udp", [{"jump": {"target": "missing"}}]],
+        ]),
+    );
+    let element: Nftables = nftables(json!([
+        {"element": {"family": "inet", "table": "filter", "name": "ports",
+            "elem": [[53, {"jump": {"target": "dns"}}]]}},
+    ]));
+    document
+        .objects
+        .to_mut()
+        .extend(element.objects.iter().cloned());
+
+    let graph = CallGraph::new(&document);
+    let names: Vec<&str> = graph.chains.iter().map(|c| c.name).collect();
+    assert_eq!(names, ["input", "tcp", "udp", "ssh", "dns"]);
+    assert_eq!(graph.chains[4].index, 5);
+    let edge = |from, to, kind| Edge { from, to, kind };
+    assert_eq!(
+        graph.edges,
+        [
+            edge(0, 1, JumpKind::Jump),
+            edge(0, 2, JumpKind::Goto),
+            edge(1, 3, JumpKind::Jump),
+            edge(2, 4, JumpKind::Jump),
+        ]
+    );
+    assert!(graph.cycles().is_empty());
+    assert!(graph.unreachable().is_empty());
+    // input -> tcp -> ssh; the goto to udp does not nest.
+    assert_eq!(graph.max_depth(), Some(2));
+}
+
+#[test]
+/// Finds loops and chains no base chain reaches.
+fn test_graph_cycles() {
+    let document = ruleset(
+        &["a", "b", "c", "d", "e"],
+        json!([
+            ["input", [{"jump": {"target": "a"}}]],
+            ["a", [{"jump": {"target": "b"}}]],
+            ["b", [{"goto": {"target": "a"}}]],
+            ["d", [{"jump": {"target": "d"}}]],
+            ["e", [{"jump": {"target": "c"}}]],
+        ]),
+    );
+    let graph = CallGraph::new(&document);
+    assert_eq!(graph.cycles(), [vec![1, 2], vec![4]]);
+    assert_eq!(graph.unreachable(), [3, 4, 5]);
+    assert_eq!(graph.max_depth(), None);
+
+    let diagnostics: Vec<String> = validate(&document)
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        diagnostics,
+        [
+            "object 2: error: loop between chains `a`, `b`",
+            "object 5: error: loop between chains `d`",
+        ]
+    );
+    assert_eq!(
+        validate(&document)[0].problem,
+        Problem::Loop {
+            chains: vec!["a".into(), "b".into()]
+        }
+    );
+}
+
+#[test]
+/// Renders tables as clusters and marks gotos, loops and unreachable chains.
+fn test_graph_dot() {
+    let document = ruleset(
+        &["a", "b", "c"],
+        json!([
+            ["input", [{"goto": {"target": "a"}}]],
+            ["a", [{"jump": {"target": "b"}}]],
+            ["b", [{"jump": {"target": "a"}}]],
+        ]),
+    );
+    assert_eq!(
+        CallGraph::new(&document).to_dot(),
+        r#"digraph ruleset {
+	subgraph cluster_0 {
+		label = "inet filter";
+		"inet filter input" [label = "input\nhook input", shape = box];
+		"inet filter a" [label = "a"];
+		"inet filter b" [label = "b"];
+		"inet filter c" [label = "c", color = gray, fontcolor = gray];
+	}
+	"inet filter input" -> "inet filter a" [style = dashed];
+	"inet filter a" -> "inet filter b" [color = red];
+	"inet filter b" -> "inet filter a" [color = red];
+}
+"#
+    );
+}
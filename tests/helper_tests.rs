@@ -1,9 +1,15 @@
-use std::{borrow::Cow, vec};
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    ffi::OsStr,
+    time::{Duration, Instant},
+    vec,
+};
 
 use nftables::{
     batch::Batch,
     expr,
-    helper::{self, NftablesError},
+    helper::{self, Netns, NftExecutor, NftablesError, ProcessExecutor},
     schema::{self, Chain, Rule, Table},
     stmt, types,
 };
@@ -128,6 +134,287 @@ fn test_remove_unknown_table() {
     assert!(matches!(err, NftablesError::NftFailed { .. }));
 }
 
+/// Executor that records invocations and answers with a canned ruleset.
+#[derive(Default)]
+struct RecordingExecutor {
+    calls: RefCell<Vec<(Vec<String>, Option<String>)>>,
+}
+
+impl NftExecutor for RecordingExecutor {
+    fn execute(&self, args: &[&OsStr], stdin: Option<&str>) -> Result<String, NftablesError> {
+        let args = args
+            .iter()
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect();
+        self.calls
+            .borrow_mut()
+            .push((args, stdin.map(ToOwned::to_owned)));
+        Ok(r#"{"nftables":[{"metainfo":{"json_schema_version":1}}]}"#.to_string())
+    }
+}
+
+#[test]
+/// Routes list and apply operations through a custom executor.
+fn test_custom_executor() {
+    let executor = RecordingExecutor::default();
+    let ruleset = example_ruleset(false);
+
+    let listed = helper::get_current_ruleset_with_executor(&executor).unwrap();
+    assert_eq!(1, listed.objects.len());
+    helper::apply_ruleset_with_executor(&ruleset, &executor).unwrap();
+    helper::apply_and_return_ruleset_with_executor(&ruleset, &executor).unwrap();
+
+    let calls = executor.calls.borrow();
+    assert_eq!(calls[0].0, ["-j", "list", "ruleset"]);
+    assert_eq!(calls[0].1, None);
+    assert_eq!(calls[1].0, ["-j", "-f", "-"]);
+    assert_eq!(
+        calls[1].1.as_deref(),
+        Some(serde_json::to_string(&ruleset).unwrap().as_str())
+    );
+    assert_eq!(calls[2].0, ["--echo", "-j", "-f", "-"]);
+}
+
+#[test]
+/// Runs a check through a custom executor, which must not commit the rule set.
+fn test_check_ruleset_executor() {
+    let executor = RecordingExecutor::default();
+    helper::check_ruleset_with_executor(&example_ruleset(false), &executor).unwrap();
+
+    let calls = executor.calls.borrow();
+    assert_eq!(calls[0].0, ["--check", "-j", "-f", "-"]);
+    assert!(calls[0].1.is_some());
+}
+
+#[test]
+#[ignore]
+#[serial]
+/// Checks a valid ruleset and an invalid one without applying either.
+fn test_check_ruleset() {
+    flush_ruleset().expect("failed to flush ruleset");
+    helper::check_ruleset(&example_ruleset(false)).unwrap();
+    let applied = helper::get_current_ruleset().unwrap();
+    // only the metainfo object is listed, the checked table was not created
+    assert_eq!(1, applied.objects.len());
+
+    let mut batch = Batch::new();
+    batch.add(schema::NfListObject::Chain(Chain {
+        table: "i-do-not-exist".into(),
+        ..Chain::default()
+    }));
+    let err = helper::check_ruleset(&batch.to_nftables())
+        .expect_err("checking a chain in an unknown table should fail");
+    assert!(matches!(err, NftablesError::NftFailed { .. }));
+}
+
+#[test]
+#[ignore]
+#[serial]
+/// Runs a fake `nft` inside a network namespace created by `unshare`.
+fn test_executor_netns() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let program = dir.path().join("nft");
+    std::fs::write(&program, "#!/bin/sh\nreadlink /proc/self/ns/net\n").unwrap();
+    std::fs::set_permissions(
+        &program,
+        std::os::unix::fs::PermissionsExt::from_mode(0o755),
+    )
+    .unwrap();
+
+    let mut holder = std::process::Command::new("unshare")
+        .args(["-n", "sleep", "30"])
+        .spawn()
+        .expect("failed to run unshare");
+    let own = std::fs::read_link("/proc/self/ns/net").unwrap();
+    let target = Netns::Pid(holder.id());
+    let expected = (0..100)
+        .find_map(|_| {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            std::fs::read_link(target.path())
+                .ok()
+                .filter(|ns| *ns != own)
+        })
+        .expect("unshare did not create a network namespace");
+
+    for netns in [target.clone(), Netns::Path(target.path())] {
+        let executor = ProcessExecutor::new(Some(&program)).with_netns(netns);
+        let output = executor.execute(&[], None).unwrap();
+        assert_eq!(expected.to_string_lossy(), output.trim_end());
+    }
+    holder.kill().unwrap();
+    holder.wait().unwrap();
+}
+
+#[test]
+/// Fails before spawning `nft` if the namespace does not exist.
+fn test_executor_unknown_netns() {
+    let executor =
+        ProcessExecutor::default().with_netns(Netns::Named("i-do-not-exist".to_string()));
+    let err = helper::get_current_ruleset_with_executor(&executor)
+        .expect_err("entering an unknown namespace should fail");
+    assert!(matches!(err, NftablesError::NftExecution { .. }));
+
+    // the raw helpers honor the namespace as well
+    let err = helper::get_current_ruleset_raw_with_executor(&executor, helper::DEFAULT_ARGS)
+        .expect_err("entering an unknown namespace should fail");
+    assert!(matches!(err, NftablesError::NftExecution { .. }));
+    let err = helper::apply_ruleset_raw_with_executor("{}", &executor, helper::DEFAULT_ARGS)
+        .expect_err("entering an unknown namespace should fail");
+    assert!(matches!(err, NftablesError::NftExecution { .. }));
+}
+
+#[test]
+/// Kills a hanging program once the executor's timeout expires.
+fn test_executor_timeout() {
+    let timeout = Duration::from_millis(200);
+    let executor = ProcessExecutor::new(Some("sleep")).with_timeout(timeout);
+
+    for stdin in [None, Some("{}")] {
+        let start = Instant::now();
+        let err = executor
+            .execute(&[OsStr::new("10")], stdin)
+            .expect_err("sleeping longer than the timeout should fail");
+        assert!(matches!(err, NftablesError::NftTimeout { timeout: t, .. } if t == timeout));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    // finishing within the deadline succeeds
+    let output = ProcessExecutor::new(Some("echo"))
+        .with_timeout(Duration::from_secs(10))
+        .execute(&[OsStr::new("done")], None)
+        .unwrap();
+    assert_eq!("done\n", output);
+}
+
+#[test]
+/// Passes raw payloads and arguments through an executor, which may time out.
+fn test_raw_executor() {
+    let executor = RecordingExecutor::default();
+    helper::get_current_ruleset_raw_with_executor(&executor, ["list", "tables"]).unwrap();
+    helper::apply_ruleset_raw_with_executor("{}", &executor, ["--echo"]).unwrap();
+    let calls = executor.calls.borrow();
+    assert_eq!(calls[0].0, ["-j", "list", "tables"]);
+    assert_eq!(calls[1].0, ["--echo", "-j", "-f", "-"]);
+    assert_eq!(calls[1].1.as_deref(), Some("{}"));
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let program = sleeping_nft(&dir);
+    let timeout = Duration::from_millis(200);
+    let executor = ProcessExecutor::new(Some(&program)).with_timeout(timeout);
+    let err = helper::get_current_ruleset_raw_with_executor(&executor, helper::DEFAULT_ARGS)
+        .expect_err("listing longer than the timeout should fail");
+    assert!(matches!(err, NftablesError::NftTimeout { .. }));
+    let err = helper::apply_ruleset_raw_with_executor("{}", &executor, helper::DEFAULT_ARGS)
+        .expect_err("applying longer than the timeout should fail");
+    assert!(matches!(err, NftablesError::NftTimeout { .. }));
+}
+
+/// Writes a fake `nft` that records its process id in `nft.pid` and sleeps.
+fn sleeping_nft(dir: &tempfile::TempDir) -> std::path::PathBuf {
+    let program = dir.path().join("nft");
+    std::fs::write(&program, "#!/bin/sh\necho $$ > \"$0.pid\"\nexec sleep 10\n").unwrap();
+    std::fs::set_permissions(
+        &program,
+        std::os::unix::fs::PermissionsExt::from_mode(0o755),
+    )
+    .unwrap();
+    program
+}
+
+#[cfg(any(feature = "tokio", feature = "async-process"))]
+mod process_async {
+    use std::time::Duration;
+
+    #[cfg(feature = "tokio")]
+    pub fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(future)
+    }
+
+    #[cfg(feature = "async-process")]
+    pub use futures_lite::future::block_on;
+
+    pub async fn sleep(duration: Duration) {
+        #[cfg(feature = "tokio")]
+        tokio::time::sleep(duration).await;
+        #[cfg(feature = "async-process")]
+        async_io::Timer::after(duration).await;
+    }
+}
+
+#[cfg(any(feature = "tokio", feature = "async-process"))]
+#[test]
+/// Kills a hanging `nft` once the asynchronous executor's timeout expires.
+fn test_executor_timeout_async() {
+    use helper::NftExecutorAsync;
+    use process_async::block_on;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let timeout = Duration::from_millis(200);
+    let executor = ProcessExecutor::new(Some(&sleeping_nft(&dir))).with_timeout(timeout);
+
+    for stdin in [None, Some("{}")] {
+        let start = Instant::now();
+        let err = block_on(executor.execute_async(&[], stdin))
+            .expect_err("sleeping longer than the timeout should fail");
+        assert!(matches!(err, NftablesError::NftTimeout { timeout: t, .. } if t == timeout));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
+
+#[cfg(any(feature = "tokio", feature = "async-process"))]
+#[test]
+/// Kills `nft` when the asynchronous invocation is dropped.
+fn test_executor_drop_async() {
+    use helper::NftExecutorAsync;
+    use process_async::{block_on, sleep};
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let program = sleeping_nft(&dir);
+    let pid_file = dir.path().join("nft.pid");
+    let executor = ProcessExecutor::new(Some(&program));
+
+    // Poll the invocation until nft runs, then drop it.
+    let pid = block_on(futures_lite::future::or(
+        async {
+            executor.execute_async(&[], None).await.unwrap();
+            panic!("nft should not finish");
+        },
+        async {
+            loop {
+                if let Ok(pid) = std::fs::read_to_string(&pid_file) {
+                    if !pid.trim().is_empty() {
+                        return pid.trim().to_string();
+                    }
+                }
+                sleep(Duration::from_millis(10)).await;
+            }
+        },
+    ));
+
+    // The killed child may linger as a zombie until it is reaped.
+    let running = || match std::fs::read_to_string(format!("/proc/{pid}/stat")) {
+        Ok(stat) => !stat
+            .rsplit(')')
+            .next()
+            .unwrap_or("")
+            .trim_start()
+            .starts_with('Z'),
+        Err(_) => false,
+    };
+    let start = Instant::now();
+    while running() {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "nft {pid} still runs"
+        );
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
 fn example_ruleset(with_undo: bool) -> schema::Nftables<'static> {
     let mut batch = Batch::new();
     // create table "test-table-01"
@@ -18,6 +18,8 @@ fn test_chain_table_rule_inet() {
                 family: NfFamily::INet,
                 name: Cow::Borrowed("some_inet_table"),
                 handle: None,
+                flags: None,
+                comment: None,
             }))),
             NfObject::CmdObject(NfCmd::Add(NfListObject::Chain(Chain {
                 family: NfFamily::INet,
@@ -30,6 +32,8 @@ fn test_chain_table_rule_inet() {
                 prio: None,
                 dev: None,
                 policy: Some(NfChainPolicy::Accept),
+                flags: None,
+                comment: None,
             }))),
         ]),
     };
@@ -59,6 +63,8 @@ fn test_flowtable() {
                 family: NfFamily::INet,
                 name: Cow::Borrowed("some_inet_table"),
                 handle: None,
+                flags: None,
+                comment: None,
             })),
             NfObject::ListObject(NfListObject::FlowTable(FlowTable {
                 family: NfFamily::INet,
@@ -80,6 +86,8 @@ fn test_flowtable() {
                 prio: None,
                 dev: None,
                 policy: Some(NfChainPolicy::Accept),
+                flags: None,
+                comment: None,
             })),
             NfObject::ListObject(NfListObject::Rule(Rule {
                 family: NfFamily::INet,
@@ -393,3 +401,123 @@ fn test_bit_flags() {
     let parsed: NfListObject = serde_json::from_value(json).unwrap();
     assert_eq!(expected, parsed);
 }
+
+#[test]
+/// Test JSON (de)serialization of table flags and comment.
+fn test_table_flags() {
+    let expected = NfListObject::Table(Table {
+        family: NfFamily::INet,
+        name: Cow::Borrowed("agent"),
+        handle: Some(3),
+        flags: Some([TableFlag::Owner, TableFlag::Persist].into()),
+        comment: Some(Cow::Borrowed("managed by agent")),
+    });
+    let json = json!({"table": {"family": "inet", "name": "agent", "handle": 3,
+        "comment": "managed by agent", "flags": ["owner", "persist"]}});
+    let parsed: NfListObject = serde_json::from_value(json).unwrap();
+    assert_eq!(expected, parsed);
+
+    // nft 1.1.4 prints a single flag as string
+    let json = json!({"table": {"family": "ip", "name": "paused", "flags": "dormant"}});
+    let parsed: NfListObject = serde_json::from_value(json).unwrap();
+    let NfListObject::Table(table) = parsed else {
+        panic!("not a table: {parsed:?}");
+    };
+    assert_eq!(table.flags, Some([TableFlag::Dormant].into()));
+
+    let mut batch = nftables::batch::Batch::new();
+    batch.add_owned_table(Table {
+        name: Cow::Borrowed("agent"),
+        ..Table::default()
+    });
+    assert_eq!(
+        serde_json::to_value(batch.to_nftables()).unwrap(),
+        json!({"nftables": [{"add": {"table": {"family": "inet", "name": "agent",
+            "flags": ["owner"]}}}]})
+    );
+}
+
+#[test]
+/// Test JSON (de)serialization of named counters with comment.
+fn test_counter_comment() {
+    let expected = NfListObject::Counter(nftables::schema::Counter {
+        family: NfFamily::INet,
+        table: Cow::Borrowed("filter"),
+        name: Cow::Borrowed("http"),
+        handle: Some(4),
+        packets: Some(3),
+        bytes: Some(180),
+        comment: Some(Cow::Borrowed("web traffic")),
+    });
+    let json = json!({"counter": {"family": "inet", "table": "filter", "name": "http",
+        "handle": 4, "packets": 3, "bytes": 180, "comment": "web traffic"}});
+    let parsed: NfListObject = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(expected, parsed);
+    assert_eq!(json, serde_json::to_value(&parsed).unwrap());
+
+    let json = json!({"counter": {"family": "inet", "table": "filter", "name": "http",
+        "packets": 0, "bytes": 0}});
+    let parsed: NfListObject = serde_json::from_value(json.clone()).unwrap();
+    let NfListObject::Counter(counter) = &parsed else {
+        panic!("not a counter: {parsed:?}");
+    };
+    assert_eq!(counter.comment, None);
+    assert_eq!(json, serde_json::to_value(&parsed).unwrap());
+}
+
+#[test]
+/// Test JSON (de)serialization of ct timeout values beyond 32 bits.
+fn test_ct_timeout_value() {
+    let json = json!({"cttimeout": {"family": "inet", "table": "filter", "name": "long",
+        "protocol": "tcp", "state": "established", "value": 5_000_000_000u64}});
+    let parsed: NfListObject = serde_json::from_value(json.clone()).unwrap();
+    let NfListObject::CTTimeout(timeout) = &parsed else {
+        panic!("not a ct timeout: {parsed:?}");
+    };
+    assert_eq!(timeout.value, Some(5_000_000_000));
+    assert_eq!(json, serde_json::to_value(&parsed).unwrap());
+}
+
+#[test]
+/// Test JSON (de)serialization of 64-bit and negative numbers.
+fn test_numbers() {
+    let parsed: Expression = serde_json::from_value(json!(u64::MAX)).unwrap();
+    assert_eq!(parsed, Expression::Number(u64::MAX));
+    let parsed: Expression = serde_json::from_value(json!(-10)).unwrap();
+    assert_eq!(parsed, Expression::SignedNumber(-10));
+    assert_eq!(serde_json::to_value(parsed).unwrap(), json!(-10));
+}
+
+#[test]
+/// Test JSON (de)serialization of destroy, list and reset commands.
+fn test_commands() {
+    let table = NfListObject::Table(Table {
+        family: NfFamily::INet,
+        name: Cow::Borrowed("agent"),
+        ..Table::default()
+    });
+    let mut batch = nftables::batch::Batch::new();
+    batch.destroy(table.clone());
+    batch.reset(ResetObject::Rules(ObjectFilter {
+        family: Some(NfFamily::INet),
+        table: Some(Cow::Borrowed("agent")),
+    }));
+    batch.reset(ResetObject::Table(Table {
+        family: NfFamily::INet,
+        name: Cow::Borrowed("agent"),
+        ..Table::default()
+    }));
+    batch.add_cmd(NfCmd::List(ListObject::Counters(ObjectFilter::default())));
+    batch.add_cmd(NfCmd::List(table.into()));
+    let expected = batch.to_nftables();
+    let json = json!({"nftables": [
+        {"destroy": {"table": {"family": "inet", "name": "agent"}}},
+        {"reset": {"rules": {"family": "inet", "table": "agent"}}},
+        {"reset": {"table": {"family": "inet", "name": "agent"}}},
+        {"list": {"counters": {}}},
+        {"list": {"table": {"family": "inet", "name": "agent"}}},
+    ]});
+    assert_eq!(serde_json::to_value(&expected).unwrap(), json);
+    let parsed: Nftables = serde_json::from_value(json).unwrap();
+    assert_eq!(expected, parsed);
+}
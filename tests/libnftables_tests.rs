@@ -0,0 +1,113 @@
+use std::{path::PathBuf, process::Command};
+
+use nftables::{
+    batch::Batch,
+    diagnostic::ErrorCategory,
+    helper::{self, NftablesError},
+    libnftables::LibNftablesExecutor,
+    schema::{NfListObject, Table},
+};
+use tempfile::TempDir;
+
+/// Minimal stand-in for libnftables.
+///
+/// Commands containing `fail` are rejected, `--echo` returns the payload,
+/// and all other JSON commands print a canned ruleset.
+const STUB_SOURCE: &str = r#"
+#include <stdbool.h>
+#include <stdlib.h>
+#include <string.h>
+
+struct nft_ctx { unsigned int flags; bool dry_run; const char *out; const char *err; };
+
+static const char *ruleset =
+    "{\"nftables\":[{\"metainfo\":{\"json_schema_version\":1}},"
+    "{\"table\":{\"family\":\"inet\",\"name\":\"stub\",\"handle\":1}}]}";
+
+struct nft_ctx *nft_ctx_new(unsigned int flags) { return calloc(1, sizeof(struct nft_ctx)); }
+void nft_ctx_free(struct nft_ctx *ctx) { free(ctx); }
+void nft_ctx_output_set_flags(struct nft_ctx *ctx, unsigned int flags) { ctx->flags = flags; }
+void nft_ctx_set_dry_run(struct nft_ctx *ctx, bool dry) { ctx->dry_run = dry; }
+int nft_ctx_buffer_output(struct nft_ctx *ctx) { return 0; }
+int nft_ctx_buffer_error(struct nft_ctx *ctx) { return 0; }
+const char *nft_ctx_get_output_buffer(struct nft_ctx *ctx) { return ctx->out; }
+const char *nft_ctx_get_error_buffer(struct nft_ctx *ctx) { return ctx->err; }
+
+int nft_run_cmd_from_buffer(struct nft_ctx *ctx, const char *buf) {
+    ctx->out = "";
+    ctx->err = "";
+    if (strstr(buf, "fail")) {
+        ctx->err = "Error: Could not process rule: No such file or directory\n";
+        return -1;
+    }
+    if (!(ctx->flags & (1 << 4)))
+        return 0;
+    if (buf[0] == '{')
+        ctx->out = (ctx->flags & (1 << 5)) ? buf : "";
+    else
+        ctx->out = ruleset;
+    return 0;
+}
+"#;
+
+fn build_stub(dir: &TempDir) -> PathBuf {
+    let source = dir.path().join("stub.c");
+    let library = dir.path().join("libnftables-stub.so");
+    std::fs::write(&source, STUB_SOURCE).unwrap();
+    let status = Command::new("cc")
+        .args(["-shared", "-fPIC", "-o"])
+        .arg(&library)
+        .arg(&source)
+        .status()
+        .expect("failed to run C compiler");
+    assert!(status.success(), "failed to build libnftables stub");
+    library
+}
+
+fn table(name: &str) -> NfListObject<'_> {
+    NfListObject::Table(Table {
+        name: name.into(),
+        ..Table::default()
+    })
+}
+
+#[test]
+/// Lists and applies rule sets through a stub libnftables.
+fn test_libnftables_stub() {
+    let dir = TempDir::new().unwrap();
+    let executor = LibNftablesExecutor::with_library(&build_stub(&dir)).unwrap();
+
+    let ruleset = helper::get_current_ruleset_with_executor(&executor).unwrap();
+    assert_eq!(2, ruleset.objects.len());
+
+    let mut batch = Batch::new();
+    batch.add(table("ok"));
+    let nftables = batch.to_nftables();
+    helper::apply_ruleset_with_executor(&nftables, &executor).unwrap();
+    let echoed = helper::apply_and_return_ruleset_with_executor(&nftables, &executor).unwrap();
+    assert_eq!(nftables, echoed);
+
+    let mut batch = Batch::new();
+    batch.add(table("fail"));
+    let err = helper::apply_ruleset_with_executor(&batch.to_nftables(), &executor)
+        .expect_err("stub should reject the payload");
+    match err {
+        NftablesError::NftFailed {
+            stderr,
+            diagnostics,
+            ..
+        } => {
+            assert!(stderr.contains("No such file"));
+            assert_eq!(Some(ErrorCategory::NotFound), diagnostics[0].category);
+        }
+        err => panic!("unexpected error: {err}"),
+    }
+}
+
+#[test]
+/// Fails to load a library that does not exist.
+fn test_libnftables_missing_library() {
+    let err = LibNftablesExecutor::with_library("/dev/null/libnftables.so.1")
+        .expect_err("loading a non-existing library should fail");
+    assert!(matches!(err, NftablesError::NftExecution { .. }));
+}
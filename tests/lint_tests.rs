@@ -0,0 +1,123 @@
+use nftables::{
+    lint::{lint, Finding, Lint, LintConfig},
+    parser::parse,
+    schema::Nftables,
+    types::NfFamily,
+};
+use serde_json::{json, Value};
+
+fn nftables(objects: Value) -> Nftables<'static> {
+    serde_json::from_value(json!({ "nftables": objects })).unwrap()
+}
+
+const RULESET: &str = r#"
+table inet filter {
+    chain input {
+        type filter hook input priority 0;
+        tcp dport { 22, 80 } accept
+        tcp dport 22 ip saddr 10.0.0.1 drop
+        tcp dport 443 counter accept
+        tcp dport 443 counter accept
+        jump logging
+        counter drop
+        udp dport 53 accept
+    }
+    chain logging {
+        log
+    }
+}
+"#;
+
+#[test]
+/// Finds shadowed, duplicate and unreachable rules.
+fn test_lint_rules() {
+    let ruleset = parse(RULESET).unwrap();
+    let lints: Vec<(usize, Lint)> = lint(&ruleset, &LintConfig::default())
+        .into_iter()
+        .map(|finding| (finding.index, finding.lint))
+        .collect();
+    let chain = || "input".to_string();
+    assert_eq!(
+        lints,
+        [
+            (
+                4,
+                Lint::Shadowed {
+                    chain: chain(),
+                    by: 3
+                }
+            ),
+            (
+                6,
+                Lint::Duplicate {
+                    chain: chain(),
+                    of: 5
+                }
+            ),
+            (
+                9,
+                Lint::Unreachable {
+                    chain: chain(),
+                    after: 8
+                }
+            ),
+        ]
+    );
+}
+
+#[test]
+/// Finds rules without counters in chains that require them.
+fn test_lint_counters() {
+    let ruleset = parse(RULESET).unwrap();
+    let config = LintConfig {
+        require_counters: vec![(NfFamily::INet, "filter".into(), "logging".into())],
+    };
+    let findings: Vec<String> = lint(&ruleset, &config)
+        .iter()
+        .filter(|finding| matches!(finding.lint, Lint::MissingCounter { .. }))
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        findings,
+        ["object 10: rule in chain `logging` has no counter"]
+    );
+}
+
+#[test]
+/// Finds intervals in sets without the interval flag and overlapping elements.
+fn test_lint_sets() {
+    let document = nftables(json!([
+        {"table": {"family": "inet", "name": "filter"}},
+        {"set": {"family": "inet", "table": "filter", "name": "blocked", "type": "ipv4_addr",
+            "handle": 3, "elem": [{"prefix": {"addr": "10.0.0.0", "len": 8}}, "10.1.2.3",
+            "192.168.0.1"]}},
+        {"set": {"family": "inet", "table": "filter", "name": "ports", "type": "inet_service",
+            "handle": 4, "flags": ["interval"],
+            "elem": [{"range": [1000, 2000]}, {"range": [1500, 2500]}, 3000]}},
+        {"add": {"element": {"family": "inet", "table": "filter", "name": "ports",
+            "elem": [2999, 3000]}}},
+    ]));
+    let findings: Vec<String> = lint(&document, &LintConfig::default())
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        findings,
+        [
+            "object 1 (handle 3): set `blocked` contains the interval `10.0.0.0/8` but lacks the interval flag",
+            "object 1 (handle 3): set `blocked` contains the overlapping elements `10.0.0.0/8` and `10.1.2.3`",
+            "object 2 (handle 4): set `ports` contains the overlapping elements `1000-2000` and `1500-2500`",
+            "object 3: set `ports` contains the overlapping elements `3000` and `3000`",
+        ]
+    );
+    let finding = Finding {
+        index: 2,
+        handle: Some(4),
+        lint: Lint::OverlappingElements {
+            set: "ports".into(),
+            first: "1000-2000".into(),
+            second: "1500-2500".into(),
+        },
+    };
+    assert!(lint(&document, &LintConfig::default()).contains(&finding));
+}
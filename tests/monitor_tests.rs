@@ -0,0 +1,170 @@
+use std::{ffi::OsStr, os::unix::fs::PermissionsExt, path::PathBuf};
+
+use nftables::{
+    diagnostic::ErrorCategory,
+    expr::Expression,
+    helper::{NftablesError, ProcessExecutor},
+    monitor::{self, MonitorEvent},
+    schema::NfListObject,
+    types::NfFamily,
+};
+use serial_test::serial;
+use tempfile::TempDir;
+
+/// Canned output of `nft -j monitor`.
+const EVENTS: &str = r#"{"add": {"table": {"family": "ip", "name": "t", "handle": 1}}}
+{"add": {"chain": {"family": "ip", "table": "t", "name": "c", "handle": 1}}}
+{"add": {"rule": {"family": "ip", "table": "t", "chain": "c", "handle": 2, "expr": [{"accept": null}]}}}
+{"add": {"element": {"family": "ip", "table": "t", "name": "s", "elem": {"set": ["10.0.0.1"]}}}}
+
+{"trace": {"id": 1234, "family": "ip", "table": "t", "chain": "c", "type": "rule"}}
+not json
+{"delete": {"table": {"family": "ip", "name": "t", "handle": 1}}}
+"#;
+
+/// Writes a fake `nft` that prints [EVENTS] when called as `nft -j monitor`,
+/// prints them between warnings filling the stderr pipe and fails when called
+/// as `nft -j monitor noisy`, and fails otherwise.
+fn fake_nft(dir: &TempDir) -> PathBuf {
+    let path = dir.path().join("nft");
+    let script = format!(
+        "#!/bin/sh\n\
+         if [ \"$*\" = \"-j monitor noisy\" ]; then\n\
+         yes 'Warning: noise' | head -n 20000 >&2\n\
+         cat <<'EOF'\n{EVENTS}EOF\n\
+         echo 'Error: Could not process rule: Operation not permitted' >&2\n\
+         exit 1\n\
+         fi\n\
+         if [ \"$*\" != \"-j monitor\" ]; then\n\
+         echo 'Error: Could not process rule: Operation not permitted' >&2\n\
+         exit 1\n\
+         fi\n\
+         cat <<'EOF'\n{EVENTS}EOF\n"
+    );
+    std::fs::write(&path, script).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+fn assert_events(events: Vec<Result<MonitorEvent<'static>, NftablesError>>) {
+    assert_eq!(7, events.len());
+    match &events[0] {
+        Ok(MonitorEvent::Add(NfListObject::Table(table))) => {
+            assert_eq!(NfFamily::IP, table.family);
+            assert_eq!("t", table.name);
+        }
+        event => panic!("unexpected event: {event:?}"),
+    }
+    assert!(matches!(
+        events[1],
+        Ok(MonitorEvent::Add(NfListObject::Chain(_)))
+    ));
+    assert!(matches!(
+        events[2],
+        Ok(MonitorEvent::Add(NfListObject::Rule(_)))
+    ));
+    match &events[3] {
+        Ok(MonitorEvent::Add(NfListObject::Element(element))) => {
+            assert_eq!(
+                [Expression::String("10.0.0.1".into())],
+                element.elem.as_ref()
+            );
+        }
+        event => panic!("unexpected event: {event:?}"),
+    }
+    match &events[4] {
+        Ok(MonitorEvent::Trace(trace)) => assert_eq!(1234, trace.id),
+        event => panic!("unexpected event: {event:?}"),
+    }
+    assert!(matches!(events[5], Err(NftablesError::NftInvalidJson(_))));
+    assert!(matches!(
+        events[6],
+        Ok(MonitorEvent::Delete(NfListObject::Table(_)))
+    ));
+}
+
+#[test]
+#[serial]
+/// Reads typed events from a fake `nft monitor`.
+fn test_monitor_events() {
+    let dir = TempDir::new().unwrap();
+    let executor = ProcessExecutor::new(Some(&fake_nft(&dir)));
+    let monitor = monitor::monitor_with_executor(&executor, &[]).unwrap();
+    assert_events(monitor.collect());
+}
+
+#[test]
+#[serial]
+/// Ends with the diagnostics of a failing `nft monitor`.
+fn test_monitor_failure() {
+    let dir = TempDir::new().unwrap();
+    let executor = ProcessExecutor::new(Some(&fake_nft(&dir)));
+    let mut monitor = monitor::monitor_with_executor(&executor, &[OsStr::new("trace")]).unwrap();
+    match monitor.next() {
+        Some(Err(NftablesError::NftFailed { diagnostics, .. })) => {
+            assert_eq!(
+                Some(ErrorCategory::PermissionDenied),
+                diagnostics[0].category
+            );
+        }
+        event => panic!("unexpected event: {event:?}"),
+    }
+    assert!(monitor.next().is_none());
+}
+
+/// Asserts that `error` carries the bounded tail of a noisy nft's stderr.
+fn assert_noisy_failure(error: &NftablesError) {
+    match error {
+        NftablesError::NftFailed {
+            stderr,
+            diagnostics,
+            ..
+        } => {
+            assert!(stderr.len() <= 64 * 1024, "{} bytes kept", stderr.len());
+            assert!(stderr.ends_with("Operation not permitted\n"), "{stderr}");
+            let category = diagnostics.last().and_then(|d| d.category.clone());
+            assert_eq!(Some(ErrorCategory::PermissionDenied), category);
+        }
+        error => panic!("unexpected error: {error:?}"),
+    }
+}
+
+#[test]
+#[serial]
+/// Keeps reading events while nft writes more to stderr than a pipe holds.
+fn test_monitor_noisy_stderr() {
+    let dir = TempDir::new().unwrap();
+    let executor = ProcessExecutor::new(Some(&fake_nft(&dir)));
+    let monitor = monitor::monitor_with_executor(&executor, &[OsStr::new("noisy")]).unwrap();
+    let mut events: Vec<_> = monitor.collect();
+    assert_noisy_failure(events.pop().unwrap().as_ref().unwrap_err());
+    assert_events(events);
+}
+
+#[cfg(feature = "async-process")]
+#[test]
+#[serial]
+/// Reads typed events from a fake `nft monitor` as a stream.
+fn test_monitor_stream() {
+    use futures_lite::{future::block_on, StreamExt};
+
+    let dir = TempDir::new().unwrap();
+    let executor = ProcessExecutor::new(Some(&fake_nft(&dir)));
+    let monitor = monitor::monitor_with_executor_async(&executor, &[]).unwrap();
+    assert_events(block_on(monitor.collect()));
+}
+
+#[cfg(feature = "async-process")]
+#[test]
+#[serial]
+/// Keeps streaming events while nft writes more to stderr than a pipe holds.
+fn test_monitor_stream_noisy_stderr() {
+    use futures_lite::{future::block_on, StreamExt};
+
+    let dir = TempDir::new().unwrap();
+    let executor = ProcessExecutor::new(Some(&fake_nft(&dir)));
+    let monitor = monitor::monitor_with_executor_async(&executor, &[OsStr::new("noisy")]).unwrap();
+    let mut events: Vec<_> = block_on(monitor.collect());
+    assert_noisy_failure(events.pop().unwrap().as_ref().unwrap_err());
+    assert_events(events);
+}
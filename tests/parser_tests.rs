@@ -0,0 +1,309 @@
+use std::fs;
+
+use nftables::{
+    diff::diff,
+    parser::{parse, parse_file},
+    schema::Nftables,
+};
+use serde_json::{json, Value};
+
+fn nftables(objects: Value) -> Nftables<'static> {
+    serde_json::from_value(json!({ "nftables": objects })).unwrap()
+}
+
+/// Rewrites what nft resolves when listing a ruleset: service names become
+/// port numbers and the order of flags and anonymous set elements is not
+/// significant.
+fn normalize(value: &mut Value, right: bool) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                normalize(value, right || key == "right" || key == "&");
+            }
+        }
+        Value::Array(items) => {
+            items.iter_mut().for_each(|item| normalize(item, right));
+            if right {
+                items.sort_by_key(|item| item.to_string());
+            }
+        }
+        Value::String(s) if right => {
+            *value = match s.as_str() {
+                "ssh" => json!(22),
+                "http" => json!(80),
+                "https" => json!(443),
+                "icmpv6" => json!("ipv6-icmp"),
+                _ => return,
+            }
+        }
+        _ => {}
+    }
+}
+
+fn normalized(nftables: &Nftables) -> Nftables<'static> {
+    let mut value = serde_json::to_value(nftables).unwrap();
+    normalize(&mut value, false);
+    serde_json::from_value(value).unwrap()
+}
+
+/// Parses `resources/test/nft/<name>.nft` and compares it to the ruleset
+/// nft listed after loading it.
+fn assert_fixture(name: &str) {
+    let parsed = parse_file(format!("resources/test/nft/{name}.nft"))
+        .unwrap_or_else(|err| panic!("failed to parse {name}.nft: {err}"));
+    let json = fs::read_to_string(format!("resources/test/json/{name}.json")).unwrap();
+    let expected: Nftables = serde_json::from_str(&json).unwrap();
+    let diff = diff(&normalized(&expected), &normalized(&parsed));
+    assert!(
+        diff.is_empty(),
+        "{name}.nft differs from {name}.json:\n{diff}"
+    );
+}
+
+#[test]
+fn test_parse_basic() {
+    assert_fixture("basic");
+}
+
+#[test]
+fn test_parse_bitflags() {
+    assert_fixture("bitflags");
+}
+
+#[test]
+fn test_parse_counter() {
+    assert_fixture("counter");
+}
+
+#[test]
+fn test_parse_flow() {
+    assert_fixture("flow");
+}
+
+#[test]
+fn test_parse_nat() {
+    assert_fixture("nat");
+}
+
+#[test]
+fn test_parse_nftables_init() {
+    assert_fixture("nftables-init");
+}
+
+#[test]
+fn test_parse_setmap() {
+    assert_fixture("setmap");
+}
+
+#[test]
+fn test_parse_space_keys() {
+    assert_fixture("space-keys");
+}
+
+#[test]
+fn test_parse_tproxy() {
+    assert_fixture("tproxy");
+}
+
+#[test]
+fn test_parse_workstation() {
+    assert_fixture("workstation");
+}
+
+#[test]
+fn test_parse_workstation_combined() {
+    assert_fixture("workstation_combined");
+}
+
+#[test]
+/// Statements the schema cannot express are reported at their location.
+fn test_parse_unsupported_synproxy_reference() {
+    let err = parse_file("resources/test/nft/synproxy.nft").unwrap_err();
+    let nft = fs::read_to_string("resources/test/nft/synproxy.nft").unwrap();
+    let line = nft.lines().nth(err.line - 1).unwrap();
+    assert!(line.contains("synproxy name"), "{err}");
+    assert!(err.path.unwrap().ends_with("synproxy.nft"));
+}
+
+#[test]
+/// Parses top-level commands into the matching command objects.
+fn test_parse_commands() {
+    let parsed = parse(
+        r#"
+        flush ruleset
+        add table inet filter
+        add chain inet filter input { type filter hook input priority filter - 10; policy drop; }
+        insert rule inet filter input index 0 tcp dport { 22, 80 } accept
+        replace rule inet filter input handle 4 ip saddr 10.0.0.0/8 counter drop
+        delete rule filter input handle 5
+        add element inet filter blocked { 10.0.0.1 timeout 1m }
+        delete table ip6 nat
+        destroy rule filter input handle 6
+        destroy set inet filter blocked
+        "#,
+    )
+    .unwrap();
+    let expected = nftables(json!([
+        {"flush": {"ruleset": null}},
+        {"add": {"table": {"family": "inet", "name": "filter"}}},
+        {"add": {"chain": {"family": "inet", "table": "filter", "name": "input",
+            "type": "filter", "hook": "input", "prio": -10, "policy": "drop"}}},
+        {"insert": {"rule": {"family": "inet", "table": "filter", "chain": "input", "index": 0,
+            "expr": [
+                {"match": {"op": "==", "left": {"payload": {"protocol": "tcp", "field": "dport"}},
+                    "right": {"set": [22, 80]}}},
+                {"accept": null},
+            ]}}},
+        {"replace": {"family": "inet", "table": "filter", "chain": "input", "handle": 4,
+            "expr": [
+                {"match": {"op": "==", "left": {"payload": {"protocol": "ip", "field": "saddr"}},
+                    "right": {"prefix": {"addr": "10.0.0.0", "len": 8}}}},
+                {"counter": null},
+                {"drop": null},
+            ]}},
+        {"delete": {"rule": {"family": "ip", "table": "filter", "chain": "input", "handle": 5,
+            "expr": []}}},
+        {"add": {"element": {"family": "inet", "table": "filter", "name": "blocked",
+            "elem": [{"elem": {"val": "10.0.0.1", "timeout": 60}}]}}},
+        {"delete": {"table": {"family": "ip6", "name": "nat"}}},
+        {"destroy": {"rule": {"family": "ip", "table": "filter", "chain": "input", "handle": 6,
+            "expr": []}}},
+        {"destroy": {"set": {"family": "inet", "table": "filter", "name": "blocked",
+            "type": "ipv4_addr"}}},
+    ]));
+    assert_eq!(parsed, expected);
+}
+
+#[test]
+/// Parses chain comments, flags and device lists, and prints them back.
+fn test_parse_chain_properties() {
+    let text = r#"
+        table netdev filter {
+            chain ingress {
+                comment "bond members"
+                type filter hook ingress devices = { eth0, eth1 } priority 0; policy accept;
+                flags offload;
+            }
+            chain single {
+                type filter hook ingress device "eth2" priority 0;
+            }
+        }
+        "#;
+    let parsed = parse(text).unwrap();
+    let expected = nftables(json!([
+        {"add": {"table": {"family": "netdev", "name": "filter"}}},
+        {"add": {"chain": {"family": "netdev", "table": "filter", "name": "ingress",
+            "type": "filter", "hook": "ingress", "prio": 0, "dev": ["eth0", "eth1"],
+            "policy": "accept", "flags": ["offload"], "comment": "bond members"}}},
+        {"add": {"chain": {"family": "netdev", "table": "filter", "name": "single",
+            "type": "filter", "hook": "ingress", "prio": 0, "dev": "eth2",
+            "policy": "accept"}}},
+    ]));
+    assert_eq!(parsed, expected);
+    assert_eq!(parse(&parsed.to_string()).unwrap(), parsed);
+}
+
+#[test]
+/// Parses `typeof` declarations of sets and maps, and prints them back.
+fn test_parse_typeof() {
+    let text = r#"
+        table inet filter {
+            set allowed {
+                typeof ip saddr . tcp dport
+            }
+            map ifaces {
+                typeof iifname : verdict
+            }
+            map marks {
+                typeof ip saddr : meta mark
+            }
+        }
+        "#;
+    let parsed = parse(text).unwrap();
+    let expected = nftables(json!([
+        {"add": {"table": {"family": "inet", "name": "filter"}}},
+        {"add": {"set": {"family": "inet", "table": "filter", "name": "allowed",
+            "type": {"typeof": {"concat": [
+                {"payload": {"protocol": "ip", "field": "saddr"}},
+                {"payload": {"protocol": "tcp", "field": "dport"}},
+            ]}}}}},
+        {"add": {"map": {"family": "inet", "table": "filter", "name": "ifaces",
+            "type": {"typeof": {"meta": {"key": "iifname"}}}, "map": "verdict"}}},
+        {"add": {"map": {"family": "inet", "table": "filter", "name": "marks",
+            "type": {"typeof": {"payload": {"protocol": "ip", "field": "saddr"}}},
+            "map": {"typeof": {"meta": {"key": "mark"}}}}}},
+    ]));
+    assert_eq!(parsed, expected);
+    assert_eq!(parse(&parsed.to_string()).unwrap(), parsed);
+}
+
+#[test]
+/// Substitutes variables and reads included files relative to the
+/// including file.
+fn test_parse_include_define() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("rules.d")).unwrap();
+    fs::write(
+        dir.path().join("rules.d/10-ssh.nft"),
+        "add rule inet filter input tcp dport $ssh_port accept\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("rules.d/20-web.nft"),
+        "add rule inet filter input ip saddr $trusted tcp dport { 80, 443 } accept\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("main.nft"),
+        r#"
+        define ssh_port = 2222
+        define trusted = { 10.0.0.1, 10.0.0.2 }
+        table inet filter {
+            chain input {
+                type filter hook input priority 0
+            }
+        }
+        include "rules.d/*.nft"
+        "#,
+    )
+    .unwrap();
+    let parsed = parse_file(dir.path().join("main.nft")).unwrap();
+    let expected = nftables(json!([
+        {"add": {"table": {"family": "inet", "name": "filter"}}},
+        {"add": {"chain": {"family": "inet", "table": "filter", "name": "input",
+            "type": "filter", "hook": "input", "prio": 0, "policy": "accept"}}},
+        {"add": {"rule": {"family": "inet", "table": "filter", "chain": "input", "expr": [
+            {"match": {"op": "==", "left": {"payload": {"protocol": "tcp", "field": "dport"}},
+                "right": 2222}},
+            {"accept": null},
+        ]}}},
+        {"add": {"rule": {"family": "inet", "table": "filter", "chain": "input", "expr": [
+            {"match": {"op": "==", "left": {"payload": {"protocol": "ip", "field": "saddr"}},
+                "right": {"set": ["10.0.0.1", "10.0.0.2"]}}},
+            {"match": {"op": "==", "left": {"payload": {"protocol": "tcp", "field": "dport"}},
+                "right": {"set": [80, 443]}}},
+            {"accept": null},
+        ]}}},
+    ]));
+    assert_eq!(parsed, expected);
+}
+
+#[test]
+/// Reports the location of syntax errors.
+fn test_parse_errors() {
+    let err = parse("table inet filter {\n  chain input {\n    tcp dport 22 acept\n  }\n}\n")
+        .unwrap_err();
+    assert_eq!((err.line, err.column), (3, 18));
+    assert_eq!(
+        err.to_string(),
+        "3:18: unknown statement or selector `acept`"
+    );
+
+    let err = parse("add rule inet filter input tcp dport $ssh accept").unwrap_err();
+    assert_eq!(err.message, "undefined variable `$ssh`");
+
+    for text in ["table ip t { chain c {", "table ip t { chain c { accept"] {
+        let err = parse(text).unwrap_err();
+        assert_eq!(err.message, "expected `}`, found end of input", "{text}");
+    }
+}
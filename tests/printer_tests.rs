@@ -0,0 +1,513 @@
+use std::fs;
+
+use nftables::{
+    diff::diff,
+    parser::parse,
+    schema::{NfObject, Nftables},
+};
+use serde_json::{json, Value};
+
+fn nftables(objects: Value) -> Nftables<'static> {
+    serde_json::from_value(json!({ "nftables": objects })).unwrap()
+}
+
+fn fixture(name: &str) -> Nftables<'static> {
+    let json = fs::read_to_string(format!("resources/test/json/{name}.json")).unwrap();
+    serde_json::from_str(&json).unwrap()
+}
+
+/// Prints the listed ruleset of a fixture and checks that parsing the text
+/// gives the same ruleset.
+fn assert_round_trip(name: &str) {
+    let expected = fixture(name);
+    let text = expected.to_string();
+    let parsed = parse(&text).unwrap_or_else(|err| panic!("{name}: {err}\n{text}"));
+    // Map elements deserialize differently from the parsed mappings.
+    let parsed: Nftables = serde_json::from_value(serde_json::to_value(&parsed).unwrap()).unwrap();
+    let diff = diff(&expected, &parsed);
+    assert!(diff.is_empty(), "{name}:\n{text}\n{diff}");
+}
+
+#[test]
+fn test_print_fixtures() {
+    for name in [
+        "basic",
+        "bitflags",
+        "counter",
+        "flow",
+        "nat",
+        "nftables-init",
+        "setmap",
+        "space-keys",
+        "tproxy",
+        "workstation",
+        "workstation_combined",
+    ] {
+        assert_round_trip(name);
+    }
+}
+
+/// Splits a line of nft syntax into words, keeping quoted strings together
+/// and separating braces, parentheses, commas, `|` and `;`.
+fn words(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                word.push(c);
+                quoted = !quoted;
+                if !quoted {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            _ if quoted => word.push(c),
+            '{' | '}' | '(' | ')' | ',' | '|' | ';' | ' ' | '\t' => {
+                words.extend((!word.is_empty()).then(|| std::mem::take(&mut word)));
+                if !c.is_whitespace() {
+                    words.push(c.to_string());
+                }
+            }
+            _ => word.push(c),
+        }
+    }
+    words.extend((!word.is_empty()).then_some(word));
+    words
+}
+
+/// Joins words with canonical spacing: `{ a, b }`, `a,b` outside of braces,
+/// `(a | b)` and `a;`. A trailing comma in braces is dropped.
+fn join(words: &[String]) -> String {
+    let mut line = String::new();
+    let mut depth = 0;
+    for (i, word) in words.iter().enumerate() {
+        let next = words.get(i + 1).map(String::as_str);
+        match word.as_str() {
+            "," if next == Some("}") => continue,
+            "," if depth > 0 => {
+                line = line.trim_end().to_string();
+                line.push(',');
+            }
+            "," | ";" | ")" => {
+                line = line.trim_end().to_string();
+                line.push_str(word);
+                if word == "," {
+                    continue;
+                }
+            }
+            "(" => {
+                line.push('(');
+                continue;
+            }
+            "{" => {
+                depth += 1;
+                line.push('{');
+            }
+            "}" => {
+                depth -= 1;
+                line.push('}');
+            }
+            _ => line.push_str(word),
+        }
+        line.push(' ');
+    }
+    line.trim_end().to_string()
+}
+
+/// Lines of nft syntax without comments, blank lines and indentation, with
+/// anonymous sets spanning several lines joined and canonical spacing.
+fn lines(text: &str) -> Vec<String> {
+    const BLOCKS: [&str; 8] = [
+        "table",
+        "chain",
+        "set",
+        "map",
+        "flowtable",
+        "counter",
+        "ct",
+        "synproxy",
+    ];
+    let mut lines = Vec::new();
+    let mut pending: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let mut quoted = false;
+        let line = match line.char_indices().find(|&(_, c)| {
+            quoted ^= c == '"';
+            c == '#' && !quoted
+        }) {
+            Some((comment, _)) => &line[..comment],
+            None => line,
+        };
+        let mut words = words(line);
+        if words.is_empty() || words == ["flush", "ruleset"] {
+            continue;
+        }
+        if !pending.is_empty() {
+            pending.append(&mut words);
+        } else if words.last().is_some_and(|w| w == "{") && !BLOCKS.contains(&words[0].as_str()) {
+            pending = words;
+        } else {
+            lines.push(join(&words));
+            continue;
+        }
+        let depth = |c: &str| pending.iter().filter(|w| *w == c).count();
+        if depth("{") == depth("}") {
+            lines.push(join(&std::mem::take(&mut pending)));
+        }
+    }
+    lines
+}
+
+/// Rewrites the lines of an nft source file the way nft lists the loaded
+/// ruleset: variables are expanded, unnamed families and default chain
+/// policies spelled out, standard priorities named, anonymous counters show
+/// their (zero) values, interface names are quoted, `and` is printed as `&`,
+/// and sets and other objects precede the chains of a table.
+fn listed(source: &str) -> Vec<String> {
+    let mut text = String::new();
+    let mut defines = Vec::new();
+    for line in source.lines() {
+        match line.trim().strip_prefix("define ") {
+            Some(define) => {
+                let (name, value) = define.split_once(" = ").unwrap();
+                defines.push((format!("${}", name.trim()), value.trim().to_string()));
+            }
+            None => {
+                text.push_str(line);
+                text.push('\n');
+            }
+        }
+    }
+    for (name, value) in &defines {
+        text = text.replace(name.as_str(), value);
+    }
+
+    // Each table is split into its header, its blocks and its closing brace.
+    let mut tables: Vec<Vec<Vec<String>>> = Vec::new();
+    for line in lines(&text) {
+        let mut words = words(&line);
+        match words[0].as_str() {
+            "table" => {
+                if words.len() == 3 {
+                    words.insert(1, "ip".to_string());
+                }
+                tables.push(vec![vec![join(&words)]]);
+                continue;
+            }
+            "type" if words.contains(&"hook".to_string()) => {
+                let position = words.iter().position(|w| w == "priority").unwrap() + 1;
+                let hook = &words[words.iter().position(|w| w == "hook").unwrap() + 1];
+                let priority = match (words[position].as_str(), hook.as_str()) {
+                    ("0", _) => Some("filter"),
+                    ("100", "postrouting") => Some("srcnat"),
+                    ("-100", "prerouting") => Some("dstnat"),
+                    _ => None,
+                };
+                if let Some(priority) = priority {
+                    words[position] = priority.to_string();
+                }
+                if !words.contains(&"policy".to_string()) {
+                    words.extend([";", "policy", "accept"].map(String::from));
+                }
+                if words.last().is_some_and(|w| w != ";") {
+                    words.push(";".to_string());
+                }
+            }
+            _ => {}
+        }
+        let mut i = 0;
+        while i < words.len() {
+            match words[i].as_str() {
+                "and" => words[i] = "&".to_string(),
+                "counter"
+                    if !matches!(
+                        words.get(i + 1).map(String::as_str),
+                        Some("packets" | "name")
+                    ) && words.last().is_some_and(|w| w != "{") =>
+                {
+                    let values = ["packets", "0", "bytes", "0"].map(String::from);
+                    words.splice(i + 1..i + 1, values);
+                }
+                "iif" | "oif" => {
+                    let value = i + 1 + usize::from(words.get(i + 1).is_some_and(|w| w == "!="));
+                    if !words[value].starts_with('"') && words[value] != "{" {
+                        words[value] = format!("\"{}\"", words[value]);
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        let table = tables.last_mut().unwrap();
+        let line = join(&words);
+        let block = table.last_mut().unwrap();
+        let closed = block.len() > 1 && block.last().is_some_and(|l| l == "}");
+        let opening = words.len() > 1 && words.last().is_some_and(|w| w == "{");
+        if closed || opening {
+            table.push(vec![line]);
+        } else {
+            block.push(line);
+        }
+    }
+
+    let mut lines = Vec::new();
+    for mut table in tables {
+        let end = table.pop().unwrap();
+        let header = table.remove(0);
+        table.sort_by_key(|block| block[0].starts_with("chain "));
+        lines.extend(header);
+        lines.extend(table.into_iter().flatten());
+        lines.extend(end);
+    }
+    lines
+}
+
+#[test]
+/// Prints the fixtures like nft lists them after loading the nft sources.
+fn test_print_fixtures_listed() {
+    // nft canonicalizes flags, prefixes, protocol and service names, sets and
+    // statement arguments beyond the generic rewrites of `listed`.
+    let fixtures: [(&str, &[(&str, &str)]); 11] = [
+        (
+            "basic",
+            &[(
+                "ct state related,established",
+                "ct state established,related",
+            )],
+        ),
+        (
+            "bitflags",
+            &[("(syn | ack | fin | rst)", "(fin | syn | rst | ack)")],
+        ),
+        ("counter", &[]),
+        ("flow", &[]),
+        ("nat", &[]),
+        (
+            "nftables-init",
+            &[
+                ("redirect to 21212", "redirect to :21212"),
+                ("== (fin | syn)", "== fin | syn"),
+                ("== (syn | rst)", "== syn | rst"),
+                ("< (fin)", "< fin"),
+                ("== (fin | psh | urg)", "== fin | psh | urg"),
+                (
+                    "log flags skuid flags ether prefix \"Invalid conntrack state: \"",
+                    "log prefix \"Invalid conntrack state: \" flags skuid flags ether",
+                ),
+                ("{ ssh, http, https }", "{ 22, 80, 443 }"),
+                ("{ http, https }", "{ 80, 443 }"),
+                ("dport ssh", "dport 22"),
+                (
+                    "{ 12.34.56.78/29, 10.11.12.0/8, 172.16.1.0/16 }",
+                    "{ 10.0.0.0/8, 12.34.56.72/29, 172.16.0.0/16 }",
+                ),
+                ("{ 8.8.8.8, 8.8.4.4 }", "{ 8.8.4.4, 8.8.8.8 }"),
+                ("!= { 127.0.0.1 }", "!= 127.0.0.1"),
+                ("nexthdr icmpv6", "nexthdr ipv6-icmp"),
+                (
+                    "log flags all prefix \"Outgoing packet dropped: \"",
+                    "log prefix \"Outgoing packet dropped: \" flags all",
+                ),
+            ],
+        ),
+        ("setmap", &[]),
+        ("space-keys", &[]),
+        ("tproxy", &[]),
+        (
+            "workstation",
+            &[
+                ("127.0.0.1/8", "127.0.0.0/8"),
+                ("::1/128", "::1"),
+                ("nexthdr icmpv6", "nexthdr ipv6-icmp"),
+            ],
+        ),
+        (
+            "workstation_combined",
+            &[
+                ("127.0.0.1/8", "127.0.0.0/8"),
+                ("::1/128", "::1"),
+                ("nexthdr icmpv6", "nexthdr ipv6-icmp"),
+            ],
+        ),
+    ];
+    for (name, rewrites) in fixtures {
+        let source = fs::read_to_string(format!("resources/test/nft/{name}.nft")).unwrap();
+        let expected: Vec<String> = listed(&source)
+            .into_iter()
+            .map(|line| {
+                rewrites
+                    .iter()
+                    .fold(line, |line, (from, to)| line.replace(from, to))
+            })
+            .collect();
+        let printed = fixture(name).to_string();
+        assert_eq!(lines(&printed), expected, "{name}:\n{printed}");
+    }
+}
+
+#[test]
+/// Prints tables like `nft list ruleset`.
+fn test_print_ruleset() {
+    let expected = "\
+table ip filter {
+\tchain output {
+\t\ttype filter hook output priority 100; policy accept;
+\t}
+
+\tchain input {
+\t\ttype filter hook input priority filter; policy accept;
+\t\tiifname \"lan0\" accept
+\t\tiifname \"wan0\" drop
+\t}
+
+\tchain forward {
+\t\ttype filter hook forward priority filter; policy drop;
+\t\tiifname \"lan0\" oifname \"wan0\" accept
+\t\tiifname \"wan0\" oifname \"lan0\" ct state established,related accept
+\t}
+}
+";
+    assert_eq!(fixture("basic").to_string(), expected);
+
+    let expected = "\
+table ip nat {
+\tmap porttoip {
+\t\ttype inet_service : ipv4_addr
+\t\telements = { 80 : 192.168.1.100, 8888 : 192.168.1.101 }
+\t}
+
+\tchain prerouting {
+\t\tdnat to tcp dport map { 80 : 192.168.1.100, 8888 : 192.168.1.101 }
+\t}
+
+\tchain postrouting {
+\t\tsnat to tcp dport map @porttoip
+\t}
+}
+";
+    assert_eq!(fixture("setmap").to_string(), expected);
+}
+
+#[test]
+/// Prints statements and commands on single lines.
+fn test_print_commands() {
+    let document = nftables(json!([
+        {"flush": {"ruleset": null}},
+        {"add": {"rule": {"family": "inet", "table": "filter", "chain": "input", "expr": [
+            {"match": {"op": "==", "left": {"payload": {"protocol": "ip", "field": "saddr"}},
+                "right": {"prefix": {"addr": "10.0.0.0", "len": 8}}}},
+            {"match": {"op": "==", "left": {"payload": {"protocol": "tcp", "field": "dport"}},
+                "right": {"set": [22, 443]}}},
+            {"counter": null},
+            {"accept": null},
+        ]}}},
+        {"add": {"set": {"family": "inet", "table": "filter", "name": "blocked",
+            "type": "ipv4_addr", "flags": ["timeout", "interval"], "timeout": 90,
+            "elem": ["10.0.0.1", {"range": ["10.1.0.0", "10.1.255.255"]}]}}},
+        {"delete": {"rule": {"family": "inet", "table": "filter", "chain": "input",
+            "handle": 5, "expr": []}}},
+        {"add": {"element": {"family": "inet", "table": "filter", "name": "blocked",
+            "elem": [{"elem": {"val": "10.0.0.2", "timeout": 3600, "comment": "web"}}]}}},
+        {"rename": {"family": "inet", "table": "filter", "name": "old", "newname": "new"}},
+    ]));
+    let expected = "\
+flush ruleset
+table inet filter {
+\tset blocked {
+\t\ttype ipv4_addr
+\t\tflags interval,timeout
+\t\ttimeout 1m30s
+\t\telements = { 10.0.0.1, 10.1.0.0-10.1.255.255 }
+\t}
+
+\tchain input {
+\t\tip saddr 10.0.0.0/8 tcp dport { 22, 443 } counter accept
+\t}
+}
+delete rule inet filter input handle 5
+add element inet filter blocked { 10.0.0.2 timeout 1h comment \"web\" }
+rename chain inet filter old new
+";
+    assert_eq!(document.to_string(), expected);
+
+    let commands: Vec<String> = document
+        .objects
+        .iter()
+        .map(|object| match object {
+            NfObject::CmdObject(cmd) => cmd.to_string(),
+            NfObject::ListObject(object) => object.to_string(),
+        })
+        .collect();
+    assert_eq!(
+        commands[1],
+        "add rule inet filter input ip saddr 10.0.0.0/8 tcp dport { 22, 443 } counter accept"
+    );
+    assert_eq!(
+        commands[2],
+        "add set inet filter blocked { type ipv4_addr; flags interval,timeout; timeout 1m30s; \
+         elements = { 10.0.0.1, 10.1.0.0-10.1.255.255 }; }"
+    );
+}
+
+#[test]
+/// Prints destroy, list and reset commands, including their plural forms.
+fn test_print_list_reset() {
+    let document = nftables(json!([
+        {"destroy": {"table": {"family": "inet", "name": "filter"}}},
+        {"list": {"ruleset": {}}},
+        {"list": {"tables": {"family": "inet"}}},
+        {"list": {"sets": {"family": "inet", "table": "filter"}}},
+        {"list": {"ct helpers": {"table": "filter"}}},
+        {"list": {"chain": {"family": "inet", "table": "filter", "name": "input"}}},
+        {"reset": {"rules": {"family": "inet", "table": "filter"}}},
+        {"reset": {"table": {"family": "inet", "name": "filter"}}},
+        {"reset": {"rule": {"family": "inet", "table": "filter", "chain": "input", "expr": []}}},
+        {"reset": {"rule": {"family": "inet", "table": "filter", "chain": "input", "handle": 3,
+            "expr": []}}},
+        {"reset": {"map": {"family": "inet", "table": "filter", "name": "ports",
+            "type": "inet_service", "map": "verdict"}}},
+        {"reset": {"element": {"family": "inet", "table": "filter", "name": "blocked",
+            "elem": ["10.0.0.1"]}}},
+    ]));
+    let expected = "\
+destroy table inet filter
+list ruleset
+list tables inet
+list sets table inet filter
+list ct helpers table filter
+list chain inet filter input
+reset rules table inet filter
+reset table inet filter
+reset rules chain inet filter input
+reset rule inet filter input handle 3
+reset map inet filter ports
+reset element inet filter blocked { 10.0.0.1 }
+";
+    assert_eq!(document.to_string(), expected);
+}
+
+#[test]
+/// Parenthesizes flags in bitmask operations.
+fn test_print_bitmask() {
+    let document = nftables(json!([
+        {"rule": {"family": "inet", "table": "filter", "chain": "input", "expr": [
+            {"match": {"op": "==",
+                "left": {"&": [{"payload": {"protocol": "tcp", "field": "flags"}},
+                    ["fin", "syn", "rst", "ack"]]},
+                "right": ["syn", "ack"]}},
+            {"match": {"op": "in", "left": {"ct": {"key": "state"}},
+                "right": ["established", "related"]}},
+            {"log": {"prefix": "SYN ", "flags": ["tcp options", "tcp sequence", "skuid"]}},
+            {"drop": null},
+        ]}},
+    ]));
+    let rule = &document.to_string();
+    assert!(
+        rule.contains(
+            "tcp flags & (fin | syn | rst | ack) == syn | ack ct state established,related \
+             log prefix \"SYN \" flags tcp sequence,options flags skuid drop"
+        ),
+        "{rule}"
+    );
+}
@@ -0,0 +1,235 @@
+use std::{cell::RefCell, ffi::OsStr};
+
+use nftables::{
+    helper::{NftExecutor, NftablesError},
+    reconcile,
+    schema::{NfCmd, NfListObject, Nftables},
+    types::NfFamily,
+};
+use serde_json::json;
+
+fn nftables(objects: serde_json::Value) -> Nftables<'static> {
+    serde_json::from_value(json!({ "nftables": objects })).unwrap()
+}
+
+fn table(family: &str, name: &str, handle: u32) -> serde_json::Value {
+    json!({"table": {"family": family, "name": name, "handle": handle}})
+}
+
+fn chain(name: &str, hook: &str, handle: u32) -> serde_json::Value {
+    json!({"chain": {"family": "inet", "table": "app", "name": name, "handle": handle,
+        "type": "filter", "hook": hook, "prio": 0, "policy": "accept"}})
+}
+
+fn rule(chain: &str, comment: &str, handle: Option<u32>) -> serde_json::Value {
+    let mut rule = json!({"family": "inet", "table": "app", "chain": chain,
+        "expr": [{"accept": null}], "comment": comment});
+    if let Some(handle) = handle {
+        rule["handle"] = handle.into();
+    }
+    json!({ "rule": rule })
+}
+
+/// Live ruleset with an owned table `inet app` and a foreign table `ip nat`.
+fn current() -> Nftables<'static> {
+    nftables(json!([
+        {"metainfo": {"version": "1.0.9", "release_name": "Old Doc Yak #3", "json_schema_version": 1}},
+        table("ip", "nat", 1),
+        table("inet", "app", 2),
+        chain("input", "input", 1),
+        rule("input", "a", Some(2)),
+        rule("input", "b", Some(3)),
+        rule("input", "c", Some(4)),
+        {"set": {"family": "inet", "table": "app", "name": "blocked", "handle": 5,
+            "type": "ipv4_addr", "elem": ["10.0.0.1", "10.0.0.2"]}},
+    ]))
+}
+
+fn desired_app(rules: &[&str], elements: &[&str]) -> Nftables<'static> {
+    let mut objects = vec![
+        json!({"table": {"family": "inet", "name": "app"}}),
+        json!({"chain": {"family": "inet", "table": "app", "name": "input",
+            "type": "filter", "hook": "input", "prio": 0, "policy": "accept"}}),
+    ];
+    objects.extend(rules.iter().map(|comment| rule("input", comment, None)));
+    objects.push(
+        json!({"set": {"family": "inet", "table": "app", "name": "blocked",
+        "type": "ipv4_addr", "elem": elements}}),
+    );
+    nftables(json!(objects))
+}
+
+fn to_json(cmds: &[NfCmd]) -> serde_json::Value {
+    serde_json::to_value(cmds).unwrap()
+}
+
+#[test]
+/// Produces no commands if the owned table already matches.
+fn test_diff_unchanged() {
+    let desired = desired_app(&["a", "b", "c"], &["10.0.0.2", "10.0.0.1"]);
+    assert!(reconcile::diff(&current(), &desired, &[]).is_empty());
+}
+
+#[test]
+/// Replaces, inserts and deletes rules by position, keeping unchanged ones.
+fn test_diff_rules_and_elements() {
+    let desired = desired_app(&["a", "b2", "d", "c"], &["10.0.0.1", "10.0.0.3"]);
+    let cmds = reconcile::diff(&current(), &desired, &[]);
+    assert_eq!(
+        json!([
+            {"delete": {"element": {"family": "inet", "table": "app", "name": "blocked", "elem": ["10.0.0.2"]}}},
+            {"add": {"element": {"family": "inet", "table": "app", "name": "blocked", "elem": ["10.0.0.3"]}}},
+            {"replace": {"family": "inet", "table": "app", "chain": "input", "handle": 3,
+                "expr": [{"accept": null}], "comment": "b2"}},
+            {"insert": {"rule": {"family": "inet", "table": "app", "chain": "input", "handle": 4,
+                "expr": [{"accept": null}], "comment": "d"}}},
+        ]),
+        to_json(&cmds)
+    );
+
+    let desired = desired_app(&["c"], &["10.0.0.1", "10.0.0.2"]);
+    let cmds = reconcile::diff(&current(), &desired, &[]);
+    assert_eq!(2, cmds.len());
+    assert!(cmds.iter().all(|cmd| matches!(
+        cmd,
+        NfCmd::Delete(NfListObject::Rule(rule)) if rule.handle == Some(2) || rule.handle == Some(3)
+    )));
+}
+
+#[test]
+/// Creates missing tables, deletes owned ones and leaves foreign ones alone.
+fn test_diff_tables() {
+    let desired = nftables(json!([
+        {"table": {"family": "inet", "name": "new"}},
+        {"chain": {"family": "inet", "table": "new", "name": "c"}},
+        {"rule": {"family": "inet", "table": "new", "chain": "c", "expr": [{"drop": null}]}},
+    ]));
+    let cmds = reconcile::diff(&current(), &desired, &[(NfFamily::INet, "app")]);
+    assert_eq!(
+        json!([
+            {"delete": {"table": {"family": "inet", "name": "app"}}},
+            {"add": {"table": {"family": "inet", "name": "new"}}},
+            {"add": {"chain": {"family": "inet", "table": "new", "name": "c"}}},
+            {"add": {"rule": {"family": "inet", "table": "new", "chain": "c", "expr": [{"drop": null}]}}},
+        ]),
+        to_json(&cmds)
+    );
+}
+
+#[test]
+/// Adds chains before the verdict map elements jumping to them.
+fn test_diff_vmap_chain() {
+    let ssh = json!({"chain": {"family": "inet", "table": "app", "name": "ssh"}});
+    let ports = json!({"map": {"family": "inet", "table": "app", "name": "ports",
+        "type": "inet_service", "map": "verdict", "elem": [[22, {"jump": {"target": "ssh"}}]]}});
+    let position = |cmds: &[NfCmd], kind: &str| {
+        let cmds = to_json(cmds);
+        let cmds = cmds.as_array().unwrap();
+        cmds.iter()
+            .position(|cmd| cmd["add"].get(kind).is_some())
+            .unwrap()
+    };
+
+    // Elements of a new map in an existing table.
+    let mut desired = desired_app(&["a", "b", "c"], &["10.0.0.1", "10.0.0.2"]);
+    let objects = desired.objects.to_mut();
+    objects.push(nftables(json!([ssh])).objects[0].clone());
+    objects.push(nftables(json!([ports])).objects[0].clone());
+    let cmds = reconcile::diff(&current(), &desired, &[]);
+    assert_eq!(3, cmds.len(), "{:#}", to_json(&cmds));
+    assert!(position(&cmds, "chain") < position(&cmds, "element"));
+
+    // Inline elements of a map in a new table.
+    let cmds = reconcile::diff(&nftables(json!([])), &desired, &[]);
+    assert!(position(&cmds, "chain") < position(&cmds, "map"));
+}
+
+#[test]
+/// Re-creates a table whose base chain changed its hook.
+fn test_diff_recreate_table() {
+    let mut desired = desired_app(&["a", "b", "c"], &["10.0.0.1", "10.0.0.2"]);
+    if let nftables::schema::NfObject::ListObject(NfListObject::Chain(chain)) =
+        &mut desired.objects.to_mut()[1]
+    {
+        chain.hook = Some(nftables::types::NfHook::Output);
+    }
+    let cmds = reconcile::diff(&current(), &desired, &[]);
+    assert!(matches!(&cmds[0], NfCmd::Delete(NfListObject::Table(t)) if t.name == "app"));
+    assert!(matches!(&cmds[1], NfCmd::Add(NfListObject::Table(t)) if t.name == "app"));
+    assert_eq!(7, cmds.len());
+}
+
+#[test]
+/// Makes a table dormant in place and re-creates it for other table changes.
+fn test_diff_table_flags() {
+    let mut desired = desired_app(&["a", "b", "c"], &["10.0.0.1", "10.0.0.2"]);
+    desired.objects.to_mut()[0] = nftables(json!([
+        {"table": {"family": "inet", "name": "app", "flags": ["dormant"]}},
+    ]))
+    .objects[0]
+        .clone();
+    let cmds = reconcile::diff(&current(), &desired, &[]);
+    assert_eq!(
+        json!([{"add": {"table": {"family": "inet", "name": "app", "flags": ["dormant"]}}}]),
+        to_json(&cmds)
+    );
+
+    // Waking it up again re-creates it.
+    let mut dormant = current();
+    dormant.objects.to_mut()[2] = nftables(json!([
+        {"table": {"family": "inet", "name": "app", "handle": 2, "flags": ["dormant"]}},
+    ]))
+    .objects[0]
+        .clone();
+    let awake = desired_app(&["a", "b", "c"], &["10.0.0.1", "10.0.0.2"]);
+    let cmds = reconcile::diff(&dormant, &awake, &[]);
+    assert!(matches!(&cmds[0], NfCmd::Delete(NfListObject::Table(t)) if t.name == "app"));
+    assert!(reconcile::diff(&dormant, &desired, &[]).is_empty());
+
+    desired.objects.to_mut()[0] = nftables(json!([
+        {"table": {"family": "inet", "name": "app", "comment": "managed"}},
+    ]))
+    .objects[0]
+        .clone();
+    let cmds = reconcile::diff(&current(), &desired, &[]);
+    assert!(matches!(&cmds[0], NfCmd::Delete(NfListObject::Table(t)) if t.name == "app"));
+    assert!(matches!(&cmds[1], NfCmd::Add(NfListObject::Table(t))
+        if t.comment.as_deref() == Some("managed")));
+}
+
+/// Fake nft that lists [current] and records applied payloads.
+struct FakeExecutor {
+    applied: RefCell<Vec<serde_json::Value>>,
+}
+
+impl NftExecutor for FakeExecutor {
+    fn execute(&self, _args: &[&OsStr], stdin: Option<&str>) -> Result<String, NftablesError> {
+        match stdin {
+            Some(payload) => {
+                let payload = serde_json::from_str(payload).unwrap();
+                self.applied.borrow_mut().push(payload);
+                Ok(String::new())
+            }
+            None => Ok(serde_json::to_string(&current()).unwrap()),
+        }
+    }
+}
+
+#[test]
+/// Applies the diff in a single transaction and only if needed.
+fn test_reconcile_with_executor() {
+    let executor = FakeExecutor {
+        applied: RefCell::new(Vec::new()),
+    };
+    let desired = desired_app(&["a", "b", "c"], &["10.0.0.1", "10.0.0.2"]);
+    let cmds = reconcile::reconcile_with_executor(&desired, &[], &executor).unwrap();
+    assert!(cmds.is_empty());
+    assert!(executor.applied.borrow().is_empty());
+
+    let desired = desired_app(&["a", "b", "c"], &["10.0.0.1"]);
+    let cmds = reconcile::reconcile_with_executor(&desired, &[], &executor).unwrap();
+    assert_eq!(1, cmds.len());
+    let applied = executor.applied.borrow();
+    assert_eq!(1, applied.len());
+    assert_eq!(to_json(&cmds), applied[0]["nftables"]);
+}
@@ -9,6 +9,8 @@ fn test_serialize() {
                 family: NfFamily::INet,
                 name: Cow::Borrowed("namib"),
                 handle: None,
+                flags: None,
+                comment: None,
             }))),
             NfObject::CmdObject(NfCmd::Add(NfListObject::Chain(Chain {
                 family: NfFamily::INet,
@@ -21,6 +23,8 @@ fn test_serialize() {
                 prio: None,
                 dev: None,
                 policy: Some(NfChainPolicy::Accept),
+                flags: None,
+                comment: None,
             }))),
             NfObject::CmdObject(NfCmd::Add(NfListObject::Rule(Rule {
                 family: NfFamily::INet,
@@ -0,0 +1,64 @@
+use nftables::{
+    monitor::MonitorEvent,
+    stmt::Statement,
+    trace::{group_traces, Trace, TraceType},
+    types::NfChainPolicy,
+};
+
+/// Canned output of `nft -j monitor trace` for two interleaved packets.
+const TRACE: &str = r#"{"trace": {"id": 1, "family": "inet", "type": "rule", "table": "filter", "chain": "input", "handle": 3, "verdict": {"jump": {"target": "ssh"}}, "iif": "eth0", "mark": 42, "network_header": "4500003c"}}
+{"trace": {"id": 2, "family": "inet", "type": "rule", "table": "filter", "chain": "input", "handle": 4, "verdict": {"accept": null}, "iif": "lo"}}
+{"trace": {"id": 1, "family": "inet", "type": "rule", "table": "filter", "chain": "ssh", "handle": 7, "verdict": {"continue": null}}}
+{"trace": {"id": 1, "family": "inet", "type": "return", "table": "filter", "chain": "ssh"}}
+{"trace": {"id": 1, "family": "inet", "type": "policy", "table": "filter", "chain": "input", "policy": "drop"}}
+"#;
+
+fn traces() -> Vec<Trace<'static>> {
+    TRACE
+        .lines()
+        .map(|line| match MonitorEvent::from_line(line).unwrap() {
+            MonitorEvent::Trace(trace) => *trace,
+            event => panic!("unexpected event: {event:?}"),
+        })
+        .collect()
+}
+
+#[test]
+/// Parses packet fields and verdicts of trace events.
+fn test_parse_trace() {
+    let traces = traces();
+    let first = &traces[0];
+    assert_eq!(1, first.id);
+    assert_eq!(TraceType::Rule, first._type);
+    assert_eq!(Some(3), first.handle);
+    assert_eq!(Some("eth0"), first.iif.as_deref());
+    assert_eq!(Some(42), first.mark);
+    assert_eq!(Some("4500003c"), first.network_header.as_deref());
+    assert!(matches!(first.verdict, Some(Statement::Jump(_))));
+    assert_eq!(None, first.decision());
+
+    assert_eq!(TraceType::Policy, traces[4]._type);
+    assert_eq!(Some(NfChainPolicy::Drop), traces[4].decision());
+}
+
+#[test]
+/// Groups interleaved trace events into per-packet paths.
+fn test_group_traces() {
+    let paths = group_traces(traces());
+    assert_eq!(2, paths.len());
+
+    let dropped = &paths[0];
+    assert_eq!(1, dropped.id);
+    assert_eq!(4, dropped.events.len());
+    assert_eq!(
+        vec![("filter", "input"), ("filter", "ssh"), ("filter", "input")],
+        dropped.chains()
+    );
+    assert_eq!(Some(NfChainPolicy::Drop), dropped.decision());
+    assert_eq!(TraceType::Policy, dropped.decided_by().unwrap()._type);
+
+    let accepted = &paths[1];
+    assert_eq!(2, accepted.id);
+    assert_eq!(Some(NfChainPolicy::Accept), accepted.decision());
+    assert_eq!(Some(4), accepted.decided_by().unwrap().handle);
+}
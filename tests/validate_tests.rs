@@ -0,0 +1,234 @@
+use std::fs;
+
+use nftables::{
+    diagnostic::Severity,
+    schema::Nftables,
+    types::{NfChainType, NfFamily, NfHook},
+    validate::{validate, Problem},
+};
+use serde_json::{json, Value};
+
+fn nftables(objects: Value) -> Nftables<'static> {
+    serde_json::from_value(json!({ "nftables": objects })).unwrap()
+}
+
+/// Returns the index and problem of each error.
+fn errors(nftables: &Nftables) -> Vec<(usize, Problem)> {
+    validate(nftables)
+        .into_iter()
+        .filter(|d| d.severity == Severity::Error)
+        .map(|d| (d.index, d.problem))
+        .collect()
+}
+
+#[test]
+/// Rulesets listed by nft are valid.
+fn test_validate_fixtures() {
+    for name in [
+        "basic",
+        "bitflags",
+        "counter",
+        "flow",
+        "nat",
+        "nftables-init",
+        "setmap",
+        "space-keys",
+        "synproxy",
+        "tproxy",
+        "workstation",
+        "workstation_combined",
+    ] {
+        let json = fs::read_to_string(format!("resources/test/json/{name}.json")).unwrap();
+        let nftables: Nftables = serde_json::from_str(&json).unwrap();
+        let diagnostics = validate(&nftables);
+        assert!(diagnostics.is_empty(), "{name}: {diagnostics:?}");
+    }
+}
+
+#[test]
+/// Reports references to undeclared tables, chains, sets and objects.
+fn test_validate_references() {
+    let document = nftables(json!([
+        {"add": {"table": {"family": "inet", "name": "filter"}}},
+        {"add": {"chain": {"family": "inet", "table": "filter", "name": "input",
+            "type": "filter", "hook": "input", "prio": 0, "policy": "drop"}}},
+        {"add": {"chain": {"family": "inet", "table": "filter", "name": "ssh"}}},
+        {"add": {"set": {"family": "inet", "table": "filter", "name": "allowed",
+            "type": "ipv4_addr"}}},
+        {"add": {"counter": {"family": "inet", "table": "filter", "name": "seen"}}},
+        // 5: valid references
+        {"add": {"rule": {"family": "inet", "table": "filter", "chain": "input", "expr": [
+            {"match": {"op": "==", "left": {"payload": {"protocol": "ip", "field": "saddr"}},
+                "right": "@allowed"}},
+            {"counter": "seen"},
+            {"jump": {"target": "ssh"}},
+        ]}}},
+        // 6: unknown chain, set, counter, helper, jump targets
+        {"add": {"rule": {"family": "inet", "table": "filter", "chain": "input", "expr": [
+            {"match": {"op": "==", "left": {"payload": {"protocol": "ip", "field": "saddr"}},
+                "right": "@blocked"}},
+            {"counter": "dropped"},
+            {"ct helper": "ftp"},
+            {"vmap": {"key": {"payload": {"protocol": "tcp", "field": "dport"}},
+                "data": {"set": [[22, {"jump": {"target": "sshd"}}],
+                                 [80, {"goto": {"target": "input"}}]]}}},
+        ]}}},
+        {"add": {"rule": {"family": "inet", "table": "filter", "chain": "output",
+            "expr": [{"accept": null}]}}},
+        {"add": {"rule": {"family": "ip", "table": "filter", "chain": "input",
+            "expr": [{"accept": null}]}}},
+        {"add": {"element": {"family": "inet", "table": "filter", "name": "denied",
+            "elem": ["10.0.0.1"]}}},
+        {"replace": {"family": "inet", "table": "filter", "chain": "input", "handle": 4,
+            "expr": [{"flow": {"op": "add", "flowtable": "@ft"}}]}},
+    ]));
+    assert_eq!(
+        errors(&document),
+        vec![
+            (
+                6,
+                Problem::UnknownSet {
+                    name: "blocked".into()
+                }
+            ),
+            (
+                6,
+                Problem::UnknownObject {
+                    kind: "counter",
+                    name: "dropped".into()
+                }
+            ),
+            (
+                6,
+                Problem::UnknownObject {
+                    kind: "ct helper",
+                    name: "ftp".into()
+                }
+            ),
+            (
+                6,
+                Problem::UnknownJumpTarget {
+                    chain: "sshd".into()
+                }
+            ),
+            (
+                6,
+                Problem::JumpToBaseChain {
+                    chain: "input".into()
+                }
+            ),
+            (
+                7,
+                Problem::UnknownChain {
+                    table: "filter".into(),
+                    chain: "output".into()
+                }
+            ),
+            (
+                8,
+                Problem::UnknownTable {
+                    family: NfFamily::IP,
+                    table: "filter".into()
+                }
+            ),
+            (
+                9,
+                Problem::UnknownSet {
+                    name: "denied".into()
+                }
+            ),
+            (10, Problem::UnknownFlowtable { name: "ft".into() }),
+        ]
+    );
+}
+
+#[test]
+/// Reports incomplete and unsupported base chains.
+fn test_validate_base_chains() {
+    let document = nftables(json!([
+        {"table": {"family": "inet", "name": "t"}},
+        {"table": {"family": "arp", "name": "t"}},
+        {"table": {"family": "netdev", "name": "t"}},
+        {"chain": {"family": "inet", "table": "t", "name": "a", "type": "filter", "prio": 0}},
+        {"chain": {"family": "inet", "table": "t", "name": "b", "type": "nat", "hook": "forward",
+            "prio": 0}},
+        {"chain": {"family": "arp", "table": "t", "name": "c", "type": "nat", "hook": "input",
+            "prio": 0}},
+        {"chain": {"family": "netdev", "table": "t", "name": "d", "type": "filter",
+            "hook": "ingress", "prio": 0}},
+        {"chain": {"family": "inet", "table": "t", "name": "e", "policy": "drop"}},
+    ]));
+    assert_eq!(
+        errors(&document),
+        vec![
+            (
+                3,
+                Problem::IncompleteBaseChain {
+                    chain: "a".into(),
+                    missing: "hook"
+                }
+            ),
+            (
+                4,
+                Problem::UnsupportedHook {
+                    family: NfFamily::INet,
+                    chain_type: NfChainType::NAT,
+                    hook: NfHook::Forward
+                }
+            ),
+            (
+                5,
+                Problem::UnsupportedChainType {
+                    family: NfFamily::ARP,
+                    chain_type: NfChainType::NAT
+                }
+            ),
+            (
+                6,
+                Problem::IncompleteBaseChain {
+                    chain: "d".into(),
+                    missing: "device"
+                }
+            ),
+        ]
+    );
+    let diagnostics = validate(&document);
+    let warning = diagnostics.last().unwrap();
+    assert_eq!(warning.severity, Severity::Warning);
+    assert_eq!(
+        warning.to_string(),
+        "object 7: warning: policy of regular chain `e` is ignored"
+    );
+}
+
+#[test]
+/// Reports NAT statements outside nat chains and statements after verdicts.
+fn test_validate_statements() {
+    let document = nftables(json!([
+        {"table": {"family": "ip", "name": "t"}},
+        {"chain": {"family": "ip", "table": "t", "name": "fwd", "type": "filter",
+            "hook": "forward", "prio": 0}},
+        {"chain": {"family": "ip", "table": "t", "name": "pre", "type": "nat",
+            "hook": "prerouting", "prio": -100}},
+        {"rule": {"family": "ip", "table": "t", "chain": "fwd", "expr": [
+            {"dnat": {"addr": "10.0.0.1"}}]}},
+        {"rule": {"family": "ip", "table": "t", "chain": "pre", "expr": [
+            {"dnat": {"addr": "10.0.0.1"}}]}},
+        {"rule": {"family": "ip", "table": "t", "chain": "pre", "expr": [
+            {"masquerade": null}]}},
+        {"rule": {"family": "ip", "table": "t", "chain": "fwd", "expr": [
+            {"accept": null}, {"counter": null}]}},
+    ]));
+    let diagnostics: Vec<String> = validate(&document)
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        diagnostics,
+        [
+            "object 3: error: `dnat` is not allowed in filter chains at hook forward",
+            "object 5: error: `masquerade` is not allowed in nat chains at hook prerouting",
+            "object 6: error: statement after verdict has no effect",
+        ]
+    );
+}
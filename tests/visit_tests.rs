@@ -0,0 +1,164 @@
+use std::borrow::Cow;
+
+use nftables::{
+    expr::{Expression, NamedExpression},
+    fold::{self, Fold},
+    parser::parse,
+    schema::{Chain, Rule, Set, Table},
+    stmt::JumpTarget,
+    visit::{self, Visit},
+    visit_mut::{self, VisitMut},
+};
+
+const RULESET: &str = r#"
+table inet filter {
+    set blocked {
+        type ipv4_addr
+    }
+    chain input {
+        type filter hook input priority 0;
+        ip saddr @blocked drop
+        tcp dport vmap { 22 : jump ssh, 80 : goto web }
+        ip daddr @allowed accept
+    }
+    chain ssh {
+        accept
+    }
+    chain web {
+        accept
+    }
+}
+"#;
+
+/// Collects the names of the sets referenced by rules.
+#[derive(Default)]
+struct SetReferences(Vec<String>);
+
+impl<'ast, 'a> Visit<'ast, 'a> for SetReferences {
+    fn visit_expression(&mut self, node: &'ast Expression<'a>) {
+        if let Expression::String(s) = node {
+            if let Some(name) = s.strip_prefix('@') {
+                self.0.push(name.to_string());
+            }
+        }
+        visit::visit_expression(self, node);
+    }
+}
+
+/// Collects the jump and goto targets.
+#[derive(Default)]
+struct Targets(Vec<String>);
+
+impl<'ast, 'a> Visit<'ast, 'a> for Targets {
+    fn visit_jump_target(&mut self, node: &'ast JumpTarget<'a>) {
+        self.0.push(node.target.to_string());
+    }
+}
+
+#[test]
+/// Walks a document by reference, down to the verdicts of anonymous maps.
+fn test_visit() {
+    let ruleset = parse(RULESET).unwrap();
+
+    let mut sets = SetReferences::default();
+    sets.visit_nftables(&ruleset);
+    assert_eq!(sets.0, ["blocked", "allowed"]);
+
+    let mut targets = Targets::default();
+    targets.visit_nftables(&ruleset);
+    assert_eq!(targets.0, ["ssh", "web"]);
+}
+
+/// Collects the payload fields.
+#[derive(Default)]
+struct Fields(Vec<String>);
+
+impl<'ast, 'a> Visit<'ast, 'a> for Fields {
+    fn visit_named_expression(&mut self, node: &'ast NamedExpression<'a>) {
+        if let NamedExpression::Payload(payload) = node {
+            self.0
+                .push(Expression::Named(NamedExpression::Payload(payload.clone())).to_string());
+        }
+        visit::visit_named_expression(self, node);
+    }
+}
+
+#[test]
+/// Walks the expressions of `typeof` set declarations.
+fn test_visit_typeof() {
+    let ruleset = parse(
+        "add set inet filter allowed { typeof ip saddr . tcp dport; }
+        add map inet filter marks { typeof ip daddr : meta mark; }",
+    )
+    .unwrap();
+    let mut fields = Fields::default();
+    fields.visit_nftables(&ruleset);
+    assert_eq!(fields.0, ["ip saddr", "tcp dport", "ip daddr"]);
+}
+
+/// Moves every table into another one.
+struct RenameTable;
+
+impl<'a> VisitMut<'a> for RenameTable {
+    fn visit_table_mut(&mut self, node: &mut Table<'a>) {
+        node.name = "firewall".into();
+    }
+
+    fn visit_chain_mut(&mut self, node: &mut Chain<'a>) {
+        node.table = "firewall".into();
+    }
+
+    fn visit_set_mut(&mut self, node: &mut Set<'a>) {
+        node.table = "firewall".into();
+    }
+
+    fn visit_rule_mut(&mut self, node: &mut Rule<'a>) {
+        node.table = "firewall".into();
+        visit_mut::visit_rule_mut(self, node);
+    }
+}
+
+#[test]
+/// Edits a document in place.
+fn test_visit_mut() {
+    let mut ruleset = parse(RULESET).unwrap();
+    RenameTable.visit_nftables_mut(&mut ruleset);
+    assert_eq!(
+        ruleset,
+        parse(&RULESET.replace("table inet filter", "table inet firewall")).unwrap()
+    );
+}
+
+/// Renames chains in jumps and gotos, and turns set lookups into anonymous sets.
+struct Rewrite;
+
+impl<'a> Fold<'a> for Rewrite {
+    fn fold_jump_target(&mut self, node: JumpTarget<'a>) -> JumpTarget<'a> {
+        JumpTarget {
+            target: Cow::Owned(format!("{}_in", node.target)),
+        }
+    }
+
+    fn fold_expression(&mut self, node: Expression<'a>) -> Expression<'a> {
+        match node {
+            Expression::String(s) if s == "@allowed" => {
+                Expression::Named(NamedExpression::Set(vec![]))
+            }
+            node => fold::fold_expression(self, node),
+        }
+    }
+}
+
+#[test]
+/// Rebuilds a document by value.
+fn test_fold() {
+    let ruleset = Rewrite.fold_nftables(parse(RULESET).unwrap());
+
+    let mut targets = Targets::default();
+    targets.visit_nftables(&ruleset);
+    assert_eq!(targets.0, ["ssh_in", "web_in"]);
+
+    let mut sets = SetReferences::default();
+    sets.visit_nftables(&ruleset);
+    assert_eq!(sets.0, ["blocked"]);
+}